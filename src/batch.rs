@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::dxf::{convert_document_with_options, write_document_to_file, ConvertOptions};
+use crate::error::JwwError;
+use crate::parser::read_document_from_file;
+
+/// Converts every `.jww` file directly inside `input_dir` to a `.dxf` of the
+/// same stem in `output_dir`, one file per rayon task.
+///
+/// Each file gets its own result, sorted by input path, so one unreadable
+/// drawing does not stop the rest. If the input directory cannot be listed
+/// or the output directory cannot be created, the only entry is that
+/// directory with the error.
+pub fn convert_directory(
+    input_dir: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    options: &ConvertOptions,
+) -> Vec<(PathBuf, Result<(), JwwError>)> {
+    let (input_dir, output_dir) = (input_dir.as_ref(), output_dir.as_ref());
+    let mut files = match list_jww_files(input_dir) {
+        Ok(files) => files,
+        Err(err) => return vec![(input_dir.to_path_buf(), Err(err.into()))],
+    };
+    if let Err(err) = fs::create_dir_all(output_dir) {
+        return vec![(output_dir.to_path_buf(), Err(err.into()))];
+    }
+    files.sort();
+
+    files
+        .into_par_iter()
+        .map(|path| {
+            let output_path = output_dir.join(path.with_extension("dxf").file_name().unwrap());
+            let result = convert_file(&path, &output_path, options);
+            (path, result)
+        })
+        .collect()
+}
+
+fn list_jww_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_jww = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("jww"));
+        if is_jww && path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn convert_file(path: &Path, output_path: &Path, options: &ConvertOptions) -> Result<(), JwwError> {
+    let document = read_document_from_file(path)?;
+    let dxf = convert_document_with_options(&document, options.clone());
+    write_document_to_file(&dxf, output_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::dxf::{convert_document, document_to_bytes, ConvertOptions};
+    use crate::parser::read_document_from_file;
+
+    use super::convert_directory;
+
+    #[test]
+    fn convert_directory_matches_serial_conversion_of_samples() {
+        let input_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples");
+        let output_dir =
+            std::env::temp_dir().join(format!("ezjww_convert_directory_{}", std::process::id()));
+
+        let results = convert_directory(&input_dir, &output_dir, &ConvertOptions::default());
+        let expected_count = fs::read_dir(&input_dir)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .path()
+                    .extension()
+                    .is_some_and(|ext| ext == "jww")
+            })
+            .count();
+        assert_eq!(results.len(), expected_count);
+        for (path, result) in &results {
+            assert!(result.is_ok(), "{}: {:?}", path.display(), result);
+            let written =
+                fs::read(output_dir.join(path.with_extension("dxf").file_name().unwrap())).unwrap();
+            let doc = read_document_from_file(path).unwrap();
+            assert_eq!(written, document_to_bytes(&convert_document(&doc)).unwrap());
+        }
+
+        let missing = convert_directory(
+            input_dir.join("missing"),
+            &output_dir,
+            &ConvertOptions::default(),
+        );
+        fs::remove_dir_all(&output_dir).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert!(missing[0].1.is_err());
+    }
+}
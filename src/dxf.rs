@@ -1,11 +1,20 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
-use std::f64::consts::PI;
+use std::f64::consts::{PI, TAU};
 use std::fmt::Write as _;
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::model::{Arc, Block, BlockDef, Entity, JwwDocument, Text};
+use encoding_rs::WINDOWS_1252;
+
+use crate::error::JwwError;
+use crate::header::{JwwHeader, LayerGroupHeader, LayerHeader};
+use crate::model::{
+    coordinates_bbox, expanded_bbox, Affine2D, Arc, Block, BlockDef, Coord2D, Dimension, Entity,
+    EntityBase, ImageRef, JwwDocument, Line, Point, PolyVertex, Polyline, Solid, Text,
+};
+use crate::parser::{group_entities, read_document_from_file, validate_block_references};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DxfLayer {
@@ -16,11 +25,31 @@ pub struct DxfLayer {
     pub locked: bool,
 }
 
+/// Value of a caller-attached XDATA item, written with the group code for
+/// its type after a group 1000 key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XDataValue {
+    /// Group 1000.
+    String(String),
+    /// Group 1040.
+    Real(f64),
+    /// Group 1071.
+    Integer(i32),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DxfLine {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    /// 24-bit RGB written as group 420 alongside the ACI color.
+    pub true_color: Option<u32>,
+    /// Key/value pairs written as XDATA under the document's
+    /// `xdata_app_id`; nothing is written when empty.
+    pub xdata: Vec<(String, XDataValue)>,
+    /// OCS extrusion direction (groups 210/220/230); `None` or (0, 0, 1)
+    /// is the default Z-up plane and is not written.
+    pub extrusion: Option<[f64; 3]>,
     pub x1: f64,
     pub y1: f64,
     pub x2: f64,
@@ -32,6 +61,9 @@ pub struct DxfCircle {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub center_x: f64,
     pub center_y: f64,
     pub radius: f64,
@@ -42,6 +74,9 @@ pub struct DxfArc {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub center_x: f64,
     pub center_y: f64,
     pub radius: f64,
@@ -54,6 +89,9 @@ pub struct DxfEllipse {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub center_x: f64,
     pub center_y: f64,
     pub major_axis_x: f64,
@@ -68,6 +106,9 @@ pub struct DxfPoint {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub x: f64,
     pub y: f64,
 }
@@ -77,12 +118,108 @@ pub struct DxfText {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
+    pub x: f64,
+    pub y: f64,
+    pub height: f64,
+    pub rotation: f64,
+    pub content: String,
+    pub style: String,
+    /// Relative character width (group 41); 1.0 keeps the style's default.
+    pub width_factor: f64,
+    pub alignment: TextAlignment,
+    /// Second alignment point (groups 11/21). Justified text falls back to
+    /// `(x, y)` when unset, since DXF positions it by this point.
+    pub alignment_point: Option<(f64, f64)>,
+    /// Drawn backwards (text generation flag 2), as in a mirrored block.
+    pub mirrored: bool,
+}
+
+/// Horizontal text justification, written as TEXT group 72.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HorizontalAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Aligned,
+    Middle,
+    Fit,
+}
+
+impl HorizontalAlignment {
+    pub fn group_code_value(self) -> i32 {
+        match self {
+            Self::Left => 0,
+            Self::Center => 1,
+            Self::Right => 2,
+            Self::Aligned => 3,
+            Self::Middle => 4,
+            Self::Fit => 5,
+        }
+    }
+}
+
+/// Vertical text justification, written as TEXT group 73.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VerticalAlignment {
+    #[default]
+    Baseline,
+    Bottom,
+    Middle,
+    Top,
+}
+
+impl VerticalAlignment {
+    pub fn group_code_value(self) -> i32 {
+        match self {
+            Self::Baseline => 0,
+            Self::Bottom => 1,
+            Self::Middle => 2,
+            Self::Top => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextAlignment {
+    pub horizontal: HorizontalAlignment,
+    pub vertical: VerticalAlignment,
+}
+
+impl TextAlignment {
+    pub const fn new(horizontal: HorizontalAlignment, vertical: VerticalAlignment) -> Self {
+        Self {
+            horizontal,
+            vertical,
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfMText {
+    pub layer: String,
+    pub color: i32,
+    pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub x: f64,
     pub y: f64,
     pub height: f64,
+    pub width: f64,
     pub rotation: f64,
+    pub attachment_point: i32,
     pub content: String,
     pub style: String,
+    /// Character tracking factor; 1.0 is the font's own spacing.
+    pub tracking: f64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -90,6 +227,9 @@ pub struct DxfSolid {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub x1: f64,
     pub y1: f64,
     pub x2: f64,
@@ -100,17 +240,128 @@ pub struct DxfSolid {
     pub y4: f64,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DxfVertex {
+    pub x: f64,
+    pub y: f64,
+    pub bulge: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfLwPolyline {
+    pub layer: String,
+    pub color: i32,
+    pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
+    pub vertices: Vec<DxfVertex>,
+    pub closed: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DxfInsert {
     pub layer: String,
     pub color: i32,
     pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub extrusion: Option<[f64; 3]>,
     pub block_name: String,
     pub x: f64,
     pub y: f64,
     pub scale_x: f64,
     pub scale_y: f64,
     pub rotation: f64,
+    /// Written as ATTRIB entities following the INSERT, closed by a SEQEND.
+    pub attributes: Vec<DxfAttribute>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfAttribute {
+    pub tag: String,
+    pub value: String,
+    pub x: f64,
+    pub y: f64,
+    pub height: f64,
+    pub rotation: f64,
+}
+
+/// 3DFACE with Z = 0; unlike [`DxfSolid`], the corners run around the
+/// outline in their natural order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfFace3D {
+    pub layer: String,
+    pub color: i32,
+    pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+    pub x3: f64,
+    pub y3: f64,
+    pub x4: f64,
+    pub y4: f64,
+}
+
+impl DxfFace3D {
+    // SOLID corners zigzag (1-2-4-3) where a 3DFACE runs around the edge, so
+    // the last two swap.
+    fn from_solid(solid: DxfSolid) -> Self {
+        Self {
+            layer: solid.layer,
+            color: solid.color,
+            line_type: solid.line_type,
+            true_color: solid.true_color,
+            xdata: solid.xdata,
+            x1: solid.x1,
+            y1: solid.y1,
+            x2: solid.x2,
+            y2: solid.y2,
+            x3: solid.x4,
+            y3: solid.y4,
+            x4: solid.x3,
+            y4: solid.y3,
+        }
+    }
+}
+
+/// Linked raster image (IMAGE with its IMAGEDEF). The U and V vectors span
+/// one pixel along the image's bottom and left edges.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfImage {
+    pub layer: String,
+    pub color: i32,
+    pub line_type: String,
+    pub true_color: Option<u32>,
+    pub xdata: Vec<(String, XDataValue)>,
+    pub path: String,
+    /// Lower-left corner.
+    pub x: f64,
+    pub y: f64,
+    pub u_x: f64,
+    pub u_y: f64,
+    pub v_x: f64,
+    pub v_y: f64,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+}
+
+impl DxfImage {
+    /// Corners counter-clockwise from the insertion point.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let (w, h) = (f64::from(self.pixel_width), f64::from(self.pixel_height));
+        let (ux, uy) = (self.u_x * w, self.u_y * w);
+        let (vx, vy) = (self.v_x * h, self.v_y * h);
+        [
+            (self.x, self.y),
+            (self.x + ux, self.y + uy),
+            (self.x + ux + vx, self.y + uy + vy),
+            (self.x + vx, self.y + vy),
+        ]
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -121,8 +372,12 @@ pub enum DxfEntity {
     Ellipse(DxfEllipse),
     Point(DxfPoint),
     Text(DxfText),
+    MText(DxfMText),
     Solid(DxfSolid),
+    Face3D(DxfFace3D),
+    LwPolyline(DxfLwPolyline),
     Insert(DxfInsert),
+    Image(DxfImage),
 }
 
 impl DxfEntity {
@@ -134,8 +389,131 @@ impl DxfEntity {
             Self::Ellipse(_) => "ELLIPSE",
             Self::Point(_) => "POINT",
             Self::Text(_) => "TEXT",
+            Self::MText(_) => "MTEXT",
             Self::Solid(_) => "SOLID",
+            Self::Face3D(_) => "3DFACE",
+            Self::LwPolyline(_) => "LWPOLYLINE",
             Self::Insert(_) => "INSERT",
+            Self::Image(_) => "IMAGE",
+        }
+    }
+
+    pub fn layer(&self) -> &str {
+        match self {
+            Self::Line(v) => &v.layer,
+            Self::Circle(v) => &v.layer,
+            Self::Arc(v) => &v.layer,
+            Self::Ellipse(v) => &v.layer,
+            Self::Point(v) => &v.layer,
+            Self::Text(v) => &v.layer,
+            Self::MText(v) => &v.layer,
+            Self::Solid(v) => &v.layer,
+            Self::Face3D(v) => &v.layer,
+            Self::LwPolyline(v) => &v.layer,
+            Self::Insert(v) => &v.layer,
+            Self::Image(v) => &v.layer,
+        }
+    }
+
+    pub fn color(&self) -> i32 {
+        match self {
+            Self::Line(v) => v.color,
+            Self::Circle(v) => v.color,
+            Self::Arc(v) => v.color,
+            Self::Ellipse(v) => v.color,
+            Self::Point(v) => v.color,
+            Self::Text(v) => v.color,
+            Self::MText(v) => v.color,
+            Self::Solid(v) => v.color,
+            Self::Face3D(v) => v.color,
+            Self::LwPolyline(v) => v.color,
+            Self::Insert(v) => v.color,
+            Self::Image(v) => v.color,
+        }
+    }
+
+    fn layer_mut(&mut self) -> &mut String {
+        match self {
+            Self::Line(v) => &mut v.layer,
+            Self::Circle(v) => &mut v.layer,
+            Self::Arc(v) => &mut v.layer,
+            Self::Ellipse(v) => &mut v.layer,
+            Self::Point(v) => &mut v.layer,
+            Self::Text(v) => &mut v.layer,
+            Self::MText(v) => &mut v.layer,
+            Self::Solid(v) => &mut v.layer,
+            Self::Face3D(v) => &mut v.layer,
+            Self::LwPolyline(v) => &mut v.layer,
+            Self::Insert(v) => &mut v.layer,
+            Self::Image(v) => &mut v.layer,
+        }
+    }
+
+    pub fn xdata(&self) -> &[(String, XDataValue)] {
+        match self {
+            Self::Line(v) => &v.xdata,
+            Self::Circle(v) => &v.xdata,
+            Self::Arc(v) => &v.xdata,
+            Self::Ellipse(v) => &v.xdata,
+            Self::Point(v) => &v.xdata,
+            Self::Text(v) => &v.xdata,
+            Self::MText(v) => &v.xdata,
+            Self::Solid(v) => &v.xdata,
+            Self::Face3D(v) => &v.xdata,
+            Self::LwPolyline(v) => &v.xdata,
+            Self::Insert(v) => &v.xdata,
+            Self::Image(v) => &v.xdata,
+        }
+    }
+
+    pub fn xdata_mut(&mut self) -> &mut Vec<(String, XDataValue)> {
+        match self {
+            Self::Line(v) => &mut v.xdata,
+            Self::Circle(v) => &mut v.xdata,
+            Self::Arc(v) => &mut v.xdata,
+            Self::Ellipse(v) => &mut v.xdata,
+            Self::Point(v) => &mut v.xdata,
+            Self::Text(v) => &mut v.xdata,
+            Self::MText(v) => &mut v.xdata,
+            Self::Solid(v) => &mut v.xdata,
+            Self::Face3D(v) => &mut v.xdata,
+            Self::LwPolyline(v) => &mut v.xdata,
+            Self::Insert(v) => &mut v.xdata,
+            Self::Image(v) => &mut v.xdata,
+        }
+    }
+
+    fn true_color_mut(&mut self) -> &mut Option<u32> {
+        match self {
+            Self::Line(v) => &mut v.true_color,
+            Self::Circle(v) => &mut v.true_color,
+            Self::Arc(v) => &mut v.true_color,
+            Self::Ellipse(v) => &mut v.true_color,
+            Self::Point(v) => &mut v.true_color,
+            Self::Text(v) => &mut v.true_color,
+            Self::MText(v) => &mut v.true_color,
+            Self::Solid(v) => &mut v.true_color,
+            Self::Face3D(v) => &mut v.true_color,
+            Self::LwPolyline(v) => &mut v.true_color,
+            Self::Insert(v) => &mut v.true_color,
+            Self::Image(v) => &mut v.true_color,
+        }
+    }
+
+    fn color_mut(&mut self) -> &mut i32 {
+        match self {
+            Self::Line(v) => &mut v.color,
+            Self::Circle(v) => &mut v.color,
+            Self::Arc(v) => &mut v.color,
+            Self::Ellipse(v) => &mut v.color,
+            Self::Point(v) => &mut v.color,
+            Self::Text(v) => &mut v.color,
+            Self::MText(v) => &mut v.color,
+            Self::Solid(v) => &mut v.color,
+            Self::Face3D(v) => &mut v.color,
+            Self::LwPolyline(v) => &mut v.color,
+            Self::Insert(v) => &mut v.color,
+            Self::Image(v) => &mut v.color,
         }
     }
 }
@@ -148,18 +526,235 @@ pub struct DxfBlock {
     pub entities: Vec<DxfEntity>,
 }
 
+/// A paper-space LAYOUT whose single viewport shows `layers` at 1:1,
+/// framing what they draw. Every other layer is frozen in the viewport.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DxfLayout {
+    pub name: String,
+    pub layers: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct DxfDocument {
     pub layers: Vec<DxfLayer>,
     pub entities: Vec<DxfEntity>,
     pub blocks: Vec<DxfBlock>,
     pub unsupported_entities: Vec<String>,
+    /// Written as `$INSUNITS`; coordinates are already expressed in these units.
+    pub units: TargetUnits,
+    /// Application name entity XDATA is written under, registered in the
+    /// APPID table when any entity carries XDATA.
+    pub xdata_app_id: String,
+    /// Paper-space layouts, written as `*Paper_Space` blocks and LAYOUT
+    /// objects. R12 has no layouts and leaves them out.
+    pub layouts: Vec<DxfLayout>,
+    /// Written as `$FINGERPRINTGUID` when set; see
+    /// [`ConvertOptions::fingerprint`]. R12 has no such variable.
+    pub fingerprint_guid: Option<String>,
+    pub version: DxfVersion,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Application name conversions register for entity XDATA.
+pub const DEFAULT_XDATA_APP_ID: &str = "EZJWW";
+
+impl DxfDocument {
+    /// Straight segments drawn by LINEs and LWPOLYLINEs, block contents
+    /// included, which is what flattening curves multiplies.
+    pub fn segment_count(&self) -> usize {
+        let block_entities = self.blocks.iter().flat_map(|block| block.entities.iter());
+        self.entities
+            .iter()
+            .chain(block_entities)
+            .map(entity_segment_count)
+            .sum()
+    }
+
+    /// Block names INSERTs refer to, block contents included, that no block
+    /// defines, sorted and without repeats. AutoCAD refuses to open a file
+    /// with any; names are compared case-insensitively as AutoCAD does.
+    pub fn validate_block_refs(&self) -> Vec<String> {
+        let defined = self
+            .blocks
+            .iter()
+            .map(|block| block.name.to_uppercase())
+            .collect::<BTreeSet<_>>();
+        let block_entities = self.blocks.iter().flat_map(|block| block.entities.iter());
+        self.entities
+            .iter()
+            .chain(block_entities)
+            .filter_map(|entity| match entity {
+                DxfEntity::Insert(v) if !defined.contains(&v.block_name.to_uppercase()) => {
+                    Some(v.block_name.clone())
+                }
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Box around what model space draws: curves count their drawn sweep and
+    /// inserts the placed contents of their blocks. Text counts only its
+    /// insertion and alignment points. Non-finite points are left out, and
+    /// `None` means nothing was drawn. Written as `$EXTMIN`/`$EXTMAX`.
+    pub fn extents(&self) -> Option<(Coord2D, Coord2D)> {
+        self.extents_of(|_| true)
+    }
+
+    fn extents_of(&self, include: impl Fn(&DxfEntity) -> bool) -> Option<(Coord2D, Coord2D)> {
+        let blocks = self
+            .blocks
+            .iter()
+            .map(|block| (block.name.as_str(), block))
+            .collect::<HashMap<_, _>>();
+        let mut points = Vec::new();
+        for entity in self.entities.iter().filter(|entity| include(entity)) {
+            extend_bounds(
+                &mut points,
+                entity,
+                &Affine2D::identity(),
+                &blocks,
+                &mut Vec::new(),
+            );
+        }
+        points.retain(|point| point.x.is_finite() && point.y.is_finite());
+        coordinates_bbox(&points)
+    }
+
+    /// Box around what the layout's layers draw, as [`Self::extents`] finds
+    /// it; the layout's viewport frames this box.
+    pub fn layout_extents(&self, layout: &DxfLayout) -> Option<(Coord2D, Coord2D)> {
+        self.extents_of(|entity| layout.layers.iter().any(|layer| layer == entity.layer()))
+    }
+
+    /// Drops blocks without entities together with every INSERT that
+    /// references them. A block left empty by the removal is dropped as well.
+    /// Returns how many blocks were removed.
+    pub fn remove_empty_blocks(&mut self) -> usize {
+        let mut removed = 0;
+        loop {
+            let empty = self
+                .blocks
+                .iter()
+                .filter(|block| block.entities.is_empty())
+                .map(|block| block.name.clone())
+                .collect::<BTreeSet<_>>();
+            if empty.is_empty() {
+                return removed;
+            }
+            removed += empty.len();
+            self.blocks.retain(|block| !empty.contains(&block.name));
+            let references_empty = |entity: &DxfEntity| matches!(entity, DxfEntity::Insert(insert) if empty.contains(&insert.block_name));
+            self.entities.retain(|entity| !references_empty(entity));
+            for block in &mut self.blocks {
+                block.entities.retain(|entity| !references_empty(entity));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ConvertOptions {
     pub explode_inserts: bool,
     pub max_block_nesting: usize,
+    /// Emits `header.memo` as an MTEXT at the given insertion point and text height.
+    pub memo_as_note: Option<(Coord2D, f64)>,
+    /// Drops entities whose resolved layer name is rejected by the filter.
+    pub layer_name_filter: Option<LayerNameFilter>,
+    /// Keeps only entities on the listed `(layer_group, layer)` slots. Block
+    /// contents are checked slot by slot as well, not by their insert's slot.
+    pub layer_filter: Option<Vec<(u16, u16)>>,
+    /// Draws a table of the used layers, each with a sample line in the
+    /// layer's color and line type, hanging down from the given top-left corner.
+    pub emit_legend: Option<Coord2D>,
+    /// Writes block definition contents as ByBlock (color 0) so they take the
+    /// color of each INSERT. Has no effect when inserts are exploded.
+    pub block_entities_byblock: bool,
+    /// Runs [`DxfDocument::remove_empty_blocks`] on the result.
+    pub prune_empty_blocks: bool,
+    /// Multiplies each entity by its layer group's scale, turning paper
+    /// millimeters into real-world millimeters (a 1:100 group scales by 100).
+    /// Block contents take the scale of their top-level insert.
+    pub apply_layer_scale: bool,
+    /// Writes all 256 JWW layers; by default layers no entity uses are dropped.
+    pub keep_all_layers: bool,
+    /// JWW coordinates are millimeters; other units scale the whole drawing.
+    pub target_units: TargetUnits,
+    /// Snaps every coordinate and length to a 1 µm grid (in the target units)
+    /// so output is reproducible for fixed-point consumers.
+    pub quantize_microns: bool,
+    /// Also writes each entity's RGB (group 420): the standard JWW pen color,
+    /// or the custom color of a SOLID drawn with pen 10.
+    pub true_color: bool,
+    // Tags each entity with its JWW pen number as integer XDATA under
+    // PEN_XDATA_KEY, which survives where several pens share one ACI. Only
+    // the GeoJSON export reads it back; the field stays `pub` so callers can
+    // still fill the rest of the struct from `Default`.
+    #[doc(hidden)]
+    pub pen_xdata: bool,
+    /// Writes SOLIDs as 3DFACEs with their corners in outline order, for
+    /// consumers that import filled quads as 3D faces.
+    pub solid_as_3dface: bool,
+    /// Mirrors the drawing across the X axis for Y-down consumers. Block
+    /// contents stay as they are; their inserts are mirrored instead.
+    pub flip_y: bool,
+    /// With `flip_y`, also mirrors TEXT, MTEXT and insert attributes.
+    /// Turning it off keeps top-level text exactly where and how JWW placed
+    /// it while the geometry flips; text in blocks moves with its insert but
+    /// still reads unmirrored.
+    pub flip_text: bool,
+    /// With `flip_y`, also mirrors SOLIDs; off leaves them unflipped, in
+    /// blocks relative to their insert.
+    pub flip_solids: bool,
+    /// Name for layers JWW left unnamed, in place of the `{group}-{layer}`
+    /// hex fallback. Every unnamed layer then shares this one DXF layer.
+    pub default_layer_name: Option<String>,
+    /// Adds filled arrowheads at both ends of each dimension line, sized
+    /// from the dimension text.
+    pub draw_dimension_arrows: bool,
+    /// Keeps only entities [`Entity::is_printable`] accepts.
+    pub printable_only: bool,
+    /// Writes every ARC, CIRCLE and ELLIPSE as an LWPOLYLINE flattened to
+    /// within [`ARC_CHORD_TOLERANCE`], for importers that handle polylines
+    /// better than curves.
+    pub arcs_as_polylines: bool,
+    /// Replaces each top-level INSERT with a rectangle around everything it
+    /// places and its block name as TEXT, for quick schematic previews.
+    pub blocks_as_bbox: bool,
+    /// Also writes block definitions no insert refers to.
+    pub keep_unreferenced_blocks: bool,
+    /// Writes temporary points, which are otherwise dropped, as POINTs on
+    /// the [`TEMP_POINTS_LAYER`] layer.
+    pub include_temporary_points: bool,
+    /// Fails [`try_convert_document_with_options`] and [`validate_conversion`]
+    /// with [`JwwError::SegmentLimitExceeded`] when the result would hold
+    /// more segments than this, as counted by [`DxfDocument::segment_count`].
+    pub max_total_segments: Option<usize>,
+    /// Draws marker points (pen style 100) as geometry: a circle for
+    /// [`POINT_MARKER_CIRCLE`] and two crossing lines for
+    /// [`POINT_MARKER_CROSS`], sized by the point's scale and turned by its
+    /// angle. Other codes stay POINTs.
+    pub expand_point_markers: bool,
+    /// Adds a paper-space layout for each layer group that has entities,
+    /// named after the group, with a viewport framing that group's extents
+    /// and freezing the layers of the other groups.
+    pub layouts_per_group: bool,
+    /// Trusted directories to read the pixel size of referenced images
+    /// from. Image paths come from the JWW file, so conversion opens no file
+    /// unless this is set, and then only the path's file name is looked up
+    /// here, as a regular file. Images not found get a pixel size from
+    /// their aspect ratio.
+    pub image_dirs: Vec<PathBuf>,
+    /// Stamps the DXF with a `$FINGERPRINTGUID` derived from a hash of the
+    /// source document, so the same drawing always gets the same GUID.
+    pub fingerprint: bool,
+    /// Moves the members of each non-zero JWW edit group (see
+    /// [`group_entities`]) into an anonymous `*U` block inserted at the
+    /// origin, so the group can be picked as one object. The INSERT takes the
+    /// draw-order place of the group's first member. Ignored with
+    /// `explode_inserts`, which asks for no inserts at all.
+    pub emit_groups_as_blocks: bool,
+    pub version: DxfVersion,
 }
 
 impl Default for ConvertOptions {
@@ -167,1687 +762,8581 @@ impl Default for ConvertOptions {
         Self {
             explode_inserts: false,
             max_block_nesting: 32,
+            memo_as_note: None,
+            layer_name_filter: None,
+            layer_filter: None,
+            emit_legend: None,
+            apply_layer_scale: false,
+            block_entities_byblock: false,
+            prune_empty_blocks: false,
+            keep_all_layers: false,
+            target_units: TargetUnits::Millimeters,
+            quantize_microns: false,
+            true_color: false,
+            pen_xdata: false,
+            solid_as_3dface: false,
+            flip_y: false,
+            flip_text: true,
+            flip_solids: true,
+            default_layer_name: None,
+            draw_dimension_arrows: false,
+            printable_only: false,
+            arcs_as_polylines: false,
+            blocks_as_bbox: false,
+            keep_unreferenced_blocks: false,
+            include_temporary_points: false,
+            max_total_segments: None,
+            expand_point_markers: false,
+            layouts_per_group: false,
+            image_dirs: Vec::new(),
+            fingerprint: false,
+            emit_groups_as_blocks: false,
+            version: DxfVersion::R2000,
         }
     }
 }
 
-pub fn convert_document(doc: &JwwDocument) -> DxfDocument {
-    convert_document_with_options(doc, ConvertOptions::default())
-}
-
-pub fn convert_document_with_options(doc: &JwwDocument, options: ConvertOptions) -> DxfDocument {
-    let layers = convert_layers(doc);
-    let block_name_map = block_name_map(doc);
-    let block_defs = block_defs_by_number(&doc.block_defs);
-
-    let mut unsupported_entities = Vec::<String>::new();
-    let entities = if options.explode_inserts {
-        convert_entities_exploded(
-            doc,
-            &doc.entities,
-            &block_name_map,
-            &block_defs,
-            &Transform2D::identity(),
-            &mut Vec::new(),
-            &mut unsupported_entities,
-            options,
-        )
-    } else {
-        convert_entities(
-            doc,
-            &doc.entities,
-            &block_name_map,
-            &mut unsupported_entities,
-        )
-    };
-    let blocks = if options.explode_inserts {
-        Vec::new()
-    } else {
-        convert_blocks(doc, &block_name_map, &mut unsupported_entities)
-    };
+impl ConvertOptions {
+    /// For laser cutters and CAM: inserts exploded and every curve written
+    /// as a polyline, so the job is plain paths on model space.
+    ///
+    /// Zero-length entities are not dropped and the JWW layers are not
+    /// merged into one, as there are no options for either yet; filter
+    /// those downstream if the cutter needs it.
+    pub fn for_laser() -> Self {
+        Self {
+            explode_inserts: true,
+            arcs_as_polylines: true,
+            ..Self::default()
+        }
+    }
 
-    DxfDocument {
-        layers,
-        entities,
-        blocks,
-        unsupported_entities,
+    /// For plotting what Jw_cad would print: hidden layers and construction
+    /// lines dropped, exact pen colors kept, and dimension arrowheads drawn.
+    ///
+    /// Colors stay on each entity rather than BYLAYER, since JWW pens do not
+    /// follow layers, and no `$LTSCALE` is written, so plotters use their
+    /// default line type scale.
+    pub fn for_print() -> Self {
+        Self {
+            printable_only: true,
+            true_color: true,
+            draw_dimension_arrows: true,
+            ..Self::default()
+        }
     }
 }
 
-pub fn document_to_string(doc: &DxfDocument) -> String {
-    let mut writer = AsciiDxfWriter::new();
-    writer.write_document(doc);
-    writer.finish()
+/// R12 output drops handles, owner handles, subclass markers, the
+/// BLOCK_RECORD table and the OBJECTS section, and rewrites entities R12 does
+/// not know (LWPOLYLINE, ELLIPSE, MTEXT) as POLYLINE and TEXT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DxfVersion {
+    R12,
+    #[default]
+    R2000,
 }
 
-pub fn write_document_to_file(doc: &DxfDocument, path: impl AsRef<Path>) -> io::Result<()> {
-    let data = document_to_string(doc);
-    fs::write(path, data)
+impl DxfVersion {
+    /// `$ACADVER` value for the version.
+    pub fn acadver(self) -> &'static str {
+        match self {
+            Self::R12 => "AC1009",
+            Self::R2000 => "AC1015",
+        }
+    }
 }
 
-struct AsciiDxfWriter {
-    out: String,
-    next_handle: u32,
-    block_record_order: Vec<String>,
-    block_record_handles: BTreeMap<String, String>,
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TargetUnits {
+    #[default]
+    Millimeters,
+    Meters,
+    Inches,
 }
 
-impl AsciiDxfWriter {
-    fn new() -> Self {
-        Self {
-            out: String::with_capacity(16 * 1024),
-            next_handle: 1,
-            block_record_order: Vec::new(),
-            block_record_handles: BTreeMap::new(),
+impl TargetUnits {
+    /// `$INSUNITS` value for the unit.
+    pub fn insunits(self) -> i32 {
+        match self {
+            Self::Inches => 1,
+            Self::Millimeters => 4,
+            Self::Meters => 6,
         }
     }
 
-    fn finish(self) -> String {
-        self.out
+    /// `$MEASUREMENT` value: 0 for imperial units, 1 for metric. JWW
+    /// itself is always metric, so only the target units decide it.
+    pub fn measurement(self) -> i32 {
+        match self {
+            Self::Inches => 0,
+            Self::Millimeters | Self::Meters => 1,
+        }
     }
 
-    fn write_document(&mut self, doc: &DxfDocument) {
-        self.ensure_block_record_table(doc);
-        self.write_header();
-        self.write_tables(doc);
-        self.write_blocks(doc);
-        self.write_entities(doc);
-        self.write_objects(doc);
-        self.group_str(0, "EOF");
+    pub fn millimeters_per_unit(self) -> f64 {
+        match self {
+            Self::Millimeters => 1.0,
+            Self::Meters => 1000.0,
+            Self::Inches => 25.4,
+        }
     }
+}
 
-    fn write_header(&mut self) {
-        self.section_start("HEADER");
-        self.group_str(9, "$ACADVER");
-        self.group_str(1, "AC1015");
-        self.group_str(9, "$DWGCODEPAGE");
-        self.group_str(3, "ANSI_1252");
-        self.group_str(9, "$MEASUREMENT");
-        self.group_i32(70, 1);
-        self.group_str(9, "$TEXTSTYLE");
-        self.group_str(7, "STANDARD");
-        self.group_str(9, "$CLAYER");
-        self.group_str(8, "0");
-        self.group_str(9, "$CELTYPE");
-        self.group_str(6, "BYLAYER");
-        self.group_str(9, "$CECOLOR");
-        self.group_i32(62, 256);
-        self.section_end();
+/// Include/exclude glob patterns (`*` and `?`) matched against resolved layer
+/// names, ignoring ASCII case. An empty include list keeps every layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LayerNameFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl LayerNameFilter {
+    pub fn matches(&self, layer_name: &str) -> bool {
+        let included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_match(pattern, layer_name));
+        included
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| glob_match(pattern, layer_name))
     }
+}
 
-    fn write_tables(&mut self, doc: &DxfDocument) {
-        self.section_start("TABLES");
-        self.write_ltype_table(doc);
-        self.write_layer_table(doc);
-        self.write_style_table();
-        self.write_block_record_table();
-        self.section_end();
+const NOTES_LAYER: &str = "NOTES";
+const COLOR_BYBLOCK: i32 = 0;
+const LEGEND_LAYER: &str = "LEGEND";
+/// Layer that [`ConvertOptions::include_temporary_points`] puts temporary
+/// points on.
+pub const TEMP_POINTS_LAYER: &str = "_TEMP_POINTS";
+
+pub fn convert_document(doc: &JwwDocument) -> DxfDocument {
+    convert_document_with_options(doc, ConvertOptions::default())
+}
+
+/// Like [`convert_document_with_options`], but enforces
+/// [`ConvertOptions::max_total_segments`], stopping as soon as the entities
+/// converted so far pass it.
+pub fn try_convert_document_with_options(
+    doc: &JwwDocument,
+    options: ConvertOptions,
+) -> Result<DxfDocument, JwwError> {
+    let limit = options.max_total_segments;
+    let mut budget = SegmentBudget::new(limit, options.arcs_as_polylines);
+    let dxf = convert_document_within(doc, options, &mut budget)?;
+    // Legends and notes are added after the entities were counted.
+    check_segment_limit(&dxf, limit)?;
+    Ok(dxf)
+}
+
+fn check_segment_limit(dxf: &DxfDocument, limit: Option<usize>) -> Result<usize, JwwError> {
+    let generated = dxf.segment_count();
+    match limit {
+        Some(limit) if generated > limit => {
+            Err(JwwError::SegmentLimitExceeded { generated, limit })
+        }
+        _ => Ok(generated),
     }
+}
 
-    fn write_ltype_table(&mut self, doc: &DxfDocument) {
-        let mut line_types = collect_line_types(doc);
-        line_types.insert("BYLAYER".to_string());
-        line_types.insert("BYBLOCK".to_string());
-        line_types.insert("CONTINUOUS".to_string());
+pub fn convert_document_with_options(doc: &JwwDocument, options: ConvertOptions) -> DxfDocument {
+    convert_document_within(doc, options, &mut SegmentBudget::new(None, false))
+        .expect("a conversion without a segment limit cannot fail")
+}
 
-        self.group_str(0, "TABLE");
-        self.group_str(2, "LTYPE");
-        self.write_handle();
-        self.group_i32(70, line_types.len() as i32);
+fn entity_segment_count(entity: &DxfEntity) -> usize {
+    match entity {
+        DxfEntity::Line(_) => 1,
+        DxfEntity::LwPolyline(v) if v.vertices.len() >= 2 => {
+            v.vertices.len() - 1 + usize::from(v.closed && v.vertices.len() > 2)
+        }
+        _ => 0,
+    }
+}
 
-        for name in line_types {
-            let (description, pattern): (&str, &[f64]) = match name.as_str() {
-                "BYLAYER" => ("", &[]),
-                "BYBLOCK" => ("", &[]),
-                "CONTINUOUS" => ("Solid line", &[]),
-                "DASHED" => ("Dashed line", &[0.6, -0.3]),
-                "DASHED2" => ("Dashed line x2", &[1.2, -0.6]),
-                "DASHDOT" => ("Dash dot", &[0.6, -0.2, 0.1, -0.2]),
-                "DOT" => ("Dotted line", &[0.1, -0.1]),
-                _ => ("", &[]),
-            };
-            let length = pattern.iter().map(|v| v.abs()).sum::<f64>();
-            self.group_str(0, "LTYPE");
-            self.write_handle();
-            self.group_str(2, &name);
-            self.group_i32(70, 0);
-            self.group_str(3, description);
-            self.group_i32(72, 65);
-            self.group_i32(73, pattern.len() as i32);
-            self.group_f64(40, length);
-            for value in pattern {
-                self.group_f64(49, *value);
-            }
-        }
+/// Running count of the segments converted entities will draw in the
+/// finished document, so a [`ConvertOptions::max_total_segments`] overrun
+/// fails before the rest of the drawing is expanded.
+struct SegmentBudget {
+    limit: Option<usize>,
+    generated: usize,
+    /// Whether curves will be flattened by `arcs_as_polylines` later on.
+    flatten_curves: bool,
+}
 
-        self.group_str(0, "ENDTAB");
+impl SegmentBudget {
+    fn new(limit: Option<usize>, flatten_curves: bool) -> Self {
+        Self {
+            limit,
+            generated: 0,
+            flatten_curves,
+        }
     }
 
-    fn write_layer_table(&mut self, doc: &DxfDocument) {
-        let mut layers = BTreeMap::<String, DxfLayer>::new();
-        for layer in &doc.layers {
-            layers
-                .entry(layer.name.clone())
-                .or_insert_with(|| layer.clone());
+    fn charge(&mut self, entities: &[DxfEntity]) -> Result<(), JwwError> {
+        let Some(limit) = self.limit else {
+            return Ok(());
+        };
+        for entity in entities {
+            self.generated += match curve_to_polyline(entity).filter(|_| self.flatten_curves) {
+                Some(polyline) => entity_segment_count(&DxfEntity::LwPolyline(polyline)),
+                None => entity_segment_count(entity),
+            };
         }
+        if self.generated > limit {
+            return Err(JwwError::SegmentLimitExceeded {
+                generated: self.generated,
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
 
-        self.group_str(0, "TABLE");
-        self.group_str(2, "LAYER");
-        self.write_handle();
-        self.group_i32(70, (layers.len() + 1) as i32);
-
-        self.group_str(0, "LAYER");
-        self.write_handle();
-        self.group_str(2, "0");
-        self.group_i32(70, 0);
-        self.group_i32(62, 7);
-        self.group_str(6, "CONTINUOUS");
+fn convert_document_within(
+    doc: &JwwDocument,
+    options: ConvertOptions,
+    budget: &mut SegmentBudget,
+) -> Result<DxfDocument, JwwError> {
+    let block_name_map = block_name_map(doc);
+    let block_defs = block_defs_by_number(&doc.block_defs);
+    let layer_names = LayerNames::new(doc, options.default_layer_name.as_deref());
+    let mut layers = convert_layers(doc, &layer_names);
 
-        for layer in layers.values() {
-            let mut flags = 0;
-            if layer.frozen {
-                flags |= 1;
-            }
-            if layer.locked {
-                flags |= 4;
+    let mut unsupported_entities = Vec::<String>::new();
+    let convert_top_level =
+        |entities: &[Entity], unsupported: &mut Vec<String>, budget: &mut SegmentBudget| {
+            if options.explode_inserts {
+                convert_entities_exploded(
+                    doc,
+                    &layer_names,
+                    entities,
+                    &block_name_map,
+                    &block_defs,
+                    &Affine2D::identity(),
+                    &mut Vec::new(),
+                    unsupported,
+                    budget,
+                    &options,
+                )
+            } else {
+                convert_entities(
+                    doc,
+                    &layer_names,
+                    entities,
+                    &block_name_map,
+                    options.blocks_as_bbox.then_some(&block_defs),
+                    unsupported,
+                    budget,
+                    &options,
+                )
             }
-            self.group_str(0, "LAYER");
-            self.write_handle();
-            self.group_str(2, &escape_unicode(&layer.name));
-            self.group_i32(70, flags);
-            self.group_i32(62, layer.color);
-            self.group_str(6, &layer.line_type);
+        };
+    let groups = if options.emit_groups_as_blocks && !options.explode_inserts {
+        group_entities(&doc.entities)
+    } else {
+        HashMap::new()
+    };
+    let group_of = groups
+        .iter()
+        .flat_map(|(&group, members)| members.iter().map(move |&index| (index, group)))
+        .collect::<HashMap<_, _>>();
+    let mut entities = Vec::new();
+    let mut group_blocks = Vec::new();
+    // Ungrouped entities are converted in runs between group members, and
+    // each group's INSERT goes where its first member was.
+    let mut run_start = 0;
+    for index in 0..doc.entities.len() {
+        let Some(&group) = group_of.get(&index) else {
+            continue;
+        };
+        let run = &doc.entities[run_start..index];
+        entities.extend(convert_top_level(run, &mut unsupported_entities, budget)?);
+        run_start = index + 1;
+        if groups[&group][0] != index {
+            continue;
         }
 
-        self.group_str(0, "ENDTAB");
-    }
-
-    fn write_style_table(&mut self) {
-        self.group_str(0, "TABLE");
-        self.group_str(2, "STYLE");
-        self.write_handle();
-        self.group_i32(70, 1);
-        self.group_str(0, "STYLE");
-        self.write_handle();
-        self.group_str(2, "STANDARD");
-        self.group_i32(70, 0);
-        self.group_f64(40, 0.0);
-        self.group_f64(41, 1.0);
-        self.group_f64(50, 0.0);
-        self.group_i32(71, 0);
-        self.group_f64(42, 2.5);
-        self.group_str(3, "txt");
-        self.group_str(4, "");
-        self.group_str(0, "ENDTAB");
+        let members = groups[&group]
+            .iter()
+            .map(|&member| doc.entities[member].clone())
+            .collect::<Vec<_>>();
+        let contents = convert_top_level(&members, &mut unsupported_entities, budget)?;
+        let Some(first) = contents.first() else {
+            continue;
+        };
+        let name = format!("*U{group}");
+        entities.push(DxfEntity::Insert(DxfInsert {
+            layer: first.layer().to_string(),
+            color: 256,
+            line_type: "BYLAYER".to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            block_name: name.clone(),
+            x: 0.0,
+            y: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            attributes: Vec::new(),
+        }));
+        group_blocks.push(DxfBlock {
+            name,
+            base_x: 0.0,
+            base_y: 0.0,
+            entities: contents,
+        });
     }
+    let run = &doc.entities[run_start..];
+    entities.extend(convert_top_level(run, &mut unsupported_entities, budget)?);
+    let mut blocks = if options.explode_inserts {
+        Vec::new()
+    } else {
+        convert_blocks(
+            doc,
+            &layer_names,
+            &block_name_map,
+            &mut unsupported_entities,
+            budget,
+            &options,
+        )?
+    };
+    blocks.extend(group_blocks);
 
-    fn write_block_record_table(&mut self) {
-        self.group_str(0, "TABLE");
-        self.group_str(2, "BLOCK_RECORD");
-        self.write_handle();
-        self.group_i32(70, self.block_record_order.len() as i32);
-
-        let names = self.block_record_order.clone();
-        for name in names {
-            let handle = self
-                .block_record_handles
-                .get(&name)
-                .cloned()
-                .expect("BLOCK_RECORD handle should exist");
-            self.group_str(0, "BLOCK_RECORD");
-            self.group_str(5, &handle);
-            self.group_str(330, "0");
-            self.group_str(100, "AcDbSymbolTableRecord");
-            self.group_str(100, "AcDbBlockTableRecord");
-            self.group_str(2, &escape_unicode(&name));
+    if let Some(position) = options.emit_legend {
+        let legend = convert_legend(&layers, &entities, &blocks, position);
+        if !legend.is_empty() {
+            layers.push(DxfLayer {
+                name: LEGEND_LAYER.to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                frozen: false,
+                locked: false,
+            });
+            entities.extend(legend);
         }
-
-        self.group_str(0, "ENDTAB");
     }
 
-    fn write_blocks(&mut self, doc: &DxfDocument) {
-        self.section_start("BLOCKS");
-        let model_owner = self.block_record_handle("*Model_Space").map(str::to_string);
-        self.write_block_definition("*Model_Space", 0.0, 0.0, &[], model_owner.as_deref());
-
-        let paper_owner = self.block_record_handle("*Paper_Space").map(str::to_string);
-        self.write_block_definition("*Paper_Space", 0.0, 0.0, &[], paper_owner.as_deref());
-
-        for block in &doc.blocks {
-            let owner = self.block_record_handle(&block.name).map(str::to_string);
-            self.write_block_definition(
-                &block.name,
-                block.base_x,
-                block.base_y,
-                &block.entities,
-                owner.as_deref(),
-            );
-        }
-        self.section_end();
+    if options.include_temporary_points {
+        layers.push(DxfLayer {
+            name: TEMP_POINTS_LAYER.to_string(),
+            color: 7,
+            line_type: "CONTINUOUS".to_string(),
+            frozen: false,
+            locked: false,
+        });
     }
 
-    fn write_entities(&mut self, doc: &DxfDocument) {
-        self.section_start("ENTITIES");
-        let owner = self.block_record_handle("*Model_Space").map(str::to_string);
-        for entity in &doc.entities {
-            self.write_entity(entity, owner.as_deref());
+    if let Some((position, height)) = options.memo_as_note {
+        if !doc.header.memo.trim().is_empty() {
+            layers.push(DxfLayer {
+                name: NOTES_LAYER.to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                frozen: false,
+                locked: false,
+            });
+            entities.push(convert_memo(&doc.header.memo, position, height));
         }
-        self.section_end();
     }
 
-    fn write_objects(&mut self, _doc: &DxfDocument) {
-        self.section_start("OBJECTS");
-        self.group_str(0, "DICTIONARY");
-        self.write_handle();
-        self.group_str(330, "0");
-        self.group_str(100, "AcDbDictionary");
-        self.group_i32(281, 1);
-        self.section_end();
+    let mut document = DxfDocument {
+        layers: Vec::new(),
+        entities,
+        blocks,
+        unsupported_entities,
+        units: options.target_units,
+        xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+        layouts: if options.layouts_per_group {
+            group_layouts(doc, &layer_names)
+        } else {
+            Vec::new()
+        },
+        fingerprint_guid: options.fingerprint.then(|| fingerprint_guid(doc)),
+        version: options.version,
+    };
+    if options.prune_empty_blocks {
+        document.remove_empty_blocks();
     }
-
-    fn write_block_definition(
-        &mut self,
-        name: &str,
-        base_x: f64,
-        base_y: f64,
-        entities: &[DxfEntity],
-        owner_handle: Option<&str>,
-    ) {
-        let block_name = escape_unicode(name);
-        self.group_str(0, "BLOCK");
-        self.write_handle();
-        if let Some(owner) = owner_handle {
-            self.group_str(330, owner);
+    if options.flip_y {
+        document.entities = document
+            .entities
+            .iter()
+            .flat_map(|entity| match entity {
+                DxfEntity::Text(_) | DxfEntity::MText(_) if !options.flip_text => {
+                    vec![entity.clone()]
+                }
+                DxfEntity::Solid(_) if !options.flip_solids => vec![entity.clone()],
+                DxfEntity::Insert(v) if !options.flip_text => {
+                    let mut flipped = flip_entity_y(entity);
+                    if let [DxfEntity::Insert(flipped)] = flipped.as_mut_slice() {
+                        flipped.attributes.clone_from(&v.attributes);
+                    }
+                    flipped
+                }
+                _ => flip_entity_y(entity),
+            })
+            .collect();
+        // Inserts carry the mirror into their blocks, so text and solids
+        // that must not flip are mirrored inside the block to cancel it.
+        for block in &mut document.blocks {
+            block.entities = block
+                .entities
+                .iter()
+                .flat_map(|entity| match entity {
+                    DxfEntity::Text(_) | DxfEntity::MText(_) if !options.flip_text => {
+                        flip_entity_y(entity)
+                    }
+                    DxfEntity::Solid(_) if !options.flip_solids => flip_entity_y(entity),
+                    DxfEntity::Insert(v) if !options.flip_text => {
+                        let mut insert = v.clone();
+                        for attribute in &mut insert.attributes {
+                            attribute.y = -attribute.y;
+                            attribute.rotation = -attribute.rotation;
+                        }
+                        vec![DxfEntity::Insert(insert)]
+                    }
+                    _ => vec![entity.clone()],
+                })
+                .collect();
         }
-        self.group_str(100, "AcDbEntity");
-        self.group_str(8, "0");
-        self.group_str(100, "AcDbBlockBegin");
-        self.group_str(2, &block_name);
-        self.group_i32(70, 0);
-        self.group_f64(10, base_x);
-        self.group_f64(20, base_y);
-        self.group_f64(30, 0.0);
-        self.group_str(3, &block_name);
-        self.group_str(1, "");
-
-        for entity in entities {
-            self.write_entity(entity, owner_handle);
+    }
+    if options.solid_as_3dface {
+        let block_entities = document
+            .blocks
+            .iter_mut()
+            .flat_map(|block| block.entities.iter_mut());
+        for entity in document.entities.iter_mut().chain(block_entities) {
+            if let DxfEntity::Solid(solid) = entity {
+                *entity = DxfEntity::Face3D(DxfFace3D::from_solid(solid.clone()));
+            }
         }
-
-        self.group_str(0, "ENDBLK");
-        self.write_handle();
-        if let Some(owner) = owner_handle {
-            self.group_str(330, owner);
+    }
+    if options.arcs_as_polylines {
+        let block_entities = document
+            .blocks
+            .iter_mut()
+            .flat_map(|block| block.entities.iter_mut());
+        for entity in document.entities.iter_mut().chain(block_entities) {
+            if let Some(polyline) = curve_to_polyline(entity) {
+                *entity = DxfEntity::LwPolyline(polyline);
+            }
         }
-        self.group_str(100, "AcDbEntity");
-        self.group_str(8, "0");
-        self.group_str(100, "AcDbBlockEnd");
     }
-
-    fn ensure_block_record_table(&mut self, doc: &DxfDocument) {
-        if !self.block_record_order.is_empty() {
-            return;
+    document.layers = sanitize_layers(
+        layers,
+        &mut document.entities,
+        &mut document.blocks,
+        &mut document.layouts,
+        options.keep_all_layers,
+    );
+    // A group that draws nothing gets no layout.
+    let layouts = std::mem::take(&mut document.layouts);
+    document.layouts = layouts
+        .into_iter()
+        .filter(|layout| document.layout_extents(layout).is_some())
+        .collect();
+
+    // Divides rather than multiplies so exact conversions such as 25.4 mm to
+    // 1 inch stay exact.
+    let divisor = options.target_units.millimeters_per_unit();
+    if divisor != 1.0 {
+        let to_target = |value: f64| value / divisor;
+        for entity in &mut document.entities {
+            scale_entity_lengths(entity, &to_target);
         }
-        self.register_block_record("*Model_Space");
-        self.register_block_record("*Paper_Space");
-        for block in &doc.blocks {
-            self.register_block_record(&block.name);
+        for block in &mut document.blocks {
+            block.base_x /= divisor;
+            block.base_y /= divisor;
+            for entity in &mut block.entities {
+                scale_entity_lengths(entity, &to_target);
+            }
         }
     }
 
-    fn register_block_record(&mut self, name: &str) {
-        if self.block_record_handles.contains_key(name) {
-            return;
+    if options.quantize_microns {
+        let steps_per_unit = 1000.0 * divisor;
+        let snap = |value: f64| (value * steps_per_unit).round() / steps_per_unit;
+        for entity in &mut document.entities {
+            scale_entity_lengths(entity, &snap);
+        }
+        for block in &mut document.blocks {
+            block.base_x = snap(block.base_x);
+            block.base_y = snap(block.base_y);
+            for entity in &mut block.entities {
+                scale_entity_lengths(entity, &snap);
+            }
         }
-        let handle = self.alloc_handle();
-        self.block_record_order.push(name.to_string());
-        self.block_record_handles.insert(name.to_string(), handle);
     }
 
-    fn block_record_handle(&self, name: &str) -> Option<&str> {
-        self.block_record_handles.get(name).map(String::as_str)
-    }
+    Ok(document)
+}
 
-    fn write_entity(&mut self, entity: &DxfEntity, owner_handle: Option<&str>) {
-        match entity {
-            DxfEntity::Line(v) => {
-                self.entity_header("LINE", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.x1);
-                self.group_f64(20, v.y1);
-                self.group_f64(30, 0.0);
-                self.group_f64(11, v.x2);
-                self.group_f64(21, v.y2);
-                self.group_f64(31, 0.0);
+/// Converts several drawings into one DXF. Each drawing's layer and named
+/// block names get its tag as a prefix, and its block definition numbers and
+/// anonymous `*U` group blocks are shifted past those of the drawings before
+/// it so inserts stay resolved.
+pub fn merge_documents(docs: &[(&str, &JwwDocument)], options: ConvertOptions) -> DxfDocument {
+    let mut merged = DxfDocument {
+        layers: Vec::new(),
+        entities: Vec::new(),
+        blocks: Vec::new(),
+        unsupported_entities: Vec::new(),
+        units: options.target_units,
+        xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+        layouts: Vec::new(),
+        fingerprint_guid: options.fingerprint.then(|| fingerprint_guid(docs)),
+        version: options.version,
+    };
+    let mut number_offset = 0u32;
+    let mut group_offset = 0u32;
+    for &(prefix, doc) in docs {
+        let mut doc = doc.clone();
+        let mut next_number = number_offset;
+        for block_def in &mut doc.block_defs {
+            block_def.number += number_offset;
+            next_number = next_number.max(block_def.number + 1);
+            if !block_def.name.is_empty() {
+                block_def.name = format!("{prefix}{}", block_def.name);
             }
-            DxfEntity::Circle(v) => {
-                self.entity_header("CIRCLE", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.center_x);
-                self.group_f64(20, v.center_y);
-                self.group_f64(30, 0.0);
-                self.group_f64(40, v.radius);
+        }
+        let block_entities = doc
+            .block_defs
+            .iter_mut()
+            .flat_map(|block_def| block_def.entities.iter_mut());
+        for entity in doc.entities.iter_mut().chain(block_entities) {
+            if let Entity::Block(block) = entity {
+                block.def_number += number_offset;
             }
-            DxfEntity::Arc(v) => {
-                self.entity_header("ARC", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.center_x);
-                self.group_f64(20, v.center_y);
-                self.group_f64(30, 0.0);
-                self.group_f64(40, v.radius);
-                self.group_f64(50, v.start_angle);
-                self.group_f64(51, v.end_angle);
-            }
-            DxfEntity::Ellipse(v) => {
-                self.entity_header("ELLIPSE", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.center_x);
-                self.group_f64(20, v.center_y);
-                self.group_f64(30, 0.0);
-                self.group_f64(11, v.major_axis_x);
-                self.group_f64(21, v.major_axis_y);
-                self.group_f64(31, 0.0);
-                self.group_f64(40, v.minor_ratio);
-                self.group_f64(41, v.start_param);
-                self.group_f64(42, v.end_param);
+        }
+        number_offset = next_number;
+
+        let mut converted = convert_document_with_options(&doc, options.clone());
+        // `*U` names must keep their prefix to stay anonymous, so they are
+        // renumbered instead.
+        let mut next_group = group_offset;
+        let group_names = converted
+            .blocks
+            .iter()
+            .filter_map(|block| {
+                let group = block.name.strip_prefix("*U")?.parse::<u32>().ok()?;
+                next_group = next_group.max(group + group_offset + 1);
+                Some((block.name.clone(), format!("*U{}", group + group_offset)))
+            })
+            .collect::<HashMap<_, _>>();
+        group_offset = next_group;
+        for block in &mut converted.blocks {
+            if let Some(name) = group_names.get(&block.name) {
+                block.name.clone_from(name);
             }
-            DxfEntity::Point(v) => {
-                self.entity_header("POINT", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.x);
-                self.group_f64(20, v.y);
-                self.group_f64(30, 0.0);
+        }
+        for layer in &mut converted.layers {
+            layer.name = format!("{prefix}{}", layer.name);
+        }
+        let block_entities = converted
+            .blocks
+            .iter_mut()
+            .flat_map(|block| block.entities.iter_mut());
+        for entity in converted.entities.iter_mut().chain(block_entities) {
+            let layer = entity.layer_mut();
+            *layer = format!("{prefix}{layer}");
+            if let DxfEntity::Insert(insert) = entity {
+                if let Some(name) = group_names.get(&insert.block_name) {
+                    insert.block_name.clone_from(name);
+                }
             }
-            DxfEntity::Text(v) => {
-                self.entity_header("TEXT", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.x);
-                self.group_f64(20, v.y);
-                self.group_f64(30, 0.0);
-                self.group_f64(40, v.height);
-                self.group_str(1, &escape_unicode(&v.content));
-                self.group_f64(50, v.rotation);
-                self.group_str(7, &escape_unicode(&v.style));
+        }
+        merged.layers.extend(converted.layers);
+        merged.entities.extend(converted.entities);
+        merged.blocks.extend(converted.blocks);
+        merged
+            .layouts
+            .extend(converted.layouts.into_iter().map(|layout| {
+                DxfLayout {
+                    name: format!("{prefix}{}", layout.name),
+                    layers: layout
+                        .layers
+                        .iter()
+                        .map(|layer| format!("{prefix}{layer}"))
+                        .collect(),
+                }
+            }));
+        merged
+            .unsupported_entities
+            .extend(converted.unsupported_entities);
+    }
+    merged
+}
+
+// Applies `scale` to every coordinate and length. INSERT scale factors are
+// untouched because the block contents are rescaled too.
+fn scale_entity_lengths(entity: &mut DxfEntity, scale: &dyn Fn(f64) -> f64) {
+    match entity {
+        DxfEntity::Line(v) => {
+            v.x1 = scale(v.x1);
+            v.y1 = scale(v.y1);
+            v.x2 = scale(v.x2);
+            v.y2 = scale(v.y2);
+        }
+        DxfEntity::Circle(v) => {
+            v.center_x = scale(v.center_x);
+            v.center_y = scale(v.center_y);
+            v.radius = scale(v.radius);
+        }
+        DxfEntity::Arc(v) => {
+            v.center_x = scale(v.center_x);
+            v.center_y = scale(v.center_y);
+            v.radius = scale(v.radius);
+        }
+        DxfEntity::Ellipse(v) => {
+            v.center_x = scale(v.center_x);
+            v.center_y = scale(v.center_y);
+            v.major_axis_x = scale(v.major_axis_x);
+            v.major_axis_y = scale(v.major_axis_y);
+        }
+        DxfEntity::Point(v) => {
+            v.x = scale(v.x);
+            v.y = scale(v.y);
+        }
+        DxfEntity::Text(v) => {
+            v.x = scale(v.x);
+            v.y = scale(v.y);
+            v.height = scale(v.height);
+            if let Some((x, y)) = &mut v.alignment_point {
+                *x = scale(*x);
+                *y = scale(*y);
             }
-            DxfEntity::Solid(v) => {
-                self.entity_header("SOLID", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_f64(10, v.x1);
-                self.group_f64(20, v.y1);
-                self.group_f64(30, 0.0);
-                self.group_f64(11, v.x2);
-                self.group_f64(21, v.y2);
-                self.group_f64(31, 0.0);
-                self.group_f64(12, v.x3);
-                self.group_f64(22, v.y3);
-                self.group_f64(32, 0.0);
-                self.group_f64(13, v.x4);
-                self.group_f64(23, v.y4);
-                self.group_f64(33, 0.0);
+        }
+        DxfEntity::MText(v) => {
+            v.x = scale(v.x);
+            v.y = scale(v.y);
+            v.height = scale(v.height);
+            v.width = scale(v.width);
+        }
+        DxfEntity::Solid(v) => {
+            v.x1 = scale(v.x1);
+            v.y1 = scale(v.y1);
+            v.x2 = scale(v.x2);
+            v.y2 = scale(v.y2);
+            v.x3 = scale(v.x3);
+            v.y3 = scale(v.y3);
+            v.x4 = scale(v.x4);
+            v.y4 = scale(v.y4);
+        }
+        DxfEntity::Face3D(v) => {
+            v.x1 = scale(v.x1);
+            v.y1 = scale(v.y1);
+            v.x2 = scale(v.x2);
+            v.y2 = scale(v.y2);
+            v.x3 = scale(v.x3);
+            v.y3 = scale(v.y3);
+            v.x4 = scale(v.x4);
+            v.y4 = scale(v.y4);
+        }
+        DxfEntity::LwPolyline(v) => {
+            for vertex in &mut v.vertices {
+                vertex.x = scale(vertex.x);
+                vertex.y = scale(vertex.y);
             }
-            DxfEntity::Insert(v) => {
-                self.entity_header("INSERT", &v.layer, v.color, &v.line_type, owner_handle);
-                self.group_str(2, &escape_unicode(&v.block_name));
-                self.group_f64(10, v.x);
-                self.group_f64(20, v.y);
-                self.group_f64(30, 0.0);
-                self.group_f64(41, v.scale_x);
-                self.group_f64(42, v.scale_y);
-                self.group_f64(43, 1.0);
-                self.group_f64(50, v.rotation);
+        }
+        DxfEntity::Insert(v) => {
+            v.x = scale(v.x);
+            v.y = scale(v.y);
+            for attribute in &mut v.attributes {
+                attribute.x = scale(attribute.x);
+                attribute.y = scale(attribute.y);
+                attribute.height = scale(attribute.height);
             }
         }
+        DxfEntity::Image(v) => {
+            v.x = scale(v.x);
+            v.y = scale(v.y);
+            v.u_x = scale(v.u_x);
+            v.u_y = scale(v.u_y);
+            v.v_x = scale(v.v_x);
+            v.v_y = scale(v.v_y);
+        }
     }
+}
 
-    fn entity_header(
-        &mut self,
-        entity_type: &str,
-        layer: &str,
-        color: i32,
-        line_type: &str,
-        owner_handle: Option<&str>,
-    ) {
-        self.group_str(0, entity_type);
-        self.write_handle();
-        if let Some(owner) = owner_handle {
-            self.group_str(330, owner);
+const NON_FINITE_COORD: &str = "NON_FINITE_COORD";
+
+// NaN and infinity have no DXF spelling, so entities carrying either in any
+// field are dropped rather than written. Returns how many were dropped.
+fn drop_non_finite(entities: &mut Vec<DxfEntity>) -> usize {
+    let before = entities.len();
+    entities.retain(|entity| entity_floats(entity).iter().all(|value| value.is_finite()));
+    before - entities.len()
+}
+
+// Every float an entity writes. The patterns name each field so a new one
+// fails to compile until it is listed here.
+pub(crate) fn entity_floats(entity: &DxfEntity) -> Vec<f64> {
+    let mut out = Vec::new();
+    let mut common = |xdata: &[(String, XDataValue)], extrusion: Option<[f64; 3]>| {
+        out.extend(xdata.iter().filter_map(|(_, value)| match value {
+            XDataValue::Real(value) => Some(*value),
+            XDataValue::String(_) | XDataValue::Integer(_) => None,
+        }));
+        out.extend(extrusion.into_iter().flatten());
+    };
+    let values: Vec<f64> = match entity {
+        DxfEntity::Line(DxfLine {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            x1,
+            y1,
+            x2,
+            y2,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*x1, *y1, *x2, *y2]
         }
-        self.group_str(8, &escape_unicode(layer));
-        self.group_i32(62, color);
-        self.group_str(6, line_type);
-    }
+        DxfEntity::Circle(DxfCircle {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            center_x,
+            center_y,
+            radius,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*center_x, *center_y, *radius]
+        }
+        DxfEntity::Arc(DxfArc {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            center_x,
+            center_y,
+            radius,
+            start_angle,
+            end_angle,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*center_x, *center_y, *radius, *start_angle, *end_angle]
+        }
+        DxfEntity::Ellipse(DxfEllipse {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            center_x,
+            center_y,
+            major_axis_x,
+            major_axis_y,
+            minor_ratio,
+            start_param,
+            end_param,
+        }) => {
+            common(xdata, *extrusion);
+            vec![
+                *center_x,
+                *center_y,
+                *major_axis_x,
+                *major_axis_y,
+                *minor_ratio,
+                *start_param,
+                *end_param,
+            ]
+        }
+        DxfEntity::Point(DxfPoint {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            x,
+            y,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*x, *y]
+        }
+        DxfEntity::Text(DxfText {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            x,
+            y,
+            height,
+            rotation,
+            content: _,
+            style: _,
+            width_factor,
+            alignment: _,
+            alignment_point,
+            mirrored: _,
+        }) => {
+            common(xdata, *extrusion);
+            let mut values = vec![*x, *y, *height, *rotation, *width_factor];
+            values.extend(alignment_point.iter().flat_map(|&(ax, ay)| [ax, ay]));
+            values
+        }
+        DxfEntity::MText(DxfMText {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            x,
+            y,
+            height,
+            width,
+            rotation,
+            attachment_point: _,
+            content: _,
+            style: _,
+            tracking,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*x, *y, *height, *width, *rotation, *tracking]
+        }
+        DxfEntity::Solid(DxfSolid {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            x4,
+            y4,
+        }) => {
+            common(xdata, *extrusion);
+            vec![*x1, *y1, *x2, *y2, *x3, *y3, *x4, *y4]
+        }
+        DxfEntity::Face3D(DxfFace3D {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            x1,
+            y1,
+            x2,
+            y2,
+            x3,
+            y3,
+            x4,
+            y4,
+        }) => {
+            common(xdata, None);
+            vec![*x1, *y1, *x2, *y2, *x3, *y3, *x4, *y4]
+        }
+        DxfEntity::LwPolyline(DxfLwPolyline {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            vertices,
+            closed: _,
+        }) => {
+            common(xdata, *extrusion);
+            vertices
+                .iter()
+                .flat_map(|&DxfVertex { x, y, bulge }| [x, y, bulge])
+                .collect()
+        }
+        DxfEntity::Insert(DxfInsert {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            extrusion,
+            block_name: _,
+            x,
+            y,
+            scale_x,
+            scale_y,
+            rotation,
+            attributes,
+        }) => {
+            common(xdata, *extrusion);
+            let mut values = vec![*x, *y, *scale_x, *scale_y, *rotation];
+            for DxfAttribute {
+                tag: _,
+                value: _,
+                x,
+                y,
+                height,
+                rotation,
+            } in attributes
+            {
+                values.extend([*x, *y, *height, *rotation]);
+            }
+            values
+        }
+        DxfEntity::Image(DxfImage {
+            layer: _,
+            color: _,
+            line_type: _,
+            true_color: _,
+            xdata,
+            path: _,
+            x,
+            y,
+            u_x,
+            u_y,
+            v_x,
+            v_y,
+            pixel_width: _,
+            pixel_height: _,
+        }) => {
+            common(xdata, None);
+            vec![*x, *y, *u_x, *u_y, *v_x, *v_y]
+        }
+    };
+    out.extend(values);
+    out
+}
 
-    fn section_start(&mut self, name: &str) {
-        self.group_str(0, "SECTION");
-        self.group_str(2, name);
-    }
+/// Fails with [`JwwError::NonFiniteGroup`] if any real value, after all
+/// conversion scaling, is NaN or infinite.
+pub fn document_to_string(doc: &DxfDocument) -> Result<String, JwwError> {
+    document_to_string_with_options(doc, WriteOptions::default()).map(|(out, _)| out)
+}
 
-    fn section_end(&mut self) {
-        self.group_str(0, "ENDSEC");
-    }
+/// Serialization settings that do not change the drawing itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// First handle to hand out. Give each file of a set its own range so
+    /// their handles do not clash when the files are spliced together.
+    /// Handle 0 is reserved in DXF, so 0 is treated as 1. A seed too close
+    /// to `u32::MAX` to number every object is rejected.
+    pub handle_seed: u32,
+}
 
-    fn group_str(&mut self, code: i32, value: &str) {
-        let _ = write!(self.out, "{code:>3}\n{value}\n");
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self { handle_seed: 1 }
     }
+}
 
-    fn group_i32(&mut self, code: i32, value: i32) {
-        let _ = write!(self.out, "{code:>3}\n{value}\n");
-    }
+/// Like [`document_to_string`], also returning the next unused handle, which
+/// can seed the following file. R12 output has no handles, so the seed comes
+/// back unchanged.
+pub fn document_to_string_with_options(
+    doc: &DxfDocument,
+    options: WriteOptions,
+) -> Result<(String, u32), JwwError> {
+    let mut writer = AsciiDxfWriter::new(doc.version);
+    writer.next_handle = Some(options.handle_seed.max(1));
+    writer.write_document(doc);
+    let (out, next_handle) = writer.finish()?;
+    next_handle
+        .map(|next_handle| (out, next_handle))
+        .ok_or(JwwError::HandleSeedOverflow(options.handle_seed))
+}
 
-    fn group_f64(&mut self, code: i32, value: f64) {
-        let _ = write!(self.out, "{code:>3}\n{value:.12}\n");
-    }
+/// Encodes the DXF in the code page its `$DWGCODEPAGE` declares (CP1252).
+/// Text fields are already `\U+` escaped to ASCII; anything else outside
+/// ASCII, such as a caller-chosen line type name, is encoded rather than
+/// left as UTF-8.
+pub fn document_to_bytes(doc: &DxfDocument) -> Result<Vec<u8>, JwwError> {
+    let text = document_to_string(doc)?;
+    let (bytes, _, _) = WINDOWS_1252.encode(&text);
+    Ok(bytes.into_owned())
+}
 
-    fn write_handle(&mut self) {
-        let handle = self.alloc_handle();
-        self.group_str(5, &handle);
-    }
+pub fn write_document_to_file(doc: &DxfDocument, path: impl AsRef<Path>) -> Result<(), JwwError> {
+    fs::write(path, document_to_bytes(doc)?)?;
+    Ok(())
+}
 
-    fn alloc_handle(&mut self) -> String {
-        let handle = format!("{:X}", self.next_handle);
-        self.next_handle += 1;
-        handle
-    }
+/// Outcome of a conversion dry run: what the DXF would hold and what could
+/// not be carried over.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionReport {
+    pub entity_count: usize,
+    pub block_count: usize,
+    /// See [`DxfDocument::segment_count`].
+    pub generated_segments: usize,
+    pub unsupported_entities: Vec<String>,
+    pub warnings: Vec<String>,
 }
 
-fn collect_line_types(doc: &DxfDocument) -> BTreeSet<String> {
-    let mut out = BTreeSet::<String>::new();
-    for layer in &doc.layers {
-        out.insert(layer.line_type.clone());
-    }
-    for entity in &doc.entities {
-        out.insert(entity_line_type(entity).to_string());
+impl ConversionReport {
+    pub fn is_clean(&self) -> bool {
+        self.unsupported_entities.is_empty() && self.warnings.is_empty()
     }
-    for block in &doc.blocks {
-        for entity in &block.entities {
-            out.insert(entity_line_type(entity).to_string());
-        }
-    }
-    out
 }
 
-fn entity_line_type(entity: &DxfEntity) -> &str {
-    match entity {
-        DxfEntity::Line(v) => &v.line_type,
-        DxfEntity::Circle(v) => &v.line_type,
-        DxfEntity::Arc(v) => &v.line_type,
-        DxfEntity::Ellipse(v) => &v.line_type,
-        DxfEntity::Point(v) => &v.line_type,
-        DxfEntity::Text(v) => &v.line_type,
-        DxfEntity::Solid(v) => &v.line_type,
-        DxfEntity::Insert(v) => &v.line_type,
-    }
+/// Parses and converts `path` like a DXF write would, without serializing
+/// or writing anything.
+pub fn validate_conversion(
+    path: impl AsRef<Path>,
+    options: ConvertOptions,
+) -> Result<ConversionReport, JwwError> {
+    let doc = read_document_from_file(path)?;
+    let dxf = try_convert_document_with_options(&doc, options)?;
+
+    let validation = validate_block_references(&doc);
+    let warnings = validation
+        .unresolved_def_numbers
+        .iter()
+        .map(|number| format!("unresolved block reference: {number}"))
+        .collect();
+    Ok(ConversionReport {
+        entity_count: dxf.entities.len(),
+        block_count: dxf.blocks.len(),
+        generated_segments: dxf.segment_count(),
+        unsupported_entities: dxf.unsupported_entities,
+        warnings,
+    })
 }
 
-fn escape_unicode(value: &str) -> String {
-    let mut out = String::with_capacity(value.len());
-    for ch in value.chars() {
-        match ch {
-            '\r' => {}
-            '\n' => out.push_str("\\P"),
-            '\\' => out.push_str("\\\\"),
-            _ if ch.is_ascii() && !ch.is_ascii_control() => out.push(ch),
-            _ => {
-                let _ = write!(out, "\\U+{:04X}", ch as u32);
-            }
-        }
-    }
-    out
+const MTEXT_CHUNK_LEN: usize = 250;
+/// Smallest layout side, so a group of a single point still gets a
+/// viewport with an area.
+const LAYOUT_MIN_SIZE: f64 = 1.0;
+
+struct ImageDefRecord {
+    handle: String,
+    name: String,
+    path: String,
+    pixel_width: u32,
+    pixel_height: u32,
+    /// `(reactor handle, IMAGE handle)` for each IMAGE of this definition.
+    reactors: Vec<(String, String)>,
 }
 
-fn block_defs_by_number(block_defs: &[BlockDef]) -> HashMap<u32, &BlockDef> {
-    let mut map = HashMap::<u32, &BlockDef>::with_capacity(block_defs.len());
-    for block_def in block_defs {
-        map.insert(block_def.number, block_def);
+fn has_images(doc: &DxfDocument) -> bool {
+    doc.entities
+        .iter()
+        .chain(doc.blocks.iter().flat_map(|block| &block.entities))
+        .any(|entity| matches!(entity, DxfEntity::Image(_)))
+}
+
+fn image_frame(image: &DxfImage) -> DxfLwPolyline {
+    DxfLwPolyline {
+        layer: image.layer.clone(),
+        color: image.color,
+        line_type: image.line_type.clone(),
+        true_color: image.true_color,
+        xdata: image.xdata.clone(),
+        extrusion: None,
+        vertices: image
+            .corners()
+            .into_iter()
+            .map(|(x, y)| DxfVertex { x, y, bulge: 0.0 })
+            .collect(),
+        closed: true,
     }
-    map
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Transform2D {
-    a: f64,
-    b: f64,
-    c: f64,
-    d: f64,
-    tx: f64,
-    ty: f64,
+fn has_xdata(doc: &DxfDocument) -> bool {
+    doc.entities
+        .iter()
+        .chain(doc.blocks.iter().flat_map(|block| &block.entities))
+        .any(|entity| !entity.xdata().is_empty())
 }
 
-impl Transform2D {
-    fn identity() -> Self {
-        Self {
-            a: 1.0,
-            b: 0.0,
-            c: 0.0,
-            d: 1.0,
-            tx: 0.0,
-            ty: 0.0,
-        }
-    }
+// What a LAYOUT object needs, for the "Model" layout as well as the
+// paper-space ones.
+struct LayoutObject<'a> {
+    name: &'a str,
+    handle: &'a str,
+    tab_order: i32,
+    min: Coord2D,
+    max: Coord2D,
+    block_record: &'a str,
+    viewport_handle: Option<&'a str>,
+}
 
-    fn from_insert(block: &Block) -> Self {
-        let cos = block.rotation.cos();
-        let sin = block.rotation.sin();
-        Self {
-            a: cos * block.scale_x,
-            b: sin * block.scale_x,
-            c: -sin * block.scale_y,
-            d: cos * block.scale_y,
-            tx: block.ref_x,
-            ty: block.ref_y,
-        }
-    }
+// Handles of a layout's objects, allocated up front so the BLOCK_RECORD
+// table can point at the LAYOUT before it is written.
+struct LayoutRecord {
+    layout: DxfLayout,
+    min: Coord2D,
+    max: Coord2D,
+    block_record: String,
+    handle: String,
+    viewport_handle: String,
+}
 
-    fn compose(&self, rhs: &Self) -> Self {
+struct AsciiDxfWriter {
+    out: String,
+    version: DxfVersion,
+    xdata_app_id: String,
+    image_defs: Vec<ImageDefRecord>,
+    layouts: Vec<LayoutRecord>,
+    /// The "Model" LAYOUT, written whenever paper-space layouts are.
+    model_layout_handle: Option<String>,
+    layer_handles: BTreeMap<String, String>,
+    /// `None` once the handles have run past `u32::MAX`.
+    next_handle: Option<u32>,
+    /// Group code of the first NaN or infinite real written.
+    non_finite_group: Option<i32>,
+    block_record_order: Vec<String>,
+    block_record_handles: BTreeMap<String, String>,
+}
+
+impl AsciiDxfWriter {
+    fn new(version: DxfVersion) -> Self {
         Self {
-            a: self.a * rhs.a + self.c * rhs.b,
-            b: self.b * rhs.a + self.d * rhs.b,
-            c: self.a * rhs.c + self.c * rhs.d,
-            d: self.b * rhs.c + self.d * rhs.d,
-            tx: self.a * rhs.tx + self.c * rhs.ty + self.tx,
-            ty: self.b * rhs.tx + self.d * rhs.ty + self.ty,
+            out: String::with_capacity(16 * 1024),
+            version,
+            xdata_app_id: String::new(),
+            image_defs: Vec::new(),
+            layouts: Vec::new(),
+            model_layout_handle: None,
+            layer_handles: BTreeMap::new(),
+            next_handle: Some(1),
+            non_finite_group: None,
+            block_record_order: Vec::new(),
+            block_record_handles: BTreeMap::new(),
         }
     }
 
-    fn apply_point(&self, x: f64, y: f64) -> (f64, f64) {
-        (
-            self.a * x + self.c * y + self.tx,
-            self.b * x + self.d * y + self.ty,
-        )
-    }
-
-    fn apply_vector(&self, x: f64, y: f64) -> (f64, f64) {
-        (self.a * x + self.c * y, self.b * x + self.d * y)
+    fn finish(self) -> Result<(String, Option<u32>), JwwError> {
+        match self.non_finite_group {
+            Some(code) => Err(JwwError::NonFiniteGroup(code)),
+            None => Ok((self.out, self.next_handle)),
+        }
     }
 
-    fn average_scale(&self) -> f64 {
-        let sx = (self.a * self.a + self.b * self.b).sqrt();
-        let sy = (self.c * self.c + self.d * self.d).sqrt();
-        (sx + sy) / 2.0
+    fn write_document(&mut self, doc: &DxfDocument) {
+        self.xdata_app_id = doc.xdata_app_id.clone();
+        if !self.is_r12() {
+            self.ensure_block_record_table(doc);
+        }
+        self.write_header(doc);
+        if !self.is_r12() && has_images(doc) {
+            self.write_image_classes();
+        }
+        self.write_tables(doc);
+        self.write_blocks(doc);
+        self.write_entities(doc);
+        if !self.is_r12() {
+            self.write_objects(doc);
+        }
+        self.group_str(0, "EOF");
     }
 
-    fn rotation_deg(&self) -> f64 {
-        self.b.atan2(self.a) * 180.0 / PI
+    fn is_r12(&self) -> bool {
+        self.version == DxfVersion::R12
     }
-}
 
-fn convert_entities_exploded(
-    doc: &JwwDocument,
-    entities: &[Entity],
-    block_name_map: &HashMap<u32, String>,
-    block_defs: &HashMap<u32, &BlockDef>,
-    transform: &Transform2D,
-    expanding_stack: &mut Vec<u32>,
-    unsupported_entities: &mut Vec<String>,
-    options: ConvertOptions,
-) -> Vec<DxfEntity> {
-    let mut out = Vec::<DxfEntity>::new();
-    for entity in entities {
-        match entity {
-            Entity::Block(block) => {
-                if expanding_stack.len() >= options.max_block_nesting {
-                    unsupported_entities.push(format!("BLOCK_DEPTH_LIMIT({})", block.def_number));
-                    continue;
-                }
-                if expanding_stack.contains(&block.def_number) {
-                    unsupported_entities.push(format!("BLOCK_CYCLE({})", block.def_number));
-                    continue;
-                }
+    fn write_header(&mut self, doc: &DxfDocument) {
+        self.section_start("HEADER");
+        self.group_str(9, "$ACADVER");
+        self.group_str(1, self.version.acadver());
+        self.group_str(9, "$DWGCODEPAGE");
+        self.group_str(3, "ANSI_1252");
+        if let Some(guid) = doc.fingerprint_guid.as_deref().filter(|_| !self.is_r12()) {
+            self.group_str(9, "$FINGERPRINTGUID");
+            self.group_str(2, guid);
+        }
+        // Neither variable exists before R2000.
+        if !self.is_r12() {
+            self.group_str(9, "$INSUNITS");
+            self.group_i32(70, doc.units.insunits());
+            self.group_str(9, "$MEASUREMENT");
+            self.group_i32(70, doc.units.measurement());
+        }
+        // `None` writes the origin.
+        let (min, max) = doc
+            .extents()
+            .unwrap_or((Coord2D::new(0.0, 0.0), Coord2D::new(0.0, 0.0)));
+        for (name, corner) in [("$EXTMIN", min), ("$EXTMAX", max)] {
+            self.group_str(9, name);
+            self.group_f64(10, corner.x);
+            self.group_f64(20, corner.y);
+            self.group_f64(30, 0.0);
+        }
+        for (name, corner) in [("$LIMMIN", min), ("$LIMMAX", max)] {
+            self.group_str(9, name);
+            self.group_f64(10, corner.x);
+            self.group_f64(20, corner.y);
+        }
+        self.group_str(9, "$TEXTSTYLE");
+        self.group_str(7, "STANDARD");
+        self.group_str(9, "$CLAYER");
+        self.group_str(8, "0");
+        self.group_str(9, "$CELTYPE");
+        self.group_str(6, "BYLAYER");
+        self.group_str(9, "$CECOLOR");
+        self.group_i32(62, 256);
+        self.section_end();
+    }
 
-                let Some(block_def) = block_defs.get(&block.def_number).copied() else {
-                    unsupported_entities.push(format!("UNRESOLVED_BLOCK({})", block.def_number));
-                    continue;
-                };
+    fn write_tables(&mut self, doc: &DxfDocument) {
+        self.section_start("TABLES");
+        self.write_ltype_table(doc);
+        self.write_layer_table(doc);
+        self.write_style_table();
+        if has_xdata(doc) {
+            self.write_appid_table();
+        }
+        if !self.is_r12() {
+            self.write_block_record_table();
+        }
+        self.section_end();
+    }
 
-                expanding_stack.push(block.def_number);
-                let child_transform = transform.compose(&Transform2D::from_insert(block));
-                let expanded = convert_entities_exploded(
-                    doc,
-                    &block_def.entities,
-                    block_name_map,
-                    block_defs,
-                    &child_transform,
-                    expanding_stack,
-                    unsupported_entities,
-                    options,
-                );
-                expanding_stack.pop();
-                out.extend(expanded);
+    fn write_ltype_table(&mut self, doc: &DxfDocument) {
+        let mut line_types = collect_line_types(doc);
+        if self.is_r12() {
+            line_types.remove("BYLAYER");
+            line_types.remove("BYBLOCK");
+        } else {
+            line_types.insert("BYLAYER".to_string());
+            line_types.insert("BYBLOCK".to_string());
+        }
+        line_types.insert("CONTINUOUS".to_string());
+
+        self.group_str(0, "TABLE");
+        self.group_str(2, "LTYPE");
+        self.write_handle();
+        self.group_i32(70, line_types.len() as i32);
+
+        for name in line_types {
+            let (description, pattern): (&str, &[f64]) = match name.as_str() {
+                "BYLAYER" => ("", &[]),
+                "BYBLOCK" => ("", &[]),
+                "CONTINUOUS" => ("Solid line", &[]),
+                // JWW's standard types keep a 2:1 dash to gap pitch, with the
+                // chain dots a tenth of the long dash.
+                "DASHED" => ("Dashed line", &[0.6, -0.3]),
+                "DASHED2" => ("Dashed line (short)", &[0.3, -0.15]),
+                "DOT" => ("Dotted line", &[0.1, -0.1]),
+                "DASHDOT" => ("Dash dot", &[1.2, -0.2, 0.12, -0.2]),
+                "DASHDOT2" => ("Dash dot (short)", &[0.6, -0.1, 0.06, -0.1]),
+                "DIVIDE" => ("Dash dot dot", &[1.2, -0.2, 0.12, -0.2, 0.12, -0.2]),
+                "DIVIDE2" => ("Dash dot dot (short)", &[0.6, -0.1, 0.06, -0.1, 0.06, -0.1]),
+                "DOT2" => ("Dotted line (fine)", &[0.05, -0.05]),
+                _ => ("", &[]),
+            };
+            let length = pattern.iter().map(|v| v.abs()).sum::<f64>();
+            self.group_str(0, "LTYPE");
+            self.write_handle();
+            self.group_str(2, &name);
+            self.group_i32(70, 0);
+            self.group_str(3, description);
+            self.group_i32(72, 65);
+            self.group_i32(73, pattern.len() as i32);
+            self.group_f64(40, length);
+            for value in pattern {
+                self.group_f64(49, *value);
             }
-            _ => match convert_entity(doc, entity, block_name_map) {
-                Some(converted) => {
-                    for dxf_entity in converted {
-                        out.extend(transform_entity_for_explode(&dxf_entity, transform));
-                    }
-                }
-                None => unsupported_entities.push(entity.entity_type().to_string()),
-            },
         }
+
+        self.group_str(0, "ENDTAB");
     }
-    out
-}
 
-fn transform_entity_for_explode(entity: &DxfEntity, transform: &Transform2D) -> Vec<DxfEntity> {
-    match entity {
-        DxfEntity::Line(v) => {
-            let (x1, y1) = transform.apply_point(v.x1, v.y1);
-            let (x2, y2) = transform.apply_point(v.x2, v.y2);
-            vec![DxfEntity::Line(DxfLine {
-                layer: v.layer.clone(),
-                color: v.color,
-                line_type: v.line_type.clone(),
-                x1,
-                y1,
-                x2,
-                y2,
-            })]
+    fn write_layer_table(&mut self, doc: &DxfDocument) {
+        let mut layers = BTreeMap::<String, DxfLayer>::new();
+        for layer in &doc.layers {
+            layers
+                .entry(layer.name.clone())
+                .or_insert_with(|| layer.clone());
         }
-        DxfEntity::Circle(v) => transform_circle_for_explode(v, transform),
-        DxfEntity::Arc(v) => transform_arc_for_explode(v, transform),
-        DxfEntity::Ellipse(v) => transform_ellipse_for_explode(v, transform),
-        DxfEntity::Point(v) => {
-            let (x, y) = transform.apply_point(v.x, v.y);
-            vec![DxfEntity::Point(DxfPoint {
-                layer: v.layer.clone(),
-                color: v.color,
-                line_type: v.line_type.clone(),
-                x,
-                y,
-            })]
+
+        self.group_str(0, "TABLE");
+        self.group_str(2, "LAYER");
+        self.write_handle();
+        self.group_i32(70, (layers.len() + 1) as i32);
+
+        self.group_str(0, "LAYER");
+        self.write_layer_handle("0");
+        self.group_str(2, "0");
+        self.group_i32(70, 0);
+        self.group_i32(62, 7);
+        self.group_str(6, "CONTINUOUS");
+
+        for layer in layers.values() {
+            let mut flags = 0;
+            if layer.frozen {
+                flags |= 1;
+            }
+            if layer.locked {
+                flags |= 4;
+            }
+            self.group_str(0, "LAYER");
+            self.write_layer_handle(&layer.name);
+            self.group_str(2, &escape_unicode(&layer.name));
+            self.group_i32(70, flags);
+            self.group_i32(62, layer.color);
+            self.group_str(6, &layer.line_type);
         }
-        DxfEntity::Text(v) => {
-            let (x, y) = transform.apply_point(v.x, v.y);
-            let height = (v.height * transform.average_scale().abs()).max(0.1);
-            vec![DxfEntity::Text(DxfText {
-                layer: v.layer.clone(),
-                color: v.color,
-                line_type: v.line_type.clone(),
-                x,
-                y,
-                height,
-                rotation: v.rotation + transform.rotation_deg(),
-                content: v.content.clone(),
-                style: v.style.clone(),
-            })]
+
+        self.group_str(0, "ENDTAB");
+    }
+
+    // Viewports refer to the layers they freeze by handle.
+    fn write_layer_handle(&mut self, name: &str) {
+        if self.is_r12() {
+            return;
         }
-        DxfEntity::Solid(v) => {
-            let (x1, y1) = transform.apply_point(v.x1, v.y1);
-            let (x2, y2) = transform.apply_point(v.x2, v.y2);
-            let (x3, y3) = transform.apply_point(v.x3, v.y3);
-            let (x4, y4) = transform.apply_point(v.x4, v.y4);
-            vec![DxfEntity::Solid(DxfSolid {
-                layer: v.layer.clone(),
-                color: v.color,
-                line_type: v.line_type.clone(),
-                x1,
-                y1,
-                x2,
-                y2,
-                x3,
-                y3,
-                x4,
-                y4,
-            })]
+        let handle = self.alloc_handle();
+        self.group_str(5, &handle);
+        self.layer_handles.insert(name.to_string(), handle);
+    }
+
+    fn write_style_table(&mut self) {
+        self.group_str(0, "TABLE");
+        self.group_str(2, "STYLE");
+        self.write_handle();
+        self.group_i32(70, 1);
+        self.group_str(0, "STYLE");
+        self.write_handle();
+        self.group_str(2, "STANDARD");
+        self.group_i32(70, 0);
+        self.group_f64(40, 0.0);
+        self.group_f64(41, 1.0);
+        self.group_f64(50, 0.0);
+        self.group_i32(71, 0);
+        self.group_f64(42, 2.5);
+        self.group_str(3, "txt");
+        self.group_str(4, "");
+        self.group_str(0, "ENDTAB");
+    }
+
+    fn write_appid_table(&mut self) {
+        let mut names = vec!["ACAD".to_string(), self.xdata_app_id.clone()];
+        names.dedup();
+        self.group_str(0, "TABLE");
+        self.group_str(2, "APPID");
+        self.write_handle();
+        self.group_i32(70, names.len() as i32);
+        for name in names {
+            self.group_str(0, "APPID");
+            self.write_handle();
+            self.group_str(2, &name);
+            self.group_i32(70, 0);
+        }
+        self.group_str(0, "ENDTAB");
+    }
+
+    fn write_block_record_table(&mut self) {
+        self.group_str(0, "TABLE");
+        self.group_str(2, "BLOCK_RECORD");
+        self.write_handle();
+        self.group_i32(70, self.block_record_order.len() as i32);
+
+        let names = self.block_record_order.clone();
+        for name in names {
+            let handle = self
+                .block_record_handles
+                .get(&name)
+                .cloned()
+                .expect("BLOCK_RECORD handle should exist");
+            self.group_str(0, "BLOCK_RECORD");
+            self.group_str(5, &handle);
+            self.group_str(330, "0");
+            self.group_str(100, "AcDbSymbolTableRecord");
+            self.group_str(100, "AcDbBlockTableRecord");
+            self.group_str(2, &escape_unicode(&name));
+            let layout_handle = match name.as_str() {
+                "*Model_Space" => self.model_layout_handle.clone(),
+                _ => self
+                    .layouts
+                    .iter()
+                    .find(|r| r.block_record == name)
+                    .map(|r| r.handle.clone()),
+            };
+            if let Some(handle) = layout_handle {
+                self.group_str(340, &handle);
+            }
+        }
+
+        self.group_str(0, "ENDTAB");
+    }
+
+    fn write_blocks(&mut self, doc: &DxfDocument) {
+        self.section_start("BLOCKS");
+        if !self.is_r12() {
+            let model_owner = self.block_record_handle("*Model_Space").map(str::to_string);
+            self.write_block_definition("*Model_Space", 0.0, 0.0, &[], model_owner.as_deref());
+
+            let paper_owner = self.block_record_handle("*Paper_Space").map(str::to_string);
+            self.write_block_definition("*Paper_Space", 0.0, 0.0, &[], paper_owner.as_deref());
+
+            // The active layout's viewport goes in ENTITIES; the others live
+            // in their own paper-space blocks.
+            for index in 1..self.layouts.len() {
+                let name = self.layouts[index].block_record.clone();
+                let owner = self.block_record_handle(&name).map(str::to_string);
+                self.write_block_begin(&name, 0.0, 0.0, owner.as_deref());
+                self.write_layout_viewport(index, owner.as_deref());
+                self.write_block_end(owner.as_deref());
+            }
+        }
+
+        for block in &doc.blocks {
+            let owner = self.block_record_handle(&block.name).map(str::to_string);
+            self.write_block_definition(
+                &block.name,
+                block.base_x,
+                block.base_y,
+                &block.entities,
+                owner.as_deref(),
+            );
+        }
+        self.section_end();
+    }
+
+    fn write_entities(&mut self, doc: &DxfDocument) {
+        self.section_start("ENTITIES");
+        let owner = self.block_record_handle("*Model_Space").map(str::to_string);
+        for entity in &doc.entities {
+            self.write_entity(entity, owner.as_deref());
+        }
+        if !self.layouts.is_empty() {
+            let paper_owner = self.block_record_handle("*Paper_Space").map(str::to_string);
+            self.write_layout_viewport(0, paper_owner.as_deref());
+        }
+        self.section_end();
+    }
+
+    // One viewport per layout, showing the layout's extents at 1:1 with the
+    // paper-space frame laid over the same rectangle. Layer "0" is never
+    // frozen, as block contents on it take the layer of their insert.
+    fn write_layout_viewport(&mut self, index: usize, owner_handle: Option<&str>) {
+        let record = &self.layouts[index];
+        let handle = record.viewport_handle.clone();
+        let (min, max) = (record.min, record.max);
+        let frozen = self
+            .layer_handles
+            .iter()
+            .filter(|(name, _)| *name != "0" && !record.layout.layers.contains(name))
+            .map(|(_, handle)| handle.clone())
+            .collect::<Vec<_>>();
+        let center = ((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+        let width = (max.x - min.x).max(LAYOUT_MIN_SIZE);
+        let height = (max.y - min.y).max(LAYOUT_MIN_SIZE);
+
+        self.group_str(0, "VIEWPORT");
+        self.group_str(5, &handle);
+        if let Some(owner) = owner_handle {
+            self.group_str(330, owner);
+        }
+        self.group_i32(67, 1);
+        self.group_str(8, "0");
+        self.group_f64(10, center.0);
+        self.group_f64(20, center.1);
+        self.group_f64(30, 0.0);
+        self.group_f64(40, width);
+        self.group_f64(41, height);
+        self.group_i32(68, 1);
+        self.group_i32(69, 2);
+        self.group_f64(12, center.0);
+        self.group_f64(22, center.1);
+        self.group_f64(16, 0.0);
+        self.group_f64(26, 0.0);
+        self.group_f64(36, 1.0);
+        self.group_f64(45, height);
+        self.group_f64(50, 0.0);
+        self.group_f64(51, 0.0);
+        self.group_i32(72, 1000);
+        for layer in &frozen {
+            self.group_str(341, layer);
+        }
+        self.group_i32(90, 0);
+    }
+
+    fn write_objects(&mut self, doc: &DxfDocument) {
+        self.section_start("OBJECTS");
+        let root_handle = self.alloc_handle();
+        let image_dict_handle = (!self.image_defs.is_empty()).then(|| self.alloc_handle());
+        let layout_dict_handle = (!self.layouts.is_empty()).then(|| self.alloc_handle());
+        self.group_str(0, "DICTIONARY");
+        self.group_str(5, &root_handle);
+        self.group_str(330, "0");
+        self.group_str(100, "AcDbDictionary");
+        self.group_i32(281, 1);
+        if let Some(handle) = &image_dict_handle {
+            self.group_str(3, "ACAD_IMAGE_DICT");
+            self.group_str(350, handle);
+        }
+        if let Some(handle) = &layout_dict_handle {
+            self.group_str(3, "ACAD_LAYOUT");
+            self.group_str(350, handle);
+        }
+        if let Some(handle) = &image_dict_handle {
+            self.write_image_objects(&root_handle, handle);
+        }
+        if let Some(handle) = &layout_dict_handle {
+            self.write_layout_objects(doc, &root_handle, handle);
+        }
+        self.section_end();
+    }
+
+    // AutoCAD expects the "Model" layout of `*Model_Space` in the layout
+    // dictionary next to the paper-space ones, and repairs the file without it.
+    fn write_layout_objects(
+        &mut self,
+        doc: &DxfDocument,
+        root_handle: &str,
+        layout_dict_handle: &str,
+    ) {
+        let layouts = std::mem::take(&mut self.layouts);
+        let model_handle = self
+            .model_layout_handle
+            .clone()
+            .expect("Model LAYOUT handle should exist");
+        self.group_str(0, "DICTIONARY");
+        self.group_str(5, layout_dict_handle);
+        self.group_str(330, root_handle);
+        self.group_str(100, "AcDbDictionary");
+        self.group_i32(281, 1);
+        self.group_str(3, "Model");
+        self.group_str(350, &model_handle);
+        for record in &layouts {
+            self.group_str(3, &escape_unicode(&record.layout.name));
+            self.group_str(350, &record.handle);
+        }
+
+        let origin = Coord2D::new(0.0, 0.0);
+        let (min, max) = doc.extents().unwrap_or((origin, origin));
+        let model = LayoutObject {
+            name: "Model",
+            handle: &model_handle,
+            tab_order: 0,
+            min,
+            max,
+            block_record: "*Model_Space",
+            viewport_handle: None,
+        };
+        self.write_layout_object(&model, layout_dict_handle);
+        for (tab_order, record) in (1..).zip(&layouts) {
+            let layout = LayoutObject {
+                name: &record.layout.name,
+                handle: &record.handle,
+                tab_order,
+                min: record.min,
+                max: record.max,
+                block_record: &record.block_record,
+                viewport_handle: Some(&record.viewport_handle),
+            };
+            self.write_layout_object(&layout, layout_dict_handle);
+        }
+    }
+
+    fn write_layout_object(&mut self, layout: &LayoutObject<'_>, layout_dict_handle: &str) {
+        let (min, max) = (layout.min, layout.max);
+        let width = (max.x - min.x).max(LAYOUT_MIN_SIZE);
+        let height = (max.y - min.y).max(LAYOUT_MIN_SIZE);
+        let block_record = self
+            .block_record_handle(layout.block_record)
+            .expect("layout BLOCK_RECORD handle should exist")
+            .to_string();
+        self.group_str(0, "LAYOUT");
+        self.group_str(5, layout.handle);
+        self.group_str(330, layout_dict_handle);
+        self.group_str(100, "AcDbPlotSettings");
+        self.group_str(1, "");
+        self.group_str(2, "none_device");
+        self.group_str(4, "");
+        self.group_str(6, "");
+        self.group_f64(44, width);
+        self.group_f64(45, height);
+        self.group_f64(142, 1.0);
+        self.group_f64(143, 1.0);
+        self.group_i32(70, 0);
+        self.group_i32(72, 1);
+        self.group_i32(73, 0);
+        self.group_i32(74, 5);
+        self.group_str(100, "AcDbLayout");
+        self.group_str(1, &escape_unicode(layout.name));
+        self.group_i32(70, 1);
+        self.group_i32(71, layout.tab_order);
+        self.group_f64(10, min.x);
+        self.group_f64(20, min.y);
+        self.group_f64(11, max.x);
+        self.group_f64(21, max.y);
+        self.group_f64(12, 0.0);
+        self.group_f64(22, 0.0);
+        self.group_f64(32, 0.0);
+        self.group_f64(14, min.x);
+        self.group_f64(24, min.y);
+        self.group_f64(34, 0.0);
+        self.group_f64(15, max.x);
+        self.group_f64(25, max.y);
+        self.group_f64(35, 0.0);
+        self.group_f64(146, 0.0);
+        self.group_i32(76, 1);
+        self.group_str(330, &block_record);
+        if let Some(viewport) = layout.viewport_handle {
+            self.group_str(331, viewport);
+        }
+    }
+
+    fn write_image_classes(&mut self) {
+        self.section_start("CLASSES");
+        let classes = [
+            ("IMAGE", "AcDbRasterImage", 127, 1),
+            ("IMAGEDEF", "AcDbRasterImageDef", 0, 0),
+            ("IMAGEDEF_REACTOR", "AcDbRasterImageDefReactor", 1, 0),
+        ];
+        for (name, class_name, proxy_flags, is_entity) in classes {
+            self.group_str(0, "CLASS");
+            self.group_str(1, name);
+            self.group_str(2, class_name);
+            self.group_str(3, "ISM");
+            self.group_i32(90, proxy_flags);
+            self.group_i32(280, 0);
+            self.group_i32(281, is_entity);
+        }
+        self.section_end();
+    }
+
+    // Each IMAGEDEF lists the reactors of the IMAGEs that use it, and each
+    // reactor points back at its IMAGE.
+    fn write_image_objects(&mut self, root_handle: &str, image_dict_handle: &str) {
+        let image_defs = std::mem::take(&mut self.image_defs);
+        self.group_str(0, "DICTIONARY");
+        self.group_str(5, image_dict_handle);
+        self.group_str(330, root_handle);
+        self.group_str(100, "AcDbDictionary");
+        self.group_i32(281, 1);
+        for def in &image_defs {
+            self.group_str(3, &escape_unicode(&def.name));
+            self.group_str(350, &def.handle);
+        }
+        for def in &image_defs {
+            self.group_str(0, "IMAGEDEF");
+            self.group_str(5, &def.handle);
+            self.group_str(102, "{ACAD_REACTORS");
+            self.group_str(330, image_dict_handle);
+            for (reactor, _) in &def.reactors {
+                self.group_str(330, reactor);
+            }
+            self.group_str(102, "}");
+            self.group_str(330, image_dict_handle);
+            self.group_str(100, "AcDbRasterImageDef");
+            self.group_i32(90, 0);
+            self.group_str(1, &escape_path(&def.path));
+            self.group_f64(10, f64::from(def.pixel_width));
+            self.group_f64(20, f64::from(def.pixel_height));
+            self.group_f64(11, 1.0);
+            self.group_f64(21, 1.0);
+            self.group_i32(280, 1);
+            self.group_i32(281, 0);
+        }
+        for def in &image_defs {
+            for (reactor, image) in &def.reactors {
+                self.group_str(0, "IMAGEDEF_REACTOR");
+                self.group_str(5, reactor);
+                self.group_str(330, image);
+                self.group_str(100, "AcDbRasterImageDefReactor");
+                self.group_i32(90, 2);
+                self.group_str(330, image);
+            }
+        }
+    }
+
+    // One IMAGEDEF per distinct path, named after the file stem.
+    fn image_def_index(&mut self, image: &DxfImage) -> usize {
+        if let Some(index) = self
+            .image_defs
+            .iter()
+            .position(|def| def.path == image.path)
+        {
+            return index;
+        }
+        let stem = image
+            .path
+            .rsplit(['/', '\\'])
+            .next()
+            .map(|file| file.rsplit_once('.').map_or(file, |(stem, _)| stem))
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or("IMAGE");
+        let mut name = stem.to_string();
+        let mut suffix = 1;
+        while self.image_defs.iter().any(|def| def.name == name) {
+            suffix += 1;
+            name = format!("{stem}_{suffix}");
+        }
+        let handle = self.alloc_handle();
+        self.image_defs.push(ImageDefRecord {
+            handle,
+            name,
+            path: image.path.clone(),
+            pixel_width: image.pixel_width,
+            pixel_height: image.pixel_height,
+            reactors: Vec::new(),
+        });
+        self.image_defs.len() - 1
+    }
+
+    fn write_block_definition(
+        &mut self,
+        name: &str,
+        base_x: f64,
+        base_y: f64,
+        entities: &[DxfEntity],
+        owner_handle: Option<&str>,
+    ) {
+        self.write_block_begin(name, base_x, base_y, owner_handle);
+        for entity in entities {
+            self.write_entity(entity, owner_handle);
+        }
+        self.write_block_end(owner_handle);
+    }
+
+    fn write_block_begin(
+        &mut self,
+        name: &str,
+        base_x: f64,
+        base_y: f64,
+        owner_handle: Option<&str>,
+    ) {
+        let block_name = escape_unicode(name);
+        self.group_str(0, "BLOCK");
+        self.write_handle();
+        if let Some(owner) = owner_handle {
+            self.group_str(330, owner);
+        }
+        self.subclass_marker("AcDbEntity");
+        self.group_str(8, "0");
+        self.subclass_marker("AcDbBlockBegin");
+        self.group_str(2, &block_name);
+        self.group_i32(70, i32::from(name.starts_with("*U")));
+        self.group_f64(10, base_x);
+        self.group_f64(20, base_y);
+        self.group_f64(30, 0.0);
+        self.group_str(3, &block_name);
+        self.group_str(1, "");
+    }
+
+    fn write_block_end(&mut self, owner_handle: Option<&str>) {
+        self.group_str(0, "ENDBLK");
+        self.write_handle();
+        if let Some(owner) = owner_handle {
+            self.group_str(330, owner);
+        }
+        self.subclass_marker("AcDbEntity");
+        self.group_str(8, "0");
+        self.subclass_marker("AcDbBlockEnd");
+    }
+
+    fn ensure_block_record_table(&mut self, doc: &DxfDocument) {
+        if !self.block_record_order.is_empty() {
+            return;
+        }
+        self.register_block_record("*Model_Space");
+        self.register_block_record("*Paper_Space");
+        if !doc.layouts.is_empty() {
+            self.model_layout_handle = Some(self.alloc_handle());
+        }
+        // The first layout owns *Paper_Space; the rest number from zero.
+        let origin = Coord2D::new(0.0, 0.0);
+        for (index, layout) in doc.layouts.iter().enumerate() {
+            let block_record = match index {
+                0 => "*Paper_Space".to_string(),
+                _ => format!("*Paper_Space{}", index - 1),
+            };
+            self.register_block_record(&block_record);
+            let handle = self.alloc_handle();
+            let viewport_handle = self.alloc_handle();
+            let (min, max) = doc.layout_extents(layout).unwrap_or((origin, origin));
+            self.layouts.push(LayoutRecord {
+                layout: layout.clone(),
+                min,
+                max,
+                block_record,
+                handle,
+                viewport_handle,
+            });
+        }
+        for block in &doc.blocks {
+            self.register_block_record(&block.name);
+        }
+    }
+
+    fn register_block_record(&mut self, name: &str) {
+        if self.block_record_handles.contains_key(name) {
+            return;
+        }
+        let handle = self.alloc_handle();
+        self.block_record_order.push(name.to_string());
+        self.block_record_handles.insert(name.to_string(), handle);
+    }
+
+    fn block_record_handle(&self, name: &str) -> Option<&str> {
+        self.block_record_handles.get(name).map(String::as_str)
+    }
+
+    fn write_entity(&mut self, entity: &DxfEntity, owner_handle: Option<&str>) {
+        match entity {
+            DxfEntity::Line(v) => {
+                self.entity_header("LINE", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x1);
+                self.group_f64(20, v.y1);
+                self.group_f64(30, 0.0);
+                self.group_f64(11, v.x2);
+                self.group_f64(21, v.y2);
+                self.group_f64(31, 0.0);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Circle(v) => {
+                self.entity_header("CIRCLE", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.center_x);
+                self.group_f64(20, v.center_y);
+                self.group_f64(30, 0.0);
+                self.group_f64(40, v.radius);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Arc(v) => {
+                self.entity_header("ARC", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.center_x);
+                self.group_f64(20, v.center_y);
+                self.group_f64(30, 0.0);
+                self.group_f64(40, v.radius);
+                self.group_f64(50, v.start_angle);
+                self.group_f64(51, v.end_angle);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Ellipse(v) if self.is_r12() => {
+                self.write_polyline_r12(&ellipse_to_polyline(v), owner_handle);
+            }
+            DxfEntity::Ellipse(v) => {
+                self.entity_header("ELLIPSE", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.center_x);
+                self.group_f64(20, v.center_y);
+                self.group_f64(30, 0.0);
+                self.group_f64(11, v.major_axis_x);
+                self.group_f64(21, v.major_axis_y);
+                self.group_f64(31, 0.0);
+                self.group_f64(40, v.minor_ratio);
+                self.group_f64(41, v.start_param);
+                self.group_f64(42, v.end_param);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Point(v) => {
+                self.entity_header("POINT", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x);
+                self.group_f64(20, v.y);
+                self.group_f64(30, 0.0);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Text(v) => {
+                self.entity_header("TEXT", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x);
+                self.group_f64(20, v.y);
+                self.group_f64(30, 0.0);
+                self.group_f64(40, v.height);
+                self.group_str(1, &escape_unicode(&v.content));
+                self.group_f64(50, v.rotation);
+                if v.width_factor != 1.0 {
+                    self.group_f64(41, v.width_factor);
+                }
+                self.group_str(7, &escape_unicode(&v.style));
+                if v.mirrored {
+                    self.group_i32(71, 2);
+                }
+                // Justified text is positioned by the second alignment point.
+                if !v.alignment.is_default() {
+                    let (align_x, align_y) = v.alignment_point.unwrap_or((v.x, v.y));
+                    self.group_i32(72, v.alignment.horizontal.group_code_value());
+                    self.group_f64(11, align_x);
+                    self.group_f64(21, align_y);
+                    self.group_f64(31, 0.0);
+                    self.group_i32(73, v.alignment.vertical.group_code_value());
+                }
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::MText(v) if self.is_r12() => {
+                for line in mtext_to_text_lines(v) {
+                    self.write_entity(&DxfEntity::Text(line), owner_handle);
+                }
+            }
+            DxfEntity::MText(v) => {
+                self.entity_header("MTEXT", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x);
+                self.group_f64(20, v.y);
+                self.group_f64(30, 0.0);
+                self.group_f64(40, v.height);
+                self.group_f64(41, v.width);
+                self.group_i32(71, v.attachment_point);
+                self.group_i32(72, 1);
+                let content = escape_unicode(&v.content);
+                if v.tracking == 1.0 {
+                    self.write_mtext_content(&content);
+                } else {
+                    self.write_mtext_content(&format!("\\T{};{content}", v.tracking));
+                }
+                self.group_str(7, &escape_unicode(&v.style));
+                self.group_f64(50, v.rotation);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Solid(v) => {
+                self.entity_header("SOLID", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x1);
+                self.group_f64(20, v.y1);
+                self.group_f64(30, 0.0);
+                self.group_f64(11, v.x2);
+                self.group_f64(21, v.y2);
+                self.group_f64(31, 0.0);
+                self.group_f64(12, v.x3);
+                self.group_f64(22, v.y3);
+                self.group_f64(32, 0.0);
+                self.group_f64(13, v.x4);
+                self.group_f64(23, v.y4);
+                self.group_f64(33, 0.0);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::Face3D(v) => {
+                self.entity_header("3DFACE", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_f64(10, v.x1);
+                self.group_f64(20, v.y1);
+                self.group_f64(30, 0.0);
+                self.group_f64(11, v.x2);
+                self.group_f64(21, v.y2);
+                self.group_f64(31, 0.0);
+                self.group_f64(12, v.x3);
+                self.group_f64(22, v.y3);
+                self.group_f64(32, 0.0);
+                self.group_f64(13, v.x4);
+                self.group_f64(23, v.y4);
+                self.group_f64(33, 0.0);
+                self.group_xdata(&v.xdata);
+            }
+            DxfEntity::LwPolyline(v) if self.is_r12() => {
+                self.write_polyline_r12(v, owner_handle);
+            }
+            DxfEntity::LwPolyline(v) => {
+                self.entity_header("LWPOLYLINE", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                self.group_i32(90, v.vertices.len() as i32);
+                self.group_i32(70, i32::from(v.closed));
+                for vertex in &v.vertices {
+                    self.group_f64(10, vertex.x);
+                    self.group_f64(20, vertex.y);
+                    if vertex.bulge != 0.0 {
+                        self.group_f64(42, vertex.bulge);
+                    }
+                }
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+            }
+            // R12 has no IMAGE; the frame keeps the placement visible.
+            DxfEntity::Image(v) if self.is_r12() => {
+                self.write_polyline_r12(&image_frame(v), owner_handle);
+            }
+            DxfEntity::Image(v) => {
+                let handle =
+                    self.entity_header("IMAGE", &v.layer, v.color, &v.line_type, owner_handle);
+                let def_index = self.image_def_index(v);
+                let def_handle = self.image_defs[def_index].handle.clone();
+                let reactor_handle = self.alloc_handle();
+                let (width, height) = (f64::from(v.pixel_width), f64::from(v.pixel_height));
+                self.group_true_color(v.true_color);
+                self.group_i32(90, 0);
+                self.group_f64(10, v.x);
+                self.group_f64(20, v.y);
+                self.group_f64(30, 0.0);
+                self.group_f64(11, v.u_x);
+                self.group_f64(21, v.u_y);
+                self.group_f64(31, 0.0);
+                self.group_f64(12, v.v_x);
+                self.group_f64(22, v.v_y);
+                self.group_f64(32, 0.0);
+                self.group_f64(13, width);
+                self.group_f64(23, height);
+                self.group_str(340, &def_handle);
+                // Show the image, also when it is not aligned with the screen.
+                self.group_i32(70, 3);
+                self.group_i32(280, 0);
+                self.group_i32(281, 50);
+                self.group_i32(282, 50);
+                self.group_i32(283, 0);
+                self.group_str(360, &reactor_handle);
+                // Rectangular clip boundary around the whole image, in pixel
+                // coordinates.
+                self.group_i32(71, 1);
+                self.group_i32(91, 2);
+                self.group_f64(14, -0.5);
+                self.group_f64(24, -0.5);
+                self.group_f64(14, width - 0.5);
+                self.group_f64(24, height - 0.5);
+                self.group_xdata(&v.xdata);
+                self.image_defs[def_index]
+                    .reactors
+                    .push((reactor_handle, handle));
+            }
+            DxfEntity::Insert(v) => {
+                let handle =
+                    self.entity_header("INSERT", &v.layer, v.color, &v.line_type, owner_handle);
+                self.group_true_color(v.true_color);
+                if !v.attributes.is_empty() {
+                    self.group_i32(66, 1);
+                }
+                self.group_str(2, &escape_unicode(&v.block_name));
+                self.group_f64(10, v.x);
+                self.group_f64(20, v.y);
+                self.group_f64(30, 0.0);
+                self.group_f64(41, v.scale_x);
+                self.group_f64(42, v.scale_y);
+                self.group_f64(43, 1.0);
+                self.group_f64(50, v.rotation);
+                self.group_extrusion(v.extrusion);
+                self.group_xdata(&v.xdata);
+                if !v.attributes.is_empty() {
+                    self.write_insert_attributes(v, &handle);
+                }
+            }
+        }
+    }
+
+    fn write_insert_attributes(&mut self, insert: &DxfInsert, insert_handle: &str) {
+        for attribute in &insert.attributes {
+            self.entity_header(
+                "ATTRIB",
+                &insert.layer,
+                insert.color,
+                &insert.line_type,
+                Some(insert_handle),
+            );
+            self.group_f64(10, attribute.x);
+            self.group_f64(20, attribute.y);
+            self.group_f64(30, 0.0);
+            self.group_f64(40, attribute.height);
+            self.group_str(1, &escape_unicode(&attribute.value));
+            self.group_str(2, &escape_unicode(&attribute.tag));
+            self.group_i32(70, 0);
+            self.group_f64(50, attribute.rotation);
+            self.group_str(7, "STANDARD");
+        }
+        self.entity_header(
+            "SEQEND",
+            &insert.layer,
+            insert.color,
+            &insert.line_type,
+            Some(insert_handle),
+        );
+    }
+
+    fn write_polyline_r12(&mut self, polyline: &DxfLwPolyline, owner_handle: Option<&str>) {
+        let DxfLwPolyline {
+            layer,
+            color,
+            line_type,
+            ..
+        } = polyline;
+        self.entity_header("POLYLINE", layer, *color, line_type, owner_handle);
+        self.group_i32(66, 1);
+        self.group_f64(10, 0.0);
+        self.group_f64(20, 0.0);
+        self.group_f64(30, 0.0);
+        self.group_i32(70, i32::from(polyline.closed));
+        self.group_extrusion(polyline.extrusion);
+        self.group_xdata(&polyline.xdata);
+        for vertex in &polyline.vertices {
+            self.entity_header("VERTEX", layer, *color, line_type, owner_handle);
+            self.group_f64(10, vertex.x);
+            self.group_f64(20, vertex.y);
+            self.group_f64(30, 0.0);
+            if vertex.bulge != 0.0 {
+                self.group_f64(42, vertex.bulge);
+            }
+            self.group_i32(70, 0);
+        }
+        self.entity_header("SEQEND", layer, *color, line_type, owner_handle);
+    }
+
+    // MTEXT content longer than 250 characters is split into leading group 3
+    // chunks followed by the final group 1 chunk.
+    fn write_mtext_content(&mut self, content: &str) {
+        let chars = content.chars().collect::<Vec<_>>();
+        let mut chunks = chars.chunks(MTEXT_CHUNK_LEN).peekable();
+        if chunks.peek().is_none() {
+            self.group_str(1, "");
+            return;
+        }
+        while let Some(chunk) = chunks.next() {
+            let text = chunk.iter().collect::<String>();
+            let code = if chunks.peek().is_some() { 3 } else { 1 };
+            self.group_str(code, &text);
+        }
+    }
+
+    fn entity_header(
+        &mut self,
+        entity_type: &str,
+        layer: &str,
+        color: i32,
+        line_type: &str,
+        owner_handle: Option<&str>,
+    ) -> String {
+        self.group_str(0, entity_type);
+        let mut handle = String::new();
+        if !self.is_r12() {
+            handle = self.alloc_handle();
+            self.group_str(5, &handle);
+            if let Some(owner) = owner_handle {
+                self.group_str(330, owner);
+            }
+        }
+        self.group_str(8, &escape_unicode(layer));
+        self.group_i32(62, color);
+        self.group_str(6, line_type);
+        handle
+    }
+
+    fn section_start(&mut self, name: &str) {
+        self.group_str(0, "SECTION");
+        self.group_str(2, name);
+    }
+
+    fn section_end(&mut self) {
+        self.group_str(0, "ENDSEC");
+    }
+
+    fn group_str(&mut self, code: i32, value: &str) {
+        let _ = write!(self.out, "{code:>3}\n{value}\n");
+    }
+
+    fn group_i32(&mut self, code: i32, value: i32) {
+        let _ = write!(self.out, "{code:>3}\n{value}\n");
+    }
+
+    // NaN and infinity have no DXF spelling; the first one turns the whole
+    // write into an error.
+    fn group_f64(&mut self, code: i32, value: f64) {
+        if !value.is_finite() {
+            self.non_finite_group.get_or_insert(code);
+        }
+        let _ = write!(self.out, "{code:>3}\n{value:.12}\n");
+    }
+
+    // Group 420 is R2004 and later, but older readers skip it; R12 output
+    // keeps to ACI colors.
+    fn group_true_color(&mut self, true_color: Option<u32>) {
+        if let Some(rgb) = true_color.filter(|_| !self.is_r12()) {
+            self.group_i32(420, (rgb & 0xFF_FFFF) as i32);
+        }
+    }
+
+    // Keys and string values share group 1000; the value's group code gives
+    // its type.
+    fn group_xdata(&mut self, xdata: &[(String, XDataValue)]) {
+        if xdata.is_empty() {
+            return;
+        }
+        let app_id = self.xdata_app_id.clone();
+        self.group_str(1001, &app_id);
+        for (key, value) in xdata {
+            self.group_str(1000, &escape_unicode(key));
+            match value {
+                XDataValue::String(value) => self.group_str(1000, &escape_unicode(value)),
+                XDataValue::Real(value) => self.group_f64(1040, *value),
+                XDataValue::Integer(value) => self.group_i32(1071, *value),
+            }
+        }
+    }
+
+    fn group_extrusion(&mut self, extrusion: Option<[f64; 3]>) {
+        let Some([x, y, z]) = extrusion else {
+            return;
+        };
+        if [x, y, z] != [0.0, 0.0, 1.0] {
+            self.group_f64(210, x);
+            self.group_f64(220, y);
+            self.group_f64(230, z);
+        }
+    }
+
+    fn write_handle(&mut self) {
+        if self.is_r12() {
+            return;
+        }
+        let handle = self.alloc_handle();
+        self.group_str(5, &handle);
+    }
+
+    fn subclass_marker(&mut self, name: &str) {
+        if !self.is_r12() {
+            self.group_str(100, name);
+        }
+    }
+
+    fn alloc_handle(&mut self) -> String {
+        let handle = self.next_handle.unwrap_or(u32::MAX);
+        self.next_handle = self.next_handle.and_then(|next| next.checked_add(1));
+        format!("{handle:X}")
+    }
+}
+
+/// Largest gap, in drawing units, between a curve and the chords that
+/// replace it under [`ConvertOptions::arcs_as_polylines`].
+pub const ARC_CHORD_TOLERANCE: f64 = 0.01;
+
+fn ellipse_to_polyline(ellipse: &DxfEllipse) -> DxfLwPolyline {
+    flatten_ellipse(ellipse, ARC_CHORD_TOLERANCE)
+}
+
+// Circles and arcs are flattened as round ellipses.
+fn curve_to_polyline(entity: &DxfEntity) -> Option<DxfLwPolyline> {
+    let ellipse = match entity {
+        DxfEntity::Circle(v) => DxfEllipse {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: v.true_color,
+            xdata: v.xdata.clone(),
+            extrusion: v.extrusion,
+            center_x: v.center_x,
+            center_y: v.center_y,
+            major_axis_x: v.radius,
+            major_axis_y: 0.0,
+            minor_ratio: 1.0,
+            start_param: 0.0,
+            end_param: 2.0 * PI,
+        },
+        DxfEntity::Arc(v) => DxfEllipse {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: v.true_color,
+            xdata: v.xdata.clone(),
+            extrusion: v.extrusion,
+            center_x: v.center_x,
+            center_y: v.center_y,
+            major_axis_x: v.radius,
+            major_axis_y: 0.0,
+            minor_ratio: 1.0,
+            start_param: v.start_angle.to_radians(),
+            end_param: v.end_angle.to_radians(),
+        },
+        DxfEntity::Ellipse(v) => v.clone(),
+        _ => return None,
+    };
+    Some(flatten_ellipse(&ellipse, ARC_CHORD_TOLERANCE))
+}
+
+// Replaces the curve with chords at equal parameter steps. Seen through the
+// affine map from the unit circle, a chord over a step `dt` strays at most
+// `a * (1 - cos(dt / 2))` from the ellipse with major radius `a`, the error of
+// a circular arc of that radius, so the step comes from the same formula.
+fn flatten_ellipse(ellipse: &DxfEllipse, tolerance: f64) -> DxfLwPolyline {
+    let mut span = ellipse.end_param - ellipse.start_param;
+    if span <= 0.0 {
+        span += 2.0 * PI;
+    }
+    let closed = (span - 2.0 * PI).abs() <= 1e-9;
+    let (major_x, major_y) = (ellipse.major_axis_x, ellipse.major_axis_y);
+    let radius = major_x.hypot(major_y);
+    let max_step = if radius > tolerance {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        PI / 2.0
+    };
+    let segments = ((span / max_step).ceil() as usize).max(4);
+    let (minor_x, minor_y) = (
+        -major_y * ellipse.minor_ratio,
+        major_x * ellipse.minor_ratio,
+    );
+    let point_count = if closed { segments } else { segments + 1 };
+    let vertices = (0..point_count)
+        .map(|i| {
+            let t = ellipse.start_param + span * i as f64 / segments as f64;
+            DxfVertex {
+                x: ellipse.center_x + major_x * t.cos() + minor_x * t.sin(),
+                y: ellipse.center_y + major_y * t.cos() + minor_y * t.sin(),
+                bulge: 0.0,
+            }
+        })
+        .collect();
+    DxfLwPolyline {
+        layer: ellipse.layer.clone(),
+        color: ellipse.color,
+        line_type: ellipse.line_type.clone(),
+        true_color: ellipse.true_color,
+        xdata: ellipse.xdata.clone(),
+        extrusion: ellipse.extrusion,
+        vertices,
+        closed,
+    }
+}
+
+// MTEXT's default line spacing is 5/3 of the text height.
+const MTEXT_LINE_SPACING: f64 = 5.0 / 3.0;
+
+fn mtext_to_text_lines(mtext: &DxfMText) -> Vec<DxfText> {
+    let lines = mtext.content.lines().collect::<Vec<_>>();
+    let spacing = mtext.height * MTEXT_LINE_SPACING;
+    let span = (lines.len().max(1) - 1) as f64 * spacing;
+    let attachment = (mtext.attachment_point.clamp(1, 9) - 1) as usize;
+    // Offset of the first baseline from the insertion point, measured along
+    // the text's up direction.
+    let first_baseline = match attachment / 3 {
+        0 => -mtext.height,
+        1 => (span + mtext.height) / 2.0 - mtext.height,
+        _ => span,
+    };
+    let horizontal = match attachment % 3 {
+        0 => HorizontalAlignment::Left,
+        1 => HorizontalAlignment::Center,
+        _ => HorizontalAlignment::Right,
+    };
+    let alignment = TextAlignment::new(horizontal, VerticalAlignment::Baseline);
+    let angle = mtext.rotation * PI / 180.0;
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let up = first_baseline - i as f64 * spacing;
+            let x = mtext.x - angle.sin() * up;
+            let y = mtext.y + angle.cos() * up;
+            DxfText {
+                layer: mtext.layer.clone(),
+                color: mtext.color,
+                line_type: mtext.line_type.clone(),
+                true_color: mtext.true_color,
+                xdata: mtext.xdata.clone(),
+                extrusion: mtext.extrusion,
+                x,
+                y,
+                height: mtext.height,
+                rotation: mtext.rotation,
+                content: line.to_string(),
+                style: mtext.style.clone(),
+                width_factor: 1.0,
+                alignment,
+                alignment_point: (!alignment.is_default()).then_some((x, y)),
+                mirrored: false,
+            }
+        })
+        .collect()
+}
+
+fn collect_line_types(doc: &DxfDocument) -> BTreeSet<String> {
+    let mut out = BTreeSet::<String>::new();
+    for layer in &doc.layers {
+        out.insert(layer.line_type.clone());
+    }
+    for entity in &doc.entities {
+        out.insert(entity_line_type(entity).to_string());
+    }
+    for block in &doc.blocks {
+        for entity in &block.entities {
+            out.insert(entity_line_type(entity).to_string());
+        }
+    }
+    out
+}
+
+fn entity_line_type(entity: &DxfEntity) -> &str {
+    match entity {
+        DxfEntity::Line(v) => &v.line_type,
+        DxfEntity::Circle(v) => &v.line_type,
+        DxfEntity::Arc(v) => &v.line_type,
+        DxfEntity::Ellipse(v) => &v.line_type,
+        DxfEntity::Point(v) => &v.line_type,
+        DxfEntity::Text(v) => &v.line_type,
+        DxfEntity::MText(v) => &v.line_type,
+        DxfEntity::Solid(v) => &v.line_type,
+        DxfEntity::Face3D(v) => &v.line_type,
+        DxfEntity::LwPolyline(v) => &v.line_type,
+        DxfEntity::Insert(v) => &v.line_type,
+        DxfEntity::Image(v) => &v.line_type,
+    }
+}
+
+fn escape_unicode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\r' => {}
+            '\n' => out.push_str("\\P"),
+            '\\' => out.push_str("\\\\"),
+            _ if ch.is_ascii() && !ch.is_ascii_control() => out.push(ch),
+            _ => {
+                let _ = write!(out, "\\U+{:04X}", ch as u32);
+            }
+        }
+    }
+    out
+}
+
+// File paths keep their backslashes as separators; only characters outside
+// the code page are escaped.
+fn escape_path(value: &str) -> String {
+    value
+        .split('\\')
+        .map(escape_unicode)
+        .collect::<Vec<_>>()
+        .join("\\")
+}
+
+fn block_defs_by_number(block_defs: &[BlockDef]) -> HashMap<u32, &BlockDef> {
+    let mut map = HashMap::<u32, &BlockDef>::with_capacity(block_defs.len());
+    for block_def in block_defs {
+        map.insert(block_def.number, block_def);
+    }
+    map
+}
+
+fn rotation_deg(transform: &Affine2D) -> f64 {
+    transform.rotation_angle() * 180.0 / PI
+}
+
+/// Direction in degrees of the vector at `angle_deg` after the transform.
+fn map_angle_deg(transform: &Affine2D, angle_deg: f64) -> f64 {
+    transform.map_direction(angle_deg * PI / 180.0).0 * 180.0 / PI
+}
+
+// Places block contents, whose base point lands on the insertion point.
+fn dxf_insert_transform(insert: &DxfInsert, block: &DxfBlock) -> Affine2D {
+    let (sin, cos) = insert.rotation.to_radians().sin_cos();
+    let (a, b) = (cos * insert.scale_x, sin * insert.scale_x);
+    let (c, d) = (-sin * insert.scale_y, cos * insert.scale_y);
+    Affine2D {
+        a,
+        b,
+        c,
+        d,
+        tx: insert.x - (a * block.base_x + c * block.base_y),
+        ty: insert.y - (b * block.base_x + d * block.base_y),
+    }
+}
+
+const MAX_BOUNDS_BLOCK_NESTING: usize = 32;
+
+// Pushes points whose box is the box of `entity` mapped through `transform`.
+// Cyclic, overly deep and dangling inserts add nothing.
+fn extend_bounds<'a>(
+    points: &mut Vec<Coord2D>,
+    entity: &'a DxfEntity,
+    transform: &Affine2D,
+    blocks: &HashMap<&str, &'a DxfBlock>,
+    stack: &mut Vec<&'a str>,
+) {
+    let mut add = |x: f64, y: f64| {
+        let (x, y) = transform.apply_point(x, y);
+        points.push(Coord2D::new(x, y));
+    };
+    match entity {
+        DxfEntity::Line(v) => {
+            add(v.x1, v.y1);
+            add(v.x2, v.y2);
+        }
+        DxfEntity::Point(v) => add(v.x, v.y),
+        DxfEntity::Text(v) => {
+            add(v.x, v.y);
+            if let Some((x, y)) = v.alignment_point {
+                add(x, y);
+            }
+        }
+        DxfEntity::MText(v) => add(v.x, v.y),
+        DxfEntity::Solid(v) => {
+            for (x, y) in [(v.x1, v.y1), (v.x2, v.y2), (v.x3, v.y3), (v.x4, v.y4)] {
+                add(x, y);
+            }
+        }
+        DxfEntity::Face3D(v) => {
+            for (x, y) in [(v.x1, v.y1), (v.x2, v.y2), (v.x3, v.y3), (v.x4, v.y4)] {
+                add(x, y);
+            }
+        }
+        DxfEntity::Image(v) => {
+            for (x, y) in v.corners() {
+                add(x, y);
+            }
+        }
+        DxfEntity::Circle(v) => {
+            let (u, w) = ((v.radius, 0.0), (0.0, v.radius));
+            extend_curve_bounds(points, transform, (v.center_x, v.center_y), u, w, 0.0, TAU);
+        }
+        DxfEntity::Arc(v) => {
+            let (u, w) = ((v.radius, 0.0), (0.0, v.radius));
+            let sweep = (v.end_angle - v.start_angle).rem_euclid(360.0).to_radians();
+            let from = v.start_angle.to_radians();
+            extend_curve_bounds(
+                points,
+                transform,
+                (v.center_x, v.center_y),
+                u,
+                w,
+                from,
+                sweep,
+            );
+        }
+        DxfEntity::Ellipse(v) => {
+            let u = (v.major_axis_x, v.major_axis_y);
+            let w = (
+                -v.major_axis_y * v.minor_ratio,
+                v.major_axis_x * v.minor_ratio,
+            );
+            let mut sweep = v.end_param - v.start_param;
+            if sweep <= 0.0 {
+                sweep += TAU;
+            }
+            let center = (v.center_x, v.center_y);
+            extend_curve_bounds(points, transform, center, u, w, v.start_param, sweep);
+        }
+        DxfEntity::LwPolyline(v) => {
+            let count = v.vertices.len();
+            let segments = if v.closed {
+                count
+            } else {
+                count.saturating_sub(1)
+            };
+            for vertex in &v.vertices {
+                add(vertex.x, vertex.y);
+            }
+            for i in 0..segments {
+                let (start, end) = (&v.vertices[i], &v.vertices[(i + 1) % count]);
+                if start.bulge != 0.0 {
+                    extend_bulge_bounds(points, transform, start, end);
+                }
+            }
+        }
+        DxfEntity::Insert(v) => {
+            let Some(&block) = blocks.get(v.block_name.as_str()) else {
+                return;
+            };
+            if stack.len() >= MAX_BOUNDS_BLOCK_NESTING || stack.contains(&block.name.as_str()) {
+                return;
+            }
+            let nested = dxf_insert_transform(v, block).then(transform);
+            stack.push(block.name.as_str());
+            for child in &block.entities {
+                extend_bounds(points, child, &nested, blocks, stack);
+            }
+            stack.pop();
+        }
+    }
+}
+
+// The curve `center + u cos t + w sin t` for `t` in `from..=from + sweep`, an
+// arc of a circle or ellipse. Affine maps keep that form, so mapping `u` and
+// `w` gives the exact box: the ends plus every axis extreme the sweep passes.
+fn extend_curve_bounds(
+    points: &mut Vec<Coord2D>,
+    transform: &Affine2D,
+    center: (f64, f64),
+    u: (f64, f64),
+    w: (f64, f64),
+    from: f64,
+    sweep: f64,
+) {
+    let (cx, cy) = transform.apply_point(center.0, center.1);
+    let (ux, uy) = transform.apply_vector(u.0, u.1);
+    let (wx, wy) = transform.apply_vector(w.0, w.1);
+    let at = |t: f64| {
+        let (sin, cos) = t.sin_cos();
+        Coord2D::new(cx + ux * cos + wx * sin, cy + uy * cos + wy * sin)
+    };
+    points.push(at(from));
+    points.push(at(from + sweep));
+    for extreme in [wx.atan2(ux), wy.atan2(uy)] {
+        for t in [extreme, extreme + PI] {
+            if sweep >= TAU || (t - from).rem_euclid(TAU) <= sweep {
+                points.push(at(t));
+            }
+        }
+    }
+}
+
+// A bulge is the tangent of a quarter of the included angle, positive when
+// the segment bows counterclockwise from `start` to `end`.
+fn extend_bulge_bounds(
+    points: &mut Vec<Coord2D>,
+    transform: &Affine2D,
+    start: &DxfVertex,
+    end: &DxfVertex,
+) {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let chord = dx.hypot(dy);
+    if chord == 0.0 {
+        return;
+    }
+    let bulge = start.bulge;
+    let offset = (1.0 - bulge * bulge) / (4.0 * bulge);
+    let center = (
+        (start.x + end.x) / 2.0 - dy * offset,
+        (start.y + end.y) / 2.0 + dx * offset,
+    );
+    let radius = chord * (1.0 + bulge * bulge) / (4.0 * bulge.abs());
+    let angle = 4.0 * bulge.atan();
+    let start_angle = (start.y - center.1).atan2(start.x - center.0);
+    let from = if angle < 0.0 {
+        start_angle + angle
+    } else {
+        start_angle
+    };
+    let (u, w) = ((radius, 0.0), (0.0, radius));
+    extend_curve_bounds(points, transform, center, u, w, from, angle.abs());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn convert_entities_exploded(
+    doc: &JwwDocument,
+    layer_names: &LayerNames,
+    entities: &[Entity],
+    block_name_map: &HashMap<u32, String>,
+    block_defs: &HashMap<u32, &BlockDef>,
+    transform: &Affine2D,
+    expanding_stack: &mut Vec<u32>,
+    unsupported_entities: &mut Vec<String>,
+    budget: &mut SegmentBudget,
+    options: &ConvertOptions,
+) -> Result<Vec<DxfEntity>, JwwError> {
+    let layer_filter = options.layer_name_filter.as_ref();
+    let mut out = Vec::<DxfEntity>::new();
+    for entity in entities {
+        if !entity_selected(doc, entity, options) {
+            continue;
+        }
+        // Nested entities inherit the scale of the top-level insert's group.
+        let scaled_transform;
+        let transform = if expanding_stack.is_empty() {
+            let scale = layer_scale(doc, entity, options);
+            scaled_transform = Affine2D::scale(scale, scale).then(transform);
+            &scaled_transform
+        } else {
+            transform
+        };
+        match entity {
+            Entity::Block(block) => {
+                if !layer_allowed(layer_names, entity, layer_filter) {
+                    continue;
+                }
+                if expanding_stack.len() >= options.max_block_nesting {
+                    unsupported_entities.push(format!("BLOCK_DEPTH_LIMIT({})", block.def_number));
+                    continue;
+                }
+                if expanding_stack.contains(&block.def_number) {
+                    unsupported_entities.push(format!("BLOCK_CYCLE({})", block.def_number));
+                    continue;
+                }
+
+                let Some(block_def) = block_defs.get(&block.def_number).copied() else {
+                    unsupported_entities.push(format!("UNRESOLVED_BLOCK({})", block.def_number));
+                    continue;
+                };
+
+                expanding_stack.push(block.def_number);
+                let child_transform = Affine2D::from_insert(block).then(transform);
+                let expanded = convert_entities_exploded(
+                    doc,
+                    layer_names,
+                    &block_def.entities,
+                    block_name_map,
+                    block_defs,
+                    &child_transform,
+                    expanding_stack,
+                    unsupported_entities,
+                    budget,
+                    options,
+                );
+                expanding_stack.pop();
+                out.extend(expanded?);
+
+                // Exploding drops the INSERT, so its attribute values survive as plain text.
+                let base = entity.base();
+                let layer = layer_names.get(base.layer_group, base.layer);
+                for attribute in convert_block_attributes(block) {
+                    let mut text = [DxfEntity::Text(DxfText {
+                        layer: layer.to_string(),
+                        color: map_color(base.pen_color),
+                        line_type: map_line_type(base.pen_style).to_string(),
+                        true_color: None,
+                        xdata: Vec::new(),
+                        extrusion: None,
+                        x: attribute.x,
+                        y: attribute.y,
+                        height: attribute.height,
+                        rotation: attribute.rotation,
+                        content: attribute.value,
+                        style: "STANDARD".to_string(),
+                        width_factor: 1.0,
+                        alignment: TextAlignment::default(),
+                        alignment_point: None,
+                        mirrored: false,
+                    })];
+                    if options.true_color {
+                        set_true_color(entity, &mut text);
+                    }
+                    if options.pen_xdata {
+                        set_pen_xdata(entity, &mut text);
+                    }
+                    out.extend(transform_entity_for_explode(&text[0], transform));
+                }
+            }
+            _ => match convert_entity(layer_names, entity, block_name_map, options) {
+                Some(mut converted) => {
+                    if options.draw_dimension_arrows {
+                        if let Entity::Dimension(dimension) = entity {
+                            add_dimension_arrows(dimension, &mut converted);
+                        }
+                    }
+                    if options.true_color {
+                        set_true_color(entity, &mut converted);
+                    }
+                    if options.pen_xdata {
+                        set_pen_xdata(entity, &mut converted);
+                    }
+                    let mut transformed = converted
+                        .iter()
+                        .flat_map(|dxf_entity| transform_entity_for_explode(dxf_entity, transform))
+                        .collect();
+                    let dropped = drop_non_finite(&mut transformed);
+                    unsupported_entities.extend((0..dropped).map(|_| NON_FINITE_COORD.to_string()));
+                    budget.charge(&transformed)?;
+                    out.extend(transformed);
+                }
+                None => unsupported_entities.push(entity.entity_type().to_string()),
+            },
+        }
+    }
+    Ok(out)
+}
+
+fn transform_entity_for_explode(entity: &DxfEntity, transform: &Affine2D) -> Vec<DxfEntity> {
+    match entity {
+        DxfEntity::Line(v) => {
+            let (x1, y1) = transform.apply_point(v.x1, v.y1);
+            let (x2, y2) = transform.apply_point(v.x2, v.y2);
+            vec![DxfEntity::Line(DxfLine {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                x1,
+                y1,
+                x2,
+                y2,
+            })]
+        }
+        DxfEntity::Circle(v) => transform_circle_for_explode(v, transform),
+        DxfEntity::Arc(v) => transform_arc_for_explode(v, transform),
+        DxfEntity::Ellipse(v) => transform_ellipse_for_explode(v, transform),
+        DxfEntity::Point(v) => {
+            let (x, y) = transform.apply_point(v.x, v.y);
+            vec![DxfEntity::Point(DxfPoint {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                x,
+                y,
+            })]
+        }
+        DxfEntity::Text(v) => {
+            let (x, y) = transform.apply_point(v.x, v.y);
+            let height = (v.height * transform.average_scale().abs()).max(0.1);
+            // Mirrored text keeps its up direction and is drawn backwards
+            // instead of being turned upside down.
+            let mirrored = v.mirrored != transform.is_mirroring();
+            let rotation = if transform.is_mirroring() {
+                map_angle_deg(transform, v.rotation + 90.0) - 90.0
+            } else {
+                v.rotation + rotation_deg(transform)
+            };
+            vec![DxfEntity::Text(DxfText {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                x,
+                y,
+                height,
+                rotation,
+                content: v.content.clone(),
+                style: v.style.clone(),
+                width_factor: v.width_factor,
+                alignment: v.alignment,
+                alignment_point: v
+                    .alignment_point
+                    .map(|(ax, ay)| transform.apply_point(ax, ay)),
+                mirrored,
+            })]
+        }
+        DxfEntity::MText(v) => {
+            let (x, y) = transform.apply_point(v.x, v.y);
+            let scale = transform.average_scale().abs();
+            vec![DxfEntity::MText(DxfMText {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                x,
+                y,
+                height: (v.height * scale).max(0.1),
+                width: v.width * scale,
+                // MTEXT cannot be mirrored, so it keeps reading along its
+                // mapped baseline.
+                rotation: if transform.is_mirroring() {
+                    map_angle_deg(transform, v.rotation)
+                } else {
+                    v.rotation + rotation_deg(transform)
+                },
+                attachment_point: v.attachment_point,
+                content: v.content.clone(),
+                style: v.style.clone(),
+                tracking: v.tracking,
+            })]
+        }
+        DxfEntity::Solid(v) => {
+            let (x1, y1) = transform.apply_point(v.x1, v.y1);
+            let (x2, y2) = transform.apply_point(v.x2, v.y2);
+            let (x3, y3) = transform.apply_point(v.x3, v.y3);
+            let (x4, y4) = transform.apply_point(v.x4, v.y4);
+            vec![DxfEntity::Solid(DxfSolid {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                x1,
+                y1,
+                x2,
+                y2,
+                x3,
+                y3,
+                x4,
+                y4,
+            })]
+        }
+        DxfEntity::Face3D(v) => {
+            let (x1, y1) = transform.apply_point(v.x1, v.y1);
+            let (x2, y2) = transform.apply_point(v.x2, v.y2);
+            let (x3, y3) = transform.apply_point(v.x3, v.y3);
+            let (x4, y4) = transform.apply_point(v.x4, v.y4);
+            vec![DxfEntity::Face3D(DxfFace3D {
+                x1,
+                y1,
+                x2,
+                y2,
+                x3,
+                y3,
+                x4,
+                y4,
+                ..v.clone()
+            })]
+        }
+        DxfEntity::LwPolyline(v) => {
+            // A mirroring transform flips the sweep direction of every bulged segment.
+            let bulge_sign = if transform.is_mirroring() { -1.0 } else { 1.0 };
+            let vertices = v
+                .vertices
+                .iter()
+                .map(|vertex| {
+                    let (x, y) = transform.apply_point(vertex.x, vertex.y);
+                    DxfVertex {
+                        x,
+                        y,
+                        bulge: vertex.bulge * bulge_sign,
+                    }
+                })
+                .collect();
+            vec![DxfEntity::LwPolyline(DxfLwPolyline {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                vertices,
+                closed: v.closed,
+            })]
+        }
+        DxfEntity::Insert(v) => {
+            let (x, y) = transform.apply_point(v.x, v.y);
+            let scale = transform.average_scale().abs();
+            let mirrored = transform.is_mirroring();
+            let map_rotation = |rotation: f64| {
+                if mirrored {
+                    map_angle_deg(transform, rotation)
+                } else {
+                    rotation + rotation_deg(transform)
+                }
+            };
+            let attributes = v
+                .attributes
+                .iter()
+                .map(|attribute| {
+                    let (x, y) = transform.apply_point(attribute.x, attribute.y);
+                    DxfAttribute {
+                        tag: attribute.tag.clone(),
+                        value: attribute.value.clone(),
+                        x,
+                        y,
+                        height: (attribute.height * scale).max(0.1),
+                        rotation: map_rotation(attribute.rotation),
+                    }
+                })
+                .collect();
+            vec![DxfEntity::Insert(DxfInsert {
+                layer: v.layer.clone(),
+                color: v.color,
+                line_type: v.line_type.clone(),
+                true_color: v.true_color,
+                xdata: v.xdata.clone(),
+                extrusion: v.extrusion,
+                block_name: v.block_name.clone(),
+                x,
+                y,
+                // A reflection R(a)·M turns R(r)·S(sx, sy) into
+                // R(a - r)·S(sx, -sy).
+                scale_x: v.scale_x,
+                scale_y: if mirrored { -v.scale_y } else { v.scale_y },
+                rotation: if mirrored {
+                    rotation_deg(transform) - v.rotation
+                } else {
+                    v.rotation + rotation_deg(transform)
+                },
+                attributes,
+            })]
+        }
+        DxfEntity::Image(v) => {
+            let (x, y) = transform.apply_point(v.x, v.y);
+            let (u_x, u_y) = transform.apply_vector(v.u_x, v.u_y);
+            let (v_x, v_y) = transform.apply_vector(v.v_x, v.v_y);
+            vec![DxfEntity::Image(DxfImage {
+                x,
+                y,
+                u_x,
+                u_y,
+                v_x,
+                v_y,
+                ..v.clone()
+            })]
+        }
+    }
+}
+
+// Unlike exploding, flipping keeps arcs and ellipses as curves: mirroring
+// across the X axis negates and swaps their start and end angles.
+fn flip_entity_y(entity: &DxfEntity) -> Vec<DxfEntity> {
+    match entity {
+        DxfEntity::Arc(v) => vec![DxfEntity::Arc(DxfArc {
+            center_y: -v.center_y,
+            start_angle: 360.0 - v.end_angle,
+            end_angle: 360.0 - v.start_angle,
+            ..v.clone()
+        })],
+        DxfEntity::Ellipse(v) => vec![DxfEntity::Ellipse(DxfEllipse {
+            center_y: -v.center_y,
+            major_axis_y: -v.major_axis_y,
+            start_param: 2.0 * PI - v.end_param,
+            end_param: 2.0 * PI - v.start_param,
+            ..v.clone()
+        })],
+        _ => {
+            let flip = Affine2D::scale(1.0, -1.0);
+            transform_entity_for_explode(entity, &flip)
+        }
+    }
+}
+
+fn transform_circle_for_explode(circle: &DxfCircle, transform: &Affine2D) -> Vec<DxfEntity> {
+    let (center_x, center_y) = transform.apply_point(circle.center_x, circle.center_y);
+    let (ux, uy) = transform.apply_vector(circle.radius, 0.0);
+    let (vx, vy) = transform.apply_vector(0.0, circle.radius);
+
+    let lu = (ux * ux + uy * uy).sqrt();
+    let lv = (vx * vx + vy * vy).sqrt();
+    if lu <= 1e-12 && lv <= 1e-12 {
+        return vec![DxfEntity::Point(DxfPoint {
+            layer: circle.layer.clone(),
+            color: circle.color,
+            line_type: circle.line_type.clone(),
+            true_color: circle.true_color,
+            xdata: circle.xdata.clone(),
+            extrusion: circle.extrusion,
+            x: center_x,
+            y: center_y,
+        })];
+    }
+
+    let denom = lu * lv;
+    let dot = if denom <= 1e-12 {
+        0.0
+    } else {
+        (ux * vx + uy * vy) / denom
+    };
+    if nearly_equal(lu, lv) && dot.abs() < 1e-6 {
+        return vec![DxfEntity::Circle(DxfCircle {
+            layer: circle.layer.clone(),
+            color: circle.color,
+            line_type: circle.line_type.clone(),
+            true_color: circle.true_color,
+            xdata: circle.xdata.clone(),
+            extrusion: circle.extrusion,
+            center_x,
+            center_y,
+            radius: (lu + lv) / 2.0,
+        })];
+    }
+
+    let (major_x, major_y, minor_ratio) = if lu >= lv {
+        (ux, uy, if lu <= 1e-12 { 1.0 } else { lv / lu })
+    } else {
+        (vx, vy, if lv <= 1e-12 { 1.0 } else { lu / lv })
+    };
+
+    vec![DxfEntity::Ellipse(DxfEllipse {
+        layer: circle.layer.clone(),
+        color: circle.color,
+        line_type: circle.line_type.clone(),
+        true_color: circle.true_color,
+        xdata: circle.xdata.clone(),
+        extrusion: circle.extrusion,
+        center_x,
+        center_y,
+        major_axis_x: major_x,
+        major_axis_y: major_y,
+        minor_ratio,
+        start_param: 0.0,
+        end_param: 2.0 * PI,
+    })]
+}
+
+fn transform_arc_for_explode(arc: &DxfArc, transform: &Affine2D) -> Vec<DxfEntity> {
+    let mut end = arc.end_angle;
+    let start = arc.start_angle;
+    if end < start {
+        end += 360.0;
+    }
+    let sweep = (end - start).abs();
+    let segments = ((sweep / 360.0) * 96.0).ceil() as usize;
+    let segments = segments.clamp(8, 192);
+
+    let mut points = Vec::<(f64, f64)>::with_capacity(segments + 1);
+    for i in 0..=segments {
+        let t = start + (end - start) * (i as f64) / (segments as f64);
+        let rad = t * PI / 180.0;
+        let x = arc.center_x + arc.radius * rad.cos();
+        let y = arc.center_y + arc.radius * rad.sin();
+        points.push(transform.apply_point(x, y));
+    }
+    // Keep the segments running counter-clockwise like the source arc.
+    if transform.is_mirroring() {
+        points.reverse();
+    }
+
+    points_to_lines(
+        points,
+        &DxfLine {
+            layer: arc.layer.clone(),
+            color: arc.color,
+            line_type: arc.line_type.clone(),
+            true_color: arc.true_color,
+            xdata: arc.xdata.clone(),
+            extrusion: arc.extrusion,
+            x1: 0.0,
+            y1: 0.0,
+            x2: 0.0,
+            y2: 0.0,
+        },
+    )
+}
+
+fn transform_ellipse_for_explode(ellipse: &DxfEllipse, transform: &Affine2D) -> Vec<DxfEntity> {
+    let start = ellipse.start_param;
+    let mut end = ellipse.end_param;
+    if end <= start {
+        end += 2.0 * PI;
+    }
+    let span = (end - start).abs();
+    let segments = ((span / (2.0 * PI)) * 128.0).ceil() as usize;
+    let segments = segments.clamp(12, 256);
+
+    let major_x = ellipse.major_axis_x;
+    let major_y = ellipse.major_axis_y;
+    let minor_x = -major_y * ellipse.minor_ratio;
+    let minor_y = major_x * ellipse.minor_ratio;
+
+    let mut points = Vec::<(f64, f64)>::with_capacity(segments + 1);
+    for i in 0..=segments {
+        let t = start + (end - start) * (i as f64) / (segments as f64);
+        let x = ellipse.center_x + major_x * t.cos() + minor_x * t.sin();
+        let y = ellipse.center_y + major_y * t.cos() + minor_y * t.sin();
+        points.push(transform.apply_point(x, y));
+    }
+    if transform.is_mirroring() {
+        points.reverse();
+    }
+
+    points_to_lines(
+        points,
+        &DxfLine {
+            layer: ellipse.layer.clone(),
+            color: ellipse.color,
+            line_type: ellipse.line_type.clone(),
+            true_color: ellipse.true_color,
+            xdata: ellipse.xdata.clone(),
+            extrusion: ellipse.extrusion,
+            x1: 0.0,
+            y1: 0.0,
+            x2: 0.0,
+            y2: 0.0,
+        },
+    )
+}
+
+// Every segment copies `style`'s layer, colors, XDATA and extrusion; its
+// coordinates are ignored.
+fn points_to_lines(points: Vec<(f64, f64)>, style: &DxfLine) -> Vec<DxfEntity> {
+    if points.len() < 2 {
+        return Vec::new();
+    }
+    let mut out = Vec::<DxfEntity>::with_capacity(points.len().saturating_sub(1));
+    for w in points.windows(2) {
+        let (x1, y1) = w[0];
+        let (x2, y2) = w[1];
+        out.push(DxfEntity::Line(DxfLine {
+            x1,
+            y1,
+            x2,
+            y2,
+            ..style.clone()
+        }));
+    }
+    out
+}
+
+fn nearly_equal(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn convert_layers(doc: &JwwDocument, layer_names: &LayerNames) -> Vec<DxfLayer> {
+    let mut layers = Vec::<DxfLayer>::with_capacity(16 * 16);
+    for g in 0..16 {
+        for l in 0..16 {
+            let layer = &doc.header.layer_groups[g].layers[l];
+            layers.push(DxfLayer {
+                name: layer_names.get(g as u16, l as u16).into_owned(),
+                color: ((g * 16 + l) % 255 + 1) as i32,
+                line_type: "CONTINUOUS".to_string(),
+                frozen: layer.state == 0,
+                locked: layer.protect != 0,
+            });
+        }
+    }
+    layers
+}
+
+// Characters AutoCAD rejects in symbol table names.
+const ILLEGAL_LAYER_CHARS: &[char] = &['<', '>', '/', '\\', '"', ':', ';', '?', '*', '|', '=', '`'];
+
+/// Drops unused layers (unless `keep_all`), replaces characters AutoCAD
+/// rejects, and suffixes names that collide afterwards. Layer names compare
+/// case-insensitively in DXF, so collisions do too. Entity layers are
+/// rewritten to match.
+pub(crate) fn sanitize_layers(
+    layers: Vec<DxfLayer>,
+    entities: &mut [DxfEntity],
+    blocks: &mut [DxfBlock],
+    layouts: &mut [DxfLayout],
+    keep_all: bool,
+) -> Vec<DxfLayer> {
+    let used = entities
+        .iter()
+        .chain(blocks.iter().flat_map(|block| block.entities.iter()))
+        .map(|entity| entity.layer().to_string())
+        .collect::<BTreeSet<_>>();
+
+    let mut taken = BTreeSet::<String>::new();
+    let mut renamed = HashMap::<String, String>::new();
+    let mut unique_name = |name: &str| {
+        let base = sanitize_layer_name(name);
+        let mut candidate = base.clone();
+        let mut suffix = 2;
+        while !taken.insert(candidate.to_uppercase()) {
+            candidate = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        candidate
+    };
+
+    let mut out = Vec::<DxfLayer>::with_capacity(layers.len());
+    for mut layer in layers {
+        // JWW slots sharing a name already share entities, so they merge.
+        if renamed.contains_key(&layer.name) || !(keep_all || used.contains(&layer.name)) {
+            continue;
+        }
+        let name = unique_name(&layer.name);
+        renamed.insert(std::mem::replace(&mut layer.name, name.clone()), name);
+        out.push(layer);
+    }
+    // Entities on slots outside the table still need legal names.
+    for name in &used {
+        if !renamed.contains_key(name) {
+            let sanitized = unique_name(name);
+            renamed.insert(name.clone(), sanitized);
+        }
+    }
+
+    let entities = entities.iter_mut().chain(
+        blocks
+            .iter_mut()
+            .flat_map(|block| block.entities.iter_mut()),
+    );
+    let layout_layers = layouts
+        .iter_mut()
+        .flat_map(|layout| layout.layers.iter_mut());
+    for layer in entities.map(DxfEntity::layer_mut).chain(layout_layers) {
+        if let Some(name) = renamed.get(layer.as_str()) {
+            if name != layer {
+                layer.clone_from(name);
+            }
+        }
+    }
+    out
+}
+
+fn sanitize_layer_name(name: &str) -> String {
+    let sanitized = name
+        .chars()
+        .map(|ch| {
+            if ch.is_control() || ILLEGAL_LAYER_CHARS.contains(&ch) {
+                '_'
+            } else {
+                ch
+            }
+        })
+        .collect::<String>();
+    if sanitized.is_empty() {
+        "_".to_string()
+    } else {
+        sanitized
+    }
+}
+
+// Some importers drop empty blocks and then fail on the inserts that use
+// them, so unless `prune_empty_blocks` asks for the opposite, every block an
+// insert names gets at least a placeholder point, including the fallback
+// `BLOCK_{n}` of an unresolved reference.
+fn convert_blocks(
+    doc: &JwwDocument,
+    layer_names: &LayerNames,
+    block_name_map: &HashMap<u32, String>,
+    unsupported_entities: &mut Vec<String>,
+    budget: &mut SegmentBudget,
+    options: &ConvertOptions,
+) -> Result<Vec<DxfBlock>, JwwError> {
+    let validation = validate_block_references(doc);
+    // Block contents inherit the layer scale of the top-level insert, which
+    // carries it in its scale factors.
+    let options = &ConvertOptions {
+        apply_layer_scale: false,
+        ..options.clone()
+    };
+    let mut blocks = Vec::<DxfBlock>::with_capacity(doc.block_defs.len());
+    for block_def in &doc.block_defs {
+        if !options.keep_unreferenced_blocks
+            && validation
+                .referenced_def_numbers
+                .binary_search(&block_def.number)
+                .is_err()
+        {
+            continue;
+        }
+        let name = block_def_name(block_def.number, &block_def.name);
+        let mut entities = convert_entities(
+            doc,
+            layer_names,
+            &block_def.entities,
+            block_name_map,
+            None,
+            unsupported_entities,
+            budget,
+            options,
+        )?;
+        if options.block_entities_byblock {
+            for entity in &mut entities {
+                *entity.color_mut() = COLOR_BYBLOCK;
+                *entity.true_color_mut() = None;
+            }
+        }
+        if entities.is_empty() && !options.prune_empty_blocks {
+            entities.push(placeholder_block_point());
+        }
+        blocks.push(DxfBlock {
+            name,
+            base_x: 0.0,
+            base_y: 0.0,
+            entities,
+        });
+    }
+    if !options.prune_empty_blocks {
+        for number in &validation.unresolved_def_numbers {
+            blocks.push(DxfBlock {
+                name: block_def_name(*number, ""),
+                base_x: 0.0,
+                base_y: 0.0,
+                entities: vec![placeholder_block_point()],
+            });
+        }
+    }
+    Ok(blocks)
+}
+
+fn placeholder_block_point() -> DxfEntity {
+    DxfEntity::Point(DxfPoint {
+        layer: "0".to_string(),
+        color: COLOR_BYBLOCK,
+        line_type: "BYBLOCK".to_string(),
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        x: 0.0,
+        y: 0.0,
+    })
+}
+
+// `bbox_block_defs` is only set for the top level under `blocks_as_bbox`.
+#[allow(clippy::too_many_arguments)]
+fn convert_entities(
+    doc: &JwwDocument,
+    layer_names: &LayerNames,
+    entities: &[Entity],
+    block_name_map: &HashMap<u32, String>,
+    bbox_block_defs: Option<&HashMap<u32, &BlockDef>>,
+    unsupported_entities: &mut Vec<String>,
+    budget: &mut SegmentBudget,
+    options: &ConvertOptions,
+) -> Result<Vec<DxfEntity>, JwwError> {
+    let mut out = Vec::<DxfEntity>::new();
+    for entity in entities {
+        if !entity_selected(doc, entity, options) {
+            continue;
+        }
+        match convert_entity(layer_names, entity, block_name_map, options) {
+            Some(mut converted) => {
+                if options.draw_dimension_arrows {
+                    if let Entity::Dimension(dimension) = entity {
+                        add_dimension_arrows(dimension, &mut converted);
+                    }
+                }
+                if options.true_color {
+                    set_true_color(entity, &mut converted);
+                }
+                if options.pen_xdata {
+                    set_pen_xdata(entity, &mut converted);
+                }
+                if let (Some(block_defs), [DxfEntity::Insert(insert)]) =
+                    (bbox_block_defs, converted.as_slice())
+                {
+                    let outline = convert_insert_bbox(insert, entity, block_defs);
+                    if !outline.is_empty() {
+                        converted = outline;
+                    }
+                }
+                let scale = layer_scale(doc, entity, options);
+                if scale != 1.0 {
+                    for e in &mut converted {
+                        scale_entity_lengths(e, &|value| value * scale);
+                        // The block takes the insert's scale, as when exploded.
+                        if let DxfEntity::Insert(v) = e {
+                            v.scale_x *= scale;
+                            v.scale_y *= scale;
+                        }
+                    }
+                }
+                let dropped = drop_non_finite(&mut converted);
+                unsupported_entities.extend((0..dropped).map(|_| NON_FINITE_COORD.to_string()));
+                budget.charge(&converted)?;
+                out.extend(converted);
+            }
+            None => unsupported_entities.push(entity.entity_type().to_string()),
+        }
+    }
+    Ok(out)
+}
+
+fn convert_entity(
+    layer_names: &LayerNames,
+    entity: &Entity,
+    block_name_map: &HashMap<u32, String>,
+    options: &ConvertOptions,
+) -> Option<Vec<DxfEntity>> {
+    let base = entity.base();
+    let layer = layer_names.get(base.layer_group, base.layer);
+    let layer_filter = options.layer_name_filter.as_ref();
+    if layer_filter.is_some_and(|filter| !filter.matches(&layer)) {
+        return Some(Vec::new());
+    }
+    let layer = layer.into_owned();
+    let color = map_color(base.pen_color);
+    let line_type = map_line_type(base.pen_style).to_string();
+
+    match entity {
+        Entity::Line(v) => Some(vec![DxfEntity::Line(DxfLine {
+            layer,
+            color,
+            line_type,
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: v.start_x,
+            y1: v.start_y,
+            x2: v.end_x,
+            y2: v.end_y,
+        })]),
+        Entity::Arc(v) => Some(convert_arc(v, layer, color, line_type)),
+        Entity::Point(v) if v.is_temporary && !options.include_temporary_points => Some(Vec::new()),
+        Entity::Point(v) => {
+            let layer = if v.is_temporary {
+                TEMP_POINTS_LAYER.to_string()
+            } else {
+                layer
+            };
+            let marker = options
+                .expand_point_markers
+                .then(|| convert_point_marker(v, &layer, color, &line_type))
+                .flatten();
+            Some(marker.unwrap_or_else(|| {
+                vec![DxfEntity::Point(DxfPoint {
+                    layer,
+                    color,
+                    line_type,
+                    true_color: None,
+                    xdata: Vec::new(),
+                    extrusion: None,
+                    x: v.x,
+                    y: v.y,
+                })]
+            }))
+        }
+        Entity::Text(v) => match v.image_ref() {
+            Some(image) => Some(vec![DxfEntity::Image(convert_image(
+                v,
+                &image,
+                &options.image_dirs,
+                layer,
+                color,
+                line_type,
+            ))]),
+            None => Some(vec![convert_text_entity(v, layer, color, line_type)]),
+        },
+        Entity::Solid(v) if !v.is_filled => Some(
+            convert_solid_outline(v, &layer, color, &line_type)
+                .into_iter()
+                .map(DxfEntity::Line)
+                .collect(),
+        ),
+        Entity::Solid(v) => Some(vec![DxfEntity::Solid(convert_solid(
+            v, layer, color, line_type,
+        ))]),
+        Entity::Block(v) => {
+            let block_name = block_name_map
+                .get(&v.def_number)
+                .cloned()
+                .unwrap_or_else(|| format!("BLOCK_{}", v.def_number));
+            Some(vec![DxfEntity::Insert(DxfInsert {
+                layer,
+                color,
+                line_type,
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion: None,
+                block_name,
+                x: v.ref_x,
+                y: v.ref_y,
+                scale_x: v.scale_x,
+                scale_y: v.scale_y,
+                rotation: rad_to_deg(v.rotation),
+                attributes: convert_block_attributes(v),
+            })])
+        }
+        Entity::Dimension(v) => {
+            let mut out = vec![
+                DxfEntity::Line(DxfLine {
+                    layer: layer.clone(),
+                    color,
+                    line_type: line_type.clone(),
+                    true_color: None,
+                    xdata: Vec::new(),
+                    extrusion: None,
+                    x1: v.line.start_x,
+                    y1: v.line.start_y,
+                    x2: v.line.end_x,
+                    y2: v.line.end_y,
+                }),
+                DxfEntity::Text(convert_text(&v.text, layer, color, line_type)),
+            ];
+            // Extension lines and arrowhead points carry their own pen and
+            // layer; temporary points follow the same rule as on their own.
+            let aux_entities = v
+                .aux_lines
+                .iter()
+                .map(|line| Entity::Line(line.clone()))
+                .chain(
+                    v.aux_points
+                        .iter()
+                        .map(|point| Entity::Point(point.clone())),
+                );
+            for aux in aux_entities {
+                out.extend(
+                    convert_entity(layer_names, &aux, block_name_map, options).unwrap_or_default(),
+                );
+            }
+            Some(out)
+        }
+        Entity::Polyline(v) => Some(vec![DxfEntity::LwPolyline(convert_polyline(
+            v, layer, color, line_type,
+        ))]),
+    }
+}
+
+/// [`Point::code`] of a marker point drawn as a circle. Jw_cad documents no
+/// meaning for the codes, so this and [`POINT_MARKER_CROSS`] are this
+/// crate's own choice; other codes are left as POINTs.
+pub const POINT_MARKER_CIRCLE: u32 = 1;
+/// [`Point::code`] of a marker point drawn as a cross, turned by its angle.
+pub const POINT_MARKER_CROSS: u32 = 2;
+/// Marker radius, in drawing units, before the point's own scale.
+const POINT_MARKER_SIZE: f64 = 1.0;
+
+// Only points with pen style 100 carry a marker; unknown codes return None
+// and stay plain POINTs.
+fn convert_point_marker(
+    point: &Point,
+    layer: &str,
+    color: i32,
+    line_type: &str,
+) -> Option<Vec<DxfEntity>> {
+    if point.base.pen_style != 100 {
+        return None;
+    }
+    let scale = if point.scale.is_finite() && point.scale > 0.0 {
+        point.scale
+    } else {
+        1.0
+    };
+    let size = POINT_MARKER_SIZE * scale;
+    match point.code {
+        POINT_MARKER_CIRCLE => Some(vec![DxfEntity::Circle(DxfCircle {
+            layer: layer.to_string(),
+            color,
+            line_type: line_type.to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            center_x: point.x,
+            center_y: point.y,
+            radius: size,
+        })]),
+        POINT_MARKER_CROSS => {
+            let (sin, cos) = point.angle.to_radians().sin_cos();
+            let arm = |ux: f64, uy: f64| {
+                DxfEntity::Line(DxfLine {
+                    layer: layer.to_string(),
+                    color,
+                    line_type: line_type.to_string(),
+                    true_color: None,
+                    xdata: Vec::new(),
+                    extrusion: None,
+                    x1: point.x - ux * size,
+                    y1: point.y - uy * size,
+                    x2: point.x + ux * size,
+                    y2: point.y + uy * size,
+                })
+            };
+            Some(vec![arm(cos, sin), arm(-sin, cos)])
+        }
+        _ => None,
+    }
+}
+
+// Arrowheads are as long as the dimension text is tall and a third as wide.
+const DIMENSION_ARROW_ASPECT: f64 = 3.0;
+
+// `converted` starts with the dimension line unless a layer filter dropped
+// the dimension, in which case there is nothing to point at.
+fn add_dimension_arrows(dimension: &Dimension, converted: &mut Vec<DxfEntity>) {
+    let Some(DxfEntity::Line(line)) = converted.first() else {
+        return;
+    };
+    let (dx, dy) = (line.x2 - line.x1, line.y2 - line.y1);
+    let length = dx.hypot(dy);
+    if length <= 0.0 || !length.is_finite() {
+        return;
+    }
+    let arrow_length = if dimension.text.size_y > 0.0 {
+        dimension.text.size_y
+    } else {
+        2.5
+    }
+    .min(length / 2.0);
+    let (ux, uy) = (dx / length, dy / length);
+    let half_width = arrow_length / DIMENSION_ARROW_ASPECT / 2.0;
+
+    // Each tip sits on an end of the line and its base lies further inside.
+    let arrow = |tip_x: f64, tip_y: f64, inward: f64| {
+        let base_x = tip_x + ux * inward * arrow_length;
+        let base_y = tip_y + uy * inward * arrow_length;
+        DxfEntity::Solid(DxfSolid {
+            layer: line.layer.clone(),
+            color: line.color,
+            line_type: line.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: tip_x,
+            y1: tip_y,
+            x2: base_x - uy * half_width,
+            y2: base_y + ux * half_width,
+            x3: base_x + uy * half_width,
+            y3: base_y - ux * half_width,
+            x4: base_x + uy * half_width,
+            y4: base_y - ux * half_width,
+        })
+    };
+    let arrows = [arrow(line.x1, line.y1, 1.0), arrow(line.x2, line.y2, -1.0)];
+    converted.extend(arrows);
+}
+
+const ATTRIBUTE_HEIGHT: f64 = 2.5;
+
+// The box spans everything the insert draws, through nested inserts; an
+// insert that places nothing gets no box and is kept as it is.
+fn convert_insert_bbox(
+    insert: &DxfInsert,
+    entity: &Entity,
+    block_defs: &HashMap<u32, &BlockDef>,
+) -> Vec<DxfEntity> {
+    let Some((min, max)) = expanded_bbox(std::slice::from_ref(entity), block_defs) else {
+        return Vec::new();
+    };
+
+    let corners = vec![
+        (min.x, min.y),
+        (max.x, min.y),
+        (max.x, max.y),
+        (min.x, max.y),
+        (min.x, min.y),
+    ];
+    let mut out = points_to_lines(
+        corners,
+        &DxfLine {
+            layer: insert.layer.clone(),
+            color: insert.color,
+            line_type: insert.line_type.clone(),
+            true_color: insert.true_color,
+            xdata: insert.xdata.clone(),
+            extrusion: insert.extrusion,
+            x1: 0.0,
+            y1: 0.0,
+            x2: 0.0,
+            y2: 0.0,
+        },
+    );
+    let size = (max.x - min.x).max(max.y - min.y);
+    let center = ((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+    out.push(DxfEntity::Text(DxfText {
+        layer: insert.layer.clone(),
+        color: insert.color,
+        line_type: insert.line_type.clone(),
+        true_color: insert.true_color,
+        xdata: insert.xdata.clone(),
+        extrusion: insert.extrusion,
+        x: center.0,
+        y: center.1,
+        height: if size > 0.0 {
+            size * 0.1
+        } else {
+            ATTRIBUTE_HEIGHT
+        },
+        rotation: 0.0,
+        content: insert.block_name.clone(),
+        style: "STANDARD".to_string(),
+        width_factor: 1.0,
+        alignment: TextAlignment::new(HorizontalAlignment::Center, VerticalAlignment::Middle),
+        alignment_point: Some(center),
+        mirrored: false,
+    }));
+    out
+}
+
+// Attributes are stacked downwards from the insertion point, one line each.
+fn convert_block_attributes(block: &Block) -> Vec<DxfAttribute> {
+    let (sin, cos) = block.rotation.sin_cos();
+    block
+        .attributes
+        .iter()
+        .enumerate()
+        .map(|(index, (tag, value))| {
+            let offset = ATTRIBUTE_HEIGHT * 1.5 * index as f64;
+            DxfAttribute {
+                tag: tag.clone(),
+                value: value.clone(),
+                x: block.ref_x + sin * offset,
+                y: block.ref_y - cos * offset,
+                height: ATTRIBUTE_HEIGHT,
+                rotation: rad_to_deg(block.rotation),
+            }
+        })
+        .collect()
+}
+
+// The JWW record runs around the outline as 1, 4, 2, 3 (by field name) and
+// DXF draws its corners 1-2-4-3, so the record order lands as 1, 4, 3, 2.
+fn convert_solid(solid: &Solid, layer: String, color: i32, line_type: String) -> DxfSolid {
+    DxfSolid {
+        layer,
+        color,
+        line_type,
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        x1: solid.point1_x,
+        y1: solid.point1_y,
+        x2: solid.point4_x,
+        y2: solid.point4_y,
+        x3: solid.point3_x,
+        y3: solid.point3_y,
+        x4: solid.point2_x,
+        y4: solid.point2_y,
+    }
+}
+
+// One LINE per side, taken around `Solid::outline` rather than the DXF
+// corner order, which zigzags.
+fn convert_solid_outline(solid: &Solid, layer: &str, color: i32, line_type: &str) -> Vec<DxfLine> {
+    let outline = solid.outline();
+    (0..outline.len())
+        .map(|i| {
+            let start = outline[i];
+            let end = outline[(i + 1) % outline.len()];
+            DxfLine {
+                layer: layer.to_string(),
+                color,
+                line_type: line_type.to_string(),
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion: None,
+                x1: start.x,
+                y1: start.y,
+                x2: end.x,
+                y2: end.y,
+            }
+        })
+        .collect()
+}
+
+fn convert_polyline(
+    polyline: &Polyline,
+    layer: String,
+    color: i32,
+    line_type: String,
+) -> DxfLwPolyline {
+    DxfLwPolyline {
+        layer,
+        color,
+        line_type,
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        vertices: polyline
+            .vertices
+            .iter()
+            .map(|vertex| DxfVertex {
+                x: vertex.point.x,
+                y: vertex.point.y,
+                bulge: vertex.bulge,
+            })
+            .collect(),
+        closed: polyline.closed,
+    }
+}
+
+fn convert_arc(arc: &Arc, layer: String, color: i32, line_type: String) -> Vec<DxfEntity> {
+    if arc.is_full_circle && arc.flatness == 1.0 {
+        return vec![DxfEntity::Circle(DxfCircle {
+            layer,
+            color,
+            line_type,
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            center_x: arc.center_x,
+            center_y: arc.center_y,
+            radius: arc.radius,
+        })];
+    }
+
+    if arc.flatness != 1.0 {
+        let mut major_radius = arc.radius;
+        let mut minor_ratio = arc.flatness;
+        let mut tilt_angle = arc.tilt_angle;
+        // JWW angles are eccentric anomalies from the tilt axis; turning the
+        // major axis a quarter turn shifts them back by as much.
+        let mut param_shift = 0.0;
+
+        if minor_ratio > 1.0 {
+            major_radius = arc.radius * arc.flatness;
+            minor_ratio = 1.0 / arc.flatness;
+            tilt_angle = arc.tilt_angle + PI / 2.0;
+            param_shift = -PI / 2.0;
+        }
+
+        let major_axis_x = major_radius * tilt_angle.cos();
+        let major_axis_y = major_radius * tilt_angle.sin();
+        let (start_param, end_param) = if arc.is_full_circle {
+            (0.0, 2.0 * PI)
+        } else {
+            let (from, to) = arc.counterclockwise_angles();
+            let start_param = from + param_shift;
+            let mut end_param = to + param_shift;
+            while end_param <= start_param {
+                end_param += 2.0 * PI;
+            }
+            (start_param, end_param)
+        };
+
+        return vec![DxfEntity::Ellipse(DxfEllipse {
+            layer,
+            color,
+            line_type,
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            center_x: arc.center_x,
+            center_y: arc.center_y,
+            major_axis_x,
+            major_axis_y,
+            minor_ratio,
+            start_param,
+            end_param,
+        })];
+    }
+
+    // Circular arc angles are also measured from the tilt axis.
+    let (from, to) = arc.counterclockwise_angles();
+    vec![DxfEntity::Arc(DxfArc {
+        layer,
+        color,
+        line_type,
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        center_x: arc.center_x,
+        center_y: arc.center_y,
+        radius: arc.radius,
+        start_angle: rad_to_deg(from + arc.tilt_angle),
+        end_angle: rad_to_deg(to + arc.tilt_angle),
+    })]
+}
+
+// TEXT is single line only; a `\P` inside it shows up literally in most
+// viewers, so content with line breaks goes out as MTEXT instead. TEXT has
+// no letter spacing either, so spaced text (字間) needs MTEXT tracking.
+fn convert_text_entity(text: &Text, layer: String, color: i32, line_type: String) -> DxfEntity {
+    let tracking = text_tracking(text);
+    if !text.content.contains('\n') && tracking == 1.0 {
+        return DxfEntity::Text(convert_text(text, layer, color, line_type));
+    }
+
+    let converted = convert_text(text, layer, color, line_type);
+    // The JWW baseline belongs to the first line, so hang the box by its top
+    // left corner from a point one text height above it.
+    let (x, y) = text_top_left(text, converted.height);
+    let longest_line = text
+        .content
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    DxfEntity::MText(DxfMText {
+        layer: converted.layer,
+        color: converted.color,
+        line_type: converted.line_type,
+        true_color: converted.true_color,
+        xdata: converted.xdata,
+        extrusion: converted.extrusion,
+        x,
+        y,
+        height: converted.height,
+        width: text.size_x.max(0.0) * tracking * longest_line as f64,
+        rotation: converted.rotation,
+        attachment_point: 1,
+        content: text.content.trim_end_matches(['\r', '\n']).to_string(),
+        style: converted.style,
+        tracking,
+    })
+}
+
+// JWW spacing is the gap added after each character, in the same units as
+// the character width, so it maps onto a tracking factor relative to it.
+fn text_tracking(text: &Text) -> f64 {
+    if text.spacing > 0.0 && text.size_x > 0.0 {
+        1.0 + text.spacing / text.size_x
+    } else {
+        1.0
+    }
+}
+
+// Pixel count along the width of an image whose size is not resolved; the
+// height follows the image's aspect ratio.
+const FALLBACK_IMAGE_PIXELS: f64 = 1000.0;
+
+fn convert_image(
+    text: &Text,
+    image: &ImageRef,
+    image_dirs: &[PathBuf],
+    layer: String,
+    color: i32,
+    line_type: String,
+) -> DxfImage {
+    let resolved = image_dirs
+        .iter()
+        .find_map(|dir| read_image_pixel_size(&dir.join(image_file_name(&image.path)?)));
+    let (pixel_width, pixel_height) = resolved.unwrap_or_else(|| {
+        let height = (FALLBACK_IMAGE_PIXELS * image.height / image.width).round();
+        (
+            FALLBACK_IMAGE_PIXELS as u32,
+            height.clamp(1.0, u32::MAX as f64) as u32,
+        )
+    });
+    let angle = text.angle.to_radians();
+    let (cos, sin) = (angle.cos(), angle.sin());
+    let u = image.width / f64::from(pixel_width);
+    let v = image.height / f64::from(pixel_height);
+    DxfImage {
+        layer,
+        color,
+        line_type,
+        true_color: None,
+        xdata: Vec::new(),
+        path: image.path.clone(),
+        x: text.start_x,
+        y: text.start_y,
+        u_x: u * cos,
+        u_y: u * sin,
+        v_x: -v * sin,
+        v_y: v * cos,
+        pixel_width,
+        pixel_height,
+    }
+}
+
+// Last component of a Windows or Unix style path. Anything that could step
+// out of the directory it is joined to gives `None`.
+fn image_file_name(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\']).next()?;
+    (!matches!(name, "" | "." | "..") && !name.contains(':')).then_some(name)
+}
+
+// Reads the pixel size from a BMP or PNG header, which is all IMAGEDEF
+// needs. Anything but a regular file, such as a FIFO or device, and any
+// other format gives `None`.
+fn read_image_pixel_size(path: &Path) -> Option<(u32, u32)> {
+    if !fs::metadata(path).ok()?.is_file() {
+        return None;
+    }
+    let mut header = [0u8; 26];
+    let mut file = fs::File::open(path).ok()?;
+    io::Read::read_exact(&mut file, &mut header).ok()?;
+    let (width, height) = if header.starts_with(b"BM") {
+        let width = i32::from_le_bytes(header[18..22].try_into().ok()?);
+        let height = i32::from_le_bytes(header[22..26].try_into().ok()?);
+        (width.unsigned_abs(), height.unsigned_abs())
+    } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+        (width, height)
+    } else {
+        return None;
+    };
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+// Sizes are millimetres whatever `text_type` says: the font kinds only pick
+// preset sizes (kind 2 is 2.5, kind 3 is 3, ...), and the stored baseline
+// spans `size_x / 2 + spacing` per half-width character at face value. No
+// bit marks a size as points, so none is converted.
+fn convert_text(text: &Text, layer: String, color: i32, line_type: String) -> DxfText {
+    let height = if text.size_y <= 0.0 { 2.5 } else { text.size_y };
+    let width_factor = if text.size_x > 0.0 && text.size_y > 0.0 {
+        text.size_x / text.size_y
+    } else {
+        1.0
+    };
+    DxfText {
+        layer,
+        color,
+        line_type,
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        x: text.start_x,
+        y: text.start_y,
+        height,
+        rotation: text.angle,
+        content: text.content.clone(),
+        style: "STANDARD".to_string(),
+        width_factor,
+        // Jw_cad places every text by the left end of its baseline; the
+        // justification picked while typing is not stored.
+        alignment: TextAlignment::default(),
+        alignment_point: None,
+        mirrored: false,
+    }
+}
+
+// The top left corner of the first line, one text height above the start of
+// the baseline.
+fn text_top_left(text: &Text, height: f64) -> (f64, f64) {
+    let (sin, cos) = text.angle.to_radians().sin_cos();
+    (text.start_x - sin * height, text.start_y + cos * height)
+}
+
+fn convert_memo(memo: &str, position: Coord2D, height: f64) -> DxfEntity {
+    DxfEntity::MText(DxfMText {
+        layer: NOTES_LAYER.to_string(),
+        color: 7,
+        line_type: "BYLAYER".to_string(),
+        true_color: None,
+        xdata: Vec::new(),
+        extrusion: None,
+        x: position.x,
+        y: position.y,
+        height: if height <= 0.0 { 2.5 } else { height },
+        width: 0.0,
+        rotation: 0.0,
+        // Top left, so the note grows downward from the requested position.
+        attachment_point: 1,
+        content: memo.trim_end().to_string(),
+        style: "STANDARD".to_string(),
+        tracking: 1.0,
+    })
+}
+
+const LEGEND_ROW_HEIGHT: f64 = 5.0;
+const LEGEND_TEXT_HEIGHT: f64 = 2.5;
+const LEGEND_SAMPLE_LEN: f64 = 15.0;
+const LEGEND_PADDING: f64 = 2.0;
+
+// One row per layer that carries at least one entity, in layer table order:
+// a sample line on the left and the layer name on the right, framed by a
+// border with a rule between rows.
+fn convert_legend(
+    layers: &[DxfLayer],
+    entities: &[DxfEntity],
+    blocks: &[DxfBlock],
+    position: Coord2D,
+) -> Vec<DxfEntity> {
+    let used = entities
+        .iter()
+        .chain(blocks.iter().flat_map(|block| block.entities.iter()))
+        .map(DxfEntity::layer)
+        .collect::<BTreeSet<_>>();
+    let mut seen = BTreeSet::<&str>::new();
+    let rows = layers
+        .iter()
+        .filter(|layer| used.contains(layer.name.as_str()) && seen.insert(&layer.name))
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return Vec::new();
+    }
+
+    let text_x = position.x + LEGEND_PADDING * 2.0 + LEGEND_SAMPLE_LEN;
+    let longest_name = rows
+        .iter()
+        .map(|layer| layer.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let right = text_x + longest_name as f64 * LEGEND_TEXT_HEIGHT + LEGEND_PADDING;
+    let bottom = position.y - rows.len() as f64 * LEGEND_ROW_HEIGHT;
+    let rule = |y: f64| {
+        DxfEntity::Line(DxfLine {
+            layer: LEGEND_LAYER.to_string(),
+            color: 256,
+            line_type: "BYLAYER".to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: position.x,
+            y1: y,
+            x2: right,
+            y2: y,
+        })
+    };
+
+    let mut out = Vec::<DxfEntity>::new();
+    for (i, layer) in rows.iter().enumerate() {
+        let top = position.y - i as f64 * LEGEND_ROW_HEIGHT;
+        let middle = top - LEGEND_ROW_HEIGHT / 2.0;
+        out.push(rule(top));
+        out.push(DxfEntity::Line(DxfLine {
+            layer: LEGEND_LAYER.to_string(),
+            color: layer.color,
+            line_type: layer.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: position.x + LEGEND_PADDING,
+            y1: middle,
+            x2: position.x + LEGEND_PADDING + LEGEND_SAMPLE_LEN,
+            y2: middle,
+        }));
+        out.push(DxfEntity::Text(DxfText {
+            layer: LEGEND_LAYER.to_string(),
+            color: 256,
+            line_type: "BYLAYER".to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x: text_x,
+            y: middle - LEGEND_TEXT_HEIGHT / 2.0,
+            height: LEGEND_TEXT_HEIGHT,
+            rotation: 0.0,
+            content: layer.name.clone(),
+            style: "STANDARD".to_string(),
+            width_factor: 1.0,
+            alignment: TextAlignment::default(),
+            alignment_point: None,
+            mirrored: false,
+        }));
+    }
+    out.push(rule(bottom));
+    for x in [position.x, right] {
+        out.push(DxfEntity::Line(DxfLine {
+            layer: LEGEND_LAYER.to_string(),
+            color: 256,
+            line_type: "BYLAYER".to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: x,
+            y1: position.y,
+            x2: x,
+            y2: bottom,
+        }));
+    }
+    out
+}
+
+fn block_name_map(doc: &JwwDocument) -> HashMap<u32, String> {
+    let mut map = HashMap::<u32, String>::with_capacity(doc.block_defs.len());
+    for block_def in &doc.block_defs {
+        map.insert(
+            block_def.number,
+            block_def_name(block_def.number, &block_def.name),
+        );
+    }
+    map
+}
+
+fn block_def_name(number: u32, raw: &str) -> String {
+    if raw.is_empty() {
+        format!("BLOCK_{number}")
+    } else {
+        raw.to_string()
+    }
+}
+
+/// DXF layer names for every group/layer slot, resolved once per conversion
+/// instead of trimming and allocating for each entity.
+struct LayerNames {
+    names: [[String; 16]; 16],
+}
+
+impl LayerNames {
+    fn new(doc: &JwwDocument, default_name: Option<&str>) -> Self {
+        Self {
+            names: std::array::from_fn(|g| {
+                std::array::from_fn(|l| {
+                    let candidate = doc.header.layer_groups[g].layers[l].name.trim();
+                    let placeholder = format!("{:X}-{:X}", g, l);
+                    // Files saved without renaming a layer may store the
+                    // placeholder itself, which is no more a name than "".
+                    if candidate.is_empty() || candidate == placeholder {
+                        match default_name {
+                            Some(name) => name.to_string(),
+                            None => placeholder,
+                        }
+                    } else {
+                        candidate.to_string()
+                    }
+                })
+            }),
+        }
+    }
+
+    fn get(&self, layer_group: u16, layer: u16) -> Cow<'_, str> {
+        match self
+            .names
+            .get(layer_group as usize)
+            .and_then(|group| group.get(layer as usize))
+        {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("{:X}-{:X}", layer_group, layer)),
+        }
+    }
+}
+
+// One layout per group, showing the group's 16 layers. Layouts whose layers
+// end up drawing nothing are dropped once the document is converted.
+fn group_layouts(doc: &JwwDocument, layer_names: &LayerNames) -> Vec<DxfLayout> {
+    let mut taken = BTreeSet::<String>::new();
+    let mut layouts = Vec::new();
+    for (index, group) in doc.header.layer_groups.iter().enumerate() {
+        let mut layers = Vec::<String>::new();
+        for layer in 0..group.layers.len() as u16 {
+            let name = layer_names.get(index as u16, layer);
+            if !layers.iter().any(|taken| *taken == name) {
+                layers.push(name.into_owned());
+            }
+        }
+
+        let base = match group.name.trim() {
+            "" => format!("Group {index:X}"),
+            name => sanitize_layer_name(name),
+        };
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while name.eq_ignore_ascii_case("Model") || !taken.insert(name.to_uppercase()) {
+            name = format!("{base}_{suffix}");
+            suffix += 1;
+        }
+        layouts.push(DxfLayout { name, layers });
+    }
+    layouts
+}
+
+const FNV128_OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV128_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+// 128-bit FNV-1a, which unlike the std hasher is fixed across releases.
+struct Fnv128(u128);
+
+impl Fnv128 {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u128::from(byte)).wrapping_mul(FNV128_PRIME);
+        }
+    }
+}
+
+/// Canonical byte form of a model value for [`fingerprint_guid`]: integers
+/// little endian, floats by their bits, and strings and lists prefixed with
+/// their length so adjacent fields cannot run together.
+trait Fingerprint {
+    fn fingerprint(&self, hasher: &mut Fnv128);
+}
+
+impl Fingerprint for u64 {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        hasher.write(&self.to_le_bytes());
+    }
+}
+
+impl Fingerprint for f64 {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.to_bits().fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for usize {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        (*self as u64).fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for u32 {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        u64::from(*self).fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for u16 {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        u64::from(*self).fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for u8 {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        u64::from(*self).fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for bool {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        u64::from(*self).fingerprint(hasher);
+    }
+}
+
+impl Fingerprint for str {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.len().fingerprint(hasher);
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl Fingerprint for String {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.as_str().fingerprint(hasher);
+    }
+}
+
+impl<T: Fingerprint + ?Sized> Fingerprint for &T {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        (**self).fingerprint(hasher);
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for Option<T> {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        match self {
+            Some(value) => {
+                1u64.fingerprint(hasher);
+                value.fingerprint(hasher);
+            }
+            None => 0u64.fingerprint(hasher),
+        }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for [T] {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.len().fingerprint(hasher);
+        for item in self {
+            item.fingerprint(hasher);
+        }
+    }
+}
+
+impl<T: Fingerprint> Fingerprint for Vec<T> {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.as_slice().fingerprint(hasher);
+    }
+}
+
+impl<T: Fingerprint, const N: usize> Fingerprint for [T; N] {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.as_slice().fingerprint(hasher);
+    }
+}
+
+impl<A: Fingerprint, B: Fingerprint> Fingerprint for (A, B) {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        self.0.fingerprint(hasher);
+        self.1.fingerprint(hasher);
+    }
+}
+
+// Destructures without `..`, so a field added to the model fails to compile
+// here until it is fingerprinted too.
+macro_rules! impl_fingerprint {
+    ($($type:ident { $($field:ident),* $(,)? })*) => {
+        $(impl Fingerprint for $type {
+            fn fingerprint(&self, hasher: &mut Fnv128) {
+                let $type { $($field),* } = self;
+                $($field.fingerprint(hasher);)*
+            }
+        })*
+    };
+}
+
+impl_fingerprint! {
+    JwwDocument { header, entities, block_defs }
+    JwwHeader {
+        version,
+        memo,
+        paper_size,
+        write_layer_group,
+        layer_groups,
+        grid_origin,
+    }
+    LayerGroupHeader { state, write_layer, scale, protect, layers, name }
+    LayerHeader { state, protect, name }
+    Coord2D { x, y }
+    EntityBase { group, pen_style, pen_color, pen_width, layer, layer_group, flag, seq }
+    Line { base, start_x, start_y, end_x, end_y }
+    Arc {
+        base,
+        center_x,
+        center_y,
+        radius,
+        start_angle,
+        arc_angle,
+        tilt_angle,
+        flatness,
+        is_full_circle,
+    }
+    Point { base, x, y, is_temporary, code, angle, scale }
+    Text {
+        base,
+        start_x,
+        start_y,
+        end_x,
+        end_y,
+        text_type,
+        size_x,
+        size_y,
+        spacing,
+        angle,
+        font_name,
+        content,
+    }
+    Solid {
+        base,
+        point1_x,
+        point1_y,
+        point2_x,
+        point2_y,
+        point3_x,
+        point3_y,
+        point4_x,
+        point4_y,
+        color,
+        is_filled,
+    }
+    Block { base, ref_x, ref_y, scale_x, scale_y, rotation, def_number, attributes }
+    Dimension { base, line, text, sxf_mode, aux_lines, aux_points }
+    PolyVertex { point, bulge }
+    Polyline { base, vertices, closed }
+    BlockDef { base, number, is_referenced, created_time, name, entities }
+}
+
+impl Fingerprint for Entity {
+    fn fingerprint(&self, hasher: &mut Fnv128) {
+        match self {
+            Self::Line(v) => (0u8, v).fingerprint(hasher),
+            Self::Arc(v) => (1u8, v).fingerprint(hasher),
+            Self::Point(v) => (2u8, v).fingerprint(hasher),
+            Self::Text(v) => (3u8, v).fingerprint(hasher),
+            Self::Solid(v) => (4u8, v).fingerprint(hasher),
+            Self::Block(v) => (5u8, v).fingerprint(hasher),
+            Self::Dimension(v) => (6u8, v).fingerprint(hasher),
+            Self::Polyline(v) => (7u8, v).fingerprint(hasher),
+        }
+    }
+}
+
+// Braced and upper case like AutoCAD's own GUIDs, with the version and
+// variant bits of an RFC 9562 version 8 (custom) UUID.
+fn fingerprint_guid(source: &(impl Fingerprint + ?Sized)) -> String {
+    let mut hasher = Fnv128(FNV128_OFFSET);
+    source.fingerprint(&mut hasher);
+    let bits = hasher.0 & !(0xF << 76) & !(0x3 << 62) | (0x8 << 76) | (0x2 << 62);
+    format!(
+        "{{{:08X}-{:04X}-{:04X}-{:04X}-{:012X}}}",
+        bits >> 96,
+        (bits >> 80) & 0xFFFF,
+        (bits >> 64) & 0xFFFF,
+        (bits >> 48) & 0xFFFF,
+        bits & 0xFFFF_FFFF_FFFF
+    )
+}
+
+fn layer_scale(doc: &JwwDocument, entity: &Entity, options: &ConvertOptions) -> f64 {
+    if !options.apply_layer_scale {
+        return 1.0;
+    }
+    let group = entity.base().layer_group as usize;
+    match doc.header.layer_groups.get(group) {
+        Some(group) if group.scale.is_finite() && group.scale > 0.0 => group.scale,
+        _ => 1.0,
+    }
+}
+
+// Filters that only look at the JWW entity, so they apply before conversion.
+fn entity_selected(doc: &JwwDocument, entity: &Entity, options: &ConvertOptions) -> bool {
+    if options.printable_only && !entity.is_printable(doc) {
+        return false;
+    }
+    let base = entity.base();
+    options
+        .layer_filter
+        .as_ref()
+        .is_none_or(|slots| slots.contains(&(base.layer_group, base.layer)))
+}
+
+fn layer_allowed(
+    layer_names: &LayerNames,
+    entity: &Entity,
+    filter: Option<&LayerNameFilter>,
+) -> bool {
+    let Some(filter) = filter else {
+        return true;
+    };
+    let base = entity.base();
+    filter.matches(&layer_names.get(base.layer_group, base.layer))
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let value = value.chars().collect::<Vec<_>>();
+    let (mut p, mut v) = (0usize, 0usize);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p].eq_ignore_ascii_case(&value[v])) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star_p, star_v)) = backtrack {
+            p = star_p + 1;
+            v = star_v + 1;
+            backtrack = Some((star_p, star_v + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+fn map_color(pen_color: u16) -> i32 {
+    match pen_color {
+        1 | 8 => 7,
+        2 => 5,
+        3 => 1,
+        4 => 6,
+        5 => 3,
+        6 => 4,
+        7 => 2,
+        9 => 8,
+        _ => ((pen_color as i32) % 255).max(1),
+    }
+}
+
+/// JWW pen for an ACI color, undoing `map_color`. Pens 1 and 8 share ACI 7,
+/// which comes back as pen 1. ByBlock, ByLayer and out-of-range values
+/// fall back to pen 1 as well; other colors keep their number, as pens
+/// above 9 do on the way out.
+pub fn aci_to_pen(aci: i32) -> u16 {
+    match aci {
+        7 => 1,
+        5 => 2,
+        1 => 3,
+        6 => 4,
+        3 => 5,
+        4 => 6,
+        2 => 7,
+        8 => 9,
+        9..=255 => aci as u16,
+        _ => 1,
+    }
+}
+
+// Jw_cad's default pen colors, matching the ACI picks in `map_color`.
+fn pen_rgb(pen_color: u16) -> Option<u32> {
+    match pen_color {
+        1 | 8 => Some(0x000000),
+        2 => Some(0x0000FF),
+        3 => Some(0xFF0000),
+        4 => Some(0xFF00FF),
+        5 => Some(0x00FF00),
+        6 => Some(0x00FFFF),
+        7 => Some(0xFFFF00),
+        9 => Some(0x808080),
+        _ => None,
+    }
+}
+
+// JWW stores custom colors as Windows COLORREFs (0x00BBGGRR); group 420
+// wants 0x00RRGGBB.
+fn colorref_to_rgb(colorref: u32) -> u32 {
+    let [red, green, blue, _] = colorref.to_le_bytes();
+    u32::from_be_bytes([0, red, green, blue])
+}
+
+/// XDATA key [`ConvertOptions::pen_xdata`] stores the JWW pen under.
+pub(crate) const PEN_XDATA_KEY: &str = "JWW_PEN";
+
+fn set_pen_xdata(entity: &Entity, converted: &mut [DxfEntity]) {
+    let pen = i32::from(entity.base().pen_color);
+    for dxf_entity in converted {
+        dxf_entity
+            .xdata_mut()
+            .push((PEN_XDATA_KEY.to_string(), XDataValue::Integer(pen)));
+    }
+}
+
+/// The pen [`ConvertOptions::pen_xdata`] recorded on `entity`, if any.
+pub(crate) fn entity_pen(entity: &DxfEntity) -> Option<u16> {
+    entity.xdata().iter().find_map(|(key, value)| match value {
+        XDataValue::Integer(pen) if key == PEN_XDATA_KEY => u16::try_from(*pen).ok(),
+        _ => None,
+    })
+}
+
+fn set_true_color(entity: &Entity, converted: &mut [DxfEntity]) {
+    let true_color = match entity {
+        Entity::Solid(Solid {
+            color: Some(colorref),
+            ..
+        }) => Some(colorref_to_rgb(*colorref)),
+        _ => pen_rgb(entity.base().pen_color),
+    };
+    for dxf_entity in converted {
+        *dxf_entity.true_color_mut() = true_color;
+    }
+}
+
+// JWW numbers its standard line types from 1: solid, dotted 1-3, one-dot
+// chain 1-2, two-dot chain 1-2, then the construction line. Random and
+// double-length types (11 and up) have no DXF counterpart.
+fn map_line_type(pen_style: u8) -> &'static str {
+    match pen_style {
+        0 | 1 => "CONTINUOUS",
+        2 => "DASHED",
+        3 => "DASHED2",
+        4 => "DOT",
+        5 => "DASHDOT",
+        6 => "DASHDOT2",
+        7 => "DIVIDE",
+        8 => "DIVIDE2",
+        9 => "DOT2",
+        _ => "BYLAYER",
+    }
+}
+
+fn rad_to_deg(rad: f64) -> f64 {
+    rad * 180.0 / PI
+}
+
+#[cfg(test)]
+mod tests {
+    use std::array;
+    use std::collections::BTreeSet;
+    use std::f64::consts::PI;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::error::JwwError;
+    use crate::header::{JwwHeader, LayerGroupHeader, LayerHeader};
+    use crate::model::{
+        Arc, Block, BlockDef, Coord2D, Entity, EntityBase, JwwDocument, Line, Point, PolyVertex,
+        Polyline, Solid, Text,
+    };
+    use crate::parser::read_document_from_file;
+
+    use super::{
+        aci_to_pen, convert_document, convert_document_with_options, document_to_bytes,
+        document_to_string, document_to_string_with_options, map_color, merge_documents,
+        try_convert_document_with_options, ConvertOptions, DxfDocument, DxfEntity, DxfInsert,
+        DxfLayer, DxfLine, DxfText, DxfVersion, HorizontalAlignment, LayerNameFilter, LayerNames,
+        TargetUnits, TextAlignment, VerticalAlignment, WriteOptions, XDataValue,
+        ARC_CHORD_TOLERANCE, DEFAULT_XDATA_APP_ID, POINT_MARKER_CROSS, TEMP_POINTS_LAYER,
+    };
+
+    fn empty_header() -> JwwHeader {
+        JwwHeader {
+            version: 600,
+            memo: String::new(),
+            paper_size: 0,
+            write_layer_group: 0,
+            layer_groups: array::from_fn(|g| LayerGroupHeader {
+                state: 0,
+                write_layer: 0,
+                scale: 1.0,
+                protect: 0,
+                name: format!("Group{g:X}"),
+                layers: array::from_fn(|l| LayerHeader {
+                    state: 0,
+                    protect: 0,
+                    name: format!("{g:X}-{l:X}"),
+                }),
+            }),
+            grid_origin: None,
+        }
+    }
+
+    fn jww_samples_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples")
+    }
+
+    #[test]
+    fn convert_document_handles_line_and_dimension() {
+        let base = EntityBase::default();
+        let line = Entity::Line(Line {
+            base,
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 10.0,
+            end_y: 0.0,
+        });
+        let dim = Entity::Dimension(crate::model::Dimension {
+            base,
+            line: Line {
+                base,
+                start_x: 0.0,
+                start_y: 1.0,
+                end_x: 10.0,
+                end_y: 1.0,
+            },
+            text: Text {
+                base,
+                start_x: 5.0,
+                start_y: 2.0,
+                end_x: 5.0,
+                end_y: 2.0,
+                text_type: 0,
+                size_x: 1.0,
+                size_y: 1.0,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: String::new(),
+                content: "1000".to_string(),
+            },
+            sxf_mode: Some(0),
+            aux_lines: vec![],
+            aux_points: vec![],
+        });
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![line, dim],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        let types = dxf
+            .entities
+            .iter()
+            .map(DxfEntity::entity_type)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec!["LINE", "LINE", "TEXT"]);
+    }
+
+    #[test]
+    fn convert_document_emits_dimension_extension_lines_and_points() {
+        let base = EntityBase::default();
+        let line = Entity::Line(Line {
+            base,
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 10.0,
+            end_y: 0.0,
+        });
+        let extension = |x: f64| Line {
+            base: EntityBase {
+                pen_color: 3,
+                ..base
+            },
+            start_x: x,
+            start_y: 0.0,
+            end_x: x,
+            end_y: 1.5,
+        };
+        let arrow_point = |x: f64, is_temporary: bool| Point {
+            base,
+            x,
+            y: 1.0,
+            is_temporary,
+            code: 0,
+            angle: 0.0,
+            scale: 1.0,
+        };
+        let dim = Entity::Dimension(crate::model::Dimension {
+            base,
+            line: Line {
+                base,
+                start_x: 0.0,
+                start_y: 1.0,
+                end_x: 10.0,
+                end_y: 1.0,
+            },
+            text: Text {
+                base,
+                start_x: 5.0,
+                start_y: 2.0,
+                end_x: 5.0,
+                end_y: 2.0,
+                text_type: 0,
+                size_x: 1.0,
+                size_y: 1.0,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: String::new(),
+                content: "1000".to_string(),
+            },
+            sxf_mode: Some(0),
+            aux_lines: vec![extension(0.0), extension(10.0)],
+            aux_points: vec![arrow_point(0.0, false), arrow_point(10.0, true)],
+        });
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![line, dim],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        let types = dxf
+            .entities
+            .iter()
+            .map(DxfEntity::entity_type)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec!["LINE", "LINE", "TEXT", "LINE", "LINE", "POINT"]);
+        assert!(contains_line(&dxf.entities, 10.0, 0.0, 10.0, 1.5));
+        // Extension lines keep their own pen color (pen 3 is red).
+        assert_eq!(dxf.entities[3].color(), 1);
+    }
+
+    #[test]
+    fn convert_document_draws_dimension_arrows_pointing_inward() {
+        let base = EntityBase::default();
+        let dim = Entity::Dimension(crate::model::Dimension {
+            base,
+            line: Line {
+                base,
+                start_x: 0.0,
+                start_y: 5.0,
+                end_x: 100.0,
+                end_y: 5.0,
+            },
+            text: Text {
+                base,
+                start_x: 50.0,
+                start_y: 6.0,
+                end_x: 50.0,
+                end_y: 6.0,
+                text_type: 0,
+                size_x: 3.0,
+                size_y: 3.0,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: String::new(),
+                content: "100".to_string(),
+            },
+            sxf_mode: None,
+            aux_lines: vec![],
+            aux_points: vec![],
+        });
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![dim],
+            block_defs: vec![],
+        };
+
+        assert!(!convert_document(&doc)
+            .entities
+            .iter()
+            .any(|e| matches!(e, DxfEntity::Solid(_))));
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                draw_dimension_arrows: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let solids = dxf
+            .entities
+            .iter()
+            .filter_map(|e| match e {
+                DxfEntity::Solid(v) => Some(v),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(solids.len(), 2);
+        for solid in &solids {
+            // Triangles repeat their last corner.
+            assert_eq!((solid.x3, solid.y3), (solid.x4, solid.y4));
+            assert_eq!(solid.x2, solid.x3);
+            assert!(nearly_eq(solid.y2 - solid.y3, 1.0));
+        }
+        assert_eq!((solids[0].x1, solids[0].y1), (0.0, 5.0));
+        assert_eq!(solids[0].x2, 3.0);
+        assert_eq!((solids[1].x1, solids[1].y1), (100.0, 5.0));
+        assert_eq!(solids[1].x2, 97.0);
+    }
+
+    #[test]
+    fn insert_attributes_are_written_as_attribs() {
+        let insert = |ref_x: f64, attributes: Vec<(String, String)>| {
+            Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                insert(0.0, vec![("ROOM".to_string(), "101".to_string())]),
+                insert(5.0, vec![]),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: String::new(),
+                entities: vec![],
+            }],
+        };
+
+        let out = document_to_string(&convert_document(&doc)).unwrap();
+        let insert = &out[out.find("  0\nINSERT\n").unwrap()..];
+        let attrib = &insert[insert.find("  0\nATTRIB\n").unwrap()..];
+        assert!(insert.contains(" 66\n1\n"));
+        assert!(attrib.contains("  1\n101\n  2\nROOM\n"));
+        assert_eq!(attrib.matches("  0\nSEQEND\n").count(), 1);
+        assert_eq!(out.matches("  0\nATTRIB\n").count(), 1);
+    }
+
+    #[test]
+    fn merge_documents_prefixes_layers_and_renumbers_blocks() {
+        let insert = |def_number: u32| {
+            Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let line = Entity::Line(Line {
+            base: EntityBase::default(),
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 1.0,
+            end_y: 0.0,
+        });
+        let block_def = |number: u32, name: &str, entities: Vec<Entity>| BlockDef {
+            base: EntityBase::default(),
+            number,
+            is_referenced: true,
+            created_time: 0,
+            name: name.to_string(),
+            entities,
+        };
+        let first = JwwDocument {
+            header: empty_header(),
+            entities: vec![insert(1)],
+            block_defs: vec![block_def(1, "Door", vec![line.clone()])],
+        };
+        // Both drawings define block 1; the second also nests it in block 2.
+        let second = JwwDocument {
+            header: empty_header(),
+            entities: vec![insert(2), line.clone()],
+            block_defs: vec![
+                block_def(1, "", vec![line.clone()]),
+                block_def(2, "Door", vec![insert(1)]),
+            ],
+        };
+
+        let merged = merge_documents(
+            &[("A-", &first), ("B-", &second)],
+            ConvertOptions::default(),
+        );
+        let block_names = merged
+            .blocks
+            .iter()
+            .map(|block| block.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(block_names, vec!["A-Door", "BLOCK_3", "B-Door"]);
+
+        let insert_names = |entities: &[DxfEntity]| {
+            entities
+                .iter()
+                .filter_map(|entity| match entity {
+                    DxfEntity::Insert(insert) => Some(insert.block_name.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(insert_names(&merged.entities), vec!["A-Door", "B-Door"]);
+        assert_eq!(insert_names(&merged.blocks[2].entities), vec!["BLOCK_3"]);
+
+        let layer_names = merged
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(layer_names, vec!["A-0-0", "B-0-0"]);
+        assert!(merged
+            .entities
+            .iter()
+            .all(|entity| { entity.layer() == "A-0-0" || entity.layer() == "B-0-0" }));
+    }
+
+    #[test]
+    fn merge_documents_keeps_def_zero_and_group_blocks_apart() {
+        let line = |group: u32| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    group,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 0.0,
+            })
+        };
+        // Zero-based block numbers, as renumber_block_defs leaves them.
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Block(Block {
+                    base: EntityBase::default(),
+                    ref_x: 0.0,
+                    ref_y: 0.0,
+                    scale_x: 1.0,
+                    scale_y: 1.0,
+                    rotation: 0.0,
+                    def_number: 0,
+                    attributes: vec![],
+                }),
+                line(1),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 0,
+                is_referenced: true,
+                created_time: 0,
+                name: String::new(),
+                entities: vec![line(0)],
+            }],
+        };
+
+        let merged = merge_documents(
+            &[("A-", &doc), ("B-", &doc)],
+            ConvertOptions {
+                emit_groups_as_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let block_names = merged
+            .blocks
+            .iter()
+            .map(|block| block.name.as_str())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            block_names,
+            BTreeSet::from(["*U1", "*U3", "BLOCK_0", "BLOCK_1"])
+        );
+        let insert_names = merged
+            .entities
+            .iter()
+            .filter_map(|entity| match entity {
+                DxfEntity::Insert(insert) => Some(insert.block_name.as_str()),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(insert_names, block_names);
+    }
+
+    #[test]
+    fn convert_document_resolves_insert_block_name() {
+        let base = EntityBase::default();
+        let entity = Entity::Block(Block {
+            base,
+            ref_x: 1.0,
+            ref_y: 2.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            def_number: 5,
+            attributes: vec![],
+        });
+
+        let block_def = BlockDef {
+            base,
+            number: 5,
+            is_referenced: true,
+            created_time: 0,
+            name: "Door".to_string(),
+            entities: vec![],
+        };
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![entity],
+            block_defs: vec![block_def],
+        };
+
+        let dxf = convert_document(&doc);
+        match &dxf.entities[0] {
+            DxfEntity::Insert(v) => assert_eq!(v.block_name, "Door"),
+            other => panic!("expected INSERT, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_block_refs_reports_dangling_inserts() {
+        let base = EntityBase::default();
+        let insert = |def_number: u32| {
+            Entity::Block(Block {
+                base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![insert(5)],
+            block_defs: vec![BlockDef {
+                base,
+                number: 5,
+                is_referenced: true,
+                created_time: 0,
+                name: "Door".to_string(),
+                entities: vec![insert(5)],
+            }],
+        };
+        let mut dxf = convert_document(&doc);
+        assert!(dxf.validate_block_refs().is_empty());
+
+        let DxfEntity::Insert(door) = dxf.entities[0].clone() else {
+            panic!("expected INSERT, got {:?}", dxf.entities[0]);
+        };
+        let to = |block_name: &str| {
+            DxfEntity::Insert(DxfInsert {
+                block_name: block_name.to_string(),
+                ..door.clone()
+            })
+        };
+        dxf.entities
+            .extend([to("Missing"), to("DOOR"), to("Missing")]);
+        dxf.blocks[0].entities.push(to("Gone"));
+        assert_eq!(dxf.validate_block_refs(), vec!["Gone", "Missing"]);
+    }
+
+    #[test]
+    fn convert_document_prunes_empty_blocks() {
+        let base = EntityBase::default();
+        let insert = |def_number: u32| {
+            Entity::Block(Block {
+                base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let block_def = |number: u32, name: &str, entities: Vec<Entity>| BlockDef {
+            base,
+            number,
+            is_referenced: true,
+            created_time: 0,
+            name: name.to_string(),
+            entities,
+        };
+        let line = Entity::Line(Line {
+            base,
+            start_x: 0.0,
+            start_y: 0.0,
+            end_x: 1.0,
+            end_y: 0.0,
+        });
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![insert(1), insert(2), insert(3)],
+            block_defs: vec![
+                block_def(1, "Empty", vec![]),
+                block_def(2, "OnlyEmpty", vec![insert(1)]),
+                block_def(3, "Door", vec![line]),
+            ],
+        };
+
+        let kept = convert_document(&doc);
+        assert_eq!(kept.blocks.len(), 3);
+
+        let pruned = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                prune_empty_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let names = pruned
+            .blocks
+            .iter()
+            .map(|block| block.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Door"]);
+        assert_eq!(pruned.entities.len(), 1);
+
+        let output = document_to_string(&pruned).unwrap();
+        let block_names = group_values_by_code(&output, 2);
+        assert!(!block_names.iter().any(|name| name == "Empty"));
+        assert!(!block_names.iter().any(|name| name == "OnlyEmpty"));
+        assert!(block_names.iter().any(|name| name == "Door"));
+    }
+
+    #[test]
+    fn convert_document_writes_block_entities_byblock() {
+        let insert_base = EntityBase {
+            pen_color: 2,
+            ..EntityBase::default()
+        };
+        let line_base = EntityBase {
+            pen_color: 5,
+            ..EntityBase::default()
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Block(Block {
+                base: insert_base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 5,
+                attributes: vec![],
+            })],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 5,
+                is_referenced: true,
+                created_time: 0,
+                name: "Door".to_string(),
+                entities: vec![Entity::Line(Line {
+                    base: line_base,
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                })],
+            }],
+        };
+
+        let plain = convert_document(&doc);
+        let mapped_line_color = plain.blocks[0].entities[0].color();
+        assert_ne!(mapped_line_color, 0);
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                block_entities_byblock: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert_eq!(dxf.blocks[0].entities[0].color(), 0);
+        assert_eq!(dxf.entities[0].color(), plain.entities[0].color());
+        assert_ne!(dxf.entities[0].color(), 0);
+
+        let out = document_to_string(&dxf).unwrap();
+        let block = &out[out.find("  0\nLINE\n").unwrap()..];
+        assert_eq!(group_values_by_code(block, 62)[0], "0");
+        let insert = &out[out.find("  0\nINSERT\n").unwrap()..];
+        assert_eq!(
+            group_values_by_code(insert, 62)[0],
+            plain.entities[0].color().to_string()
+        );
+    }
+
+    #[test]
+    fn convert_document_explode_inserts_expands_nested_blocks() {
+        let base = EntityBase::default();
+        let top_insert = Entity::Block(Block {
+            base,
+            ref_x: 10.0,
+            ref_y: 20.0,
+            scale_x: 2.0,
+            scale_y: 2.0,
+            rotation: 0.0,
+            def_number: 1,
+            attributes: vec![],
+        });
+
+        let block_2 = BlockDef {
+            base,
+            number: 2,
+            is_referenced: true,
+            created_time: 0,
+            name: "B2".to_string(),
+            entities: vec![Entity::Line(Line {
+                base,
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 0.0,
+                end_y: 1.0,
+            })],
+        };
+
+        let block_1 = BlockDef {
+            base,
+            number: 1,
+            is_referenced: true,
+            created_time: 0,
+            name: "B1".to_string(),
+            entities: vec![
+                Entity::Line(Line {
+                    base,
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                }),
+                Entity::Block(Block {
+                    base,
+                    ref_x: 0.0,
+                    ref_y: 2.0,
+                    scale_x: 1.0,
+                    scale_y: 1.0,
+                    rotation: 0.0,
+                    def_number: 2,
+                    attributes: vec![],
+                }),
+            ],
+        };
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![top_insert],
+            block_defs: vec![block_1, block_2],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                max_block_nesting: 32,
+                ..ConvertOptions::default()
+            },
+        );
+
+        assert!(dxf.blocks.is_empty());
+        assert!(!dxf.entities.is_empty());
+        assert!(!dxf
+            .entities
+            .iter()
+            .any(|e| matches!(e, DxfEntity::Insert(_))));
+
+        assert!(contains_line(&dxf.entities, 10.0, 20.0, 12.0, 20.0));
+        assert!(contains_line(&dxf.entities, 10.0, 24.0, 10.0, 26.0));
+    }
+
+    #[test]
+    fn convert_document_explode_inserts_handles_mirrored_insert() {
+        let base = EntityBase::default();
+        let insert = |scale_x: f64, scale_y: f64| {
+            Entity::Block(Block {
+                base,
+                ref_x: 10.0,
+                ref_y: 0.0,
+                scale_x,
+                scale_y,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![],
+            })
+        };
+        let block_def = BlockDef {
+            base,
+            number: 1,
+            is_referenced: true,
+            created_time: 0,
+            name: "Door".to_string(),
+            entities: vec![
+                Entity::Line(Line {
+                    base,
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                }),
+                Entity::Text(Text {
+                    base,
+                    start_x: 1.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                    text_type: 0,
+                    size_x: 1.0,
+                    size_y: 1.0,
+                    spacing: 0.0,
+                    angle: 30.0,
+                    font_name: String::new(),
+                    content: "A".to_string(),
+                }),
+            ],
+        };
+        let explode = |insert: Entity| {
+            convert_document_with_options(
+                &JwwDocument {
+                    header: empty_header(),
+                    entities: vec![insert],
+                    block_defs: vec![block_def.clone()],
+                },
+                ConvertOptions {
+                    explode_inserts: true,
+                    ..ConvertOptions::default()
+                },
+            )
+        };
+        let text_of = |dxf: &DxfDocument| {
+            dxf.entities
+                .iter()
+                .find_map(|entity| match entity {
+                    DxfEntity::Text(v) => Some(v.clone()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let mirrored = explode(insert(-1.0, 1.0));
+        assert!(contains_line(&mirrored.entities, 10.0, 0.0, 9.0, 0.0));
+        let text = text_of(&mirrored);
+        assert!(nearly_eq(text.x, 9.0) && nearly_eq(text.y, 0.0));
+        // Up stays at 120° while the baseline runs backwards.
+        assert!(text.mirrored);
+        assert!(nearly_eq(text.rotation.rem_euclid(360.0), 330.0));
+        let output = document_to_string(&mirrored).unwrap();
+        assert!(group_values_by_code(&output, 71).contains(&"2".to_string()));
+
+        // Mirroring twice is a half turn, which needs no flag.
+        let text = text_of(&explode(insert(-1.0, -1.0)));
+        assert!(!text.mirrored);
+        assert!(nearly_eq(text.rotation.rem_euclid(360.0), 210.0));
+    }
+
+    #[test]
+    fn convert_document_explode_inserts_detects_cycle() {
+        let base = EntityBase::default();
+        let top_insert = Entity::Block(Block {
+            base,
+            ref_x: 0.0,
+            ref_y: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            def_number: 1,
+            attributes: vec![],
+        });
+
+        let block_1 = BlockDef {
+            base,
+            number: 1,
+            is_referenced: true,
+            created_time: 0,
+            name: "B1".to_string(),
+            entities: vec![Entity::Block(Block {
+                base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 2,
+                attributes: vec![],
+            })],
+        };
+        let block_2 = BlockDef {
+            base,
+            number: 2,
+            is_referenced: true,
+            created_time: 0,
+            name: "B2".to_string(),
+            entities: vec![Entity::Block(Block {
+                base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![],
+            })],
+        };
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![top_insert],
+            block_defs: vec![block_1, block_2],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                max_block_nesting: 32,
+                ..ConvertOptions::default()
+            },
+        );
+
+        assert!(dxf
+            .unsupported_entities
+            .iter()
+            .any(|v| v.starts_with("BLOCK_CYCLE(")));
+    }
+
+    #[test]
+    fn convert_document_explode_inserts_reports_unresolved_block() {
+        let base = EntityBase::default();
+        let top_insert = Entity::Block(Block {
+            base,
+            ref_x: 0.0,
+            ref_y: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            def_number: 999,
+            attributes: vec![],
+        });
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![top_insert],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                max_block_nesting: 32,
+                ..ConvertOptions::default()
+            },
+        );
+
+        assert!(dxf.entities.is_empty());
+        assert!(dxf.blocks.is_empty());
+        assert!(dxf
+            .unsupported_entities
+            .iter()
+            .any(|v| v == "UNRESOLVED_BLOCK(999)"));
+    }
+
+    #[test]
+    fn convert_document_explode_inserts_enforces_depth_limit() {
+        let base = EntityBase::default();
+        let top_insert = Entity::Block(Block {
+            base,
+            ref_x: 0.0,
+            ref_y: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            rotation: 0.0,
+            def_number: 1,
+            attributes: vec![],
+        });
+
+        let block_2 = BlockDef {
+            base,
+            number: 2,
+            is_referenced: true,
+            created_time: 0,
+            name: "B2".to_string(),
+            entities: vec![Entity::Line(Line {
+                base,
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 0.0,
+            })],
+        };
+
+        let block_1 = BlockDef {
+            base,
+            number: 1,
+            is_referenced: true,
+            created_time: 0,
+            name: "B1".to_string(),
+            entities: vec![Entity::Block(Block {
+                base,
+                ref_x: 5.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 2,
+                attributes: vec![],
+            })],
+        };
+
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![top_insert],
+            block_defs: vec![block_1, block_2],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                max_block_nesting: 1,
+                ..ConvertOptions::default()
+            },
+        );
+
+        assert!(dxf.entities.is_empty());
+        assert!(dxf
+            .unsupported_entities
+            .iter()
+            .any(|v| v == "BLOCK_DEPTH_LIMIT(2)"));
+    }
+
+    #[test]
+    fn document_to_string_emits_minimum_dxf_sections() {
+        let base = EntityBase::default();
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base,
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 10.0,
+                end_y: 0.0,
+            })],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        let out = document_to_string(&dxf).unwrap();
+
+        assert!(out.contains("  0\nSECTION\n  2\nHEADER\n"));
+        assert!(out.contains("  2\nTABLES\n"));
+        assert!(out.contains("  2\nBLOCKS\n"));
+        assert!(out.contains("  2\nENTITIES\n"));
+        assert!(out.contains("  0\nLINE\n"));
+        assert!(out.ends_with("  0\nEOF\n"));
+    }
+
+    #[test]
+    fn document_to_string_escapes_unicode_fields() {
+        let dxf = DxfDocument {
+            layers: vec![DxfLayer {
+                name: "図面".to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                frozen: false,
+                locked: false,
+            }],
+            entities: vec![DxfEntity::Text(DxfText {
+                layer: "図面".to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion: None,
+                x: 0.0,
+                y: 0.0,
+                height: 2.5,
+                rotation: 0.0,
+                content: "日本語".to_string(),
+                style: "STANDARD".to_string(),
+                width_factor: 1.0,
+                alignment: TextAlignment::default(),
+                alignment_point: None,
+                mirrored: false,
+            })],
+            blocks: vec![],
+            unsupported_entities: vec![],
+            units: TargetUnits::Millimeters,
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::R2000,
+        };
+
+        let out = document_to_string(&dxf).unwrap();
+        assert!(out.contains("\\U+56F3\\U+9762"));
+        assert!(out.contains("\\U+65E5\\U+672C\\U+8A9E"));
+        assert_eq!(document_to_bytes(&dxf).unwrap(), out.as_bytes());
+    }
+
+    #[test]
+    fn document_to_bytes_encodes_cp1252() {
+        let dxf = DxfDocument {
+            layers: vec![DxfLayer {
+                name: "0".to_string(),
+                color: 7,
+                line_type: "CAÑO".to_string(),
+                frozen: false,
+                locked: false,
+            }],
+            entities: vec![],
+            blocks: vec![],
+            unsupported_entities: vec![],
+            units: TargetUnits::Millimeters,
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::R2000,
+        };
+
+        let bytes = document_to_bytes(&dxf).unwrap();
+        assert!(bytes.windows(4).any(|w| w == b"CA\xD1O"));
+        assert!(!bytes.windows(2).any(|w| w == "Ñ".as_bytes()));
+        assert!(std::str::from_utf8(&bytes).is_err());
+    }
+
+    #[test]
+    fn document_to_string_writes_non_default_extrusion() {
+        let line = |extrusion| {
+            DxfEntity::Line(DxfLine {
+                layer: "0".to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion,
+                x1: 0.0,
+                y1: 0.0,
+                x2: 1.0,
+                y2: 0.0,
+            })
+        };
+        let dxf = DxfDocument {
+            layers: vec![],
+            entities: vec![
+                line(Some([0.0, 0.0, -1.0])),
+                line(Some([0.0, 0.0, 1.0])),
+                line(None),
+            ],
+            blocks: vec![],
+            unsupported_entities: vec![],
+            units: TargetUnits::Millimeters,
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::R2000,
+        };
+
+        let out = document_to_string(&dxf).unwrap();
+        assert_eq!(group_values_by_code(&out, 210).len(), 1);
+        assert_eq!(group_values_by_code(&out, 220).len(), 1);
+        let z = group_values_by_code(&out, 230);
+        assert_eq!(z.len(), 1);
+        assert_eq!(z[0].parse::<f64>().unwrap(), -1.0);
+    }
+
+    #[test]
+    fn document_to_string_writes_text_alignment_codes() {
+        let text = |alignment| {
+            DxfEntity::Text(DxfText {
+                layer: "0".to_string(),
+                color: 7,
+                line_type: "CONTINUOUS".to_string(),
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion: None,
+                x: 3.0,
+                y: 4.0,
+                height: 2.5,
+                rotation: 0.0,
+                content: "A".to_string(),
+                style: "STANDARD".to_string(),
+                width_factor: 1.0,
+                alignment,
+                alignment_point: None,
+                mirrored: false,
+            })
+        };
+        let dxf = DxfDocument {
+            layers: vec![],
+            entities: vec![text(TextAlignment::new(
+                HorizontalAlignment::Center,
+                VerticalAlignment::Middle,
+            ))],
+            blocks: vec![],
+            unsupported_entities: vec![],
+            units: TargetUnits::Millimeters,
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::R2000,
+        };
+        let out = document_to_string(&dxf).unwrap();
+        let entity = &out[out.find("  0\nTEXT\n").unwrap()..];
+        assert_eq!(group_values_by_code(entity, 72), vec!["1"]);
+        assert_eq!(group_values_by_code(entity, 73), vec!["2"]);
+        assert_eq!(group_values_by_code(entity, 11), vec!["3.000000000000"]);
+        assert_eq!(group_values_by_code(entity, 21), vec!["4.000000000000"]);
+
+        let dxf = DxfDocument {
+            entities: vec![text(TextAlignment::default())],
+            ..dxf
+        };
+        let out = document_to_string(&dxf).unwrap();
+        let entity = &out[out.find("  0\nTEXT\n").unwrap()..];
+        assert!(group_values_by_code(entity, 72).is_empty());
+        assert!(group_values_by_code(entity, 73).is_empty());
+    }
+
+    #[test]
+    fn convert_and_write_all_jww_samples() {
+        let dir = jww_samples_dir();
+        let mut files = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "jww").unwrap_or(false))
+            .collect::<Vec<_>>();
+        files.sort();
+        assert!(!files.is_empty(), "no .jww files found in jww_samples");
+
+        for path in files {
+            let doc = read_document_from_file(&path)
+                .unwrap_or_else(|e| panic!("failed parsing {}: {e}", path.display()));
+            let dxf = convert_document(&doc);
+            let output = document_to_string(&dxf).unwrap();
+            assert!(output.starts_with("  0\nSECTION\n  2\nHEADER\n"));
+            assert!(output.ends_with("  0\nEOF\n"));
+            assert!(
+                dxf.unsupported_entities.is_empty(),
+                "unsupported entities in {}: {:?}",
+                path.display(),
+                dxf.unsupported_entities
+            );
+        }
+    }
+
+    #[test]
+    fn layer_names_match_per_entity_resolution() {
+        let mut header = empty_header();
+        header.layer_groups[0].layers[1].name = "  Walls  ".to_string();
+        header.layer_groups[2].layers[3].name = "   ".to_string();
+        let doc = JwwDocument {
+            header,
+            entities: Vec::new(),
+            block_defs: Vec::new(),
+        };
+
+        let names = LayerNames::new(&doc, None);
+        assert_eq!(names.get(0, 1), "Walls");
+        assert_eq!(names.get(2, 3), "2-3");
+        assert_eq!(names.get(15, 15), "F-F");
+        assert_eq!(names.get(16, 0), "10-0");
+        assert_eq!(names.get(0, 20), "0-14");
+
+        for path in fs::read_dir(jww_samples_dir())
+            .unwrap()
+            .filter_map(Result::ok)
+        {
+            let path = path.path();
+            if path.extension().map(|ext| ext != "jww").unwrap_or(true) {
+                continue;
+            }
+            let doc = read_document_from_file(&path).unwrap();
+            let names = LayerNames::new(&doc, None);
+            for g in 0..16u16 {
+                for l in 0..16u16 {
+                    let raw = doc.header.layer_groups[g as usize].layers[l as usize]
+                        .name
+                        .trim();
+                    let expected = if raw.is_empty() {
+                        format!("{g:X}-{l:X}")
+                    } else {
+                        raw.to_string()
+                    };
+                    assert_eq!(names.get(g, l), expected, "{}", path.display());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn convert_document_maps_jww_line_types_to_dash_patterns() {
+        let line = |pen_style: u8| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    pen_style,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 10.0,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: (1..=9).map(line).collect(),
+            block_defs: Vec::new(),
+        };
+
+        let dxf = convert_document(&doc);
+        let line_types = dxf
+            .entities
+            .iter()
+            .map(|entity| match entity {
+                DxfEntity::Line(v) => v.line_type.as_str(),
+                other => panic!("expected LINE, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            line_types,
+            vec![
+                "CONTINUOUS",
+                "DASHED",
+                "DASHED2",
+                "DOT",
+                "DASHDOT",
+                "DASHDOT2",
+                "DIVIDE",
+                "DIVIDE2",
+                "DOT2"
+            ]
+        );
+
+        let out = document_to_string(&dxf).unwrap();
+        let ltype = |name: &str| {
+            out.split("  0\nLTYPE\n")
+                .skip(1)
+                .find(|entry| group_values_by_code(entry, 2)[0] == name)
+                .unwrap_or_else(|| panic!("no LTYPE {name}"))
+                .to_string()
+        };
+        // One-dot chain: dash, gap, dot, gap.
+        assert_eq!(group_values_by_code(&ltype("DASHDOT"), 73)[0], "4");
+        // Two-dot chain: dash, gap, dot, gap, dot, gap.
+        let divide = ltype("DIVIDE");
+        assert_eq!(group_values_by_code(&divide, 73)[0], "6");
+        let pattern = group_values_by_code(&divide, 49)
+            .iter()
+            .map(|v| v.parse::<f64>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pattern.len(), 6);
+        assert!(pattern[0] > 0.0 && pattern[2] > 0.0 && pattern[4] > 0.0);
+        assert_eq!(pattern[2], pattern[4]);
+        assert!(pattern[2] < pattern[0]);
+    }
+
+    #[test]
+    fn convert_document_printable_only_drops_hidden_and_construction_entities() {
+        let mut header = empty_header();
+        header.layer_groups[0].state = 2;
+        header.layer_groups[0].layers[0].state = 2;
+        header.layer_groups[0].layers[1].state = 0;
+        let line = |layer: u16, pen_color: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer,
+                    pen_color,
+                    pen_style: 1,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: f64::from(layer + pen_color),
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![
+                line(0, 1),
+                // Construction pen.
+                line(0, 9),
+                // Hidden layer.
+                line(1, 1),
+                Entity::Point(Point {
+                    base: EntityBase {
+                        layer_group: 0,
+                        layer: 0,
+                        ..EntityBase::default()
+                    },
+                    x: 0.0,
+                    y: 0.0,
+                    is_temporary: true,
+                    code: 0,
+                    angle: 0.0,
+                    scale: 1.0,
+                }),
+            ],
+            block_defs: vec![],
+        };
+        let printable = doc
+            .entities
+            .iter()
+            .map(|entity| entity.is_printable(&doc))
+            .collect::<Vec<_>>();
+        assert_eq!(printable, vec![true, false, false, false]);
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                printable_only: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert_eq!(dxf.entities.len(), 1);
+        assert!(contains_line(&dxf.entities, 0.0, 0.0, 1.0, 0.0));
+        assert_eq!(convert_document(&doc).entities.len(), 3);
+    }
+
+    #[test]
+    fn convert_document_layer_filter_keeps_listed_slots_only() {
+        let line = |layer_group: u16, layer: u16, x: f64| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer_group,
+                    layer,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: x,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                line(0, 1, 10.0),
+                line(2, 3, 20.0),
+                Entity::Block(Block {
+                    base: EntityBase {
+                        layer_group: 2,
+                        layer: 3,
+                        ..EntityBase::default()
+                    },
+                    ref_x: 100.0,
+                    ref_y: 0.0,
+                    scale_x: 1.0,
+                    scale_y: 1.0,
+                    rotation: 0.0,
+                    def_number: 1,
+                    attributes: vec![],
+                }),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "Mixed".to_string(),
+                entities: vec![line(0, 1, 30.0), line(2, 3, 40.0)],
+            }],
+        };
+        let options = ConvertOptions {
+            layer_filter: Some(vec![(2, 3)]),
+            ..ConvertOptions::default()
+        };
+
+        let dxf = convert_document_with_options(&doc, options.clone());
+        assert_eq!(dxf.entities.len(), 2);
+        assert!(contains_line(&dxf.entities, 0.0, 0.0, 20.0, 0.0));
+        assert!(matches!(dxf.entities[1], DxfEntity::Insert(_)));
+        assert_eq!(dxf.blocks[0].entities.len(), 1);
+        assert!(contains_line(&dxf.blocks[0].entities, 0.0, 0.0, 40.0, 0.0));
+
+        let exploded = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                ..options
+            },
+        );
+        assert_eq!(exploded.entities.len(), 2);
+        assert!(contains_line(&exploded.entities, 0.0, 0.0, 20.0, 0.0));
+        assert!(contains_line(&exploded.entities, 100.0, 0.0, 140.0, 0.0));
+    }
+
+    #[test]
+    fn aci_to_pen_inverts_map_color() {
+        for pen in [1, 2, 3, 4, 5, 6, 7, 9] {
+            assert_eq!(aci_to_pen(map_color(pen)), pen, "pen {pen}");
+        }
+        // Pen 8 draws in the same color as pen 1.
+        assert_eq!(aci_to_pen(map_color(8)), 1);
+        assert_eq!(aci_to_pen(map_color(42)), 42);
+        assert_eq!(aci_to_pen(0), 1);
+        assert_eq!(aci_to_pen(256), 1);
+    }
+
+    #[test]
+    fn convert_document_puts_unnamed_layers_on_default_layer_name() {
+        let mut header = empty_header();
+        header.layer_groups[0].layers[1].name = "Walls".to_string();
+        let line = |layer: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 10.0,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![line(0), line(1), line(2)],
+            block_defs: Vec::new(),
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                default_layer_name: Some("DEFAULT".to_string()),
+                ..ConvertOptions::default()
+            },
+        );
+        let entity_layers = dxf
+            .entities
+            .iter()
+            .map(|entity| entity.layer())
+            .collect::<Vec<_>>();
+        assert_eq!(entity_layers, vec!["DEFAULT", "Walls", "DEFAULT"]);
+        let layer_names = dxf
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(layer_names, vec!["DEFAULT", "Walls"]);
+    }
+
+    #[test]
+    fn document_to_string_has_objects_section_and_unique_handles() {
+        let base = EntityBase::default();
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Line(Line {
+                    base,
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 10.0,
+                    end_y: 0.0,
+                }),
+                Entity::Text(Text {
+                    base,
+                    start_x: 5.0,
+                    start_y: 2.0,
+                    end_x: 5.0,
+                    end_y: 2.0,
+                    text_type: 0,
+                    size_x: 1.0,
+                    size_y: 1.0,
+                    spacing: 0.0,
+                    angle: 0.0,
+                    font_name: String::new(),
+                    content: "abc".to_string(),
+                }),
+            ],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        let out = document_to_string(&dxf).unwrap();
+
+        assert!(out.contains("  2\nOBJECTS\n"));
+        assert!(out.contains("  2\nBLOCK_RECORD\n"));
+        assert!(out.contains("  2\n*Model_Space\n"));
+        assert!(out.contains("  2\n*Paper_Space\n"));
+
+        let handles = group_values_by_code(&out, 5);
+        assert!(!handles.is_empty());
+        let unique = handles.iter().collect::<BTreeSet<_>>();
+        assert_eq!(unique.len(), handles.len());
+        assert!(handles
+            .iter()
+            .all(|h| !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit())));
+    }
+
+    #[test]
+    fn document_to_string_with_options_starts_handles_at_seed() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 10.0,
+                end_y: 0.0,
+            })],
+            block_defs: vec![],
+        };
+        let dxf = convert_document(&doc);
+
+        let (out, next) = document_to_string_with_options(
+            &dxf,
+            WriteOptions {
+                handle_seed: 0x1000,
+            },
+        )
+        .unwrap();
+        let handles = group_values_by_code(&out, 5);
+        let values = handles
+            .iter()
+            .map(|h| u32::from_str_radix(h, 16).unwrap())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(values.first(), Some(&0x1000));
+        assert_eq!(next, values.last().unwrap() + 1);
+
+        // The next file continues where this one stopped, so the two share
+        // no handles.
+        let (second, _) =
+            document_to_string_with_options(&dxf, WriteOptions { handle_seed: next }).unwrap();
+        let first_handles = handles.iter().collect::<BTreeSet<_>>();
+        assert!(group_values_by_code(&second, 5)
+            .iter()
+            .all(|h| !first_handles.contains(h)));
+
+        let (default_out, _) =
+            document_to_string_with_options(&dxf, WriteOptions::default()).unwrap();
+        assert_eq!(default_out, document_to_string(&dxf).unwrap());
+
+        let seed = u32::MAX - 2;
+        assert!(matches!(
+            document_to_string_with_options(&dxf, WriteOptions { handle_seed: seed }),
+            Err(JwwError::HandleSeedOverflow(s)) if s == seed
+        ));
+    }
+
+    #[test]
+    fn convert_document_emits_memo_as_note() {
+        let mut header = empty_header();
+        header.memo = "Line one\nLine two".to_string();
+        let doc = JwwDocument {
+            header,
+            entities: vec![],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                memo_as_note: Some((Coord2D::new(10.0, 20.0), 3.5)),
+                ..ConvertOptions::default()
+            },
+        );
+        assert!(dxf.layers.iter().any(|layer| layer.name == "NOTES"));
+        match dxf.entities.last() {
+            Some(DxfEntity::MText(v)) => {
+                assert_eq!(v.layer, "NOTES");
+                assert_eq!((v.x, v.y, v.height), (10.0, 20.0, 3.5));
+            }
+            other => panic!("expected MTEXT, got {:?}", other),
+        }
+
+        let out = document_to_string(&dxf).unwrap();
+        assert!(out.contains("  0\nMTEXT\n"));
+        assert!(out.contains("  1\nLine one\\PLine two\n"));
+
+        let without = convert_document(&doc);
+        assert!(without.entities.is_empty());
+    }
+
+    #[test]
+    fn convert_document_emits_legend_for_used_layers() {
+        let mut header = empty_header();
+        header.layer_groups[0].layers[0].name = "WALL".to_string();
+        header.layer_groups[0].layers[2].name = "DOOR".to_string();
+        let line_on = |layer: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 10.0,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![line_on(2), line_on(0), line_on(2)],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                emit_legend: Some(Coord2D::new(100.0, 50.0)),
+                ..ConvertOptions::default()
+            },
+        );
+        assert!(dxf.layers.iter().any(|layer| layer.name == "LEGEND"));
+        let legend = dxf
+            .entities
+            .iter()
+            .filter(|entity| entity.layer() == "LEGEND")
+            .collect::<Vec<_>>();
+        let names = legend
+            .iter()
+            .filter_map(|entity| match entity {
+                DxfEntity::Text(v) => Some(v.content.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["WALL", "DOOR"]);
+
+        let door_color = dxf
+            .layers
+            .iter()
+            .find(|layer| layer.name == "DOOR")
+            .unwrap()
+            .color;
+        assert!(legend.iter().any(|entity| matches!(
+            entity,
+            DxfEntity::Line(v) if v.color == door_color && v.y1 == v.y2 && v.y1 == 42.5
+        )));
+
+        let plain = convert_document(&doc);
+        assert!(plain
+            .entities
+            .iter()
+            .all(|entity| entity.layer() != "LEGEND"));
+    }
+
+    #[test]
+    fn convert_document_sanitizes_and_prunes_layers() {
+        let mut header = empty_header();
+        header.layer_groups[0].layers[0].name = "A/B".to_string();
+        header.layer_groups[0].layers[1].name = "A_B".to_string();
+        header.layer_groups[0].layers[2].name = "Plan<1>".to_string();
+        let line_on = |layer: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 1.0,
+            })
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![line_on(0), line_on(1)],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: false,
+                created_time: 0,
+                name: "B".to_string(),
+                entities: vec![line_on(2)],
+            }],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                keep_unreferenced_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let layer_names = dxf
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(layer_names, vec!["A_B", "A_B_2", "Plan_1_"]);
+        let entity_layers = dxf
+            .entities
+            .iter()
+            .chain(&dxf.blocks[0].entities)
+            .map(DxfEntity::layer)
+            .collect::<Vec<_>>();
+        assert_eq!(entity_layers, vec!["A_B", "A_B_2", "Plan_1_"]);
+
+        let all = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                keep_all_layers: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert_eq!(all.layers.len(), 256);
+        assert_eq!(all.layers[0].name, "A_B");
+    }
+
+    #[test]
+    fn convert_document_filters_layers_by_name_glob() {
+        let mut header = empty_header();
+        header.layer_groups[0].layers[0].name = "WALL-EXT".to_string();
+        header.layer_groups[0].layers[1].name = "wall-int".to_string();
+        header.layer_groups[0].layers[2].name = "DOOR".to_string();
+        let line_on = |layer: u16, x: f64| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer,
+                    ..EntityBase::default()
+                },
+                start_x: x,
+                start_y: 0.0,
+                end_x: x,
+                end_y: 1.0,
+            })
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![line_on(0, 0.0), line_on(1, 1.0), line_on(2, 2.0)],
+            block_defs: vec![],
+        };
+
+        let keep_walls = ConvertOptions {
+            layer_name_filter: Some(LayerNameFilter {
+                include: vec!["WALL*".to_string()],
+                exclude: vec![],
+            }),
+            ..ConvertOptions::default()
+        };
+        let dxf = convert_document_with_options(&doc, keep_walls.clone());
+        let layers = dxf
+            .entities
+            .iter()
+            .map(|entity| match entity {
+                DxfEntity::Line(v) => v.layer.as_str(),
+                other => panic!("unexpected entity {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(layers, vec!["WALL-EXT", "wall-int"]);
+
+        let exploded = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                ..keep_walls
+            },
+        );
+        assert_eq!(exploded.entities.len(), 2);
+
+        let skip_interior = ConvertOptions {
+            layer_name_filter: Some(LayerNameFilter {
+                include: vec![],
+                exclude: vec!["*-INT".to_string(), "D??R".to_string()],
+            }),
+            ..ConvertOptions::default()
+        };
+        let dxf = convert_document_with_options(&doc, skip_interior);
+        assert_eq!(dxf.entities.len(), 1);
+        assert!(contains_line(&dxf.entities, 0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn convert_document_writes_text_width_factor_at_baseline_left() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Text(Text {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 40.0,
+                end_y: 0.0,
+                text_type: 2 | (2 << 16),
+                size_x: 10.0,
+                size_y: 5.0,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: "MS Gothic".to_string(),
+                content: "ABCD".to_string(),
+            })],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        match dxf.entities.as_slice() {
+            [DxfEntity::Text(v)] => {
+                assert_eq!(v.width_factor, 2.0);
+                // High text_type bits are not read as a justification.
+                assert!(v.alignment.is_default());
+                assert_eq!(v.alignment_point, None);
+                assert_eq!((v.x, v.y), (0.0, 0.0));
+            }
+            other => panic!("expected TEXT, got {:?}", other),
         }
-        DxfEntity::Insert(v) => {
-            let (x, y) = transform.apply_point(v.x, v.y);
-            vec![DxfEntity::Insert(DxfInsert {
-                layer: v.layer.clone(),
-                color: v.color,
-                line_type: v.line_type.clone(),
-                block_name: v.block_name.clone(),
-                x,
-                y,
-                scale_x: v.scale_x,
-                scale_y: v.scale_y,
-                rotation: v.rotation + transform.rotation_deg(),
-            })]
+
+        let out = document_to_string(&dxf).unwrap();
+        let entity = &out[out.find("  0\nTEXT\n").unwrap()..];
+        assert_eq!(group_values_by_code(entity, 41), vec!["2.000000000000"]);
+        assert!(group_values_by_code(entity, 72).is_empty());
+        assert!(group_values_by_code(entity, 11).is_empty());
+    }
+
+    #[test]
+    fn convert_document_writes_multiline_text_as_mtext() {
+        let text = |content: &str| {
+            Entity::Text(Text {
+                base: EntityBase::default(),
+                start_x: 10.0,
+                start_y: 20.0,
+                end_x: 50.0,
+                end_y: 20.0,
+                text_type: 0,
+                size_x: 4.0,
+                size_y: 5.0,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: "MS Gothic".to_string(),
+                content: content.to_string(),
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![text("FIRST\r\nLINE2"), text("SINGLE")],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document(&doc);
+        match dxf.entities.as_slice() {
+            [DxfEntity::MText(mtext), DxfEntity::Text(single)] => {
+                assert_eq!(mtext.attachment_point, 1);
+                assert_eq!((mtext.x, mtext.y), (10.0, 25.0));
+                assert_eq!(mtext.width, 20.0);
+                assert_eq!(mtext.height, 5.0);
+                assert_eq!(single.content, "SINGLE");
+            }
+            other => panic!("expected MTEXT then TEXT, got {:?}", other),
         }
+
+        let out = document_to_string(&dxf).unwrap();
+        let entity = &out[out.find("  0\nMTEXT\n").unwrap()..];
+        assert_eq!(group_values_by_code(entity, 1)[0], "FIRST\\PLINE2");
+        assert_eq!(group_values_by_code(entity, 71)[0], "1");
+        assert_eq!(out.matches("  0\nTEXT\n").count(), 1);
     }
-}
 
-fn transform_circle_for_explode(circle: &DxfCircle, transform: &Transform2D) -> Vec<DxfEntity> {
-    let (center_x, center_y) = transform.apply_point(circle.center_x, circle.center_y);
-    let (ux, uy) = transform.apply_vector(circle.radius, 0.0);
-    let (vx, vy) = transform.apply_vector(0.0, circle.radius);
+    #[test]
+    fn convert_document_writes_spaced_text_as_mtext_with_tracking() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Text(Text {
+                base: EntityBase::default(),
+                start_x: 10.0,
+                start_y: 20.0,
+                end_x: 50.0,
+                end_y: 20.0,
+                text_type: 0,
+                size_x: 2.0,
+                size_y: 5.0,
+                spacing: 1.0,
+                angle: 0.0,
+                font_name: "MS Gothic".to_string(),
+                content: "TITLE".to_string(),
+            })],
+            block_defs: vec![],
+        };
 
-    let lu = (ux * ux + uy * uy).sqrt();
-    let lv = (vx * vx + vy * vy).sqrt();
-    if lu <= 1e-12 && lv <= 1e-12 {
-        return vec![DxfEntity::Point(DxfPoint {
-            layer: circle.layer.clone(),
-            color: circle.color,
-            line_type: circle.line_type.clone(),
-            x: center_x,
-            y: center_y,
-        })];
+        let dxf = convert_document(&doc);
+        match dxf.entities.as_slice() {
+            [DxfEntity::MText(mtext)] => {
+                assert_eq!(mtext.tracking, 1.5);
+                assert_eq!(mtext.content, "TITLE");
+                assert_eq!(mtext.width, 15.0);
+            }
+            other => panic!("expected a single MTEXT, got {:?}", other),
+        }
+
+        let out = document_to_string(&dxf).unwrap();
+        let entity = &out[out.find("  0\nMTEXT\n").unwrap()..];
+        assert_eq!(group_values_by_code(entity, 1)[0], "\\T1.5;TITLE");
+        assert!(!out.contains("  0\nTEXT\n"));
     }
 
-    let denom = lu * lv;
-    let dot = if denom <= 1e-12 {
-        0.0
-    } else {
-        (ux * vx + uy * vy) / denom
-    };
-    if nearly_equal(lu, lv) && dot.abs() < 1e-6 {
-        return vec![DxfEntity::Circle(DxfCircle {
-            layer: circle.layer.clone(),
-            color: circle.color,
-            line_type: circle.line_type.clone(),
-            center_x,
-            center_y,
-            radius: (lu + lv) / 2.0,
-        })];
+    #[test]
+    fn document_to_string_writes_r12_without_handles_or_block_records() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Line(Line {
+                    base: EntityBase::default(),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 10.0,
+                    end_y: 0.0,
+                }),
+                Entity::Polyline(Polyline {
+                    base: EntityBase::default(),
+                    vertices: vec![
+                        PolyVertex::new(Coord2D::new(0.0, 0.0), 0.0),
+                        PolyVertex::new(Coord2D::new(10.0, 0.0), 1.0),
+                        PolyVertex::new(Coord2D::new(10.0, 10.0), 0.0),
+                    ],
+                    closed: true,
+                }),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "R12".to_string(),
+                entities: vec![],
+            }],
+        };
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                version: DxfVersion::R12,
+                keep_unreferenced_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let out = document_to_string(&dxf).unwrap();
+
+        assert!(out.contains("  9\n$ACADVER\n  1\nAC1009\n"));
+        assert!(!out.contains("AcDbEntity"));
+        assert!(!out.contains("BLOCK_RECORD"));
+        assert!(!out.contains("OBJECTS"));
+        assert!(!out.contains("LWPOLYLINE"));
+        assert!(group_values_by_code(&out, 5).is_empty());
+        assert!(group_values_by_code(&out, 330).is_empty());
+
+        let entities = &out[out.find("  2\nENTITIES\n").unwrap()..];
+        assert!(entities.contains("  0\nLINE\n"));
+        assert!(entities.contains("  0\nPOLYLINE\n"));
+        assert_eq!(entities.matches("  0\nVERTEX\n").count(), 3);
+        assert!(entities.contains("  0\nSEQEND\n"));
+        assert!(entities.ends_with("  0\nENDSEC\n  0\nEOF\n"));
+        assert!(out.contains("  0\nBLOCK\n  8\n0\n  2\nR12\n"));
     }
 
-    let (major_x, major_y, minor_ratio) = if lu >= lv {
-        (ux, uy, if lu <= 1e-12 { 1.0 } else { lv / lu })
-    } else {
-        (vx, vy, if lv <= 1e-12 { 1.0 } else { lu / lv })
-    };
+    #[test]
+    fn convert_document_applies_layer_group_scale() {
+        let mut header = empty_header();
+        header.layer_groups[1].scale = 100.0;
+        let on_group = |layer_group: u16| EntityBase {
+            layer_group,
+            ..EntityBase::default()
+        };
+        let doc = JwwDocument {
+            header,
+            entities: vec![
+                Entity::Line(Line {
+                    base: on_group(1),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                }),
+                Entity::Arc(Arc {
+                    base: on_group(1),
+                    center_x: 1.0,
+                    center_y: 1.0,
+                    radius: 0.5,
+                    start_angle: 0.0,
+                    arc_angle: 2.0 * PI,
+                    tilt_angle: 0.0,
+                    flatness: 1.0,
+                    is_full_circle: true,
+                }),
+                Entity::Line(Line {
+                    base: on_group(0),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                }),
+            ],
+            block_defs: vec![],
+        };
 
-    vec![DxfEntity::Ellipse(DxfEllipse {
-        layer: circle.layer.clone(),
-        color: circle.color,
-        line_type: circle.line_type.clone(),
-        center_x,
-        center_y,
-        major_axis_x: major_x,
-        major_axis_y: major_y,
-        minor_ratio,
-        start_param: 0.0,
-        end_param: 2.0 * PI,
-    })]
-}
+        for explode_inserts in [false, true] {
+            let dxf = convert_document_with_options(
+                &doc,
+                ConvertOptions {
+                    apply_layer_scale: true,
+                    explode_inserts,
+                    ..ConvertOptions::default()
+                },
+            );
+            assert!(contains_line(&dxf.entities, 0.0, 0.0, 100.0, 0.0));
+            assert!(contains_line(&dxf.entities, 0.0, 0.0, 1.0, 0.0));
+            assert!(dxf.entities.iter().any(|entity| matches!(
+                entity,
+                DxfEntity::Circle(v) if nearly_eq(v.radius, 50.0) && nearly_eq(v.center_x, 100.0)
+            )));
+
+            let out = document_to_string(&dxf).unwrap();
+            let entity = &out[out.find("  0\nLINE\n").unwrap()..];
+            assert_eq!(group_values_by_code(entity, 11)[0], "100.000000000000");
+        }
 
-fn transform_arc_for_explode(arc: &DxfArc, transform: &Transform2D) -> Vec<DxfEntity> {
-    let mut end = arc.end_angle;
-    let start = arc.start_angle;
-    if end < start {
-        end += 360.0;
+        let unscaled = convert_document(&doc);
+        assert!(!contains_line(&unscaled.entities, 0.0, 0.0, 100.0, 0.0));
     }
-    let sweep = (end - start).abs();
-    let segments = ((sweep / 360.0) * 96.0).ceil() as usize;
-    let segments = segments.clamp(8, 192);
 
-    let mut points = Vec::<(f64, f64)>::with_capacity(segments + 1);
-    for i in 0..=segments {
-        let t = start + (end - start) * (i as f64) / (segments as f64);
-        let rad = t * PI / 180.0;
-        let x = arc.center_x + arc.radius * rad.cos();
-        let y = arc.center_y + arc.radius * rad.sin();
-        points.push(transform.apply_point(x, y));
+    #[test]
+    fn convert_document_applies_layer_group_scale_to_blocks_as_when_exploded() {
+        let mut header = empty_header();
+        header.layer_groups[1].scale = 100.0;
+        header.layer_groups[2].scale = 50.0;
+        let doc = JwwDocument {
+            header,
+            entities: vec![Entity::Block(Block {
+                base: EntityBase {
+                    layer_group: 1,
+                    ..EntityBase::default()
+                },
+                ref_x: 1.0,
+                ref_y: 2.0,
+                scale_x: 2.0,
+                scale_y: 2.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![],
+            })],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "B".to_string(),
+                entities: vec![Entity::Line(Line {
+                    base: EntityBase {
+                        layer_group: 2,
+                        ..EntityBase::default()
+                    },
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 0.0,
+                })],
+            }],
+        };
+        let convert = |explode_inserts: bool| {
+            convert_document_with_options(
+                &doc,
+                ConvertOptions {
+                    apply_layer_scale: true,
+                    explode_inserts,
+                    ..ConvertOptions::default()
+                },
+            )
+        };
+
+        let exploded = convert(true);
+        assert!(contains_line(
+            &exploded.entities,
+            100.0,
+            200.0,
+            300.0,
+            200.0
+        ));
+
+        let dxf = convert(false);
+        let [DxfEntity::Insert(insert)] = dxf.entities.as_slice() else {
+            panic!("expected one INSERT, got {:?}", dxf.entities);
+        };
+        let [DxfEntity::Line(line)] = dxf.blocks[0].entities.as_slice() else {
+            panic!("expected one LINE, got {:?}", dxf.blocks[0].entities);
+        };
+        let place = |x: f64, y: f64| (insert.x + insert.scale_x * x, insert.y + insert.scale_y * y);
+        let (x1, y1) = place(line.x1, line.y1);
+        let (x2, y2) = place(line.x2, line.y2);
+        assert!(contains_line(&exploded.entities, x1, y1, x2, y2));
     }
 
-    points_to_lines(points, arc.layer.clone(), arc.color, arc.line_type.clone())
-}
+    #[test]
+    fn convert_document_measures_arc_angles_from_tilt_axis() {
+        let arc = |start_angle: f64, tilt_angle: f64, flatness: f64| {
+            Entity::Arc(Arc {
+                base: EntityBase::default(),
+                center_x: 0.0,
+                center_y: 0.0,
+                radius: 10.0,
+                start_angle,
+                arc_angle: PI / 2.0,
+                tilt_angle,
+                flatness,
+                is_full_circle: false,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                arc(PI / 4.0, 0.0, 0.5),
+                arc(PI / 4.0, 0.3, 0.5),
+                arc(PI / 4.0, 0.0, 2.0),
+                arc(PI / 4.0, 0.3, 1.0),
+            ],
+            block_defs: vec![],
+        };
 
-fn transform_ellipse_for_explode(ellipse: &DxfEllipse, transform: &Transform2D) -> Vec<DxfEntity> {
-    let start = ellipse.start_param;
-    let mut end = ellipse.end_param;
-    if end <= start {
-        end += 2.0 * PI;
+        let dxf = convert_document(&doc);
+        let params = dxf
+            .entities
+            .iter()
+            .take(3)
+            .map(|entity| match entity {
+                DxfEntity::Ellipse(v) => (v.start_param, v.end_param, v.minor_ratio),
+                other => panic!("expected ELLIPSE, got {:?}", other),
+            })
+            .collect::<Vec<_>>();
+
+        // Elliptical angles are already eccentric anomalies.
+        let (start, end, ratio) = params[0];
+        assert!(nearly_eq(ratio, 0.5));
+        assert!(nearly_eq(start, PI / 4.0));
+        assert!(nearly_eq(end, 3.0 * PI / 4.0));
+        // Tilt only rotates the frame the angles are measured in.
+        assert!(nearly_eq(params[1].0, start) && nearly_eq(params[1].1, end));
+        // Flatness above 1 swaps the axes: 45°..135° from the tilt axis
+        // becomes -45°..45° from the major axis at 90°.
+        let (start, end, ratio) = params[2];
+        assert!(nearly_eq(ratio, 0.5));
+        assert!(nearly_eq(start, -PI / 4.0));
+        assert!(nearly_eq(end, PI / 4.0));
+
+        // A tilted circular arc adds the tilt to its angles.
+        let DxfEntity::Arc(circular) = &dxf.entities[3] else {
+            panic!("expected ARC, got {:?}", dxf.entities[3]);
+        };
+        assert!(nearly_eq(
+            circular.start_angle,
+            (PI / 4.0 + 0.3).to_degrees()
+        ));
+        assert!(nearly_eq(
+            circular.end_angle,
+            (3.0 * PI / 4.0 + 0.3).to_degrees()
+        ));
     }
-    let span = (end - start).abs();
-    let segments = ((span / (2.0 * PI)) * 128.0).ceil() as usize;
-    let segments = segments.clamp(12, 256);
 
-    let major_x = ellipse.major_axis_x;
-    let major_y = ellipse.major_axis_y;
-    let minor_x = -major_y * ellipse.minor_ratio;
-    let minor_y = major_x * ellipse.minor_ratio;
+    #[test]
+    fn convert_document_quantizes_to_microns() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: 10.00049,
+                start_y: 10.0005,
+                end_x: -2.0000004,
+                end_y: 3.1234567,
+            })],
+            block_defs: vec![],
+        };
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                quantize_microns: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let DxfEntity::Line(line) = &dxf.entities[0] else {
+            panic!("expected LINE");
+        };
+        assert_eq!(line.x1, 10.0);
+        assert_eq!(line.y1, 10.001);
+        assert_eq!(line.x2, -2.0);
+        assert_eq!(line.y2, 3.123);
+
+        let unsnapped = convert_document(&doc);
+        assert!(contains_line(
+            &unsnapped.entities,
+            10.00049,
+            10.0005,
+            -2.0000004,
+            3.1234567
+        ));
+    }
+
+    #[test]
+    fn document_to_string_writes_true_color_when_enabled() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Solid(Solid {
+                    base: EntityBase {
+                        pen_color: 10,
+                        ..EntityBase::default()
+                    },
+                    point1_x: 0.0,
+                    point1_y: 0.0,
+                    point2_x: 1.0,
+                    point2_y: 0.0,
+                    point3_x: 1.0,
+                    point3_y: 1.0,
+                    point4_x: 0.0,
+                    point4_y: 1.0,
+                    // COLORREF for RGB (0x56, 0x34, 0x12).
+                    color: Some(0x0012_3456),
+                    is_filled: true,
+                }),
+                Entity::Line(Line {
+                    base: EntityBase {
+                        pen_color: 2,
+                        ..EntityBase::default()
+                    },
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 1.0,
+                }),
+            ],
+            block_defs: vec![],
+        };
+
+        let options = ConvertOptions {
+            true_color: true,
+            ..ConvertOptions::default()
+        };
+        let text =
+            document_to_string(&convert_document_with_options(&doc, options.clone())).unwrap();
+        assert_eq!(
+            group_values_by_code(&text, 420),
+            vec![0x0056_3412.to_string(), "255".to_string()]
+        );
 
-    let mut points = Vec::<(f64, f64)>::with_capacity(segments + 1);
-    for i in 0..=segments {
-        let t = start + (end - start) * (i as f64) / (segments as f64);
-        let x = ellipse.center_x + major_x * t.cos() + minor_x * t.sin();
-        let y = ellipse.center_y + major_y * t.cos() + minor_y * t.sin();
-        points.push(transform.apply_point(x, y));
+        let r12 = document_to_string(&convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                version: DxfVersion::R12,
+                ..options
+            },
+        ))
+        .unwrap();
+        assert!(group_values_by_code(&r12, 420).is_empty());
+        let plain = document_to_string(&convert_document(&doc)).unwrap();
+        assert!(group_values_by_code(&plain, 420).is_empty());
     }
 
-    points_to_lines(
-        points,
-        ellipse.layer.clone(),
-        ellipse.color,
-        ellipse.line_type.clone(),
-    )
-}
+    #[test]
+    fn document_to_string_keeps_true_color_of_exploded_arcs() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Block(Block {
+                base: EntityBase {
+                    pen_color: 1,
+                    ..EntityBase::default()
+                },
+                ref_x: 10.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![("ROOM".to_string(), "101".to_string())],
+            })],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "B".to_string(),
+                entities: vec![Entity::Arc(Arc {
+                    base: EntityBase {
+                        pen_color: 2,
+                        ..EntityBase::default()
+                    },
+                    center_x: 0.0,
+                    center_y: 0.0,
+                    radius: 5.0,
+                    start_angle: 0.0,
+                    arc_angle: PI / 2.0,
+                    tilt_angle: 0.0,
+                    flatness: 1.0,
+                    is_full_circle: false,
+                })],
+            }],
+        };
 
-fn points_to_lines(
-    points: Vec<(f64, f64)>,
-    layer: String,
-    color: i32,
-    line_type: String,
-) -> Vec<DxfEntity> {
-    if points.len() < 2 {
-        return Vec::new();
-    }
-    let mut out = Vec::<DxfEntity>::with_capacity(points.len().saturating_sub(1));
-    for w in points.windows(2) {
-        let (x1, y1) = w[0];
-        let (x2, y2) = w[1];
-        out.push(DxfEntity::Line(DxfLine {
-            layer: layer.clone(),
-            color,
-            line_type: line_type.clone(),
-            x1,
-            y1,
-            x2,
-            y2,
-        }));
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                explode_inserts: true,
+                true_color: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let lines = dxf
+            .entities
+            .iter()
+            .filter(|entity| matches!(entity, DxfEntity::Line(_)))
+            .count();
+        assert!(lines >= 8);
+        let text = document_to_string(&dxf).unwrap();
+        let colors = group_values_by_code(&text, 420);
+        assert_eq!(colors.len(), lines + 1);
+        assert_eq!(colors.iter().filter(|c| *c == "255").count(), lines);
+        // The attribute, exploded to TEXT, keeps the insert's pen color.
+        assert_eq!(colors.iter().filter(|c| *c == "0").count(), 1);
     }
-    out
-}
 
-fn nearly_equal(a: f64, b: f64) -> bool {
-    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
-}
+    #[test]
+    fn convert_document_writes_solid_as_3dface_in_outline_order() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Solid(Solid {
+                base: EntityBase::default(),
+                point1_x: 0.0,
+                point1_y: 0.0,
+                point4_x: 2.0,
+                point4_y: 0.0,
+                point2_x: 2.0,
+                point2_y: 1.0,
+                point3_x: 0.0,
+                point3_y: 1.0,
+                color: None,
+                is_filled: true,
+            })],
+            block_defs: vec![],
+        };
 
-fn convert_layers(doc: &JwwDocument) -> Vec<DxfLayer> {
-    let mut layers = Vec::<DxfLayer>::with_capacity(16 * 16);
-    for g in 0..16 {
-        for l in 0..16 {
-            let layer = &doc.header.layer_groups[g].layers[l];
-            let name = if layer.name.is_empty() {
-                format!("{:X}-{:X}", g, l)
-            } else {
-                layer.name.clone()
-            };
-            layers.push(DxfLayer {
-                name,
-                color: ((g * 16 + l) % 255 + 1) as i32,
-                line_type: "CONTINUOUS".to_string(),
-                frozen: layer.state == 0,
-                locked: layer.protect != 0,
-            });
-        }
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                solid_as_3dface: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let [DxfEntity::Face3D(face)] = dxf.entities.as_slice() else {
+            panic!("expected a single 3DFACE, got {:?}", dxf.entities);
+        };
+        assert_eq!(
+            [face.x1, face.y1, face.x2, face.y2, face.x3, face.y3, face.x4, face.y4],
+            [0.0, 0.0, 2.0, 0.0, 2.0, 1.0, 0.0, 1.0]
+        );
+
+        let text = document_to_string(&dxf).unwrap();
+        let entity_types = group_values_by_code(&text, 0);
+        assert!(entity_types.iter().any(|value| value == "3DFACE"));
+        assert!(!entity_types.iter().any(|value| value == "SOLID"));
+        assert_eq!(group_values_by_code(&text, 12), vec!["2.000000000000"]);
+        assert_eq!(group_values_by_code(&text, 13), vec!["0.000000000000"]);
+        assert_eq!(group_values_by_code(&text, 33), vec!["0.000000000000"]);
     }
-    layers
-}
 
-fn convert_blocks(
-    doc: &JwwDocument,
-    block_name_map: &HashMap<u32, String>,
-    unsupported_entities: &mut Vec<String>,
-) -> Vec<DxfBlock> {
-    let mut blocks = Vec::<DxfBlock>::with_capacity(doc.block_defs.len());
-    for block_def in &doc.block_defs {
-        let name = block_def_name(block_def.number, &block_def.name);
-        let entities = convert_entities(
-            doc,
-            &block_def.entities,
-            block_name_map,
-            unsupported_entities,
-        );
-        blocks.push(DxfBlock {
-            name,
-            base_x: 0.0,
-            base_y: 0.0,
-            entities,
+    #[test]
+    fn solid_as_3dface_keeps_irregular_and_sample_solids_simple() {
+        // A trapezoid, corners in record order (point 1, 4, 2, 3).
+        let trapezoid = Entity::Solid(Solid {
+            base: EntityBase::default(),
+            point1_x: 0.0,
+            point1_y: 0.0,
+            point4_x: 4.0,
+            point4_y: 0.0,
+            point2_x: 3.0,
+            point2_y: 2.0,
+            point3_x: 1.0,
+            point3_y: 2.0,
+            color: None,
+            is_filled: true,
         });
-    }
-    blocks
-}
+        let mut doc =
+            read_document_from_file(jww_samples_dir().join("Ａマンション平面例.jww")).unwrap();
+        doc.entities.push(trapezoid);
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                solid_as_3dface: true,
+                ..ConvertOptions::default()
+            },
+        );
 
-fn convert_entities(
-    doc: &JwwDocument,
-    entities: &[Entity],
-    block_name_map: &HashMap<u32, String>,
-    unsupported_entities: &mut Vec<String>,
-) -> Vec<DxfEntity> {
-    let mut out = Vec::<DxfEntity>::new();
-    for entity in entities {
-        match convert_entity(doc, entity, block_name_map) {
-            Some(converted) => {
-                for e in converted {
-                    out.push(e);
+        let block_entities = dxf.blocks.iter().flat_map(|block| block.entities.iter());
+        let faces = dxf
+            .entities
+            .iter()
+            .chain(block_entities)
+            .filter_map(|entity| match entity {
+                DxfEntity::Face3D(v) => {
+                    Some([(v.x1, v.y1), (v.x2, v.y2), (v.x3, v.y3), (v.x4, v.y4)])
                 }
-            }
-            None => unsupported_entities.push(entity.entity_type().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(faces.len(), 80);
+        assert!(faces.contains(&[(0.0, 0.0), (4.0, 0.0), (3.0, 2.0), (1.0, 2.0)]));
+        for face in faces {
+            assert!(is_simple_quad(face), "bow-tie 3DFACE {face:?}");
         }
     }
-    out
-}
 
-fn convert_entity(
-    doc: &JwwDocument,
-    entity: &Entity,
-    block_name_map: &HashMap<u32, String>,
-) -> Option<Vec<DxfEntity>> {
-    let base = entity.base();
-    let layer = layer_name(doc, base.layer_group, base.layer);
-    let color = map_color(base.pen_color);
-    let line_type = map_line_type(base.pen_style).to_string();
+    #[test]
+    fn convert_document_flip_y_mirrors_across_x_axis() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Line(Line {
+                    base: EntityBase::default(),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 0.0,
+                    end_y: 10.0,
+                }),
+                Entity::Arc(Arc {
+                    base: EntityBase::default(),
+                    center_x: 5.0,
+                    center_y: 5.0,
+                    radius: 2.0,
+                    start_angle: 0.0,
+                    arc_angle: PI / 2.0,
+                    tilt_angle: 0.0,
+                    flatness: 1.0,
+                    is_full_circle: false,
+                }),
+                Entity::Block(Block {
+                    base: EntityBase::default(),
+                    ref_x: 3.0,
+                    ref_y: 4.0,
+                    scale_x: 1.0,
+                    scale_y: 1.0,
+                    rotation: PI / 6.0,
+                    def_number: 1,
+                    attributes: vec![],
+                }),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "B".to_string(),
+                entities: vec![Entity::Line(Line {
+                    base: EntityBase::default(),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 1.0,
+                    end_y: 1.0,
+                })],
+            }],
+        };
 
-    match entity {
-        Entity::Line(v) => Some(vec![DxfEntity::Line(DxfLine {
-            layer,
-            color,
-            line_type,
-            x1: v.start_x,
-            y1: v.start_y,
-            x2: v.end_x,
-            y2: v.end_y,
-        })]),
-        Entity::Arc(v) => Some(convert_arc(v, layer, color, line_type)),
-        Entity::Point(v) => {
-            if v.is_temporary {
-                Some(Vec::new())
-            } else {
-                Some(vec![DxfEntity::Point(DxfPoint {
-                    layer,
-                    color,
-                    line_type,
-                    x: v.x,
-                    y: v.y,
-                })])
-            }
-        }
-        Entity::Text(v) => Some(vec![DxfEntity::Text(convert_text(
-            v, layer, color, line_type,
-        ))]),
-        Entity::Solid(v) => Some(vec![DxfEntity::Solid(DxfSolid {
-            layer,
-            color,
-            line_type,
-            x1: v.point1_x,
-            y1: v.point1_y,
-            x2: v.point2_x,
-            y2: v.point2_y,
-            x3: v.point3_x,
-            y3: v.point3_y,
-            x4: v.point4_x,
-            y4: v.point4_y,
-        })]),
-        Entity::Block(v) => {
-            let block_name = block_name_map
-                .get(&v.def_number)
-                .cloned()
-                .unwrap_or_else(|| format!("BLOCK_{}", v.def_number));
-            Some(vec![DxfEntity::Insert(DxfInsert {
-                layer,
-                color,
-                line_type,
-                block_name,
-                x: v.ref_x,
-                y: v.ref_y,
-                scale_x: v.scale_x,
-                scale_y: v.scale_y,
-                rotation: rad_to_deg(v.rotation),
-            })])
-        }
-        Entity::Dimension(v) => Some(vec![
-            DxfEntity::Line(DxfLine {
-                layer: layer.clone(),
-                color,
-                line_type: line_type.clone(),
-                x1: v.line.start_x,
-                y1: v.line.start_y,
-                x2: v.line.end_x,
-                y2: v.line.end_y,
-            }),
-            DxfEntity::Text(convert_text(&v.text, layer, color, line_type)),
-        ]),
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                flip_y: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert!(contains_line(&dxf.entities, 0.0, 0.0, 0.0, -10.0));
+        let DxfEntity::Arc(arc) = &dxf.entities[1] else {
+            panic!("expected ARC, got {:?}", dxf.entities[1]);
+        };
+        assert_eq!((arc.center_x, arc.center_y), (5.0, -5.0));
+        assert!(nearly_eq(arc.start_angle, 270.0));
+        assert!(nearly_eq(arc.end_angle, 360.0));
+        let DxfEntity::Insert(insert) = &dxf.entities[2] else {
+            panic!("expected INSERT, got {:?}", dxf.entities[2]);
+        };
+        assert_eq!((insert.x, insert.y), (3.0, -4.0));
+        assert!(nearly_eq(insert.rotation, -30.0));
+        assert_eq!(insert.scale_y, -1.0);
+        // Block contents are left alone; the insert carries the mirror.
+        assert!(contains_line(&dxf.blocks[0].entities, 0.0, 0.0, 1.0, 1.0));
     }
-}
 
-fn convert_arc(arc: &Arc, layer: String, color: i32, line_type: String) -> Vec<DxfEntity> {
-    if arc.is_full_circle && arc.flatness == 1.0 {
-        return vec![DxfEntity::Circle(DxfCircle {
-            layer,
-            color,
-            line_type,
-            center_x: arc.center_x,
-            center_y: arc.center_y,
-            radius: arc.radius,
-        })];
+    #[test]
+    fn convert_document_flip_text_off_keeps_text_in_place() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                Entity::Line(Line {
+                    base: EntityBase::default(),
+                    start_x: 0.0,
+                    start_y: 0.0,
+                    end_x: 0.0,
+                    end_y: 10.0,
+                }),
+                Entity::Text(Text {
+                    base: EntityBase::default(),
+                    start_x: 2.0,
+                    start_y: 3.0,
+                    end_x: 12.0,
+                    end_y: 3.0,
+                    text_type: 0,
+                    size_x: 2.5,
+                    size_y: 2.5,
+                    spacing: 0.0,
+                    angle: 0.0,
+                    font_name: String::new(),
+                    content: "A".to_string(),
+                }),
+            ],
+            block_defs: Vec::new(),
+        };
+
+        let options = ConvertOptions {
+            flip_y: true,
+            flip_text: false,
+            ..ConvertOptions::default()
+        };
+        let dxf = convert_document_with_options(&doc, options.clone());
+        assert!(contains_line(&dxf.entities, 0.0, 0.0, 0.0, -10.0));
+        let DxfEntity::Text(text) = &dxf.entities[1] else {
+            panic!("expected TEXT, got {:?}", dxf.entities[1]);
+        };
+        assert_eq!((text.x, text.y), (2.0, 3.0));
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                flip_text: true,
+                ..options
+            },
+        );
+        let DxfEntity::Text(text) = &dxf.entities[1] else {
+            panic!("expected TEXT, got {:?}", dxf.entities[1]);
+        };
+        assert_eq!((text.x, text.y), (2.0, -3.0));
+    }
+
+    #[test]
+    fn convert_document_flip_text_and_solids_off_apply_inside_blocks() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x: 0.0,
+                ref_y: 5.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![],
+            })],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "B".to_string(),
+                entities: vec![
+                    Entity::Line(Line {
+                        base: EntityBase::default(),
+                        start_x: 0.0,
+                        start_y: 0.0,
+                        end_x: 1.0,
+                        end_y: 1.0,
+                    }),
+                    Entity::Text(Text {
+                        base: EntityBase::default(),
+                        start_x: 2.0,
+                        start_y: 3.0,
+                        end_x: 12.0,
+                        end_y: 3.0,
+                        text_type: 0,
+                        size_x: 2.5,
+                        size_y: 2.5,
+                        spacing: 0.0,
+                        angle: 0.0,
+                        font_name: String::new(),
+                        content: "A".to_string(),
+                    }),
+                    Entity::Solid(Solid {
+                        base: EntityBase::default(),
+                        point1_x: 0.0,
+                        point1_y: 0.0,
+                        point2_x: 1.0,
+                        point2_y: 0.0,
+                        point3_x: 1.0,
+                        point3_y: 2.0,
+                        point4_x: 0.0,
+                        point4_y: 2.0,
+                        color: None,
+                        is_filled: true,
+                    }),
+                ],
+            }],
+        };
+        let block_entities = |options: ConvertOptions| {
+            let dxf = convert_document_with_options(&doc, options);
+            let [DxfEntity::Insert(insert)] = dxf.entities.as_slice() else {
+                panic!("expected one INSERT, got {:?}", dxf.entities);
+            };
+            assert_eq!((insert.y, insert.scale_y), (-5.0, -1.0));
+            dxf.blocks[0].entities.clone()
+        };
+
+        let kept = block_entities(ConvertOptions {
+            flip_y: true,
+            flip_text: false,
+            flip_solids: false,
+            ..ConvertOptions::default()
+        });
+        assert!(contains_line(&kept, 0.0, 0.0, 1.0, 1.0));
+        let text = kept
+            .iter()
+            .find_map(|entity| match entity {
+                DxfEntity::Text(v) => Some(v),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!((text.x, text.y, text.mirrored), (2.0, -3.0, true));
+        let solid = kept
+            .iter()
+            .find_map(|entity| match entity {
+                DxfEntity::Solid(v) => Some(v),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(solid.y3, -2.0);
+
+        let flipped = block_entities(ConvertOptions {
+            flip_y: true,
+            ..ConvertOptions::default()
+        });
+        let plain = convert_document(&doc);
+        assert_eq!(flipped, plain.blocks[0].entities);
     }
 
-    if arc.flatness != 1.0 {
-        let mut major_radius = arc.radius;
-        let mut minor_ratio = arc.flatness;
-        let mut tilt_angle = arc.tilt_angle;
+    #[test]
+    fn convert_document_writes_extents_from_entities() {
+        let empty = JwwDocument {
+            header: empty_header(),
+            entities: Vec::new(),
+            block_defs: Vec::new(),
+        };
+        let out = document_to_string(&convert_document(&empty)).unwrap();
+        let header = &out[out.find("  9\n$EXTMAX\n").unwrap()..];
+        assert_eq!(group_values_by_code(header, 10)[0], "0.000000000000");
+        assert_eq!(group_values_by_code(header, 20)[0], "0.000000000000");
 
-        if minor_ratio > 1.0 {
-            major_radius = arc.radius * arc.flatness;
-            minor_ratio = 1.0 / arc.flatness;
-            tilt_angle = arc.tilt_angle + PI / 2.0;
-        }
+        let doc = JwwDocument {
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: -20.0,
+                start_y: 5.0,
+                end_x: 300.0,
+                end_y: 75.0,
+            })],
+            ..empty
+        };
+        let dxf = convert_document(&doc);
+        assert_eq!(
+            dxf.extents(),
+            Some((Coord2D::new(-20.0, 5.0), Coord2D::new(300.0, 75.0)))
+        );
+        let out = document_to_string(&dxf).unwrap();
+        let header = &out[out.find("  9\n$EXTMAX\n").unwrap()..];
+        assert_eq!(group_values_by_code(header, 10)[0], "300.000000000000");
+        assert_eq!(group_values_by_code(header, 20)[0], "75.000000000000");
+        assert_eq!(group_values_by_code(header, 30)[0], "0.000000000000");
+        let header = &out[out.find("  9\n$LIMMIN\n").unwrap()..];
+        assert_eq!(group_values_by_code(header, 10)[0], "-20.000000000000");
+        assert_eq!(group_values_by_code(header, 20)[0], "5.000000000000");
+    }
 
-        let major_axis_x = major_radius * tilt_angle.cos();
-        let major_axis_y = major_radius * tilt_angle.sin();
-        let start_param = if arc.is_full_circle {
-            0.0
-        } else {
-            arc.start_angle
+    #[test]
+    fn extents_cover_arc_sweeps_placed_blocks_and_notes() {
+        let circle = |center_x: f64, radius: f64, arc_angle: f64| {
+            Entity::Arc(Arc {
+                base: EntityBase::default(),
+                center_x,
+                center_y: 0.0,
+                radius,
+                start_angle: 0.0,
+                arc_angle,
+                tilt_angle: 0.0,
+                flatness: 1.0,
+                is_full_circle: arc_angle >= 2.0 * PI,
+            })
         };
-        let end_param = if arc.is_full_circle {
-            2.0 * PI
-        } else {
-            arc.start_angle + arc.arc_angle
+        let mut header = empty_header();
+        header.memo = "note".to_string();
+        let doc = JwwDocument {
+            header,
+            entities: vec![
+                // The upper half reaches y = 10, though both ends sit on y = 0.
+                circle(0.0, 10.0, PI),
+                Entity::Block(Block {
+                    base: EntityBase::default(),
+                    ref_x: 100.0,
+                    ref_y: 0.0,
+                    scale_x: 2.0,
+                    scale_y: 2.0,
+                    rotation: PI / 2.0,
+                    def_number: 1,
+                    attributes: vec![],
+                }),
+            ],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "Ring".to_string(),
+                entities: vec![circle(10.0, 5.0, 2.0 * PI)],
+            }],
         };
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                memo_as_note: Some((Coord2D::new(-50.0, -40.0), 2.5)),
+                ..ConvertOptions::default()
+            },
+        );
 
-        return vec![DxfEntity::Ellipse(DxfEllipse {
-            layer,
-            color,
-            line_type,
-            center_x: arc.center_x,
-            center_y: arc.center_y,
-            major_axis_x,
-            major_axis_y,
-            minor_ratio,
-            start_param,
-            end_param,
-        })];
+        // The ring lands centered on (100, 20) with radius 10.
+        assert_eq!(
+            dxf.extents(),
+            Some((Coord2D::new(-50.0, -40.0), Coord2D::new(110.0, 30.0)))
+        );
     }
 
-    vec![DxfEntity::Arc(DxfArc {
-        layer,
-        color,
-        line_type,
-        center_x: arc.center_x,
-        center_y: arc.center_y,
-        radius: arc.radius,
-        start_angle: rad_to_deg(arc.start_angle),
-        end_angle: rad_to_deg(arc.start_angle + arc.arc_angle),
-    })]
-}
+    #[test]
+    fn emit_groups_as_blocks_moves_group_members_into_anonymous_block() {
+        let line = |group: u32, end_x: f64| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    group,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![line(4, 1.0), line(0, 2.0), line(4, 3.0)],
+            block_defs: Vec::new(),
+        };
 
-fn convert_text(text: &Text, layer: String, color: i32, line_type: String) -> DxfText {
-    DxfText {
-        layer,
-        color,
-        line_type,
-        x: text.start_x,
-        y: text.start_y,
-        height: if text.size_y <= 0.0 { 2.5 } else { text.size_y },
-        rotation: text.angle,
-        content: text.content.clone(),
-        style: "STANDARD".to_string(),
+        let dxf = convert_document(&doc);
+        assert_eq!(dxf.entities.len(), 3);
+        assert!(dxf.blocks.is_empty());
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                emit_groups_as_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        // The INSERT keeps the draw-order place of the group's first member.
+        let [DxfEntity::Insert(insert), DxfEntity::Line(line)] = dxf.entities.as_slice() else {
+            panic!("expected INSERT then LINE, got {:?}", dxf.entities);
+        };
+        assert_eq!(line.x2, 2.0);
+        assert_eq!(insert.block_name, "*U4");
+        assert_eq!((insert.x, insert.y), (0.0, 0.0));
+        assert_eq!(dxf.blocks.len(), 1);
+        assert_eq!(dxf.blocks[0].name, "*U4");
+        assert!(contains_line(&dxf.blocks[0].entities, 0.0, 0.0, 1.0, 0.0));
+        assert!(contains_line(&dxf.blocks[0].entities, 0.0, 0.0, 3.0, 0.0));
+
+        let out = document_to_string(&dxf).unwrap();
+        assert!(out.contains("AcDbBlockBegin\n  2\n*U4\n 70\n1\n"));
+        assert!(out.contains("AcDbBlockBegin\n  2\n*Model_Space\n 70\n0\n"));
+
+        // Exploding asks for no inserts, so the members stay in place.
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                emit_groups_as_blocks: true,
+                explode_inserts: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert!(dxf.blocks.is_empty());
+        let ends = dxf
+            .entities
+            .iter()
+            .map(|entity| match entity {
+                DxfEntity::Line(v) => v.x2,
+                other => panic!("expected LINE, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ends, [1.0, 2.0, 3.0]);
     }
-}
 
-fn block_name_map(doc: &JwwDocument) -> HashMap<u32, String> {
-    let mut map = HashMap::<u32, String>::with_capacity(doc.block_defs.len());
-    for block_def in &doc.block_defs {
-        map.insert(
-            block_def.number,
-            block_def_name(block_def.number, &block_def.name),
+    #[test]
+    fn fingerprint_guid_follows_source_document() {
+        let options = ConvertOptions {
+            fingerprint: true,
+            ..ConvertOptions::default()
+        };
+        let line = |end_x: f64| JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x,
+                end_y: 0.0,
+            })],
+            block_defs: Vec::new(),
+        };
+
+        let guid = convert_document_with_options(&line(10.0), options.clone())
+            .fingerprint_guid
+            .unwrap();
+        assert_eq!(guid.len(), 38);
+        assert!(guid.starts_with('{') && guid.ends_with('}'));
+        assert_eq!(&guid[15..16], "8");
+        // Pinned, so a change to the canonical form is a deliberate one.
+        assert_eq!(guid, "{820074EC-947C-81A1-B74D-9A9B62BEFB38}");
+        let again = convert_document_with_options(&line(10.0), options.clone());
+        assert_eq!(again.fingerprint_guid.as_deref(), Some(guid.as_str()));
+        let other = convert_document_with_options(&line(11.0), options.clone());
+        assert_ne!(other.fingerprint_guid.as_deref(), Some(guid.as_str()));
+
+        let out = document_to_string(&again).unwrap();
+        assert!(out.contains(&format!("  9\n$FINGERPRINTGUID\n  2\n{guid}\n")));
+        assert!(convert_document(&line(10.0)).fingerprint_guid.is_none());
+        let r12 = convert_document_with_options(
+            &line(10.0),
+            ConvertOptions {
+                version: DxfVersion::R12,
+                ..options
+            },
         );
+        assert!(!document_to_string(&r12)
+            .unwrap()
+            .contains("$FINGERPRINTGUID"));
     }
-    map
-}
 
-fn block_def_name(number: u32, raw: &str) -> String {
-    if raw.is_empty() {
-        format!("BLOCK_{number}")
-    } else {
-        raw.to_string()
+    #[test]
+    fn convert_document_scales_to_target_units() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 25.4,
+                end_y: 0.0,
+            })],
+            block_defs: vec![],
+        };
+
+        let out = document_to_string(&convert_document(&doc)).unwrap();
+        let header = &out[out.find("  9\n$INSUNITS\n").unwrap()..];
+        assert_eq!(group_values_by_code(header, 70)[0], "4");
+        assert_eq!(group_values_by_code(header, 70)[1], "1");
+
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                target_units: TargetUnits::Inches,
+                ..ConvertOptions::default()
+            },
+        );
+        let DxfEntity::Line(line) = &dxf.entities[0] else {
+            panic!("expected LINE, got {:?}", dxf.entities[0]);
+        };
+        assert_eq!(line.x2, 1.0);
+
+        let out = document_to_string(&dxf).unwrap();
+        let header = &out[out.find("  9\n$INSUNITS\n").unwrap()..];
+        assert_eq!(group_values_by_code(header, 70)[0], "1");
+        // $MEASUREMENT follows $INSUNITS and switches to imperial.
+        assert_eq!(group_values_by_code(header, 70)[1], "0");
+        let entity = &out[out.find("  0\nLINE\n").unwrap()..];
+        assert_eq!(group_values_by_code(entity, 11), vec!["1.000000000000"]);
     }
-}
 
-fn layer_name(doc: &JwwDocument, layer_group: u16, layer: u16) -> String {
-    let g = layer_group as usize;
-    let l = layer as usize;
-    if g < 16 && l < 16 {
-        let candidate = doc.header.layer_groups[g].layers[l].name.trim();
-        if !candidate.is_empty() {
-            return candidate.to_string();
-        }
+    #[test]
+    fn convert_document_orders_solid_corners_for_dxf() {
+        // Corners in record order, which walks the outline: 1, 4, 2, 3.
+        let solid = |p2: (f64, f64), p3: (f64, f64)| {
+            Entity::Solid(Solid {
+                base: EntityBase::default(),
+                point1_x: 0.0,
+                point1_y: 0.0,
+                point4_x: 10.0,
+                point4_y: 0.0,
+                point2_x: p2.0,
+                point2_y: p2.1,
+                point3_x: p3.0,
+                point3_y: p3.1,
+                color: None,
+                is_filled: true,
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![
+                solid((10.0, 10.0), (0.0, 10.0)),
+                solid((5.0, 8.0), (5.0, 8.0)),
+            ],
+            block_defs: vec![],
+        };
+
+        let out = document_to_string(&convert_document(&doc)).unwrap();
+        let entities = &out[out.find("  0\nSOLID\n").unwrap()..];
+        let corners = |code_x: i32, code_y: i32| {
+            group_values_by_code(entities, code_x)
+                .into_iter()
+                .zip(group_values_by_code(entities, code_y))
+                .map(|(x, y)| (x.parse::<f64>().unwrap(), y.parse::<f64>().unwrap()))
+                .collect::<Vec<_>>()
+        };
+        // Quad: 1-2 is one edge and 3-4 the opposite edge in reverse, so the
+        // outline 10-11-13-12 walks the square without crossing itself.
+        assert_eq!(corners(10, 20), vec![(0.0, 0.0), (0.0, 0.0)]);
+        assert_eq!(corners(11, 21), vec![(10.0, 0.0), (10.0, 0.0)]);
+        assert_eq!(corners(12, 22), vec![(0.0, 10.0), (5.0, 8.0)]);
+        assert_eq!(corners(13, 23), vec![(10.0, 10.0), (5.0, 8.0)]);
     }
-    format!("{:X}-{:X}", layer_group, layer)
-}
 
-fn map_color(pen_color: u16) -> i32 {
-    match pen_color {
-        1 | 8 => 7,
-        2 => 5,
-        3 => 1,
-        4 => 6,
-        5 => 3,
-        6 => 4,
-        7 => 2,
-        9 => 8,
-        _ => ((pen_color as i32) % 255).max(1),
+    // True when edges a-b and c-d cross at a point inside both.
+    fn segments_cross(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+        let side = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+            (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+        };
+        side(a, b, c) * side(a, b, d) < -1e-9 && side(c, d, a) * side(c, d, b) < -1e-9
     }
-}
 
-fn map_line_type(pen_style: u8) -> &'static str {
-    match pen_style {
-        0 => "CONTINUOUS",
-        1 => "DASHED",
-        2 => "DASHDOT",
-        3 => "DOT",
-        4 => "DASHED2",
-        _ => "BYLAYER",
+    fn is_simple_quad(outline: [(f64, f64); 4]) -> bool {
+        let [a, b, c, d] = outline;
+        !segments_cross(a, b, c, d) && !segments_cross(b, c, d, a)
     }
-}
 
-fn rad_to_deg(rad: f64) -> f64 {
-    rad * 180.0 / PI
-}
+    #[test]
+    fn sample_solids_convert_to_simple_quads() {
+        let doc =
+            read_document_from_file(jww_samples_dir().join("Ａマンション平面例.jww")).unwrap();
+        let dxf = convert_document(&doc);
+        let block_entities = dxf.blocks.iter().flat_map(|block| block.entities.iter());
+        let solids = dxf
+            .entities
+            .iter()
+            .chain(block_entities)
+            .filter_map(|entity| match entity {
+                DxfEntity::Solid(v) => Some(v),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(solids.len(), 79);
+        for v in solids {
+            // SOLID corners are drawn 1-2-4-3.
+            let outline = [(v.x1, v.y1), (v.x2, v.y2), (v.x4, v.y4), (v.x3, v.y3)];
+            assert!(is_simple_quad(outline), "bow-tie SOLID {outline:?}");
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::array;
-    use std::collections::BTreeSet;
-    use std::fs;
-    use std::path::{Path, PathBuf};
+    #[test]
+    fn convert_document_emits_polyline_bulge() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Polyline(Polyline {
+                base: EntityBase::default(),
+                vertices: vec![
+                    PolyVertex::new(Coord2D::new(0.0, 0.0), 1.0),
+                    PolyVertex::new(Coord2D::new(10.0, 0.0), 0.0),
+                    PolyVertex::new(Coord2D::new(10.0, 5.0), 0.0),
+                ],
+                closed: true,
+            })],
+            block_defs: vec![],
+        };
 
-    use crate::header::{JwwHeader, LayerGroupHeader, LayerHeader};
-    use crate::model::{Block, BlockDef, Entity, EntityBase, JwwDocument, Line, Text};
-    use crate::parser::read_document_from_file;
+        let dxf = convert_document(&doc);
+        match dxf.entities.as_slice() {
+            [DxfEntity::LwPolyline(v)] => {
+                assert!(v.closed);
+                assert_eq!(v.vertices.len(), 3);
+                assert_eq!(v.vertices[0].bulge, 1.0);
+            }
+            other => panic!("expected LWPOLYLINE, got {:?}", other),
+        }
 
-    use super::{
-        convert_document, convert_document_with_options, document_to_string, ConvertOptions,
-        DxfDocument, DxfEntity, DxfLayer, DxfText,
-    };
+        let out = document_to_string(&dxf).unwrap();
+        let polyline = &out[out.find("  0\nLWPOLYLINE\n").unwrap()..];
+        assert_eq!(group_values_by_code(polyline, 90), vec!["3"]);
+        assert_eq!(group_values_by_code(polyline, 42), vec!["1.000000000000"]);
+    }
 
-    fn empty_header() -> JwwHeader {
-        JwwHeader {
-            version: 600,
-            memo: String::new(),
-            paper_size: 0,
-            write_layer_group: 0,
-            layer_groups: array::from_fn(|g| LayerGroupHeader {
-                state: 0,
-                write_layer: 0,
-                scale: 1.0,
-                protect: 0,
-                name: format!("Group{g:X}"),
-                layers: array::from_fn(|l| LayerHeader {
-                    state: 0,
-                    protect: 0,
-                    name: format!("{g:X}-{l:X}"),
-                }),
-            }),
+    fn group_values_by_code(dxf: &str, target_code: i32) -> Vec<String> {
+        let mut out = Vec::<String>::new();
+        let mut lines = dxf.lines();
+        while let Some(code_line) = lines.next() {
+            let Some(value_line) = lines.next() else {
+                break;
+            };
+            if code_line.trim().parse::<i32>().ok() == Some(target_code) {
+                out.push(value_line.to_string());
+            }
         }
+        out
+    }
+
+    fn contains_line(entities: &[DxfEntity], x1: f64, y1: f64, x2: f64, y2: f64) -> bool {
+        entities.iter().any(|entity| {
+            if let DxfEntity::Line(line) = entity {
+                nearly_eq(line.x1, x1)
+                    && nearly_eq(line.y1, y1)
+                    && nearly_eq(line.x2, x2)
+                    && nearly_eq(line.y2, y2)
+            } else {
+                false
+            }
+        })
     }
 
-    fn jww_samples_dir() -> PathBuf {
-        Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples")
+    fn nearly_eq(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
     }
 
     #[test]
-    fn convert_document_handles_line_and_dimension() {
-        let base = EntityBase::default();
-        let line = Entity::Line(Line {
-            base,
+    fn convert_document_skips_entities_with_non_finite_coordinates() {
+        let line = |end_x: f64| {
+            Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x,
+                end_y: 0.0,
+            })
+        };
+        let circle = Entity::Arc(Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: f64::NAN,
+            start_angle: 0.0,
+            arc_angle: 2.0 * PI,
+            tilt_angle: 0.0,
+            flatness: 1.0,
+            is_full_circle: true,
+        });
+        let text = Entity::Text(Text {
+            base: EntityBase::default(),
             start_x: 0.0,
             start_y: 0.0,
-            end_x: 10.0,
+            end_x: 1.0,
             end_y: 0.0,
+            text_type: 0,
+            size_x: 2.5,
+            size_y: 2.5,
+            spacing: 0.0,
+            angle: f64::INFINITY,
+            font_name: String::new(),
+            content: "A".to_string(),
         });
-        let dim = Entity::Dimension(crate::model::Dimension {
-            base,
-            line: Line {
-                base,
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![line(1.0), line(f64::INFINITY), line(f64::NAN), circle, text],
+            block_defs: vec![],
+        };
+
+        for explode_inserts in [false, true] {
+            let dxf = convert_document_with_options(
+                &doc,
+                ConvertOptions {
+                    explode_inserts,
+                    ..ConvertOptions::default()
+                },
+            );
+            assert_eq!(dxf.entities.len(), 1);
+            assert!(contains_line(&dxf.entities, 0.0, 0.0, 1.0, 0.0));
+            assert_eq!(dxf.unsupported_entities, vec!["NON_FINITE_COORD"; 4]);
+            let text = document_to_string(&dxf).unwrap();
+            assert!(!text.contains("inf") && !text.contains("NaN"));
+        }
+    }
+
+    #[test]
+    fn document_to_string_rejects_non_finite_values() {
+        // Finite when converted, infinite once snapped to microns.
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Line(Line {
+                base: EntityBase::default(),
                 start_x: 0.0,
-                start_y: 1.0,
-                end_x: 10.0,
-                end_y: 1.0,
-            },
-            text: Text {
-                base,
-                start_x: 5.0,
-                start_y: 2.0,
-                end_x: 5.0,
-                end_y: 2.0,
-                text_type: 0,
-                size_x: 1.0,
-                size_y: 1.0,
-                spacing: 0.0,
-                angle: 0.0,
-                font_name: String::new(),
-                content: "1000".to_string(),
+                start_y: 0.0,
+                end_x: f64::MAX,
+                end_y: 0.0,
+            })],
+            block_defs: vec![],
+        };
+        let mut dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                quantize_microns: true,
+                ..ConvertOptions::default()
             },
-            sxf_mode: Some(0),
-            aux_lines: vec![],
-            aux_points: vec![],
-        });
+        );
+        assert!(matches!(
+            document_to_string(&dxf),
+            Err(JwwError::NonFiniteGroup(11))
+        ));
+
+        // Built by hand, so never seen by the conversion's own check.
+        dxf.entities = vec![DxfEntity::Line(DxfLine {
+            layer: "0".to_string(),
+            color: 7,
+            line_type: "CONTINUOUS".to_string(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: 0.0,
+            y1: f64::NAN,
+            x2: 1.0,
+            y2: 1.0,
+        })];
+        assert!(matches!(
+            document_to_string(&dxf),
+            Err(JwwError::NonFiniteGroup(20))
+        ));
+        assert!(matches!(
+            document_to_bytes(&dxf),
+            Err(JwwError::NonFiniteGroup(20))
+        ));
+    }
 
+    #[test]
+    fn convert_document_arcs_as_polylines_flattens_circles_and_arcs() {
+        let arc = |arc_angle: f64, is_full_circle: bool| {
+            Entity::Arc(Arc {
+                base: EntityBase::default(),
+                center_x: 5.0,
+                center_y: 0.0,
+                radius: 10.0,
+                start_angle: 0.0,
+                arc_angle,
+                tilt_angle: 0.0,
+                flatness: 1.0,
+                is_full_circle,
+            })
+        };
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![line, dim],
+            entities: vec![arc(2.0 * PI, true), arc(PI / 2.0, false)],
             block_defs: vec![],
         };
+        assert!(convert_document(&doc)
+            .entities
+            .iter()
+            .any(|entity| matches!(entity, DxfEntity::Circle(_))));
 
-        let dxf = convert_document(&doc);
-        let types = dxf
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                arcs_as_polylines: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let polylines = dxf
             .entities
             .iter()
-            .map(DxfEntity::entity_type)
+            .map(|entity| match entity {
+                DxfEntity::LwPolyline(v) => v,
+                other => panic!("expected LWPOLYLINE, got {:?}", other),
+            })
             .collect::<Vec<_>>();
-        assert_eq!(types, vec!["LINE", "LINE", "TEXT"]);
+        assert_eq!(polylines.len(), 2);
+        assert!(polylines[0].closed);
+        assert!(!polylines[1].closed);
+        for polyline in &polylines {
+            assert!(polyline.vertices.len() > 4);
+            for pair in polyline.vertices.windows(2) {
+                let mid_x = (pair[0].x + pair[1].x) / 2.0 - 5.0;
+                let mid_y = (pair[0].y + pair[1].y) / 2.0;
+                assert!(10.0 - mid_x.hypot(mid_y) <= ARC_CHORD_TOLERANCE + 1e-9);
+            }
+        }
+        let quarter = &polylines[1].vertices;
+        assert!(nearly_eq(quarter[0].x, 15.0) && nearly_eq(quarter[0].y, 0.0));
+        let last = quarter.last().unwrap();
+        assert!(nearly_eq(last.x, 5.0) && nearly_eq(last.y, 10.0));
     }
 
     #[test]
-    fn convert_document_resolves_insert_block_name() {
-        let base = EntityBase::default();
-        let entity = Entity::Block(Block {
-            base,
-            ref_x: 1.0,
-            ref_y: 2.0,
-            scale_x: 1.0,
-            scale_y: 1.0,
-            rotation: 0.0,
-            def_number: 5,
-        });
-
-        let block_def = BlockDef {
-            base,
-            number: 5,
-            is_referenced: true,
-            name: "Door".to_string(),
-            entities: vec![],
+    fn arcs_as_polylines_keeps_ellipse_chords_within_tolerance() {
+        let arc = Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: 1000.0,
+            start_angle: PI / 4.0,
+            arc_angle: PI,
+            tilt_angle: 0.3,
+            flatness: 0.25,
+            is_full_circle: false,
         };
-
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![entity],
-            block_defs: vec![block_def],
+            entities: vec![Entity::Arc(arc.clone())],
+            block_defs: vec![],
         };
-
-        let dxf = convert_document(&doc);
-        match &dxf.entities[0] {
-            DxfEntity::Insert(v) => assert_eq!(v.block_name, "Door"),
-            other => panic!("expected INSERT, got {:?}", other),
+        let dxf = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                arcs_as_polylines: true,
+                ..ConvertOptions::default()
+            },
+        );
+        let [DxfEntity::LwPolyline(polyline)] = dxf.entities.as_slice() else {
+            panic!("expected one LWPOLYLINE, got {:?}", dxf.entities);
+        };
+        let vertices = &polyline.vertices;
+        let (first, last) = (&vertices[0], vertices.last().unwrap());
+        let (start, end) = (arc.start_point(), arc.end_point());
+        assert!(nearly_eq(first.x, start.x) && nearly_eq(first.y, start.y));
+        assert!(nearly_eq(last.x, end.x) && nearly_eq(last.y, end.y));
+
+        // Walk the curve finely and measure how far it strays from the chord
+        // under each step.
+        let steps = vertices.len() - 1;
+        for (i, pair) in vertices.windows(2).enumerate() {
+            let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            let length = dx.hypot(dy);
+            for k in 1..16 {
+                let t = (i as f64 + k as f64 / 16.0) / steps as f64;
+                let point = arc.point_at_angle(arc.start_angle + arc.arc_angle * t);
+                let gap = ((point.x - pair[0].x) * dy - (point.y - pair[0].y) * dx).abs() / length;
+                assert!(gap <= ARC_CHORD_TOLERANCE + 1e-9, "gap {gap} on step {i}");
+            }
         }
     }
 
     #[test]
-    fn convert_document_explode_inserts_expands_nested_blocks() {
-        let base = EntityBase::default();
-        let top_insert = Entity::Block(Block {
-            base,
-            ref_x: 10.0,
-            ref_y: 20.0,
-            scale_x: 2.0,
-            scale_y: 2.0,
-            rotation: 0.0,
-            def_number: 1,
-        });
-
-        let block_2 = BlockDef {
-            base,
-            number: 2,
-            is_referenced: true,
-            name: "B2".to_string(),
+    fn document_to_string_writes_entity_xdata_under_app_id() {
+        let doc = JwwDocument {
+            header: empty_header(),
             entities: vec![Entity::Line(Line {
-                base,
+                base: EntityBase::default(),
                 start_x: 0.0,
                 start_y: 0.0,
-                end_x: 0.0,
-                end_y: 1.0,
+                end_x: 1.0,
+                end_y: 0.0,
             })],
+            block_defs: vec![],
         };
+        let mut dxf = convert_document(&doc);
+        let plain = document_to_string(&dxf).unwrap();
+        assert!(!plain.contains("1001\n"));
+        assert!(!plain.contains("APPID"));
+
+        dxf.xdata_app_id = "CLASSIFIER".to_string();
+        dxf.entities[0].xdata_mut().extend([
+            ("class".to_string(), XDataValue::String("wall".to_string())),
+            ("score".to_string(), XDataValue::Real(0.5)),
+            ("rank".to_string(), XDataValue::Integer(3)),
+        ]);
+        let out = document_to_string(&dxf).unwrap();
+        let line = &out[out.find("  0\nLINE\n").unwrap()..];
+        let line = &line[..line[1..].find("  0\n").unwrap() + 1];
+        assert!(line.ends_with(
+            "1001\nCLASSIFIER\n1000\nclass\n1000\nwall\n1000\nscore\n1040\n0.500000000000\n1000\nrank\n1071\n3\n"
+        ));
+        let appid = &out[out.find("  2\nAPPID\n").unwrap()..];
+        assert_eq!(group_values_by_code(appid, 2)[1..3], ["ACAD", "CLASSIFIER"]);
+        assert_eq!(DEFAULT_XDATA_APP_ID, convert_document(&doc).xdata_app_id);
+    }
 
-        let block_1 = BlockDef {
-            base,
-            number: 1,
-            is_referenced: true,
-            name: "B1".to_string(),
+    #[test]
+    fn convert_document_writes_jww_image_as_image_with_imagedef() {
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![Entity::Text(Text {
+                base: EntityBase::default(),
+                start_x: 10.0,
+                start_y: 20.0,
+                end_x: 110.0,
+                end_y: 20.0,
+                text_type: 0,
+                size_x: 2.5,
+                size_y: 2.5,
+                spacing: 0.0,
+                angle: 90.0,
+                font_name: String::new(),
+                content: "^@BMC:\\photos\\site.bmp,100,50,0,0,1,0".to_string(),
+            })],
+            block_defs: vec![],
+        };
+        let dxf = convert_document(&doc);
+        let DxfEntity::Image(image) = &dxf.entities[0] else {
+            panic!("expected IMAGE, got {:?}", dxf.entities[0]);
+        };
+        assert_eq!(image.path, "C:\\photos\\site.bmp");
+        // Without image_dirs nothing is opened, so the pixel size follows the
+        // aspect ratio.
+        assert_eq!((image.pixel_width, image.pixel_height), (1000, 500));
+        let corners = image.corners();
+        assert!(nearly_eq(corners[1].0, 10.0) && nearly_eq(corners[1].1, 120.0));
+        assert!(nearly_eq(corners[2].0, -40.0) && nearly_eq(corners[2].1, 120.0));
+
+        let out = document_to_string(&dxf).unwrap();
+        assert!(out.contains("  0\nCLASS\n  1\nIMAGEDEF\n"));
+        let entity = &out[out.find("  0\nIMAGE\n").unwrap()..];
+        let entity = &entity[..entity[1..].find("  0\n").unwrap() + 1];
+        let image_handle = &group_values_by_code(entity, 5)[0];
+        let def_handle = &group_values_by_code(entity, 340)[0];
+        let reactor_handle = &group_values_by_code(entity, 360)[0];
+        assert_eq!(group_values_by_code(entity, 13), ["1000.000000000000"]);
+
+        let objects = &out[out.find("  2\nOBJECTS\n").unwrap()..];
+        assert!(objects.contains("  3\nACAD_IMAGE_DICT\n"));
+        assert!(objects.contains(&format!("  3\nsite\n350\n{def_handle}\n")));
+        let def = &objects[objects.find("  0\nIMAGEDEF\n").unwrap()..];
+        assert_eq!(group_values_by_code(def, 5)[0], *def_handle);
+        assert_eq!(group_values_by_code(def, 1)[0], "C:\\photos\\site.bmp");
+        assert!(def.contains(&format!("330\n{reactor_handle}\n102\n}}\n")));
+        let reactor = &objects[objects.find("  0\nIMAGEDEF_REACTOR\n").unwrap()..];
+        assert_eq!(group_values_by_code(reactor, 5)[0], *reactor_handle);
+        assert_eq!(group_values_by_code(reactor, 330)[0], *image_handle);
+
+        let r12 = document_to_string(&DxfDocument {
+            version: DxfVersion::R12,
+            ..dxf.clone()
+        })
+        .unwrap();
+        assert!(!r12.contains("IMAGE"));
+        assert!(r12.contains("  0\nPOLYLINE\n"));
+    }
+
+    #[test]
+    fn image_dirs_opt_into_reading_image_sizes_by_file_name() {
+        let dir = std::env::temp_dir().join(format!("ezjww_image_dirs_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        png.extend([8, 2, 0, 0, 0]);
+        fs::write(dir.join("site.png"), png).unwrap();
+
+        let image = |path: &str| {
+            Entity::Text(Text {
+                base: EntityBase::default(),
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 100.0,
+                end_y: 0.0,
+                text_type: 0,
+                size_x: 2.5,
+                size_y: 2.5,
+                spacing: 0.0,
+                angle: 0.0,
+                font_name: String::new(),
+                content: format!("^@BM{path},100,50,0,0,1,0"),
+            })
+        };
+        let doc = JwwDocument {
+            header: empty_header(),
             entities: vec![
-                Entity::Line(Line {
-                    base,
-                    start_x: 0.0,
-                    start_y: 0.0,
-                    end_x: 1.0,
-                    end_y: 0.0,
-                }),
-                Entity::Block(Block {
-                    base,
-                    ref_x: 0.0,
-                    ref_y: 2.0,
-                    scale_x: 1.0,
-                    scale_y: 1.0,
-                    rotation: 0.0,
-                    def_number: 2,
-                }),
+                image("\\\\server\\share\\site.png"),
+                image("/elsewhere/site.png"),
+                image("missing.png"),
             ],
+            block_defs: vec![],
+        };
+        let pixel_sizes = |options: ConvertOptions| {
+            convert_document_with_options(&doc, options)
+                .entities
+                .iter()
+                .map(|entity| match entity {
+                    DxfEntity::Image(v) => (v.pixel_width, v.pixel_height),
+                    other => panic!("expected IMAGE, got {other:?}"),
+                })
+                .collect::<Vec<_>>()
         };
 
+        // By default the paths are never opened.
+        assert_eq!(pixel_sizes(ConvertOptions::default()), [(1000, 500); 3]);
+        // Only the file name is looked up, and only in the trusted directory.
+        let sizes = pixel_sizes(ConvertOptions {
+            image_dirs: vec![dir.clone()],
+            ..ConvertOptions::default()
+        });
+        assert_eq!(sizes, [(640, 480), (640, 480), (1000, 500)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn convert_document_blocks_as_bbox_replaces_insert_with_outline() {
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![top_insert],
-            block_defs: vec![block_1, block_2],
+            entities: vec![Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x: 100.0,
+                ref_y: 100.0,
+                scale_x: 2.0,
+                scale_y: 2.0,
+                rotation: 0.0,
+                def_number: 1,
+                attributes: vec![],
+            })],
+            block_defs: vec![BlockDef {
+                base: EntityBase::default(),
+                number: 1,
+                is_referenced: true,
+                created_time: 0,
+                name: "DESK".to_string(),
+                entities: vec![
+                    Entity::Line(Line {
+                        base: EntityBase::default(),
+                        start_x: 0.0,
+                        start_y: 0.0,
+                        end_x: 10.0,
+                        end_y: 5.0,
+                    }),
+                    // Centered on the line's far end, so only its sweep, not
+                    // its center, reaches past x = 10.
+                    Entity::Arc(Arc {
+                        base: EntityBase::default(),
+                        center_x: 10.0,
+                        center_y: 5.0,
+                        radius: 2.0,
+                        start_angle: -PI / 2.0,
+                        arc_angle: PI,
+                        tilt_angle: 0.0,
+                        flatness: 1.0,
+                        is_full_circle: false,
+                    }),
+                ],
+            }],
         };
 
         let dxf = convert_document_with_options(
             &doc,
             ConvertOptions {
-                explode_inserts: true,
-                max_block_nesting: 32,
+                blocks_as_bbox: true,
+                ..ConvertOptions::default()
             },
         );
-
-        assert!(dxf.blocks.is_empty());
-        assert!(!dxf.entities.is_empty());
+        assert_eq!(dxf.entities.len(), 5);
         assert!(!dxf
             .entities
             .iter()
-            .any(|e| matches!(e, DxfEntity::Insert(_))));
-
-        assert!(contains_line(&dxf.entities, 10.0, 20.0, 12.0, 20.0));
-        assert!(contains_line(&dxf.entities, 10.0, 24.0, 10.0, 26.0));
+            .any(|entity| matches!(entity, DxfEntity::Insert(_))));
+        // The arc bulges 4 units right of x = 120 and up to y = 114.
+        assert!(contains_line(&dxf.entities, 100.0, 100.0, 124.0, 100.0));
+        assert!(contains_line(&dxf.entities, 124.0, 100.0, 124.0, 114.0));
+        assert!(contains_line(&dxf.entities, 124.0, 114.0, 100.0, 114.0));
+        assert!(contains_line(&dxf.entities, 100.0, 114.0, 100.0, 100.0));
+        assert!(!contains_line(&dxf.entities, 100.0, 100.0, 124.0, 114.0));
+        let DxfEntity::Text(label) = &dxf.entities[4] else {
+            panic!("expected TEXT, got {:?}", dxf.entities[4]);
+        };
+        assert_eq!(label.content, "DESK");
+        assert_eq!(label.alignment_point, Some((112.0, 107.0)));
     }
 
     #[test]
-    fn convert_document_explode_inserts_detects_cycle() {
-        let base = EntityBase::default();
-        let top_insert = Entity::Block(Block {
-            base,
-            ref_x: 0.0,
-            ref_y: 0.0,
-            scale_x: 1.0,
-            scale_y: 1.0,
-            rotation: 0.0,
-            def_number: 1,
-        });
-
-        let block_1 = BlockDef {
-            base,
-            number: 1,
-            is_referenced: true,
-            name: "B1".to_string(),
-            entities: vec![Entity::Block(Block {
-                base,
+    fn convert_document_keeps_inserts_resolvable_with_placeholder_blocks() {
+        let insert = |def_number: u32| {
+            Entity::Block(Block {
+                base: EntityBase::default(),
                 ref_x: 0.0,
                 ref_y: 0.0,
                 scale_x: 1.0,
                 scale_y: 1.0,
                 rotation: 0.0,
-                def_number: 2,
-            })],
+                def_number,
+                attributes: vec![],
+            })
         };
-        let block_2 = BlockDef {
-            base,
-            number: 2,
+        let block_def = |number: u32, name: &str| BlockDef {
+            base: EntityBase::default(),
+            number,
             is_referenced: true,
-            name: "B2".to_string(),
-            entities: vec![Entity::Block(Block {
-                base,
-                ref_x: 0.0,
-                ref_y: 0.0,
-                scale_x: 1.0,
-                scale_y: 1.0,
-                rotation: 0.0,
-                def_number: 1,
+            created_time: 0,
+            name: name.to_string(),
+            // A temporary point converts to nothing.
+            entities: vec![Entity::Point(Point {
+                base: EntityBase::default(),
+                x: 1.0,
+                y: 1.0,
+                is_temporary: true,
+                code: 0,
+                angle: 0.0,
+                scale: 1.0,
             })],
         };
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![insert(1), insert(7)],
+            block_defs: vec![block_def(1, "EMPTY"), block_def(2, "UNUSED")],
+        };
+
+        let dxf = convert_document(&doc);
+        let names = dxf
+            .blocks
+            .iter()
+            .map(|block| block.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["EMPTY", "BLOCK_7"]);
+        for entity in &dxf.entities {
+            let DxfEntity::Insert(insert) = entity else {
+                panic!("expected INSERT, got {entity:?}");
+            };
+            let block = dxf
+                .blocks
+                .iter()
+                .find(|block| block.name == insert.block_name)
+                .unwrap();
+            assert!(matches!(
+                block.entities.as_slice(),
+                [DxfEntity::Point(point)] if (point.x, point.y) == (0.0, 0.0)
+            ));
+        }
+
+        let kept = convert_document_with_options(
+            &doc,
+            ConvertOptions {
+                keep_unreferenced_blocks: true,
+                ..ConvertOptions::default()
+            },
+        );
+        assert!(kept.blocks.iter().any(|block| block.name == "UNUSED"));
+    }
 
+    #[test]
+    fn convert_document_include_temporary_points_keeps_them_on_own_layer() {
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![top_insert],
-            block_defs: vec![block_1, block_2],
+            entities: vec![Entity::Point(Point {
+                base: EntityBase::default(),
+                x: 12.0,
+                y: 34.0,
+                is_temporary: true,
+                code: 0,
+                angle: 0.0,
+                scale: 1.0,
+            })],
+            block_defs: Vec::new(),
         };
 
+        let dxf = convert_document(&doc);
+        assert!(dxf.entities.is_empty());
+        assert!(!dxf
+            .layers
+            .iter()
+            .any(|layer| layer.name == TEMP_POINTS_LAYER));
+
         let dxf = convert_document_with_options(
             &doc,
             ConvertOptions {
-                explode_inserts: true,
-                max_block_nesting: 32,
+                include_temporary_points: true,
+                ..ConvertOptions::default()
             },
         );
-
+        let [DxfEntity::Point(point)] = dxf.entities.as_slice() else {
+            panic!("expected one POINT, got {:?}", dxf.entities);
+        };
+        assert_eq!((point.x, point.y), (12.0, 34.0));
+        assert_eq!(point.layer, TEMP_POINTS_LAYER);
         assert!(dxf
-            .unsupported_entities
+            .layers
             .iter()
-            .any(|v| v.starts_with("BLOCK_CYCLE(")));
+            .any(|layer| layer.name == TEMP_POINTS_LAYER));
     }
 
     #[test]
-    fn convert_document_explode_inserts_reports_unresolved_block() {
-        let base = EntityBase::default();
-        let top_insert = Entity::Block(Block {
-            base,
-            ref_x: 0.0,
-            ref_y: 0.0,
-            scale_x: 1.0,
-            scale_y: 1.0,
-            rotation: 0.0,
-            def_number: 999,
+    fn try_convert_document_aborts_past_max_total_segments() {
+        let circle = Entity::Arc(Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: 1000.0,
+            start_angle: 0.0,
+            arc_angle: 2.0 * PI,
+            tilt_angle: 0.0,
+            flatness: 1.0,
+            is_full_circle: true,
         });
+        let doc = JwwDocument {
+            header: empty_header(),
+            entities: vec![circle; 10],
+            block_defs: vec![],
+        };
+        let options = |max_total_segments| ConvertOptions {
+            explode_inserts: true,
+            arcs_as_polylines: true,
+            max_total_segments,
+            ..ConvertOptions::default()
+        };
+
+        let dxf = try_convert_document_with_options(&doc, options(None)).unwrap();
+        let generated = dxf.segment_count();
+        let per_circle = generated / 10;
+        assert!(per_circle > 10, "{generated}");
+        assert!(try_convert_document_with_options(&doc, options(Some(generated))).is_ok());
+
+        // Conversion stops at the second circle rather than after all ten.
+        let err = try_convert_document_with_options(&doc, options(Some(per_circle))).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                JwwError::SegmentLimitExceeded { generated: g, limit } if g == 2 * per_circle && limit == per_circle
+            ),
+            "{err:?}"
+        );
+    }
 
+    #[test]
+    fn convert_document_expand_point_markers_draws_cross() {
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![top_insert],
+            entities: vec![Entity::Point(Point {
+                base: EntityBase {
+                    pen_style: 100,
+                    ..EntityBase::default()
+                },
+                x: 10.0,
+                y: 20.0,
+                is_temporary: false,
+                code: POINT_MARKER_CROSS,
+                angle: 0.0,
+                scale: 2.0,
+            })],
             block_defs: vec![],
         };
+        assert!(matches!(
+            convert_document(&doc).entities.as_slice(),
+            [DxfEntity::Point(_)]
+        ));
 
         let dxf = convert_document_with_options(
             &doc,
             ConvertOptions {
-                explode_inserts: true,
-                max_block_nesting: 32,
+                expand_point_markers: true,
+                ..ConvertOptions::default()
             },
         );
-
-        assert!(dxf.entities.is_empty());
-        assert!(dxf.blocks.is_empty());
-        assert!(dxf
-            .unsupported_entities
-            .iter()
-            .any(|v| v == "UNRESOLVED_BLOCK(999)"));
+        assert_eq!(dxf.entities.len(), 2);
+        assert!(contains_line(&dxf.entities, 8.0, 20.0, 12.0, 20.0));
+        assert!(contains_line(&dxf.entities, 10.0, 18.0, 10.0, 22.0));
     }
 
     #[test]
-    fn convert_document_explode_inserts_enforces_depth_limit() {
-        let base = EntityBase::default();
-        let top_insert = Entity::Block(Block {
-            base,
-            ref_x: 0.0,
-            ref_y: 0.0,
-            scale_x: 1.0,
-            scale_y: 1.0,
-            rotation: 0.0,
-            def_number: 1,
-        });
-
-        let block_2 = BlockDef {
-            base,
-            number: 2,
-            is_referenced: true,
-            name: "B2".to_string(),
-            entities: vec![Entity::Line(Line {
-                base,
-                start_x: 0.0,
+    fn layouts_per_group_writes_layout_and_paper_space_per_group() {
+        let line = |layer_group: u16, offset: f64| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    layer_group,
+                    ..EntityBase::default()
+                },
+                start_x: offset,
                 start_y: 0.0,
-                end_x: 1.0,
-                end_y: 0.0,
-            })],
-        };
-
-        let block_1 = BlockDef {
-            base,
-            number: 1,
-            is_referenced: true,
-            name: "B1".to_string(),
-            entities: vec![Entity::Block(Block {
-                base,
-                ref_x: 5.0,
-                ref_y: 0.0,
-                scale_x: 1.0,
-                scale_y: 1.0,
-                rotation: 0.0,
-                def_number: 2,
-            })],
+                end_x: offset + 100.0,
+                end_y: 50.0,
+            })
         };
-
+        let mut header = empty_header();
+        header.layer_groups[0].name = String::new();
+        header.layer_groups[2].name = "Plan".to_string();
+        // The circle's center is inside the line's box, but not its rim.
+        let circle = Entity::Arc(Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: 10.0,
+            start_angle: 0.0,
+            arc_angle: 2.0 * PI,
+            tilt_angle: 0.0,
+            flatness: 1.0,
+            is_full_circle: true,
+        });
         let doc = JwwDocument {
-            header: empty_header(),
-            entities: vec![top_insert],
-            block_defs: vec![block_1, block_2],
+            header,
+            entities: vec![line(0, 0.0), circle, line(2, 1000.0)],
+            block_defs: vec![],
         };
 
         let dxf = convert_document_with_options(
             &doc,
             ConvertOptions {
-                explode_inserts: true,
-                max_block_nesting: 1,
+                layouts_per_group: true,
+                ..ConvertOptions::default()
             },
         );
+        let names = dxf
+            .layouts
+            .iter()
+            .map(|layout| layout.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Group 0", "Plan"]);
+        assert_eq!(
+            dxf.layout_extents(&dxf.layouts[0]),
+            Some((Coord2D::new(-10.0, -10.0), Coord2D::new(100.0, 50.0)))
+        );
+        assert_eq!(
+            dxf.layout_extents(&dxf.layouts[1]),
+            Some((Coord2D::new(1000.0, 0.0), Coord2D::new(1100.0, 50.0)))
+        );
 
-        assert!(dxf.entities.is_empty());
-        assert!(dxf
-            .unsupported_entities
+        let out = document_to_string(&dxf).unwrap();
+        assert_eq!(out.matches("  0\nLAYOUT\n").count(), 3);
+        assert_eq!(out.matches("  0\nVIEWPORT\n").count(), 2);
+        assert!(out.contains("  3\nACAD_LAYOUT\n"));
+        assert!(out.contains("  3\nModel\n"));
+        assert!(out.contains("AcDbLayout\n  1\nModel\n"));
+        let handle_of = |kind: &str, name: &str| {
+            let at = out.find(&format!("  2\n{name}\n")).unwrap();
+            let record = &out[out[..at].rfind(&format!("  0\n{kind}\n")).unwrap()..at];
+            group_values_by_code(record, 5)[0].to_string()
+        };
+        // *Model_Space and its layout point at each other.
+        let model_record = handle_of("BLOCK_RECORD", "*Model_Space");
+        let at = out.find("  2\n*Model_Space\n").unwrap();
+        let model_layout = group_values_by_code(&out[at..], 340)[0].clone();
+        let layout_at = out
+            .find(&format!("  0\nLAYOUT\n  5\n{model_layout}\n"))
+            .unwrap();
+        let layout_end = layout_at + 1 + out[layout_at + 1..].find("  0\n").unwrap();
+        assert!(out[layout_at..layout_end].contains(&format!("330\n{model_record}\n")));
+        // Each viewport freezes the other group's layer, and only that.
+        let viewports = out
+            .split("  0\nVIEWPORT\n")
+            .skip(1)
+            .map(|viewport| &viewport[..viewport.find("  0\n").unwrap()])
+            .collect::<Vec<_>>();
+        assert_eq!(viewports.len(), 2);
+        let frozen = viewports
             .iter()
-            .any(|v| v == "BLOCK_DEPTH_LIMIT(2)"));
+            .map(|viewport| group_values_by_code(&format!("  0\nVIEWPORT\n{viewport}"), 341))
+            .collect::<BTreeSet<_>>();
+        let expected = [
+            vec![handle_of("LAYER", "0-0")],
+            vec![handle_of("LAYER", "2-0")],
+        ];
+        assert_eq!(frozen, expected.into_iter().collect::<BTreeSet<_>>());
+        assert!(out.contains("  2\n*Paper_Space\n"));
+        assert!(out.contains("  2\n*Paper_Space0\n"));
+        assert!(out.contains("  1\nPlan\n"));
+        assert!(!out.contains("*Paper_Space1"));
+
+        let r12 = document_to_string(&DxfDocument {
+            version: DxfVersion::R12,
+            ..dxf
+        })
+        .unwrap();
+        assert!(!r12.contains("LAYOUT"));
+        assert!(!r12.contains("VIEWPORT"));
     }
 
     #[test]
-    fn document_to_string_emits_minimum_dxf_sections() {
-        let base = EntityBase::default();
+    fn convert_options_presets_set_their_bundles() {
+        let laser = ConvertOptions::for_laser();
+        assert!(laser.arcs_as_polylines);
+        assert!(laser.explode_inserts);
+        assert!(!laser.printable_only);
+
+        let print = ConvertOptions::for_print();
+        assert!(print.printable_only);
+        assert!(print.true_color);
+        assert!(!print.arcs_as_polylines);
+    }
+
+    #[test]
+    fn convert_document_clockwise_arc_sweeps_counterclockwise_in_dxf() {
+        let arc = Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: 10.0,
+            start_angle: PI / 2.0,
+            arc_angle: -PI / 2.0,
+            tilt_angle: 0.0,
+            flatness: 1.0,
+            is_full_circle: false,
+        };
+        assert!(arc.is_clockwise());
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![Entity::Line(Line {
-                base,
-                start_x: 0.0,
-                start_y: 0.0,
-                end_x: 10.0,
-                end_y: 0.0,
-            })],
+            entities: vec![Entity::Arc(arc)],
             block_defs: vec![],
         };
 
         let dxf = convert_document(&doc);
-        let out = document_to_string(&dxf);
-
-        assert!(out.contains("  0\nSECTION\n  2\nHEADER\n"));
-        assert!(out.contains("  2\nTABLES\n"));
-        assert!(out.contains("  2\nBLOCKS\n"));
-        assert!(out.contains("  2\nENTITIES\n"));
-        assert!(out.contains("  0\nLINE\n"));
-        assert!(out.ends_with("  0\nEOF\n"));
-    }
-
-    #[test]
-    fn document_to_string_escapes_unicode_fields() {
-        let dxf = DxfDocument {
-            layers: vec![DxfLayer {
-                name: "図面".to_string(),
-                color: 7,
-                line_type: "CONTINUOUS".to_string(),
-                frozen: false,
-                locked: false,
-            }],
-            entities: vec![DxfEntity::Text(DxfText {
-                layer: "図面".to_string(),
-                color: 7,
-                line_type: "CONTINUOUS".to_string(),
-                x: 0.0,
-                y: 0.0,
-                height: 2.5,
-                rotation: 0.0,
-                content: "日本語".to_string(),
-                style: "STANDARD".to_string(),
-            })],
-            blocks: vec![],
-            unsupported_entities: vec![],
+        let [DxfEntity::Arc(converted)] = dxf.entities.as_slice() else {
+            panic!("expected one ARC, got {:?}", dxf.entities);
         };
-
-        let out = document_to_string(&dxf);
-        assert!(out.contains("\\U+56F3\\U+9762"));
-        assert!(out.contains("\\U+65E5\\U+672C\\U+8A9E"));
+        // The visible quarter runs from 0 to 90 degrees, not the other three.
+        assert!(nearly_eq(converted.start_angle, 0.0));
+        assert!(nearly_eq(converted.end_angle, 90.0));
     }
 
     #[test]
-    fn convert_and_write_all_jww_samples() {
-        let dir = jww_samples_dir();
-        let mut files = fs::read_dir(&dir)
-            .unwrap()
-            .filter_map(Result::ok)
-            .map(|e| e.path())
-            .filter(|p| p.extension().map(|ext| ext == "jww").unwrap_or(false))
-            .collect::<Vec<_>>();
-        files.sort();
-        assert!(!files.is_empty(), "no .jww files found in jww_samples");
-
-        for path in files {
-            let doc = read_document_from_file(&path)
-                .unwrap_or_else(|e| panic!("failed parsing {}: {e}", path.display()));
-            let dxf = convert_document(&doc);
-            let output = document_to_string(&dxf);
-            assert!(output.starts_with("  0\nSECTION\n  2\nHEADER\n"));
-            assert!(output.ends_with("  0\nEOF\n"));
-            assert!(
-                dxf.unsupported_entities.is_empty(),
-                "unsupported entities in {}: {:?}",
-                path.display(),
-                dxf.unsupported_entities
-            );
+    fn sample_clockwise_arcs_keep_their_ends() {
+        let mut arcs = Vec::new();
+        for entry in fs::read_dir(jww_samples_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map(|ext| ext != "jww").unwrap_or(true) {
+                continue;
+            }
+            let doc = read_document_from_file(&path).unwrap();
+            arcs.extend(doc.entities.into_iter().filter(|entity| {
+                matches!(entity, Entity::Arc(arc) if arc.is_clockwise() && !arc.is_full_circle)
+            }));
         }
-    }
+        assert_eq!(arcs.len(), 27);
 
-    #[test]
-    fn document_to_string_has_objects_section_and_unique_handles() {
-        let base = EntityBase::default();
         let doc = JwwDocument {
             header: empty_header(),
-            entities: vec![
-                Entity::Line(Line {
-                    base,
-                    start_x: 0.0,
-                    start_y: 0.0,
-                    end_x: 10.0,
-                    end_y: 0.0,
-                }),
-                Entity::Text(Text {
-                    base,
-                    start_x: 5.0,
-                    start_y: 2.0,
-                    end_x: 5.0,
-                    end_y: 2.0,
-                    text_type: 0,
-                    size_x: 1.0,
-                    size_y: 1.0,
-                    spacing: 0.0,
-                    angle: 0.0,
-                    font_name: String::new(),
-                    content: "abc".to_string(),
-                }),
-            ],
+            entities: arcs,
             block_defs: vec![],
         };
-
         let dxf = convert_document(&doc);
-        let out = document_to_string(&dxf);
-
-        assert!(out.contains("  2\nOBJECTS\n"));
-        assert!(out.contains("  2\nBLOCK_RECORD\n"));
-        assert!(out.contains("  2\n*Model_Space\n"));
-        assert!(out.contains("  2\n*Paper_Space\n"));
-
-        let handles = group_values_by_code(&out, 5);
-        assert!(!handles.is_empty());
-        let unique = handles.iter().collect::<BTreeSet<_>>();
-        assert_eq!(unique.len(), handles.len());
-        assert!(handles
-            .iter()
-            .all(|h| !h.is_empty() && h.chars().all(|c| c.is_ascii_hexdigit())));
-    }
-
-    fn group_values_by_code(dxf: &str, target_code: i32) -> Vec<String> {
-        let mut out = Vec::<String>::new();
-        let mut lines = dxf.lines();
-        while let Some(code_line) = lines.next() {
-            let Some(value_line) = lines.next() else {
-                break;
+        assert_eq!(dxf.entities.len(), doc.entities.len());
+        let near = |a: (f64, f64), b: Coord2D| (a.0 - b.x).abs() < 1e-6 && (a.1 - b.y).abs() < 1e-6;
+        for (source, converted) in doc.entities.iter().zip(&dxf.entities) {
+            let Entity::Arc(arc) = source else {
+                unreachable!()
             };
-            if code_line.trim().parse::<i32>().ok() == Some(target_code) {
-                out.push(value_line.to_string());
-            }
+            let (from, to) = match converted {
+                DxfEntity::Arc(v) => {
+                    let at = |deg: f64| {
+                        let (sin, cos) = deg.to_radians().sin_cos();
+                        (v.center_x + v.radius * cos, v.center_y + v.radius * sin)
+                    };
+                    (at(v.start_angle), at(v.end_angle))
+                }
+                DxfEntity::Ellipse(v) => {
+                    let at = |param: f64| {
+                        let (sin, cos) = param.sin_cos();
+                        let (minor_x, minor_y) = (-v.major_axis_y, v.major_axis_x);
+                        (
+                            v.center_x + v.major_axis_x * cos + v.minor_ratio * minor_x * sin,
+                            v.center_y + v.major_axis_y * cos + v.minor_ratio * minor_y * sin,
+                        )
+                    };
+                    (at(v.start_param), at(v.end_param))
+                }
+                other => panic!("expected ARC or ELLIPSE, got {other:?}"),
+            };
+            // Drawn counterclockwise, so the DXF sweep starts at the JWW end.
+            assert!(
+                near(from, arc.end_point()) && near(to, arc.start_point()),
+                "{arc:?} became {converted:?}"
+            );
         }
-        out
-    }
-
-    fn contains_line(entities: &[DxfEntity], x1: f64, y1: f64, x2: f64, y2: f64) -> bool {
-        entities.iter().any(|entity| {
-            if let DxfEntity::Line(line) = entity {
-                nearly_eq(line.x1, x1)
-                    && nearly_eq(line.y1, y1)
-                    && nearly_eq(line.x2, x2)
-                    && nearly_eq(line.y2, y2)
-            } else {
-                false
-            }
-        })
-    }
-
-    fn nearly_eq(a: f64, b: f64) -> bool {
-        (a - b).abs() < 1e-6
     }
 }
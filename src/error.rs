@@ -9,6 +9,31 @@ pub enum JwwError {
     EntityListNotFound,
     UnknownClassPid(u32),
     UnknownEntityClass(String),
+    /// Header written by JW_CAD for DOS, whose fixed-width name fields this
+    /// parser does not read.
+    DosLayout(u32),
+    /// Version outside [`crate::supported_versions`]; the record layouts are
+    /// unknown, so parsing would silently produce garbage.
+    UnsupportedVersion(u32),
+    /// Conversion would exceed [`crate::ConvertOptions::max_total_segments`].
+    SegmentLimitExceeded {
+        generated: usize,
+        limit: usize,
+    },
+    /// NaN or infinity in the named field, which JSON cannot represent.
+    NonFiniteValue(&'static str),
+    /// NaN or infinity in a real-valued DXF group, with that group's code.
+    NonFiniteGroup(i32),
+    /// [`crate::WriteOptions::handle_seed`] leaves too few handles below
+    /// `u32::MAX` for the document.
+    HandleSeedOverflow(u32),
+    /// `source` hit while reading record `index` (null records included) of
+    /// the top-level entity list, whose record starts at file `offset`.
+    ParseAt {
+        index: usize,
+        offset: usize,
+        source: Box<JwwError>,
+    },
 }
 
 impl Display for JwwError {
@@ -20,11 +45,47 @@ impl Display for JwwError {
             Self::EntityListNotFound => write!(f, "could not find entity list in file"),
             Self::UnknownClassPid(pid) => write!(f, "unknown class PID: {pid}"),
             Self::UnknownEntityClass(name) => write!(f, "unknown entity class: {name}"),
+            Self::DosLayout(version) => write!(
+                f,
+                "unsupported JW_CAD for DOS header layout (version {version})"
+            ),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported JWW version: {version}")
+            }
+            Self::SegmentLimitExceeded { generated, limit } => write!(
+                f,
+                "conversion would generate {generated} line segments, over the limit of {limit}"
+            ),
+            Self::NonFiniteValue(field) => {
+                write!(
+                    f,
+                    "`{field}` is NaN or infinite, which JSON cannot represent"
+                )
+            }
+            Self::NonFiniteGroup(code) => {
+                write!(f, "DXF group {code} is NaN or infinite")
+            }
+            Self::HandleSeedOverflow(seed) => {
+                write!(f, "DXF handles starting at {seed:X} run past FFFFFFFF")
+            }
+            Self::ParseAt {
+                index,
+                offset,
+                source,
+            } => write!(f, "entity {index} at byte {offset}: {source}"),
         }
     }
 }
 
-impl Error for JwwError {}
+impl Error for JwwError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::ParseAt { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for JwwError {
     fn from(value: std::io::Error) -> Self {
@@ -11,31 +11,87 @@ from pathlib import Path
 from typing import Any
 
 from ezjww._core import (
+    PyDxfArc,
+    PyDxfCircle,
+    PyDxfDocument,
+    PyDxfLayer,
+    PyDxfLine,
+    PyDxfPoint,
+    PyDxfText,
+    bounding_box,
+    check_conversion,
+    convert_directory_py,
+    entity_offsets,
+    export_geojson,
+    export_svg,
     hello_from_bin,
     is_jww_file,
+    jww_version,
+    merge_dxf,
+    probe_jww,
     read_document,
+    read_document_bytes,
+    read_document_dict,
+    read_document_dict_bytes,
+    read_dxf_bytes,
     read_dxf_document,
+    read_dxf_document_bytes,
     read_dxf_string,
+    read_dxf_string_bytes,
+    read_dxf_string_filtered,
     read_header,
+    read_json,
+    read_json_bytes,
+    read_report,
+    validate_file,
     write_dxf,
+    write_dxf_document,
 )
 from ezjww.plot import plot_dxf_document, plot_jww
 
 __all__ = [
     "Drawing",
     "Modelspace",
+    "PyDxfArc",
+    "PyDxfCircle",
+    "PyDxfDocument",
+    "PyDxfLayer",
+    "PyDxfLine",
+    "PyDxfPoint",
+    "PyDxfText",
     "audit",
     "bbox",
+    "bounding_box",
+    "check_conversion",
+    "convert_directory_py",
+    "entity_offsets",
+    "export_geojson",
+    "export_svg",
     "hello_from_bin",
     "is_jww_file",
+    "jww_version",
+    "merge_dxf",
     "new",
+    "probe_jww",
     "readfile",
     "read_header",
     "read_document",
+    "read_document_bytes",
+    "read_document_dict",
+    "read_document_dict_bytes",
+    "read_dxf_bytes",
     "read_dxf_document",
+    "read_dxf_document_bytes",
     "read_dxf_string",
+    "read_dxf_string_bytes",
+    "read_dxf_string_filtered",
+    "read_json",
+    "read_json_bytes",
+    "read_report",
     "to_dxf_string",
+    "validate_file",
     "write_dxf",
+    "write_dxf_document",
     "plot_dxf_document",
     "plot_jww",
     "report",
@@ -99,7 +155,7 @@ class Drawing:
     @classmethod
     def from_file(cls, path: str | Path) -> "Drawing":
         source = str(path)
-        jww_document = read_document(source)
+        jww_document = read_document_dict(source)
         return cls(source_path=source, jww_document=jww_document)
 
     @classmethod
@@ -710,7 +766,7 @@ def _dxf_entity_points(entity: dict[str, Any]) -> list[tuple[float, float]]:
             ]
         if entity_type == "POINT":
             return [(_as_float(entity["x"]), _as_float(entity["y"]))]
-        if entity_type == "TEXT":
+        if entity_type in {"TEXT", "MTEXT"}:
             return [(_as_float(entity["x"]), _as_float(entity["y"]))]
         if entity_type == "INSERT":
             return [(_as_float(entity["x"]), _as_float(entity["y"]))]
@@ -721,6 +777,21 @@ def _dxf_entity_points(entity: dict[str, Any]) -> list[tuple[float, float]]:
                 (_as_float(entity["x3"]), _as_float(entity["y3"])),
                 (_as_float(entity["x4"]), _as_float(entity["y4"])),
             ]
+        if entity_type == "LWPOLYLINE":
+            return [
+                (_as_float(vertex["x"]), _as_float(vertex["y"]))
+                for vertex in entity["vertices"]
+            ]
+        if entity_type == "IMAGE":
+            x = _as_float(entity["x"])
+            y = _as_float(entity["y"])
+            width = _as_float(entity["pixel_width"])
+            height = _as_float(entity["pixel_height"])
+            ux = _as_float(entity["u_x"]) * width
+            uy = _as_float(entity["u_y"]) * width
+            vx = _as_float(entity["v_x"]) * height
+            vy = _as_float(entity["v_y"]) * height
+            return [(x, y), (x + ux, y + uy), (x + ux + vx, y + uy + vy), (x + vx, y + vy)]
         if entity_type == "CIRCLE":
             cx = _as_float(entity["center_x"])
             cy = _as_float(entity["center_y"])
@@ -977,7 +1048,7 @@ def _run(argv: list[str] | None = None) -> int:
         return 0
 
     if args.command == "info":
-        doc = read_document(args.path)
+        doc = read_document_dict(args.path)
         if args.json:
             _print_json(doc)
             return 0
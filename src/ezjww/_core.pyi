@@ -1,4 +1,4 @@
-from typing import TypedDict
+from typing import Sequence, TypedDict
 
 
 class LayerHeader(TypedDict):
@@ -20,8 +20,17 @@ class JwwHeader(TypedDict):
     version: int
     memo: str
     paper_size: int
+    paper_size_mm: tuple[float, float] | None
     write_layer_group: int
     layer_groups: list[LayerGroupHeader]
+    grid_origin: tuple[float, float] | None
+
+
+class EntityFlags(TypedDict):
+    figure: bool
+    hatch: bool
+    dimension: bool
+    fitting: bool
 
 
 class EntityBase(TypedDict):
@@ -32,6 +41,8 @@ class EntityBase(TypedDict):
     layer: int
     layer_group: int
     flag: int
+    flags: EntityFlags
+    seq: int
 
 
 class LinePayload(TypedDict):
@@ -64,6 +75,12 @@ class TextPayload(TypedDict):
     content: str
 
 
+class PolyVertexPayload(TypedDict):
+    x: float
+    y: float
+    bulge: float
+
+
 class JwwEntity(TypedDict, total=False):
     type: str
     base: EntityBase
@@ -100,6 +117,7 @@ class JwwEntity(TypedDict, total=False):
     point4_x: float
     point4_y: float
     color: int | None
+    is_filled: bool
     ref_x: float
     ref_y: float
     scale_x: float
@@ -107,16 +125,20 @@ class JwwEntity(TypedDict, total=False):
     rotation: float
     def_number: int
     block_name: str | None
+    attributes: list[tuple[str, str]]
     line: LinePayload
     text: TextPayload
     sxf_mode: int | None
     aux_lines: list[LinePayload]
     aux_points: list[PointPayload]
+    vertices: list[PolyVertexPayload]
+    closed: bool
 
 
 class BlockDef(TypedDict):
     number: int
     is_referenced: bool
+    created_time: int
     name: str
     base: EntityBase
     entities: list[JwwEntity]
@@ -126,6 +148,7 @@ class BlockReferenceValidation(TypedDict):
     total_references: int
     resolved_references: int
     unresolved_def_numbers: list[int]
+    referenced_def_numbers: list[int]
     has_unresolved: bool
 
 
@@ -135,9 +158,308 @@ class JwwDocument(TypedDict):
     block_defs: list[BlockDef]
     block_def_names: dict[int, str]
     entity_counts: dict[str, int]
+    counts_by_layer: dict[str, dict[str, int]]
+    pen_usage: dict[tuple[int, int, int], int]
+    groups: dict[int, list[int]]
     validation: BlockReferenceValidation
 
 
+class PyLayer:
+    state: int
+    protect: int
+    name: str
+
+
+class PyLayerGroup:
+    state: int
+    write_layer: int
+    scale: float
+    protect: int
+    name: str
+    layers: list[PyLayer]
+
+
+class PyHeader:
+    version: int
+    memo: str
+    paper_size: int
+    paper_size_mm: tuple[float, float] | None
+    write_layer_group: int
+    layer_groups: list[PyLayerGroup]
+    grid_origin: tuple[float, float] | None
+
+
+class PyEntityBase:
+    group: int
+    pen_style: int
+    pen_color: int
+    pen_width: int
+    layer: int
+    layer_group: int
+    flag: int
+    flags: EntityFlags
+    seq: int
+
+
+class PyEntity:
+    type: str
+    base: PyEntityBase
+
+
+class PyLine(PyEntity):
+    start_x: float
+    start_y: float
+    end_x: float
+    end_y: float
+
+
+class PyArc(PyEntity):
+    center_x: float
+    center_y: float
+    radius: float
+    start_angle: float
+    arc_angle: float
+    tilt_angle: float
+    flatness: float
+    is_full_circle: bool
+
+
+class PyPoint(PyEntity):
+    x: float
+    y: float
+    is_temporary: bool
+    code: int
+    angle: float
+    scale: float
+
+
+class PyText(PyEntity):
+    start_x: float
+    start_y: float
+    end_x: float
+    end_y: float
+    text_type: int
+    size_x: float
+    size_y: float
+    spacing: float
+    angle: float
+    font_name: str
+    content: str
+
+
+class PySolid(PyEntity):
+    point1_x: float
+    point1_y: float
+    point2_x: float
+    point2_y: float
+    point3_x: float
+    point3_y: float
+    point4_x: float
+    point4_y: float
+    color: int | None
+    is_filled: bool
+
+
+class PyBlock(PyEntity):
+    ref_x: float
+    ref_y: float
+    scale_x: float
+    scale_y: float
+    rotation: float
+    def_number: int
+    block_name: str | None
+    attributes: list[tuple[str, str]]
+
+
+class PyDimension(PyEntity):
+    line: PyLine
+    text: PyText
+    sxf_mode: int | None
+    aux_lines: list[PyLine]
+    aux_points: list[PyPoint]
+
+
+class PyPolyline(PyEntity):
+    vertices: list[tuple[float, float, float]]
+    closed: bool
+
+
+class PyBlockDef:
+    number: int
+    is_referenced: bool
+    created_time: int
+    name: str
+    base: PyEntityBase
+    entities: list[PyEntity]
+
+
+class PyDocument:
+    header: PyHeader
+    entities: list[PyEntity]
+    block_defs: list[PyBlockDef]
+    groups: dict[int, list[int]]
+
+
+class PyDxfLayer:
+    name: str
+    color: int
+    line_type: str
+    frozen: bool
+    locked: bool
+    def __init__(
+        self,
+        name: str,
+        color: int = 7,
+        line_type: str = "CONTINUOUS",
+        frozen: bool = False,
+        locked: bool = False,
+    ) -> None: ...
+
+
+class PyDxfLine:
+    x1: float
+    y1: float
+    x2: float
+    y2: float
+    layer: str
+    color: int
+    line_type: str
+    def __init__(
+        self,
+        x1: float,
+        y1: float,
+        x2: float,
+        y2: float,
+        layer: str = "0",
+        color: int = 256,
+        line_type: str = "BYLAYER",
+    ) -> None: ...
+
+
+class PyDxfCircle:
+    center_x: float
+    center_y: float
+    radius: float
+    layer: str
+    color: int
+    line_type: str
+    def __init__(
+        self,
+        center_x: float,
+        center_y: float,
+        radius: float,
+        layer: str = "0",
+        color: int = 256,
+        line_type: str = "BYLAYER",
+    ) -> None: ...
+
+
+class PyDxfArc:
+    center_x: float
+    center_y: float
+    radius: float
+    start_angle: float
+    end_angle: float
+    layer: str
+    color: int
+    line_type: str
+    def __init__(
+        self,
+        center_x: float,
+        center_y: float,
+        radius: float,
+        start_angle: float,
+        end_angle: float,
+        layer: str = "0",
+        color: int = 256,
+        line_type: str = "BYLAYER",
+    ) -> None: ...
+
+
+class PyDxfPoint:
+    x: float
+    y: float
+    layer: str
+    color: int
+    line_type: str
+    def __init__(
+        self,
+        x: float,
+        y: float,
+        layer: str = "0",
+        color: int = 256,
+        line_type: str = "BYLAYER",
+    ) -> None: ...
+
+
+class PyDxfText:
+    x: float
+    y: float
+    height: float
+    content: str
+    rotation: float
+    style: str
+    layer: str
+    color: int
+    line_type: str
+    def __init__(
+        self,
+        x: float,
+        y: float,
+        height: float,
+        content: str,
+        rotation: float = 0.0,
+        style: str = "STANDARD",
+        layer: str = "0",
+        color: int = 256,
+        line_type: str = "BYLAYER",
+    ) -> None: ...
+
+
+PyDxfEntity = PyDxfLine | PyDxfCircle | PyDxfArc | PyDxfPoint | PyDxfText
+
+
+class PyDxfDocument:
+    @property
+    def layers(self) -> list[PyDxfLayer]: ...
+    @property
+    def entities(self) -> list[PyDxfEntity]: ...
+    def __init__(
+        self,
+        layers: list[PyDxfLayer] = ...,
+        entities: list[PyDxfEntity] = ...,
+    ) -> None: ...
+
+
+class BoundingBox(TypedDict):
+    min_x: float
+    min_y: float
+    max_x: float
+    max_y: float
+
+
+class JwwProbe(TypedDict):
+    is_jww: bool
+    version: int | None
+    readable_header: bool
+
+
+class ParseReport(TypedDict):
+    class_counts: dict[str, int]
+    block_def_count: int
+    unresolved_block_refs: list[int]
+    skipped_records: int
+
+
+class ConversionCheck(TypedDict):
+    ok: bool
+    entity_count: int
+    block_count: int
+    generated_segments: int
+    unsupported_entities: list[str]
+    warnings: list[str]
+
+
 class DxfLayer(TypedDict):
     name: str
     color: int
@@ -146,6 +468,15 @@ class DxfLayer(TypedDict):
     locked: bool
 
 
+class DxfAttribute(TypedDict):
+    tag: str
+    value: str
+    x: float
+    y: float
+    height: float
+    rotation: float
+
+
 class DxfEntity(TypedDict, total=False):
     type: str
     layer: str
@@ -171,6 +502,13 @@ class DxfEntity(TypedDict, total=False):
     rotation: float
     content: str
     style: str
+    width: float
+    attachment_point: int
+    tracking: float
+    width_factor: float
+    horizontal_alignment: int
+    vertical_alignment: int
+    mirrored: bool
     x3: float
     y3: float
     x4: float
@@ -178,6 +516,16 @@ class DxfEntity(TypedDict, total=False):
     block_name: str
     scale_x: float
     scale_y: float
+    vertices: list[PolyVertexPayload]
+    closed: bool
+    attributes: list[DxfAttribute]
+    path: str
+    u_x: float
+    u_y: float
+    v_x: float
+    v_y: float
+    pixel_width: int
+    pixel_height: int
 
 
 class DxfBlock(TypedDict):
@@ -196,21 +544,77 @@ class DxfDocument(TypedDict):
 
 def hello_from_bin() -> str: ...
 def is_jww_file(path: str) -> bool: ...
+def jww_version(path: str) -> int | None: ...
+def probe_jww(path: str) -> JwwProbe: ...
 def read_header(path: str) -> JwwHeader: ...
-def read_document(path: str) -> JwwDocument: ...
+def read_document(path: str) -> PyDocument: ...
+def read_document_bytes(data: bytes) -> PyDocument: ...
+def read_document_dict(path: str) -> JwwDocument: ...
+def read_document_dict_bytes(data: bytes) -> JwwDocument: ...
+def bounding_box(path: str) -> BoundingBox | None: ...
+def entity_offsets(path: str) -> list[int]: ...
+def read_report(path: str) -> ParseReport: ...
+def validate_file(path: str) -> ParseReport: ...
+def read_json(path: str) -> str: ...
+def read_json_bytes(data: bytes) -> str: ...
+def convert_directory_py(
+    input_dir: str, output_dir: str
+) -> list[tuple[str, bool, str | None]]: ...
 def read_dxf_document(
     path: str,
     explode_inserts: bool = False,
     max_block_nesting: int = 32,
 ) -> DxfDocument: ...
+def read_dxf_document_bytes(
+    data: bytes,
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+) -> DxfDocument: ...
 def read_dxf_string(
     path: str,
     explode_inserts: bool = False,
     max_block_nesting: int = 32,
 ) -> str: ...
+def read_dxf_string_bytes(
+    data: bytes,
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+) -> str: ...
+def read_dxf_string_filtered(
+    path: str,
+    layers: Sequence[Sequence[int]],
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+) -> str: ...
+def read_dxf_bytes(
+    path: str,
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+) -> bytes: ...
 def write_dxf(
     path: str,
     output_path: str,
     explode_inserts: bool = False,
     max_block_nesting: int = 32,
 ) -> None: ...
+def check_conversion(
+    path: str,
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+    max_total_segments: int | None = None,
+) -> ConversionCheck: ...
+def merge_dxf(
+    paths: list[str],
+    prefixes: list[str],
+    output_path: str,
+    explode_inserts: bool = False,
+    max_block_nesting: int = 32,
+) -> None: ...
+def write_dxf_document(document: PyDxfDocument, output_path: str) -> None: ...
+def export_svg(
+    path: str,
+    output_path: str,
+    width: float = 800.0,
+    height: float = 600.0,
+) -> None: ...
+def export_geojson(path: str, output_path: str) -> None: ...
@@ -50,9 +50,9 @@ def _line_style(line_type: str) -> str:
         return "-"
     if name in {"DASHED", "DASHED2"}:
         return "--"
-    if name == "DASHDOT":
+    if name in {"DASHDOT", "DASHDOT2", "DIVIDE", "DIVIDE2"}:
         return "-."
-    if name == "DOT":
+    if name in {"DOT", "DOT2"}:
         return ":"
     return "-"
 
@@ -204,7 +204,7 @@ def plot_dxf_document(
         elif entity_type == "POINT":
             if draw_points:
                 ax.scatter([entity["x"]], [entity["y"]], s=point_size, c=[color], marker="o")
-        elif entity_type == "TEXT":
+        elif entity_type in {"TEXT", "MTEXT"}:
             if draw_text:
                 content = str(entity.get("content", ""))
                 height = max(6.0, float(entity.get("height", 2.5)) * text_scale)
@@ -219,11 +219,12 @@ def plot_dxf_document(
                     va="bottom",
                 )
         elif entity_type == "SOLID":
+            # DXF SOLID corners are ordered 1-2-4-3 around the outline.
             points = [
                 (entity["x1"], entity["y1"]),
                 (entity["x2"], entity["y2"]),
-                (entity["x3"], entity["y3"]),
                 (entity["x4"], entity["y4"]),
+                (entity["x3"], entity["y3"]),
             ]
             patch = patches.Polygon(
                 points,
@@ -234,6 +235,13 @@ def plot_dxf_document(
                 alpha=0.3,
             )
             ax.add_patch(patch)
+        elif entity_type == "LWPOLYLINE":
+            points = [(vertex["x"], vertex["y"]) for vertex in entity.get("vertices", [])]
+            if entity.get("closed") and points:
+                points.append(points[0])
+            if points:
+                xs, ys = zip(*points)
+                ax.plot(xs, ys, color=color, linewidth=linewidth, linestyle=line_style)
         elif entity_type == "INSERT":
             if draw_inserts:
                 x = float(entity.get("x", 0.0))
@@ -0,0 +1,205 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::dxf::{
+    aci_to_pen, convert_document_with_options, entity_pen, ConvertOptions, DxfEntity,
+};
+use crate::model::JwwDocument;
+
+/// Converts the drawing to a GeoJSON FeatureCollection in drawing units.
+///
+/// Inserts are exploded and arcs, circles and ellipses are tessellated the
+/// same way DXF conversion does it, so every curve comes out as a
+/// LineString. Lines and polylines are LineStrings, SOLIDs are Polygons and
+/// points are Points; text, images and other annotations are left out. Each
+/// feature carries its `layer` name and JWW pen `color`.
+pub fn document_to_geojson(doc: &JwwDocument) -> String {
+    let dxf = convert_document_with_options(
+        doc,
+        ConvertOptions {
+            explode_inserts: true,
+            arcs_as_polylines: true,
+            pen_xdata: true,
+            ..ConvertOptions::default()
+        },
+    );
+
+    let mut out = String::from("{\"type\":\"FeatureCollection\",\"features\":[");
+    let mut first = true;
+    for entity in &dxf.entities {
+        let Some(geometry) = entity_geometry(entity) else {
+            continue;
+        };
+        if !first {
+            out.push(',');
+        }
+        first = false;
+        let _ = write!(
+            out,
+            "{{\"type\":\"Feature\",\"geometry\":{geometry},\"properties\":{{\"layer\":\"{}\",\"color\":{}}}}}",
+            escape_json(entity.layer()),
+            entity_pen(entity).unwrap_or_else(|| aci_to_pen(entity.color()))
+        );
+    }
+    out.push_str("]}\n");
+    out
+}
+
+pub fn write_geojson_to_file(doc: &JwwDocument, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, document_to_geojson(doc))
+}
+
+fn entity_geometry(entity: &DxfEntity) -> Option<String> {
+    match entity {
+        DxfEntity::Line(v) => Some(line_string(&[(v.x1, v.y1), (v.x2, v.y2)])),
+        DxfEntity::LwPolyline(v) => {
+            let mut points = v
+                .vertices
+                .iter()
+                .map(|vertex| (vertex.x, vertex.y))
+                .collect::<Vec<_>>();
+            if v.closed {
+                if let Some(&start) = points.first() {
+                    points.push(start);
+                }
+            }
+            (points.len() >= 2).then(|| line_string(&points))
+        }
+        // DXF SOLID corners zigzag (1, 2, 4, 3); GeoJSON rings run around
+        // the outline, counter-clockwise for an exterior ring.
+        DxfEntity::Solid(v) => {
+            let mut ring = vec![(v.x1, v.y1), (v.x2, v.y2), (v.x4, v.y4), (v.x3, v.y3)];
+            ring.dedup();
+            if signed_area(&ring) < 0.0 {
+                ring.reverse();
+            }
+            ring.push(ring[0]);
+            Some(format!(
+                "{{\"type\":\"Polygon\",\"coordinates\":[{}]}}",
+                coordinates(&ring)
+            ))
+        }
+        DxfEntity::Point(v) => Some(format!(
+            "{{\"type\":\"Point\",\"coordinates\":[{},{}]}}",
+            v.x, v.y
+        )),
+        _ => None,
+    }
+}
+
+fn line_string(points: &[(f64, f64)]) -> String {
+    format!(
+        "{{\"type\":\"LineString\",\"coordinates\":{}}}",
+        coordinates(points)
+    )
+}
+
+fn coordinates(points: &[(f64, f64)]) -> String {
+    let pairs = points
+        .iter()
+        .map(|(x, y)| format!("[{x},{y}]"))
+        .collect::<Vec<_>>();
+    format!("[{}]", pairs.join(","))
+}
+
+fn signed_area(points: &[(f64, f64)]) -> f64 {
+    let mut area = 0.0;
+    for (i, &(x1, y1)) in points.iter().enumerate() {
+        let (x2, y2) = points[(i + 1) % points.len()];
+        area += x1 * y2 - x2 * y1;
+    }
+    area / 2.0
+}
+
+fn escape_json(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::header::JwwHeader;
+    use crate::model::{Entity, EntityBase, JwwDocument, Line};
+
+    use super::document_to_geojson;
+
+    #[test]
+    fn document_to_geojson_writes_line_as_line_string() {
+        let doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            entities: vec![Entity::Line(Line {
+                base: EntityBase {
+                    pen_color: 2,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 100.0,
+                end_y: 50.5,
+            })],
+            block_defs: Vec::new(),
+        };
+
+        let out = document_to_geojson(&doc);
+        assert!(out.starts_with("{\"type\":\"FeatureCollection\",\"features\":[{"));
+        assert!(out
+            .contains("\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[0,0],[100,50.5]]}"));
+        assert!(out.contains("\"color\":2}"));
+        assert_eq!(out.matches("\"type\":\"Feature\"").count(), 1);
+    }
+
+    #[test]
+    fn document_to_geojson_keeps_pens_that_share_an_aci() {
+        let line = |pen_color: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    pen_color,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 0.0,
+            })
+        };
+        let doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            // Pens 1 and 8 both convert to ACI 7.
+            entities: vec![line(1), line(8)],
+            block_defs: Vec::new(),
+        };
+
+        let out = document_to_geojson(&doc);
+        assert!(out.contains("\"color\":1}"));
+        assert!(out.contains("\"color\":8}"));
+    }
+}
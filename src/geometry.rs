@@ -0,0 +1,56 @@
+use crate::model::Coord2D;
+
+/// Even-odd ray casting test. `polygon` is an implicitly closed ring; a
+/// repeated closing vertex is harmless. Points exactly on an edge may land
+/// on either side.
+pub fn point_in_polygon(point: Coord2D, polygon: &[Coord2D]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut previous = polygon[polygon.len() - 1];
+    for &current in polygon {
+        if (current.y > point.y) != (previous.y > point.y) {
+            let crossing_x = current.x
+                + (point.y - current.y) * (previous.x - current.x) / (previous.y - current.y);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::Coord2D;
+
+    use super::point_in_polygon;
+
+    #[test]
+    fn point_in_polygon_uses_even_odd_rule() {
+        let square = [
+            Coord2D::new(0.0, 0.0),
+            Coord2D::new(1.0, 0.0),
+            Coord2D::new(1.0, 1.0),
+            Coord2D::new(0.0, 1.0),
+        ];
+        assert!(point_in_polygon(Coord2D::new(0.5, 0.5), &square));
+        assert!(!point_in_polygon(Coord2D::new(2.0, 2.0), &square));
+        assert!(!point_in_polygon(Coord2D::new(-0.5, 0.5), &square));
+
+        // L-shaped room: the notch is outside.
+        let l_shape = [
+            Coord2D::new(0.0, 0.0),
+            Coord2D::new(2.0, 0.0),
+            Coord2D::new(2.0, 1.0),
+            Coord2D::new(1.0, 1.0),
+            Coord2D::new(1.0, 2.0),
+            Coord2D::new(0.0, 2.0),
+        ];
+        assert!(point_in_polygon(Coord2D::new(0.5, 1.5), &l_shape));
+        assert!(!point_in_polygon(Coord2D::new(1.5, 1.5), &l_shape));
+        assert!(!point_in_polygon(Coord2D::new(0.5, 0.5), &square[..2]));
+    }
+}
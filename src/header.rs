@@ -1,12 +1,32 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io::Read;
+use std::ops::RangeInclusive;
 use std::path::Path;
 
+use encoding_rs::{Encoding, SHIFT_JIS};
+
 use crate::error::JwwError;
+use crate::model::Coord2D;
 use crate::reader::Reader;
 
 pub const JWW_SIGNATURE: &[u8; 8] = b"JwwData.";
 
+// Versions below this come from JW_CAD for DOS converters, whose headers use
+// fixed-width name fields instead of length-prefixed strings.
+const MIN_WINDOWS_VERSION: u32 = 100;
+
+// Covers the fixed header fields with room for long layer names; names cut
+// off past this only fall back to their defaults, which a probe ignores.
+const PROBE_LEN: u64 = 64 * 1024;
+
+/// File versions whose header and record layouts this crate understands,
+/// from the Windows 2.x releases through Jw_cad 8 (700).
+pub fn supported_versions() -> RangeInclusive<u32> {
+    MIN_WINDOWS_VERSION..=700
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerHeader {
     pub state: u32,
     pub protect: u32,
@@ -14,6 +34,7 @@ pub struct LayerHeader {
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerGroupHeader {
     pub state: u32,
     pub write_layer: u32,
@@ -24,27 +45,148 @@ pub struct LayerGroupHeader {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JwwHeader {
     pub version: u32,
     pub memo: String,
     pub paper_size: u32,
     pub write_layer_group: u32,
     pub layer_groups: [LayerGroupHeader; 16],
+    /// Jw_cad's grid (目盛) reference point in paper millimetres, usually
+    /// minus half the sheet size (-297, -210 on A3) or zero. It only anchors
+    /// the on-screen grid: it is not a georeference, and conversion does not
+    /// apply it to coordinates. `None` when the header ends before it or it
+    /// is not finite.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub grid_origin: Option<Coord2D>,
+}
+
+/// The `paper_size` codes JWW writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperSize {
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+    /// JWW's 2A to 5A: that many portrait A0 sheets side by side.
+    A0x2,
+    A0x3,
+    A0x4,
+    A0x5,
+    /// Sheets sized by a real-world length (10 m, 50 m, 100 m); the header
+    /// does not record their dimensions.
+    Meters10,
+    Meters50,
+    Meters100,
+    Other(u32),
+}
+
+impl PaperSize {
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => Self::A0,
+            1 => Self::A1,
+            2 => Self::A2,
+            3 => Self::A3,
+            4 => Self::A4,
+            8 => Self::A0x2,
+            9 => Self::A0x3,
+            10 => Self::A0x4,
+            11 => Self::A0x5,
+            12 => Self::Meters10,
+            13 => Self::Meters50,
+            14 => Self::Meters100,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Landscape `(width, height)` in millimeters, or `None` when the size
+    /// is not a fixed sheet.
+    pub fn dimensions_mm(self) -> Option<(f64, f64)> {
+        match self {
+            Self::A0 => Some((1189.0, 841.0)),
+            Self::A1 => Some((841.0, 594.0)),
+            Self::A2 => Some((594.0, 420.0)),
+            Self::A3 => Some((420.0, 297.0)),
+            Self::A4 => Some((297.0, 210.0)),
+            Self::A0x2 => Some((1682.0, 1189.0)),
+            Self::A0x3 => Some((2523.0, 1189.0)),
+            Self::A0x4 => Some((3364.0, 1189.0)),
+            Self::A0x5 => Some((4205.0, 1189.0)),
+            Self::Meters10 | Self::Meters50 | Self::Meters100 | Self::Other(_) => None,
+        }
+    }
+}
+
+impl JwwHeader {
+    pub fn paper(&self) -> PaperSize {
+        PaperSize::from_code(self.paper_size)
+    }
+
+    pub fn paper_size_mm(&self) -> Option<(f64, f64)> {
+        self.paper().dimensions_mm()
+    }
+}
+
+/// What [`probe_header`] can tell about a file without reading its entities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JwwProbe {
+    pub is_jww: bool,
+    /// The version field, whenever the signature is followed by one, even if
+    /// this crate cannot read that version.
+    pub version: Option<u32>,
+    /// Whether the header parses, which includes having a supported version.
+    pub readable_header: bool,
 }
 
 pub fn is_jww_signature(data: &[u8]) -> bool {
     data.len() >= JWW_SIGNATURE.len() && &data[..JWW_SIGNATURE.len()] == JWW_SIGNATURE
 }
 
+pub fn probe_header(data: &[u8]) -> JwwProbe {
+    if !is_jww_signature(data) {
+        return JwwProbe::default();
+    }
+    let version = data
+        .get(JWW_SIGNATURE.len()..JWW_SIGNATURE.len() + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().expect("slice is four bytes")));
+    JwwProbe {
+        is_jww: true,
+        version,
+        readable_header: parse_header(data).is_ok(),
+    }
+}
+
+/// Probes only the start of the file, so it stays cheap on large drawings.
+pub fn probe_file(path: impl AsRef<Path>) -> Result<JwwProbe, JwwError> {
+    let mut data = Vec::new();
+    File::open(path)?.take(PROBE_LEN).read_to_end(&mut data)?;
+    Ok(probe_header(&data))
+}
+
 pub fn parse_header(data: &[u8]) -> Result<JwwHeader, JwwError> {
+    parse_header_with_encoding(data, SHIFT_JIS)
+}
+
+pub(crate) fn parse_header_with_encoding(
+    data: &[u8],
+    encoding: &'static Encoding,
+) -> Result<JwwHeader, JwwError> {
     if !is_jww_signature(data) {
         return Err(JwwError::InvalidSignature);
     }
 
-    let mut reader = Reader::new(data);
+    let mut reader = Reader::with_encoding(data, encoding);
     reader.skip(JWW_SIGNATURE.len())?;
 
     let version = reader.read_u32()?;
+    if version < MIN_WINDOWS_VERSION {
+        return Err(JwwError::DosLayout(version));
+    }
+    if !supported_versions().contains(&version) {
+        return Err(JwwError::UnsupportedVersion(version));
+    }
     let memo = reader.read_cstring()?;
     let paper_size = reader.read_u32()?;
     let write_layer_group = reader.read_u32()?;
@@ -65,13 +207,23 @@ pub fn parse_header(data: &[u8]) -> Result<JwwHeader, JwwError> {
         }
     }
 
-    // Layer names and group names are stored later in the header block.
-    // If this optional extraction fails, keep deterministic default names.
-    if parse_layer_names(&mut reader, version, &mut layer_groups).is_err() {
-        apply_default_layer_names(&mut layer_groups);
-    } else {
-        apply_default_layer_names_for_blanks(&mut layer_groups);
-    }
+    // The origin and the layer and group names are stored later in the
+    // header block. If this optional extraction fails, keep deterministic
+    // default names.
+    let grid_origin = match parse_grid_origin(&mut reader, version) {
+        Ok(origin) => {
+            if parse_layer_names(&mut reader, &mut layer_groups).is_err() {
+                apply_default_layer_names(&mut layer_groups);
+            } else {
+                apply_default_layer_names_for_blanks(&mut layer_groups);
+            }
+            origin
+        }
+        Err(_) => {
+            apply_default_layer_names(&mut layer_groups);
+            None
+        }
+    };
 
     Ok(JwwHeader {
         version,
@@ -79,23 +231,21 @@ pub fn parse_header(data: &[u8]) -> Result<JwwHeader, JwwError> {
         paper_size,
         write_layer_group,
         layer_groups,
+        grid_origin,
     })
 }
 
-fn parse_layer_names(
-    reader: &mut Reader<'_>,
-    version: u32,
-    layer_groups: &mut [LayerGroupHeader; 16],
-) -> Result<(), JwwError> {
-    // Only version >= 300 layout is currently supported for this section.
-    if version < 300 {
-        return Err(JwwError::UnexpectedEof("layer names"));
+fn parse_grid_origin(reader: &mut Reader<'_>, version: u32) -> Result<Option<Coord2D>, JwwError> {
+    if version >= 300 {
+        // Skip fields defined before layer names in jwdatafmt:
+        // 14 dummy DWORD + 5 dimension DWORD + 1 dummy DWORD + max-draw-width DWORD.
+        reader.skip((14 + 5 + 1 + 1) * 4)?;
+    } else {
+        // 2.x files have no dummy DWORD between the dimension settings and
+        // max-draw-width; the rest of the layout matches.
+        reader.skip((14 + 5 + 1) * 4)?;
     }
 
-    // Skip fields defined before layer names in jwdatafmt:
-    // 14 dummy DWORD + 5 dimension DWORD + 1 dummy DWORD + max-draw-width DWORD.
-    reader.skip((14 + 5 + 1 + 1) * 4)?;
-
     // Printer/memory settings before names:
     // printer origin(x,y) [16]
     // printer scale [8]
@@ -104,16 +254,23 @@ fn parse_layer_names(
     // memori min [8]
     // memori x/y [16]
     // memori origin x/y [16]
-    reader.skip(16 + 8 + 4 + 4 + 8 + 16 + 16)?;
+    reader.skip(16 + 8 + 4 + 4 + 8 + 16)?;
+    let x = reader.read_f64()?;
+    let y = reader.read_f64()?;
+    Ok((x.is_finite() && y.is_finite()).then(|| Coord2D::new(x, y)))
+}
 
-    for g in 0..16 {
-        for l in 0..16 {
-            layer_groups[g].layers[l].name = reader.read_cstring()?;
+fn parse_layer_names(
+    reader: &mut Reader<'_>,
+    layer_groups: &mut [LayerGroupHeader; 16],
+) -> Result<(), JwwError> {
+    for group in layer_groups.iter_mut() {
+        for layer in &mut group.layers {
+            layer.name = reader.read_cstring()?;
         }
     }
 
-    for (g, group) in layer_groups.iter_mut().enumerate() {
-        let _ = g;
+    for group in layer_groups.iter_mut() {
         group.name = reader.read_cstring()?;
     }
 
@@ -152,7 +309,12 @@ mod tests {
     use std::fs;
     use std::path::{Path, PathBuf};
 
-    use super::{is_jww_signature, parse_header, read_header_from_file, JwwError};
+    use crate::model::Coord2D;
+
+    use super::{
+        is_jww_signature, parse_header, probe_file, probe_header, read_header_from_file,
+        supported_versions, JwwError, JwwProbe, PaperSize, JWW_SIGNATURE,
+    };
 
     fn jww_samples_dir() -> PathBuf {
         Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples")
@@ -203,6 +365,10 @@ mod tests {
                 path.display()
             );
             assert_eq!(header.layer_groups.len(), 16);
+            let origin = header
+                .grid_origin
+                .unwrap_or_else(|| panic!("no grid origin in {}", path.display()));
+            assert!(origin.x.is_finite() && origin.y.is_finite());
             for group in &header.layer_groups {
                 assert_eq!(group.layers.len(), 16);
                 assert!(
@@ -221,6 +387,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_layer_names_from_pre_300_header() {
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(JWW_SIGNATURE);
+        data.extend_from_slice(&230u32.to_le_bytes());
+        data.push(0); // memo
+        data.extend_from_slice(&0u32.to_le_bytes()); // paper size
+        data.extend_from_slice(&0u32.to_le_bytes()); // write layer group
+        for _ in 0..16 {
+            data.extend_from_slice(&0u32.to_le_bytes()); // state
+            data.extend_from_slice(&0u32.to_le_bytes()); // write layer
+            data.extend_from_slice(&1.0f64.to_le_bytes()); // scale
+            data.extend_from_slice(&0u32.to_le_bytes()); // protect
+            for _ in 0..16 {
+                data.extend_from_slice(&0u32.to_le_bytes()); // layer state
+                data.extend_from_slice(&0u32.to_le_bytes()); // layer protect
+            }
+        }
+        // 14 dummy + 5 dimension + max-draw-width DWORDs, then printer and
+        // grid settings.
+        data.resize(data.len() + (14 + 5 + 1) * 4, 0);
+        data.resize(data.len() + 16 + 8 + 4 + 4 + 8 + 16, 0);
+        data.extend_from_slice(&1250.0f64.to_le_bytes()); // memori origin x
+        data.extend_from_slice(&(-40.5f64).to_le_bytes()); // memori origin y
+        for index in 0..16 * 16 {
+            let name = if index == 1 { "Walls" } else { "" };
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+        }
+        for index in 0..16 {
+            let name = if index == 0 { "Plan" } else { "" };
+            data.push(name.len() as u8);
+            data.extend_from_slice(name.as_bytes());
+        }
+
+        let header = parse_header(&data).unwrap();
+        assert_eq!(header.version, 230);
+        assert_eq!(header.layer_groups[0].name, "Plan");
+        assert_eq!(header.layer_groups[0].layers[1].name, "Walls");
+        assert_eq!(header.layer_groups[0].layers[0].name, "0-0");
+        assert_eq!(header.layer_groups[1].name, "Group1");
+        assert_eq!(header.grid_origin, Some(Coord2D::new(1250.0, -40.5)));
+    }
+
     #[test]
     fn extracts_non_default_layer_names_when_present() {
         let path = jww_samples_dir().join("Ａマンション平面例.jww");
@@ -235,4 +445,92 @@ mod tests {
         assert_ne!(group0.name, "Group0");
         assert_ne!(layer0.name, "0-0");
     }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(JWW_SIGNATURE);
+        data.extend_from_slice(&999u32.to_le_bytes());
+        data.resize(data.len() + 4096, 0);
+
+        let err = parse_header(&data).unwrap_err();
+        assert!(matches!(err, JwwError::UnsupportedVersion(999)));
+        assert!(supported_versions().contains(&600));
+        assert!(supported_versions().contains(&700));
+        assert!(!supported_versions().contains(&999));
+    }
+
+    #[test]
+    fn dos_layout_header_is_reported() {
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(JWW_SIGNATURE);
+        data.extend_from_slice(&42u32.to_le_bytes());
+        // Fixed 8-byte memo field as written by the DOS converters.
+        data.extend_from_slice(b"PLAN\0\0\0\0");
+        data.resize(data.len() + 4096, 0);
+
+        let err = parse_header(&data).unwrap_err();
+        assert!(matches!(err, JwwError::DosLayout(42)));
+        assert!(err.to_string().contains("DOS"));
+    }
+
+    #[test]
+    fn paper_size_codes_resolve_to_sheet_dimensions() {
+        assert_eq!(PaperSize::from_code(1), PaperSize::A1);
+        assert_eq!(PaperSize::A1.dimensions_mm(), Some((841.0, 594.0)));
+        assert_eq!(
+            PaperSize::from_code(3).dimensions_mm(),
+            Some((420.0, 297.0))
+        );
+        assert_eq!(
+            PaperSize::from_code(8).dimensions_mm(),
+            Some((1682.0, 1189.0))
+        );
+        assert_eq!(PaperSize::from_code(12), PaperSize::Meters10);
+        assert_eq!(PaperSize::Meters10.dimensions_mm(), None);
+        assert_eq!(PaperSize::from_code(99), PaperSize::Other(99));
+        assert_eq!(PaperSize::Other(99).dimensions_mm(), None);
+
+        // Test5 is drawn on A1.
+        let header = read_header_from_file(jww_samples_dir().join("Test5.jww")).unwrap();
+        assert_eq!(header.paper(), PaperSize::A1);
+        assert_eq!(header.paper_size_mm(), Some((841.0, 594.0)));
+    }
+
+    #[test]
+    fn probe_reports_version_and_header_readability() {
+        let probe = probe_file(jww_samples_dir().join("Test1.jww")).unwrap();
+        assert_eq!(
+            probe,
+            JwwProbe {
+                is_jww: true,
+                version: Some(600),
+                readable_header: true,
+            }
+        );
+
+        // Signature and version only.
+        let mut truncated = JWW_SIGNATURE.to_vec();
+        truncated.extend_from_slice(&600u32.to_le_bytes());
+        assert_eq!(truncated.len(), 12);
+        assert_eq!(
+            probe_header(&truncated),
+            JwwProbe {
+                is_jww: true,
+                version: Some(600),
+                readable_header: false,
+            }
+        );
+        assert_eq!(
+            probe_header(&truncated[..10]),
+            JwwProbe {
+                is_jww: true,
+                version: None,
+                readable_header: false,
+            }
+        );
+
+        assert_eq!(probe_header(b"NotJwwData at all"), JwwProbe::default());
+        assert_eq!(probe_header(b""), JwwProbe::default());
+    }
 }
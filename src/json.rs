@@ -0,0 +1,387 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+
+use serde::ser::{self, Serialize};
+
+use crate::error::JwwError;
+use crate::model::{Entity, JwwDocument};
+
+// Entities are internally tagged on `type` with the same names as
+// `Entity::entity_type`. JSON has no NaN or infinity, and serde_json would
+// quietly write them as `null`, so a document holding one (only corrupt
+// files do) is rejected instead.
+pub fn document_to_json(doc: &JwwDocument) -> Result<String, JwwError> {
+    check_finite(doc)?;
+    Ok(serde_json::to_string(doc).expect("JwwDocument serialization cannot fail"))
+}
+
+#[derive(serde::Serialize)]
+struct EntityLine<'a> {
+    #[serde(flatten)]
+    entity: &'a Entity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block: Option<u32>,
+}
+
+/// Writes one JSON object per line (JSON Lines): the top-level entities,
+/// then each block definition's entities with `block` set to its number.
+/// Entities are tagged as in [`document_to_json`], which also rejects
+/// non-finite values; nothing is written then.
+pub fn document_to_jsonl<W: Write>(doc: &JwwDocument, mut writer: W) -> Result<(), JwwError> {
+    check_finite(doc)?;
+    let top_level = doc.entities.iter().map(|entity| (entity, None));
+    let in_blocks = doc.block_defs.iter().flat_map(|block_def| {
+        block_def
+            .entities
+            .iter()
+            .map(|entity| (entity, Some(block_def.number)))
+    });
+    for (entity, block) in top_level.chain(in_blocks) {
+        serde_json::to_writer(&mut writer, &EntityLine { entity, block })
+            .map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(writer.flush()?)
+}
+
+fn check_finite(doc: &JwwDocument) -> Result<(), JwwError> {
+    doc.serialize(FiniteCheck)
+        .map_err(|NonFinite(field)| JwwError::NonFiniteValue(field))
+}
+
+// Walks a value through serde and fails on the first NaN or infinite float,
+// naming the struct field that holds it.
+struct FiniteCheck;
+
+#[derive(Debug)]
+struct NonFinite(&'static str);
+
+impl Display for NonFinite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "non-finite value in `{}`", self.0)
+    }
+}
+
+impl std::error::Error for NonFinite {}
+
+impl ser::Error for NonFinite {
+    fn custom<T: Display>(_: T) -> Self {
+        Self("")
+    }
+}
+
+impl ser::Serializer for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_f32(self, v: f32) -> Result<(), NonFinite> {
+        self.serialize_f64(f64::from(v))
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), NonFinite> {
+        if v.is_finite() {
+            Ok(())
+        } else {
+            Err(NonFinite(""))
+        }
+    }
+    fn serialize_bool(self, _: bool) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_i8(self, _: i8) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_i16(self, _: i16) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_i32(self, _: i32) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_i64(self, _: i64) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_u8(self, _: u8) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_u16(self, _: u16) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_u32(self, _: u32) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_u64(self, _: u64) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_char(self, _: char) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_str(self, _: &str) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_none(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+    ) -> Result<(), NonFinite> {
+        Ok(())
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        value.serialize(self)
+    }
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_tuple(self, _: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, NonFinite> {
+        Ok(self)
+    }
+}
+
+// The innermost field wins, so the error names the float itself.
+fn check_field<T: ?Sized + Serialize>(key: &'static str, value: &T) -> Result<(), NonFinite> {
+    value
+        .serialize(FiniteCheck)
+        .map_err(|NonFinite(field)| NonFinite(if field.is_empty() { key } else { field }))
+}
+
+impl ser::SerializeSeq for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), NonFinite> {
+        key.serialize(FiniteCheck)
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NonFinite> {
+        value.serialize(FiniteCheck)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        check_field(key, value)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for FiniteCheck {
+    type Ok = ();
+    type Error = NonFinite;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NonFinite> {
+        check_field(key, value)
+    }
+    fn end(self) -> Result<(), NonFinite> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::error::JwwError;
+    use crate::model::{BlockDef, Entity, EntityBase, JwwDocument, Line};
+    use crate::parser::read_document_from_file;
+
+    use super::{document_to_json, document_to_jsonl};
+
+    #[test]
+    fn document_to_json_round_trips_sample() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("jww_samples")
+            .join("Test1.jww");
+        let doc = read_document_from_file(&path).unwrap();
+
+        let json = document_to_json(&doc).unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(
+            value["entities"][0]["type"],
+            doc.entities[0].entity_type(),
+            "entities should be tagged with their entity type"
+        );
+
+        let restored = serde_json::from_str::<JwwDocument>(&json).unwrap();
+        assert_eq!(restored, doc);
+    }
+
+    #[test]
+    fn document_to_json_rejects_non_finite_values() {
+        let mut doc = read_document_from_file(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("jww_samples")
+                .join("Test1.jww"),
+        )
+        .unwrap();
+        doc.entities = vec![Entity::Line(Line {
+            base: EntityBase::default(),
+            start_x: 1.0,
+            start_y: f64::INFINITY,
+            end_x: f64::NAN,
+            end_y: 2.0,
+        })];
+
+        assert!(matches!(
+            document_to_json(&doc),
+            Err(JwwError::NonFiniteValue("start_y"))
+        ));
+        let mut out = Vec::new();
+        assert!(matches!(
+            document_to_jsonl(&doc, &mut out),
+            Err(JwwError::NonFiniteValue("start_y"))
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn document_to_jsonl_writes_one_line_per_entity() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("jww_samples")
+            .join("Test1.jww");
+        let mut doc = read_document_from_file(&path).unwrap();
+        doc.block_defs.push(BlockDef {
+            base: EntityBase::default(),
+            number: 7,
+            is_referenced: false,
+            created_time: 0,
+            name: "Door".to_string(),
+            entities: doc.entities[..2].to_vec(),
+        });
+
+        let mut out = Vec::new();
+        document_to_jsonl(&doc, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines = text
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+
+        let block_entity_count = doc
+            .block_defs
+            .iter()
+            .map(|b| b.entities.len())
+            .sum::<usize>();
+        assert_eq!(lines.len(), doc.entities.len() + block_entity_count);
+        assert_eq!(lines[0]["type"], doc.entities[0].entity_type());
+        assert!(lines[0].get("block").is_none());
+        assert_eq!(lines[doc.entities.len()]["block"], 7);
+        assert_eq!(lines[doc.entities.len()]["type"], lines[0]["type"]);
+    }
+}
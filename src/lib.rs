@@ -1,9 +1,19 @@
+#[cfg(feature = "parallel")]
+mod batch;
 mod dxf;
 mod error;
+mod geojson;
+mod geometry;
 mod header;
+#[cfg(feature = "serde")]
+mod json;
 mod model;
 mod parser;
+mod py_types;
 mod reader;
+mod stream;
+mod svg;
+mod writer;
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -11,51 +21,434 @@ use std::io::Read;
 
 use pyo3::exceptions::{PyIOError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::types::{PyBytes, PyDict, PyList};
 
+#[cfg(feature = "parallel")]
+pub use batch::convert_directory;
 pub use dxf::{
-    convert_document, convert_document_with_options, document_to_string, write_document_to_file,
-    ConvertOptions, DxfArc, DxfBlock, DxfCircle, DxfDocument, DxfEllipse, DxfEntity, DxfInsert,
-    DxfLayer, DxfLine, DxfPoint, DxfSolid, DxfText,
+    aci_to_pen, convert_document, convert_document_with_options, document_to_bytes,
+    document_to_string, document_to_string_with_options, merge_documents,
+    try_convert_document_with_options, validate_conversion, write_document_to_file,
+    ConversionReport, ConvertOptions, DxfArc, DxfAttribute, DxfBlock, DxfCircle, DxfDocument,
+    DxfEllipse, DxfEntity, DxfFace3D, DxfImage, DxfInsert, DxfLayer, DxfLayout, DxfLine,
+    DxfLwPolyline, DxfMText, DxfPoint, DxfSolid, DxfText, DxfVersion, DxfVertex,
+    HorizontalAlignment, LayerNameFilter, TargetUnits, TextAlignment, VerticalAlignment,
+    WriteOptions, XDataValue, ARC_CHORD_TOLERANCE, DEFAULT_XDATA_APP_ID, POINT_MARKER_CIRCLE,
+    POINT_MARKER_CROSS, TEMP_POINTS_LAYER,
 };
 pub use error::JwwError;
+pub use geojson::{document_to_geojson, write_geojson_to_file};
+pub use geometry::point_in_polygon;
 pub use header::{
-    is_jww_signature, parse_header, read_header_from_file, JwwHeader, LayerGroupHeader, LayerHeader,
+    is_jww_signature, parse_header, probe_file, probe_header, read_header_from_file,
+    supported_versions, JwwHeader, JwwProbe, LayerGroupHeader, LayerHeader, PaperSize,
 };
+#[cfg(feature = "serde")]
+pub use json::{document_to_json, document_to_jsonl};
 pub use model::{
-    collect_entity_coordinates, coordinates_bbox, Arc, Block, BlockDef, Coord2D, Dimension, Entity,
-    EntityBase, JwwDocument, Line, Point, Solid, Text,
+    block_def_created_datetime, collect_entity_coordinates, collect_entity_coordinates_expanded,
+    coordinates_bbox, extract_layer, renumber_block_defs, repair_solids, Affine2D, Arc, Block,
+    BlockDef, Coord2D, Dimension, Entity, EntityBase, EntityFlags, ImageRef, JwwDocument, Line,
+    Point, PolyVertex, Polyline, Solid, Text,
 };
 pub use parser::{
-    block_def_name_map, entity_counts, parse_document, read_document_from_file, resolve_block_name,
-    validate_block_references, BlockReferenceValidation,
+    block_def_name_map, collect_pen_usage, entity_counts, entity_counts_by_layer, group_entities,
+    parse_document, parse_document_reported, parse_document_with_options, parse_entities_from,
+    parse_entities_from_with_options, parse_entity_offsets, read_document_from_file,
+    read_document_from_file_with_options, resolve_block_name, validate_block_references,
+    validate_document, BlockReferenceValidation, JwwClass, ParseOptions, ParseReport, ResumeState,
 };
+pub use reader::Reader;
+pub use stream::EntityStream;
+pub use svg::{document_to_svg, document_to_svg_with_options, write_svg_to_file, SvgOptions};
+pub use writer::{document_to_jww_bytes, write_jww, WRITE_VERSION};
 
-#[pyfunction]
-fn hello_from_bin() -> String {
-    "Hello from ezjww!".to_string()
-}
+/// A Python module implemented in Rust. The name of this function must match
+/// the `lib.name` setting in the `Cargo.toml`, else Python will not be able to
+/// import the module.
+///
+/// pyo3 0.22 expands each `#[pyfunction]` into a wrapper item next to the
+/// function, and for PyResult returns that wrapper trips
+/// `clippy::useless_conversion`. An allow on the function does not reach the
+/// wrapper, so the pyfunctions are declared in here and the allow stays on
+/// this function rather than on the whole crate.
+#[pymodule]
+#[allow(clippy::useless_conversion)]
+fn _core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    #[pyfunction]
+    fn hello_from_bin() -> String {
+        "Hello from ezjww!".to_string()
+    }
 
-#[pyfunction]
-fn is_jww_file(path: &str) -> PyResult<bool> {
-    let mut file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
-    let mut signature = [0_u8; 8];
-    match file.read_exact(&mut signature) {
-        Ok(()) => Ok(is_jww_signature(&signature)),
-        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
-        Err(err) => Err(PyIOError::new_err(err.to_string())),
+    #[pyfunction]
+    fn is_jww_file(path: &str) -> PyResult<bool> {
+        let mut file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        let mut signature = [0_u8; 8];
+        match file.read_exact(&mut signature) {
+            Ok(()) => Ok(is_jww_signature(&signature)),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(PyIOError::new_err(err.to_string())),
+        }
+    }
+
+    // Reads only the signature and the version field after it, without
+    // checking that this crate can parse that version.
+    #[pyfunction]
+    fn jww_version(path: &str) -> PyResult<Option<u32>> {
+        let mut file = File::open(path).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        let mut prefix = [0_u8; 12];
+        match file.read_exact(&mut prefix) {
+            Ok(()) if is_jww_signature(&prefix) => Ok(Some(u32::from_le_bytes(
+                prefix[8..].try_into().expect("slice is four bytes"),
+            ))),
+            Ok(()) => Ok(None),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(err) => Err(PyIOError::new_err(err.to_string())),
+        }
+    }
+
+    #[pyfunction]
+    fn probe_jww(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let probe = probe_file(path).map_err(to_py_err)?;
+        let out = PyDict::new_bound(py);
+        out.set_item("is_jww", probe.is_jww)?;
+        out.set_item("version", probe.version)?;
+        out.set_item("readable_header", probe.readable_header)?;
+        Ok(out.unbind().into())
+    }
+
+    #[pyfunction]
+    fn read_header(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let header = read_header_from_file(path).map_err(to_py_err)?;
+        Ok(header_to_pydict(py, &header)?.unbind().into())
+    }
+
+    #[pyfunction]
+    fn read_document(py: Python<'_>, path: &str) -> PyResult<Py<py_types::PyDocument>> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        py_types::document_to_pyclass(py, &document)
+    }
+
+    #[pyfunction]
+    fn read_document_bytes(py: Python<'_>, data: &[u8]) -> PyResult<Py<py_types::PyDocument>> {
+        let document = parse_document(data).map_err(to_py_err)?;
+        py_types::document_to_pyclass(py, &document)
+    }
+
+    #[cfg(feature = "serde")]
+    #[pyfunction]
+    fn read_json(path: &str) -> PyResult<String> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        document_to_json(&document).map_err(to_py_err)
+    }
+
+    #[cfg(feature = "serde")]
+    #[pyfunction]
+    fn read_json_bytes(data: &[u8]) -> PyResult<String> {
+        let document = parse_document(data).map_err(to_py_err)?;
+        document_to_json(&document).map_err(to_py_err)
+    }
+
+    /// Returns `(filename, ok, error_message)` for each converted file.
+    #[cfg(feature = "parallel")]
+    #[pyfunction]
+    fn convert_directory_py(
+        py: Python<'_>,
+        input_dir: &str,
+        output_dir: &str,
+    ) -> Vec<(String, bool, Option<String>)> {
+        let results = py
+            .allow_threads(|| convert_directory(input_dir, output_dir, &ConvertOptions::default()));
+        results
+            .into_iter()
+            .map(|(path, result)| {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string());
+                match result {
+                    Ok(()) => (name, true, None),
+                    Err(err) => (name, false, Some(err.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    #[pyfunction]
+    fn read_document_dict(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        document_to_pydict(py, &document)
+    }
+
+    #[pyfunction]
+    fn read_document_dict_bytes(py: Python<'_>, data: &[u8]) -> PyResult<PyObject> {
+        let document = parse_document(data).map_err(to_py_err)?;
+        document_to_pydict(py, &document)
+    }
+
+    #[pyfunction]
+    fn entity_offsets(path: &str) -> PyResult<Vec<usize>> {
+        let data = std::fs::read(path).map_err(|err| PyIOError::new_err(err.to_string()))?;
+        parse_entity_offsets(&data).map_err(to_py_err)
+    }
+
+    #[pyfunction]
+    fn read_report(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let data = std::fs::read(path).map_err(|err| PyIOError::new_err(err.to_string()))?;
+        let (_, report) = parse_document_reported(&data).map_err(to_py_err)?;
+        parse_report_to_pyobject(py, report)
+    }
+
+    // Same report as `read_report`, without building the document.
+    #[pyfunction]
+    fn validate_file(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let data = std::fs::read(path).map_err(|err| PyIOError::new_err(err.to_string()))?;
+        let report = validate_document(&data).map_err(to_py_err)?;
+        parse_report_to_pyobject(py, report)
+    }
+
+    #[pyfunction]
+    fn bounding_box(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let Some((min, max)) = document.expanded_bbox() else {
+            return Ok(py.None());
+        };
+        let out = PyDict::new_bound(py);
+        out.set_item("min_x", min.x)?;
+        out.set_item("min_y", min.y)?;
+        out.set_item("max_x", max.x)?;
+        out.set_item("max_y", max.y)?;
+        Ok(out.unbind().into())
+    }
+
+    #[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_document(
+        py: Python<'_>,
+        path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<PyObject> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let dxf_document = convert_for_read(&document, explode_inserts, max_block_nesting);
+        Ok(dxf_document_to_pydict(py, &dxf_document)?.unbind().into())
+    }
+
+    #[pyfunction(signature = (data, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_document_bytes(
+        py: Python<'_>,
+        data: &[u8],
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<PyObject> {
+        let document = parse_document(data).map_err(to_py_err)?;
+        let dxf_document = convert_for_read(&document, explode_inserts, max_block_nesting);
+        Ok(dxf_document_to_pydict(py, &dxf_document)?.unbind().into())
+    }
+
+    #[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_string(
+        path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<String> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let dxf_document = convert_for_read(&document, explode_inserts, max_block_nesting);
+        document_to_string(&dxf_document).map_err(to_py_err)
+    }
+
+    #[pyfunction(signature = (data, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_string_bytes(
+        data: &[u8],
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<String> {
+        let document = parse_document(data).map_err(to_py_err)?;
+        let dxf_document = convert_for_read(&document, explode_inserts, max_block_nesting);
+        document_to_string(&dxf_document).map_err(to_py_err)
+    }
+
+    #[pyfunction(signature = (path, layers, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_string_filtered(
+        path: &str,
+        layers: Vec<[u16; 2]>,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<String> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let options = ConvertOptions {
+            explode_inserts,
+            max_block_nesting,
+            layer_filter: Some(
+                layers
+                    .into_iter()
+                    .map(|[group, layer]| (group, layer))
+                    .collect(),
+            ),
+            ..ConvertOptions::default()
+        };
+        let dxf_document = convert_document_with_options(&document, options);
+        document_to_string(&dxf_document).map_err(to_py_err)
+    }
+
+    #[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32))]
+    fn read_dxf_bytes(
+        py: Python<'_>,
+        path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<Py<PyBytes>> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let dxf_document = convert_for_read(&document, explode_inserts, max_block_nesting);
+        let bytes = document_to_bytes(&dxf_document).map_err(to_py_err)?;
+        Ok(PyBytes::new_bound(py, &bytes).unbind())
+    }
+
+    #[pyfunction(signature = (path, output_path, explode_inserts=false, max_block_nesting=32))]
+    fn write_dxf(
+        path: &str,
+        output_path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<()> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let options = ConvertOptions {
+            explode_inserts,
+            max_block_nesting,
+            ..ConvertOptions::default()
+        };
+        let dxf_document = convert_document_with_options(&document, options);
+        write_document_to_file(&dxf_document, output_path).map_err(to_py_err)?;
+        Ok(())
+    }
+
+    #[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32, max_total_segments=None))]
+    fn check_conversion(
+        py: Python<'_>,
+        path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+        max_total_segments: Option<usize>,
+    ) -> PyResult<PyObject> {
+        let options = ConvertOptions {
+            explode_inserts,
+            max_block_nesting,
+            max_total_segments,
+            ..ConvertOptions::default()
+        };
+        let report = validate_conversion(path, options).map_err(to_py_err)?;
+        let out = PyDict::new_bound(py);
+        out.set_item("ok", report.is_clean())?;
+        out.set_item("entity_count", report.entity_count)?;
+        out.set_item("block_count", report.block_count)?;
+        out.set_item("generated_segments", report.generated_segments)?;
+        out.set_item("unsupported_entities", report.unsupported_entities)?;
+        out.set_item("warnings", report.warnings)?;
+        Ok(out.unbind().into())
+    }
+
+    #[pyfunction(signature = (paths, prefixes, output_path, explode_inserts=false, max_block_nesting=32))]
+    fn merge_dxf(
+        paths: Vec<String>,
+        prefixes: Vec<String>,
+        output_path: &str,
+        explode_inserts: bool,
+        max_block_nesting: usize,
+    ) -> PyResult<()> {
+        if paths.len() != prefixes.len() {
+            return Err(PyValueError::new_err(
+                "paths and prefixes must have the same length",
+            ));
+        }
+        let documents = paths
+            .iter()
+            .map(|path| read_document_from_file(path).map_err(to_py_err))
+            .collect::<PyResult<Vec<_>>>()?;
+        let tagged = prefixes
+            .iter()
+            .map(String::as_str)
+            .zip(&documents)
+            .collect::<Vec<_>>();
+        let options = ConvertOptions {
+            explode_inserts,
+            max_block_nesting,
+            ..ConvertOptions::default()
+        };
+        let dxf_document = merge_documents(&tagged, options);
+        write_document_to_file(&dxf_document, output_path).map_err(to_py_err)?;
+        Ok(())
+    }
+
+    #[pyfunction]
+    fn write_dxf_document(
+        py: Python<'_>,
+        document: PyRef<'_, py_types::PyDxfDocument>,
+        output_path: &str,
+    ) -> PyResult<()> {
+        let dxf_document = document.to_dxf(py)?;
+        write_document_to_file(&dxf_document, output_path).map_err(to_py_err)?;
+        Ok(())
     }
-}
 
-#[pyfunction]
-fn read_header(py: Python<'_>, path: &str) -> PyResult<PyObject> {
-    let header = read_header_from_file(path).map_err(to_py_err)?;
-    Ok(header_to_pydict(py, &header)?.unbind().into())
+    #[pyfunction(signature = (path, output_path, width=800.0, height=600.0))]
+    fn export_svg(path: &str, output_path: &str, width: f64, height: f64) -> PyResult<()> {
+        if !(width > 0.0 && height > 0.0) {
+            return Err(PyValueError::new_err("width and height must be positive"));
+        }
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        let dxf_document = convert_document(&document);
+        let options = SvgOptions {
+            width: Some(width),
+            height: Some(height),
+        };
+        write_svg_to_file(&dxf_document, options, output_path)
+            .map_err(|err| PyIOError::new_err(err.to_string()))?;
+        Ok(())
+    }
+
+    #[pyfunction]
+    fn export_geojson(path: &str, output_path: &str) -> PyResult<()> {
+        let document = read_document_from_file(path).map_err(to_py_err)?;
+        write_geojson_to_file(&document, output_path)
+            .map_err(|err| PyIOError::new_err(err.to_string()))?;
+        Ok(())
+    }
+
+    m.add_function(wrap_pyfunction!(hello_from_bin, m)?)?;
+    m.add_function(wrap_pyfunction!(is_jww_file, m)?)?;
+    m.add_function(wrap_pyfunction!(jww_version, m)?)?;
+    m.add_function(wrap_pyfunction!(probe_jww, m)?)?;
+    m.add_function(wrap_pyfunction!(read_header, m)?)?;
+    m.add_function(wrap_pyfunction!(read_document, m)?)?;
+    m.add_function(wrap_pyfunction!(read_document_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(read_document_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(read_document_dict_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(bounding_box, m)?)?;
+    #[cfg(feature = "serde")]
+    m.add_function(wrap_pyfunction!(read_json, m)?)?;
+    #[cfg(feature = "serde")]
+    m.add_function(wrap_pyfunction!(read_json_bytes, m)?)?;
+    #[cfg(feature = "parallel")]
+    m.add_function(wrap_pyfunction!(convert_directory_py, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_document, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_document_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_string, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_string_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_string_filtered, m)?)?;
+    m.add_function(wrap_pyfunction!(read_dxf_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(write_dxf, m)?)?;
+    m.add_function(wrap_pyfunction!(write_dxf_document, m)?)?;
+    m.add_function(wrap_pyfunction!(check_conversion, m)?)?;
+    m.add_function(wrap_pyfunction!(entity_offsets, m)?)?;
+    m.add_function(wrap_pyfunction!(read_report, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_file, m)?)?;
+    m.add_function(wrap_pyfunction!(merge_dxf, m)?)?;
+    m.add_function(wrap_pyfunction!(export_svg, m)?)?;
+    m.add_function(wrap_pyfunction!(export_geojson, m)?)?;
+    py_types::register_classes(m)?;
+    Ok(())
 }
 
-#[pyfunction]
-fn read_document(py: Python<'_>, path: &str) -> PyResult<PyObject> {
-    let document = read_document_from_file(path).map_err(to_py_err)?;
+fn document_to_pydict(py: Python<'_>, document: &JwwDocument) -> PyResult<PyObject> {
     let out = PyDict::new_bound(py);
     let header = header_to_pydict(py, &document.header)?;
     out.set_item("header", header)?;
@@ -80,7 +473,10 @@ fn read_document(py: Python<'_>, path: &str) -> PyResult<PyObject> {
 
     let counts = entity_counts_to_pydict(py, entity_counts(&document.entities))?;
     out.set_item("entity_counts", counts)?;
-    let validation = validate_block_references(&document);
+    out.set_item("counts_by_layer", counts_by_layer_to_pydict(py, document)?)?;
+    out.set_item("pen_usage", collect_pen_usage(document))?;
+    out.set_item("groups", group_entities(&document.entities))?;
+    let validation = validate_block_references(document);
     out.set_item(
         "validation",
         block_reference_validation_to_pydict(py, &validation)?,
@@ -89,53 +485,26 @@ fn read_document(py: Python<'_>, path: &str) -> PyResult<PyObject> {
     Ok(out.unbind().into())
 }
 
-#[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32))]
-fn read_dxf_document(
-    py: Python<'_>,
-    path: &str,
-    explode_inserts: bool,
-    max_block_nesting: usize,
-) -> PyResult<PyObject> {
-    let document = read_document_from_file(path).map_err(to_py_err)?;
-    let options = ConvertOptions {
-        explode_inserts,
-        max_block_nesting,
-    };
-    let dxf_document = convert_document_with_options(&document, options);
-    Ok(dxf_document_to_pydict(py, &dxf_document)?.unbind().into())
-}
-
-#[pyfunction(signature = (path, explode_inserts=false, max_block_nesting=32))]
-fn read_dxf_string(
-    path: &str,
-    explode_inserts: bool,
-    max_block_nesting: usize,
-) -> PyResult<String> {
-    let document = read_document_from_file(path).map_err(to_py_err)?;
-    let options = ConvertOptions {
-        explode_inserts,
-        max_block_nesting,
-    };
-    let dxf_document = convert_document_with_options(&document, options);
-    Ok(document_to_string(&dxf_document))
+fn parse_report_to_pyobject(py: Python<'_>, report: ParseReport) -> PyResult<PyObject> {
+    let out = PyDict::new_bound(py);
+    out.set_item("class_counts", report.class_counts)?;
+    out.set_item("block_def_count", report.block_def_count)?;
+    out.set_item("unresolved_block_refs", report.unresolved_block_refs)?;
+    out.set_item("skipped_records", report.skipped_records)?;
+    Ok(out.unbind().into())
 }
 
-#[pyfunction(signature = (path, output_path, explode_inserts=false, max_block_nesting=32))]
-fn write_dxf(
-    path: &str,
-    output_path: &str,
+fn convert_for_read(
+    document: &JwwDocument,
     explode_inserts: bool,
     max_block_nesting: usize,
-) -> PyResult<()> {
-    let document = read_document_from_file(path).map_err(to_py_err)?;
+) -> DxfDocument {
     let options = ConvertOptions {
         explode_inserts,
         max_block_nesting,
+        ..ConvertOptions::default()
     };
-    let dxf_document = convert_document_with_options(&document, options);
-    write_document_to_file(&dxf_document, output_path)
-        .map_err(|err| PyIOError::new_err(err.to_string()))?;
-    Ok(())
+    convert_document_with_options(document, options)
 }
 
 fn to_py_err(err: JwwError) -> PyErr {
@@ -152,6 +521,13 @@ fn to_py_err(err: JwwError) -> PyErr {
         JwwError::UnknownEntityClass(name) => {
             PyValueError::new_err(format!("unknown entity class: {name}"))
         }
+        err @ (JwwError::DosLayout(_)
+        | JwwError::UnsupportedVersion(_)
+        | JwwError::SegmentLimitExceeded { .. }
+        | JwwError::NonFiniteValue(_)
+        | JwwError::NonFiniteGroup(_)
+        | JwwError::HandleSeedOverflow(_)
+        | JwwError::ParseAt { .. }) => PyValueError::new_err(err.to_string()),
     }
 }
 
@@ -160,7 +536,12 @@ fn header_to_pydict<'py>(py: Python<'py>, header: &JwwHeader) -> PyResult<Bound<
     out.set_item("version", header.version)?;
     out.set_item("memo", &header.memo)?;
     out.set_item("paper_size", header.paper_size)?;
+    out.set_item("paper_size_mm", header.paper_size_mm())?;
     out.set_item("write_layer_group", header.write_layer_group)?;
+    out.set_item(
+        "grid_origin",
+        header.grid_origin.map(|origin| (origin.x, origin.y)),
+    )?;
 
     let layer_groups = PyList::empty_bound(py);
     for group in &header.layer_groups {
@@ -204,6 +585,11 @@ fn entity_to_pydict<'py>(
     base_dict.set_item("layer", base.layer)?;
     base_dict.set_item("layer_group", base.layer_group)?;
     base_dict.set_item("flag", base.flag)?;
+    base_dict.set_item(
+        "flags",
+        py_types::entity_flags_to_pydict(py, &base.flags())?,
+    )?;
+    base_dict.set_item("seq", base.seq)?;
     out.set_item("base", base_dict)?;
 
     match entity {
@@ -254,6 +640,7 @@ fn entity_to_pydict<'py>(
             out.set_item("point4_x", v.point4_x)?;
             out.set_item("point4_y", v.point4_y)?;
             out.set_item("color", v.color)?;
+            out.set_item("is_filled", v.is_filled)?;
         }
         Entity::Block(v) => {
             out.set_item("ref_x", v.ref_x)?;
@@ -263,6 +650,7 @@ fn entity_to_pydict<'py>(
             out.set_item("rotation", v.rotation)?;
             out.set_item("def_number", v.def_number)?;
             out.set_item("block_name", block_name_map.get(&v.def_number).cloned())?;
+            out.set_item("attributes", &v.attributes)?;
         }
         Entity::Dimension(v) => {
             out.set_item("line", line_to_pydict(py, &v.line)?)?;
@@ -281,6 +669,18 @@ fn entity_to_pydict<'py>(
             }
             out.set_item("aux_points", aux_points)?;
         }
+        Entity::Polyline(v) => {
+            let vertices = PyList::empty_bound(py);
+            for vertex in &v.vertices {
+                let vertex_dict = PyDict::new_bound(py);
+                vertex_dict.set_item("x", vertex.point.x)?;
+                vertex_dict.set_item("y", vertex.point.y)?;
+                vertex_dict.set_item("bulge", vertex.bulge)?;
+                vertices.append(vertex_dict)?;
+            }
+            out.set_item("vertices", vertices)?;
+            out.set_item("closed", v.closed)?;
+        }
     }
 
     Ok(out)
@@ -435,6 +835,30 @@ fn dxf_entity_to_pydict<'py>(py: Python<'py>, entity: &DxfEntity) -> PyResult<Bo
             out.set_item("rotation", v.rotation)?;
             out.set_item("content", &v.content)?;
             out.set_item("style", &v.style)?;
+            out.set_item("width_factor", v.width_factor)?;
+            out.set_item("mirrored", v.mirrored)?;
+            out.set_item(
+                "horizontal_alignment",
+                v.alignment.horizontal.group_code_value(),
+            )?;
+            out.set_item(
+                "vertical_alignment",
+                v.alignment.vertical.group_code_value(),
+            )?;
+        }
+        DxfEntity::MText(v) => {
+            out.set_item("layer", &v.layer)?;
+            out.set_item("color", v.color)?;
+            out.set_item("line_type", &v.line_type)?;
+            out.set_item("x", v.x)?;
+            out.set_item("y", v.y)?;
+            out.set_item("height", v.height)?;
+            out.set_item("width", v.width)?;
+            out.set_item("rotation", v.rotation)?;
+            out.set_item("attachment_point", v.attachment_point)?;
+            out.set_item("content", &v.content)?;
+            out.set_item("style", &v.style)?;
+            out.set_item("tracking", v.tracking)?;
         }
         DxfEntity::Solid(v) => {
             out.set_item("layer", &v.layer)?;
@@ -449,6 +873,34 @@ fn dxf_entity_to_pydict<'py>(py: Python<'py>, entity: &DxfEntity) -> PyResult<Bo
             out.set_item("x4", v.x4)?;
             out.set_item("y4", v.y4)?;
         }
+        DxfEntity::Face3D(v) => {
+            out.set_item("layer", &v.layer)?;
+            out.set_item("color", v.color)?;
+            out.set_item("line_type", &v.line_type)?;
+            out.set_item("x1", v.x1)?;
+            out.set_item("y1", v.y1)?;
+            out.set_item("x2", v.x2)?;
+            out.set_item("y2", v.y2)?;
+            out.set_item("x3", v.x3)?;
+            out.set_item("y3", v.y3)?;
+            out.set_item("x4", v.x4)?;
+            out.set_item("y4", v.y4)?;
+        }
+        DxfEntity::LwPolyline(v) => {
+            out.set_item("layer", &v.layer)?;
+            out.set_item("color", v.color)?;
+            out.set_item("line_type", &v.line_type)?;
+            let vertices = PyList::empty_bound(py);
+            for vertex in &v.vertices {
+                let vertex_dict = PyDict::new_bound(py);
+                vertex_dict.set_item("x", vertex.x)?;
+                vertex_dict.set_item("y", vertex.y)?;
+                vertex_dict.set_item("bulge", vertex.bulge)?;
+                vertices.append(vertex_dict)?;
+            }
+            out.set_item("vertices", vertices)?;
+            out.set_item("closed", v.closed)?;
+        }
         DxfEntity::Insert(v) => {
             out.set_item("layer", &v.layer)?;
             out.set_item("color", v.color)?;
@@ -459,6 +911,32 @@ fn dxf_entity_to_pydict<'py>(py: Python<'py>, entity: &DxfEntity) -> PyResult<Bo
             out.set_item("scale_x", v.scale_x)?;
             out.set_item("scale_y", v.scale_y)?;
             out.set_item("rotation", v.rotation)?;
+            let attributes = PyList::empty_bound(py);
+            for attribute in &v.attributes {
+                let attribute_dict = PyDict::new_bound(py);
+                attribute_dict.set_item("tag", &attribute.tag)?;
+                attribute_dict.set_item("value", &attribute.value)?;
+                attribute_dict.set_item("x", attribute.x)?;
+                attribute_dict.set_item("y", attribute.y)?;
+                attribute_dict.set_item("height", attribute.height)?;
+                attribute_dict.set_item("rotation", attribute.rotation)?;
+                attributes.append(attribute_dict)?;
+            }
+            out.set_item("attributes", attributes)?;
+        }
+        DxfEntity::Image(v) => {
+            out.set_item("layer", &v.layer)?;
+            out.set_item("color", v.color)?;
+            out.set_item("line_type", &v.line_type)?;
+            out.set_item("path", &v.path)?;
+            out.set_item("x", v.x)?;
+            out.set_item("y", v.y)?;
+            out.set_item("u_x", v.u_x)?;
+            out.set_item("u_y", v.u_y)?;
+            out.set_item("v_x", v.v_x)?;
+            out.set_item("v_y", v.v_y)?;
+            out.set_item("pixel_width", v.pixel_width)?;
+            out.set_item("pixel_height", v.pixel_height)?;
         }
     }
 
@@ -476,6 +954,32 @@ fn entity_counts_to_pydict<'py>(
     Ok(out)
 }
 
+// Keyed by the default DXF layer name ("G-L" in hex) so the keys stay stable
+// whatever the layers are called.
+fn counts_by_layer_to_pydict<'py>(
+    py: Python<'py>,
+    document: &JwwDocument,
+) -> PyResult<Bound<'py, PyDict>> {
+    let mut counts = entity_counts_by_layer(&document.entities);
+    for block_def in &document.block_defs {
+        for (layer, by_type) in entity_counts_by_layer(&block_def.entities) {
+            let merged = counts.entry(layer).or_default();
+            for (entity_type, count) in by_type {
+                *merged.entry(entity_type).or_insert(0) += count;
+            }
+        }
+    }
+
+    let out = PyDict::new_bound(py);
+    for ((layer_group, layer), by_type) in counts {
+        out.set_item(
+            format!("{layer_group:X}-{layer:X}"),
+            entity_counts_to_pydict(py, by_type)?,
+        )?;
+    }
+    Ok(out)
+}
+
 fn block_def_to_pydict<'py>(
     py: Python<'py>,
     block_def: &BlockDef,
@@ -484,6 +988,7 @@ fn block_def_to_pydict<'py>(
     let out = PyDict::new_bound(py);
     out.set_item("number", block_def.number)?;
     out.set_item("is_referenced", block_def.is_referenced)?;
+    out.set_item("created_time", block_def.created_time)?;
     out.set_item("name", &block_def.name)?;
 
     let base = &block_def.base;
@@ -495,6 +1000,11 @@ fn block_def_to_pydict<'py>(
     base_dict.set_item("layer", base.layer)?;
     base_dict.set_item("layer_group", base.layer_group)?;
     base_dict.set_item("flag", base.flag)?;
+    base_dict.set_item(
+        "flags",
+        py_types::entity_flags_to_pydict(py, &base.flags())?,
+    )?;
+    base_dict.set_item("seq", base.seq)?;
     out.set_item("base", base_dict)?;
 
     let entities = PyList::empty_bound(py);
@@ -524,21 +1034,7 @@ fn block_reference_validation_to_pydict<'py>(
     out.set_item("total_references", validation.total_references)?;
     out.set_item("resolved_references", validation.resolved_references)?;
     out.set_item("unresolved_def_numbers", &validation.unresolved_def_numbers)?;
+    out.set_item("referenced_def_numbers", &validation.referenced_def_numbers)?;
     out.set_item("has_unresolved", validation.has_unresolved())?;
     Ok(out)
 }
-
-/// A Python module implemented in Rust. The name of this function must match
-/// the `lib.name` setting in the `Cargo.toml`, else Python will not be able to
-/// import the module.
-#[pymodule]
-fn _core(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(hello_from_bin, m)?)?;
-    m.add_function(wrap_pyfunction!(is_jww_file, m)?)?;
-    m.add_function(wrap_pyfunction!(read_header, m)?)?;
-    m.add_function(wrap_pyfunction!(read_document, m)?)?;
-    m.add_function(wrap_pyfunction!(read_dxf_document, m)?)?;
-    m.add_function(wrap_pyfunction!(read_dxf_string, m)?)?;
-    m.add_function(wrap_pyfunction!(write_dxf, m)?)?;
-    Ok(())
-}
@@ -1,6 +1,17 @@
+use std::collections::{BTreeSet, HashMap};
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::geometry::point_in_polygon;
 use crate::header::JwwHeader;
 
+const MAX_EXPANDED_BLOCK_NESTING: usize = 32;
+// Jw_cad draws construction geometry in pen 9 or line type 9 and never
+// plots it.
+const CONSTRUCTION_PEN: u16 = 9;
+const CONSTRUCTION_PEN_STYLE: u8 = 9;
+
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityBase {
     pub group: u32,
     pub pen_style: u8,
@@ -9,9 +20,54 @@ pub struct EntityBase {
     pub layer: u16,
     pub layer_group: u16,
     pub flag: u16,
+    /// Position of the entity in the list it was parsed from, so repeated
+    /// parses of the same file agree on it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seq: usize,
+}
+
+impl EntityBase {
+    pub fn flags(&self) -> EntityFlags {
+        EntityFlags::from_bits(self.flag)
+    }
+}
+
+const FLAG_FIGURE: u16 = 0x0010;
+const FLAG_HATCH: u16 = 0x0020;
+const FLAG_DIMENSION: u16 = 0x0040;
+const FLAG_FITTING: u16 = 0x0080;
+
+/// The attribute bits of [`EntityBase::flag`] that Jw_cad's attribute
+/// selection works on. The high byte varies with the entity class and is
+/// left undecoded. Visibility and edit protection are not entity flags in
+/// JWW; they come from the layer and layer group states. Only solids use a
+/// low bit of their own (outline vs. fill); no fill or line-end bit has been
+/// seen on arc records, so arcs always convert as outlines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntityFlags {
+    /// Part of a placed figure (図形属性).
+    pub figure: bool,
+    /// Drawn by the hatching command (ハッチ属性).
+    pub hatch: bool,
+    /// Part of a dimension (寸法属性).
+    pub dimension: bool,
+    /// Part of a door or window fitting (建具属性).
+    pub fitting: bool,
+}
+
+impl EntityFlags {
+    pub fn from_bits(flag: u16) -> Self {
+        Self {
+            figure: flag & FLAG_FIGURE != 0,
+            hatch: flag & FLAG_HATCH != 0,
+            dimension: flag & FLAG_DIMENSION != 0,
+            fitting: flag & FLAG_FITTING != 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Coord2D {
     pub x: f64,
     pub y: f64,
@@ -23,6 +79,135 @@ impl Coord2D {
     }
 }
 
+/// 2D affine map `(x, y) -> (a*x + c*y + tx, b*x + d*y + ty)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Affine2D {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub tx: f64,
+    pub ty: f64,
+}
+
+impl Default for Affine2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Affine2D {
+    pub const fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    pub const fn translation(dx: f64, dy: f64) -> Self {
+        Self {
+            tx: dx,
+            ty: dy,
+            ..Self::identity()
+        }
+    }
+
+    /// Counter-clockwise rotation about the origin, in radians.
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            ..Self::identity()
+        }
+    }
+
+    pub const fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            a: sx,
+            d: sy,
+            ..Self::identity()
+        }
+    }
+
+    /// Placement of a block definition's contents by `block`: scale, then
+    /// rotation, then the move to the reference point.
+    pub(crate) fn from_insert(block: &Block) -> Self {
+        let (sin, cos) = block.rotation.sin_cos();
+        Self {
+            a: cos * block.scale_x,
+            b: sin * block.scale_x,
+            c: -sin * block.scale_y,
+            d: cos * block.scale_y,
+            tx: block.ref_x,
+            ty: block.ref_y,
+        }
+    }
+
+    /// Applies `self` first and `next` second.
+    pub fn then(&self, next: &Self) -> Self {
+        Self {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            tx: next.a * self.tx + next.c * self.ty + next.tx,
+            ty: next.b * self.tx + next.d * self.ty + next.ty,
+        }
+    }
+
+    pub fn apply(&self, point: Coord2D) -> Coord2D {
+        let (x, y) = self.apply_point(point.x, point.y);
+        Coord2D::new(x, y)
+    }
+
+    pub(crate) fn apply_point(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    fn apply_xy(&self, x: &mut f64, y: &mut f64) {
+        (*x, *y) = self.apply_point(*x, *y);
+    }
+
+    pub(crate) fn apply_vector(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.a * x + self.c * y, self.b * x + self.d * y)
+    }
+
+    /// Direction (radians) and length of the unit vector at `angle` after mapping.
+    pub(crate) fn map_direction(&self, angle: f64) -> (f64, f64) {
+        let (x, y) = self.apply_vector(angle.cos(), angle.sin());
+        (y.atan2(x), x.hypot(y))
+    }
+
+    /// Direction (radians) the x axis takes after mapping.
+    pub(crate) fn rotation_angle(&self) -> f64 {
+        self.b.atan2(self.a)
+    }
+
+    /// Mean length of the mapped unit axes.
+    pub(crate) fn average_scale(&self) -> f64 {
+        let sx = (self.a * self.a + self.b * self.b).sqrt();
+        let sy = (self.c * self.c + self.d * self.d).sqrt();
+        (sx + sy) / 2.0
+    }
+
+    /// Whether the transform flips handedness, e.g. an insert with one
+    /// negative scale.
+    pub(crate) fn is_mirroring(&self) -> bool {
+        self.a * self.d - self.b * self.c < 0.0
+    }
+}
+
 pub fn coordinates_bbox(points: &[Coord2D]) -> Option<(Coord2D, Coord2D)> {
     let first = points.first().copied()?;
     let mut min_x = first.x;
@@ -41,6 +226,7 @@ pub fn coordinates_bbox(points: &[Coord2D]) -> Option<(Coord2D, Coord2D)> {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
     pub base: EntityBase,
     pub start_x: f64,
@@ -49,20 +235,123 @@ pub struct Line {
     pub end_y: f64,
 }
 
+impl Line {
+    fn transform(&mut self, transform: &Affine2D) {
+        transform.apply_xy(&mut self.start_x, &mut self.start_y);
+        transform.apply_xy(&mut self.end_x, &mut self.end_y);
+    }
+}
+
+/// `CDataEnko` record. JWW has no separate ellipse class: ellipses and
+/// elliptical arcs are circles squashed by `flatness` (minor/major ratio)
+/// along the axis at `tilt_angle`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Arc {
     pub base: EntityBase,
     pub center_x: f64,
     pub center_y: f64,
     pub radius: f64,
+    /// Radians from the tilt axis, not the x axis. On an ellipse this is the
+    /// eccentric anomaly of the start point rather than its direction.
     pub start_angle: f64,
     pub arc_angle: f64,
     pub tilt_angle: f64,
+    /// Ratio of the axis perpendicular to `tilt_angle` to `radius`; 1.0 for a
+    /// circle. It may exceed 1.0, in which case the tilted axis is the minor one.
     pub flatness: f64,
     pub is_full_circle: bool,
 }
 
+impl Arc {
+    /// JWW sweeps from `start_angle` by `arc_angle`, clockwise when the
+    /// sweep is negative.
+    pub fn is_clockwise(&self) -> bool {
+        self.arc_angle < 0.0
+    }
+
+    /// The sweep as a counterclockwise `(from, to)` pair of angles, the
+    /// direction DXF arcs are always drawn in: a clockwise arc runs from its
+    /// end back to its start. Like `start_angle`, both are measured from the
+    /// tilt axis.
+    pub fn counterclockwise_angles(&self) -> (f64, f64) {
+        let end = self.start_angle + self.arc_angle;
+        if self.is_clockwise() {
+            (end, self.start_angle)
+        } else {
+            (self.start_angle, end)
+        }
+    }
+
+    pub fn start_point(&self) -> Coord2D {
+        self.point_at_angle(self.start_angle)
+    }
+
+    pub fn end_point(&self) -> Coord2D {
+        self.point_at_angle(self.start_angle + self.arc_angle)
+    }
+
+    /// Point on the arc's ellipse at `angle`, measured from the tilt axis the
+    /// way JWW gives arc ends: the eccentric anomaly, which on a circle is
+    /// just the angle from the tilt axis.
+    pub fn point_at_angle(&self, angle: f64) -> Coord2D {
+        let (sin_tilt, cos_tilt) = self.tilt_angle.sin_cos();
+        let u = self.radius * angle.cos();
+        let v = self.radius * self.flatness * angle.sin();
+        Coord2D::new(
+            self.center_x + u * cos_tilt - v * sin_tilt,
+            self.center_y + u * sin_tilt + v * cos_tilt,
+        )
+    }
+
+    /// Bounds of the drawn sweep: its end points plus every axis-aligned
+    /// extreme of the ellipse the sweep passes through.
+    pub fn bounding_box(&self) -> (Coord2D, Coord2D) {
+        let (a, b) = (self.radius, self.radius * self.flatness);
+        let (sin_tilt, cos_tilt) = self.tilt_angle.sin_cos();
+        let x_extreme = (-b * sin_tilt).atan2(a * cos_tilt);
+        let y_extreme = (b * cos_tilt).atan2(a * sin_tilt);
+        let extremes = [x_extreme, x_extreme + PI, y_extreme, y_extreme + PI];
+
+        let full = self.is_full_circle || self.arc_angle.abs() >= TAU;
+        let (from, to) = self.counterclockwise_angles();
+        let sweep = to - from;
+
+        let mut points = vec![self.point_at_angle(from), self.point_at_angle(to)];
+        for angle in extremes {
+            if full || (angle - from).rem_euclid(TAU) <= sweep {
+                points.push(self.point_at_angle(angle));
+            }
+        }
+        coordinates_bbox(&points).expect("arc bounds always have points")
+    }
+
+    // Start angles are measured from the tilt axis (as eccentric anomalies on
+    // an ellipse), so they carry through the transform unchanged unless it
+    // mirrors, which keeps them exact for similarity transforms.
+    fn transform(&mut self, transform: &Affine2D) {
+        let circular = self.flatness == 1.0;
+
+        transform.apply_xy(&mut self.center_x, &mut self.center_y);
+        let (tilt, major) = transform.map_direction(self.tilt_angle);
+        let (_, minor) = transform.map_direction(self.tilt_angle + FRAC_PI_2);
+        self.tilt_angle = tilt;
+        self.radius *= major;
+        if major > 0.0 {
+            self.flatness *= minor / major;
+        }
+        if circular && (self.flatness - 1.0).abs() < 1e-12 {
+            self.flatness = 1.0;
+        }
+
+        if transform.is_mirroring() {
+            self.start_angle = -(self.start_angle + self.arc_angle);
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub base: EntityBase,
     pub x: f64,
@@ -73,7 +362,16 @@ pub struct Point {
     pub scale: f64,
 }
 
+impl Point {
+    fn transform(&mut self, transform: &Affine2D) {
+        transform.apply_xy(&mut self.x, &mut self.y);
+        let (angle, _) = transform.map_direction(self.angle.to_radians());
+        self.angle = angle.to_degrees();
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Text {
     pub base: EntityBase,
     pub start_x: f64,
@@ -89,7 +387,54 @@ pub struct Text {
     pub content: String,
 }
 
+// Jw_cad stores a placed raster image as a text whose content is this
+// prefix followed by `path,width,height` and optional trimming fields.
+const IMAGE_TEXT_PREFIX: &str = "^@BM";
+
+/// A raster image Jw_cad placed in the drawing. The text's start point is the
+/// image's lower-left corner and its angle the image rotation.
 #[derive(Debug, Clone, PartialEq)]
+pub struct ImageRef {
+    pub path: String,
+    /// Size on the drawing, in drawing units.
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Text {
+    /// The image this text stands for, if it is a `^@BM` image record with a
+    /// path and a positive size.
+    pub fn image_ref(&self) -> Option<ImageRef> {
+        let mut fields = self.content.strip_prefix(IMAGE_TEXT_PREFIX)?.split(',');
+        let path = fields.next()?.trim();
+        let width = fields.next()?.trim().parse::<f64>().ok()?;
+        let height = fields.next()?.trim().parse::<f64>().ok()?;
+        let valid = |v: f64| v.is_finite() && v > 0.0;
+        if path.is_empty() || !valid(width) || !valid(height) {
+            return None;
+        }
+        Some(ImageRef {
+            path: path.to_string(),
+            width,
+            height,
+        })
+    }
+
+    fn transform(&mut self, transform: &Affine2D) {
+        transform.apply_xy(&mut self.start_x, &mut self.start_y);
+        transform.apply_xy(&mut self.end_x, &mut self.end_y);
+        let angle = self.angle.to_radians();
+        let (mapped, x_scale) = transform.map_direction(angle);
+        let (_, y_scale) = transform.map_direction(angle + FRAC_PI_2);
+        self.angle = mapped.to_degrees();
+        self.size_x *= x_scale;
+        self.size_y *= y_scale;
+        self.spacing *= x_scale;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Solid {
     pub base: EntityBase,
     pub point1_x: f64,
@@ -100,10 +445,89 @@ pub struct Solid {
     pub point3_y: f64,
     pub point4_x: f64,
     pub point4_y: f64,
+    /// Custom color of a pen-10 solid as a Windows COLORREF (0x00BBGGRR).
     pub color: Option<u32>,
+    /// False for an outline-only solid, drawn as its four edges.
+    #[cfg_attr(feature = "serde", serde(default = "default_filled"))]
+    pub is_filled: bool,
+}
+
+#[cfg(feature = "serde")]
+fn default_filled() -> bool {
+    true
+}
+
+impl Solid {
+    /// Corners in JWW record order, which walks the outline: the fields
+    /// follow Jw_cad's names, so that is point 1, 4, 2, 3.
+    pub fn outline(&self) -> [Coord2D; 4] {
+        [
+            Coord2D::new(self.point1_x, self.point1_y),
+            Coord2D::new(self.point4_x, self.point4_y),
+            Coord2D::new(self.point2_x, self.point2_y),
+            Coord2D::new(self.point3_x, self.point3_y),
+        ]
+    }
+
+    fn set_outline(&mut self, outline: [Coord2D; 4]) {
+        let [a, b, c, d] = outline;
+        (self.point1_x, self.point1_y) = (a.x, a.y);
+        (self.point4_x, self.point4_y) = (b.x, b.y);
+        (self.point2_x, self.point2_y) = (c.x, c.y);
+        (self.point3_x, self.point3_y) = (d.x, d.y);
+    }
+
+    /// Shoelace area of the outline; positive when wound counter-clockwise.
+    pub fn signed_area(&self) -> f64 {
+        let outline = self.outline();
+        let mut twice_area = 0.0;
+        for i in 0..4 {
+            let a = outline[i];
+            let b = outline[(i + 1) % 4];
+            twice_area += a.x * b.y - b.x * a.y;
+        }
+        twice_area / 2.0
+    }
+
+    /// Untangles a bow-tie outline and rewinds it counter-clockwise. Returns
+    /// whether any corner moved.
+    pub fn repair(&mut self) -> bool {
+        let original = self.outline();
+        let [p1, p2, p3, p4] = original;
+        let mut outline = if segments_cross(p2, p3, p4, p1) {
+            [p1, p2, p4, p3]
+        } else if segments_cross(p1, p2, p3, p4) {
+            [p1, p3, p2, p4]
+        } else {
+            original
+        };
+        self.set_outline(outline);
+
+        if self.signed_area() < 0.0 {
+            let [p1, p2, p3, p4] = outline;
+            // Triangles keep their doubled corner last, which is where the
+            // DXF writer expects it.
+            outline = if p3 == p4 {
+                [p1, p3, p2, p2]
+            } else {
+                [p1, p4, p3, p2]
+            };
+            self.set_outline(outline);
+        }
+        outline != original
+    }
+}
+
+fn segments_cross(a: Coord2D, b: Coord2D, c: Coord2D, d: Coord2D) -> bool {
+    let orient =
+        |p: Coord2D, q: Coord2D, r: Coord2D| (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+    let (d1, d2) = (orient(a, b, c), orient(a, b, d));
+    let (d3, d4) = (orient(c, d, a), orient(c, d, b));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub base: EntityBase,
     pub ref_x: f64,
@@ -112,9 +536,15 @@ pub struct Block {
     pub scale_y: f64,
     pub rotation: f64,
     pub def_number: u32,
+    /// Per-insert attribute overrides as `(tag, value)` pairs, written to
+    /// DXF as ATTRIBs. No JWW record layout for them is known, so parsed
+    /// inserts leave this empty and only documents built in code fill it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub attributes: Vec<(String, String)>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dimension {
     pub base: EntityBase,
     pub line: Line,
@@ -124,16 +554,51 @@ pub struct Dimension {
     pub aux_points: Vec<Point>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolyVertex {
+    pub point: Coord2D,
+    /// Tangent of a quarter of the included angle of the segment starting at
+    /// this vertex; 0.0 for a straight segment, negative for clockwise arcs.
+    pub bulge: f64,
+}
+
+impl PolyVertex {
+    pub const fn new(point: Coord2D, bulge: f64) -> Self {
+        Self { point, bulge }
+    }
+}
+
+// JWW has no native polyline class; this variant is built programmatically
+// (e.g. by downstream tools) so curved outlines survive DXF conversion.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Polyline {
+    pub base: EntityBase,
+    pub vertices: Vec<PolyVertex>,
+    pub closed: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockDef {
     pub base: EntityBase,
     pub number: u32,
     pub is_referenced: bool,
+    /// MFC `CTime` of the definition: seconds since 1970-01-01 UTC, or 0
+    /// when the file does not record it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub created_time: u32,
     pub name: String,
     pub entities: Vec<Entity>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")
+)]
 pub enum Entity {
     Line(Line),
     Arc(Arc),
@@ -142,6 +607,7 @@ pub enum Entity {
     Solid(Solid),
     Block(Block),
     Dimension(Dimension),
+    Polyline(Polyline),
 }
 
 impl Entity {
@@ -160,6 +626,7 @@ impl Entity {
             Self::Solid(_) => "SOLID",
             Self::Block(_) => "BLOCK",
             Self::Dimension(_) => "DIMENSION",
+            Self::Polyline(_) => "POLYLINE",
         }
     }
 
@@ -172,9 +639,46 @@ impl Entity {
             Self::Solid(v) => &v.base,
             Self::Block(v) => &v.base,
             Self::Dimension(v) => &v.base,
+            Self::Polyline(v) => &v.base,
         }
     }
 
+    pub fn base_mut(&mut self) -> &mut EntityBase {
+        match self {
+            Self::Line(v) => &mut v.base,
+            Self::Arc(v) => &mut v.base,
+            Self::Point(v) => &mut v.base,
+            Self::Text(v) => &mut v.base,
+            Self::Solid(v) => &mut v.base,
+            Self::Block(v) => &mut v.base,
+            Self::Dimension(v) => &mut v.base,
+            Self::Polyline(v) => &mut v.base,
+        }
+    }
+
+    /// Whether Jw_cad would plot the entity: its layer and layer group are
+    /// not hidden, it is not drawn with the construction (補助) pen color or
+    /// line type, and it is not a temporary point. The entity `flag` bits
+    /// carry no print state, so they are not consulted.
+    pub fn is_printable(&self, doc: &JwwDocument) -> bool {
+        let base = self.base();
+        if base.pen_color == CONSTRUCTION_PEN || base.pen_style == CONSTRUCTION_PEN_STYLE {
+            return false;
+        }
+        if matches!(self, Self::Point(point) if point.is_temporary) {
+            return false;
+        }
+        // Slots outside the header are not hidden by anything.
+        let Some(group) = doc.header.layer_groups.get(base.layer_group as usize) else {
+            return true;
+        };
+        let layer_hidden = group
+            .layers
+            .get(base.layer as usize)
+            .is_some_and(|layer| layer.state == 0);
+        group.state != 0 && !layer_hidden
+    }
+
     // Common extraction helper for downstream converters (e.g. DXF writer).
     // Returns control-like points that are explicit in each entity payload.
     pub fn common_coordinates(&self) -> Vec<Coord2D> {
@@ -212,6 +716,63 @@ impl Entity {
                 }
                 points
             }
+            Self::Polyline(v) => v.vertices.iter().map(|vertex| vertex.point).collect(),
+        }
+    }
+
+    /// Returns a copy with every coordinate mapped through `transform`.
+    /// Angles, sizes and insert scales follow the mapped axes; a mirroring
+    /// transform reverses arc sweeps and polyline bulges. Under a non-uniform
+    /// scale an arc keeps its tilt axis, so skewed ellipses are approximated.
+    pub fn transformed(&self, transform: &Affine2D) -> Entity {
+        let mut entity = self.clone();
+        entity.transform(transform);
+        entity
+    }
+
+    fn transform(&mut self, transform: &Affine2D) {
+        match self {
+            Self::Line(v) => v.transform(transform),
+            Self::Arc(v) => v.transform(transform),
+            Self::Point(v) => v.transform(transform),
+            Self::Text(v) => v.transform(transform),
+            Self::Solid(v) => {
+                transform.apply_xy(&mut v.point1_x, &mut v.point1_y);
+                transform.apply_xy(&mut v.point2_x, &mut v.point2_y);
+                transform.apply_xy(&mut v.point3_x, &mut v.point3_y);
+                transform.apply_xy(&mut v.point4_x, &mut v.point4_y);
+            }
+            Self::Block(v) => {
+                transform.apply_xy(&mut v.ref_x, &mut v.ref_y);
+                let (x_angle, x_scale) = transform.map_direction(v.rotation);
+                let (_, y_scale) = transform.map_direction(v.rotation + FRAC_PI_2);
+                v.rotation = x_angle;
+                v.scale_x *= x_scale;
+                v.scale_y *= if transform.is_mirroring() {
+                    -y_scale
+                } else {
+                    y_scale
+                };
+            }
+            Self::Dimension(v) => {
+                v.line.transform(transform);
+                v.text.transform(transform);
+                for line in &mut v.aux_lines {
+                    line.transform(transform);
+                }
+                for point in &mut v.aux_points {
+                    point.transform(transform);
+                }
+            }
+            Self::Polyline(v) => {
+                let mirroring = transform.is_mirroring();
+                for vertex in &mut v.vertices {
+                    vertex.point = transform.apply(vertex.point);
+                    if mirroring {
+                        vertex.bulge = -vertex.bulge;
+                    }
+                }
+            }
         }
     }
 
@@ -219,18 +780,175 @@ impl Entity {
         self.common_coordinates().into_iter().next()
     }
 
+    /// Arcs are bounded by their drawn sweep rather than their center.
     pub fn common_coordinate_bbox(&self) -> Option<(Coord2D, Coord2D)> {
-        coordinates_bbox(&self.common_coordinates())
+        match self {
+            Self::Arc(arc) => Some(arc.bounding_box()),
+            _ => coordinates_bbox(&self.common_coordinates()),
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct JwwDocument {
     pub header: JwwHeader,
     pub entities: Vec<Entity>,
     pub block_defs: Vec<BlockDef>,
 }
 
+impl JwwDocument {
+    /// Top-level entities whose first [`Entity::common_coordinates`] point
+    /// lies inside `polygon`, such as the entities of one room. Block inserts
+    /// are placed by their reference point and not expanded.
+    pub fn entities_in_region(&self, polygon: &[Coord2D]) -> Vec<&Entity> {
+        self.entities
+            .iter()
+            .filter(|entity| {
+                entity
+                    .common_coordinates()
+                    .first()
+                    .is_some_and(|&point| point_in_polygon(point, polygon))
+            })
+            .collect()
+    }
+
+    /// Box around everything the drawing draws, block inserts expanded like
+    /// [`expanded_bbox`].
+    pub(crate) fn expanded_bbox(&self) -> Option<(Coord2D, Coord2D)> {
+        let block_defs = self
+            .block_defs
+            .iter()
+            .map(|block_def| (block_def.number, block_def))
+            .collect::<HashMap<u32, &BlockDef>>();
+        expanded_bbox(&self.entities, &block_defs)
+    }
+}
+
+/// Normalizes every SOLID, including those inside block definitions, to a
+/// simple counter-clockwise outline. The DXF corner order is still applied by
+/// the writer. Returns how many solids were changed.
+pub fn repair_solids(doc: &mut JwwDocument) -> usize {
+    let block_entities = doc
+        .block_defs
+        .iter_mut()
+        .flat_map(|block_def| block_def.entities.iter_mut());
+    let mut repaired = 0;
+    for entity in doc.entities.iter_mut().chain(block_entities) {
+        if let Entity::Solid(solid) = entity {
+            if solid.repair() {
+                repaired += 1;
+            }
+        }
+    }
+    repaired
+}
+
+/// Copies the top-level entities on one layer into a new document that keeps
+/// the full header, so layer names still resolve. Block definitions reached
+/// from the extracted inserts, directly or through nesting, come along.
+pub fn extract_layer(doc: &JwwDocument, layer_group: u16, layer: u16) -> JwwDocument {
+    let entities = doc
+        .entities
+        .iter()
+        .filter(|entity| {
+            let base = entity.base();
+            base.layer_group == layer_group && base.layer == layer
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut referenced = BTreeSet::<u32>::new();
+    let mut pending = block_refs(&entities);
+    while let Some(number) = pending.pop() {
+        if !referenced.insert(number) {
+            continue;
+        }
+        for block_def in doc.block_defs.iter().filter(|def| def.number == number) {
+            pending.extend(block_refs(&block_def.entities));
+        }
+    }
+
+    JwwDocument {
+        header: doc.header.clone(),
+        entities,
+        block_defs: doc
+            .block_defs
+            .iter()
+            .filter(|block_def| referenced.contains(&block_def.number))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Renumbers block definitions to `0..N` in their current order and rewrites
+/// every insert, nested ones included, to match. Inserts of numbers with no
+/// definition get fresh numbers after `N`, so they stay unresolved rather than
+/// landing on an unrelated definition.
+pub fn renumber_block_defs(doc: &mut JwwDocument) {
+    let mut renumbered = HashMap::<u32, u32>::with_capacity(doc.block_defs.len());
+    for (index, block_def) in doc.block_defs.iter_mut().enumerate() {
+        let number = index as u32;
+        renumbered.entry(block_def.number).or_insert(number);
+        block_def.number = number;
+    }
+
+    let mut next_number = doc.block_defs.len() as u32;
+    let block_entities = doc
+        .block_defs
+        .iter_mut()
+        .flat_map(|block_def| block_def.entities.iter_mut());
+    for entity in doc.entities.iter_mut().chain(block_entities) {
+        if let Entity::Block(block) = entity {
+            block.def_number = *renumbered.entry(block.def_number).or_insert_with(|| {
+                next_number += 1;
+                next_number - 1
+            });
+        }
+    }
+}
+
+fn block_refs(entities: &[Entity]) -> Vec<u32> {
+    entities
+        .iter()
+        .filter_map(|entity| match entity {
+            Entity::Block(block) => Some(block.def_number),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Decodes [`BlockDef::created_time`] into UTC
+/// `(year, month, day, hour, minute, second)`; `None` when it is unset.
+pub fn block_def_created_datetime(block_def: &BlockDef) -> Option<(i32, u32, u32, u32, u32, u32)> {
+    if block_def.created_time == 0 {
+        return None;
+    }
+    let seconds = i64::from(block_def.created_time);
+    let (days, secs_of_day) = (seconds / 86_400, (seconds % 86_400) as u32);
+
+    // Days-to-civil conversion over 400-year eras starting on March 1st.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+
+    Some((
+        year,
+        month,
+        day,
+        secs_of_day / 3_600,
+        secs_of_day % 3_600 / 60,
+        secs_of_day % 60,
+    ))
+}
+
 pub fn collect_entity_coordinates(entities: &[Entity]) -> Vec<Coord2D> {
     let mut points = Vec::<Coord2D>::new();
     for entity in entities {
@@ -239,12 +957,134 @@ pub fn collect_entity_coordinates(entities: &[Entity]) -> Vec<Coord2D> {
     points
 }
 
+/// Like [`collect_entity_coordinates`], but each block insert is replaced by
+/// its definition's geometry placed through the insert transform. Cyclic and
+/// overly deep references are skipped, as are inserts of missing definitions.
+pub fn collect_entity_coordinates_expanded(doc: &JwwDocument) -> Vec<Coord2D> {
+    let block_defs = doc
+        .block_defs
+        .iter()
+        .map(|block_def| (block_def.number, block_def))
+        .collect::<HashMap<u32, &BlockDef>>();
+    let mut points = Vec::<Coord2D>::new();
+    walk_expanded(
+        &doc.entities,
+        &block_defs,
+        &Affine2D::identity(),
+        &mut Vec::new(),
+        &mut |entity, transform| {
+            points.extend(
+                entity
+                    .common_coordinates()
+                    .into_iter()
+                    .map(|point| transform.apply(point)),
+            );
+        },
+    );
+    points
+}
+
+/// Box around the drawn geometry of `entities`, block inserts expanded like
+/// [`collect_entity_coordinates_expanded`]. Each entity is placed first and
+/// then bounded by [`Entity::common_coordinate_bbox`], so an arc counts its
+/// sweep rather than its center. Temporary points are never drawn and are
+/// left out.
+pub(crate) fn expanded_bbox(
+    entities: &[Entity],
+    block_defs: &HashMap<u32, &BlockDef>,
+) -> Option<(Coord2D, Coord2D)> {
+    let mut corners = Vec::new();
+    walk_expanded(
+        entities,
+        block_defs,
+        &Affine2D::identity(),
+        &mut Vec::new(),
+        &mut |entity, transform| {
+            if matches!(entity, Entity::Point(point) if point.is_temporary) {
+                return;
+            }
+            if let Some((min, max)) = entity.transformed(transform).common_coordinate_bbox() {
+                corners.extend([min, max]);
+            }
+        },
+    );
+    coordinates_bbox(&corners)
+}
+
+// Calls `visit` with every non-insert entity and the transform that places
+// it, descending into block definitions. Cyclic, overly deep and dangling
+// inserts are skipped.
+fn walk_expanded(
+    entities: &[Entity],
+    block_defs: &HashMap<u32, &BlockDef>,
+    transform: &Affine2D,
+    expanding_stack: &mut Vec<u32>,
+    visit: &mut impl FnMut(&Entity, &Affine2D),
+) {
+    for entity in entities {
+        let Entity::Block(block) = entity else {
+            visit(entity, transform);
+            continue;
+        };
+        if expanding_stack.len() >= MAX_EXPANDED_BLOCK_NESTING
+            || expanding_stack.contains(&block.def_number)
+        {
+            continue;
+        }
+        let Some(block_def) = block_defs.get(&block.def_number).copied() else {
+            continue;
+        };
+
+        let child_transform = Affine2D::from_insert(block).then(transform);
+        expanding_stack.push(block.def_number);
+        walk_expanded(
+            &block_def.entities,
+            block_defs,
+            &child_transform,
+            expanding_stack,
+            visit,
+        );
+        expanding_stack.pop();
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
+    use crate::header::JwwHeader;
+    use crate::parser::read_document_from_file;
+
     use super::{
-        collect_entity_coordinates, coordinates_bbox, Arc, Coord2D, Dimension, Entity, EntityBase,
-        Line, Point, Solid, Text,
+        block_def_created_datetime, block_refs, collect_entity_coordinates,
+        collect_entity_coordinates_expanded, coordinates_bbox, extract_layer, renumber_block_defs,
+        repair_solids, segments_cross, Affine2D, Arc, Block, BlockDef, Coord2D, Dimension, Entity,
+        EntityBase, EntityFlags, JwwDocument, Line, Point, Solid, Text,
     };
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    #[test]
+    fn entity_flags_decode_attribute_bits() {
+        // The most common flag on sample lines; the high byte is not an
+        // attribute bit.
+        let base = EntityBase {
+            flag: 0x4090,
+            ..EntityBase::default()
+        };
+        assert_eq!(
+            base.flags(),
+            EntityFlags {
+                figure: true,
+                hatch: false,
+                dimension: false,
+                fitting: true,
+            }
+        );
+        assert!(EntityFlags::from_bits(0x0020).hatch);
+        assert_eq!(EntityFlags::from_bits(0), EntityFlags::default());
+        assert!(EntityFlags::from_bits(0x0010).figure);
+        assert!(EntityFlags::from_bits(0x0040).dimension);
+    }
 
     #[test]
     fn line_common_coordinates_and_bbox() {
@@ -264,6 +1104,84 @@ mod tests {
         assert_eq!(max, Coord2D::new(4.0, 6.0));
     }
 
+    fn arc(start_angle: f64, arc_angle: f64, tilt_angle: f64, flatness: f64) -> Arc {
+        Arc {
+            base: EntityBase::default(),
+            center_x: 0.0,
+            center_y: 0.0,
+            radius: 10.0,
+            start_angle,
+            arc_angle,
+            tilt_angle,
+            flatness,
+            is_full_circle: arc_angle >= 2.0 * PI,
+        }
+    }
+
+    fn assert_coord_near(actual: Coord2D, x: f64, y: f64) {
+        assert!(
+            (actual.x - x).abs() < 1e-9 && (actual.y - y).abs() < 1e-9,
+            "{actual:?} != ({x}, {y})"
+        );
+    }
+
+    #[test]
+    fn quarter_arc_bbox_includes_crossed_extreme() {
+        let quarter = arc(PI / 4.0, FRAC_PI_2, 0.0, 1.0);
+        let half_diagonal = 10.0 / 2f64.sqrt();
+        assert_coord_near(quarter.start_point(), half_diagonal, half_diagonal);
+        assert_coord_near(quarter.end_point(), -half_diagonal, half_diagonal);
+
+        let (min, max) = quarter.bounding_box();
+        assert_coord_near(min, -half_diagonal, half_diagonal);
+        assert_coord_near(max, half_diagonal, 10.0);
+        assert_eq!(
+            Entity::Arc(quarter.clone()).common_coordinate_bbox(),
+            Some((min, max))
+        );
+
+        // The same sweep given clockwise from its other end.
+        let (cw_min, cw_max) = arc(3.0 * PI / 4.0, -FRAC_PI_2, 0.0, 1.0).bounding_box();
+        assert_coord_near(cw_min, -half_diagonal, half_diagonal);
+        assert_coord_near(cw_max, half_diagonal, 10.0);
+    }
+
+    #[test]
+    fn flattened_ellipse_bbox_follows_tilt() {
+        let (min, max) = arc(0.0, 2.0 * PI, FRAC_PI_2, 0.5).bounding_box();
+        assert_coord_near(min, -5.0, -10.0);
+        assert_coord_near(max, 5.0, 10.0);
+
+        // Tilted 45 degrees, both half extents are sqrt(a^2 + b^2) / sqrt(2).
+        let half_extent = (100.0f64 + 25.0).sqrt() / 2f64.sqrt();
+        let (min, max) = arc(0.0, 2.0 * PI, PI / 4.0, 0.5).bounding_box();
+        assert_coord_near(min, -half_extent, -half_extent);
+        assert_coord_near(max, half_extent, half_extent);
+
+        // A flattened arc's angles are eccentric anomalies: 45° lands on
+        // (a cos 45°, b sin 45°), not on the ellipse's 45° diagonal.
+        let flat = arc(PI / 4.0, FRAC_PI_4, 0.0, 0.5);
+        assert_coord_near(flat.start_point(), 10.0 / 2f64.sqrt(), 5.0 / 2f64.sqrt());
+        assert_coord_near(flat.end_point(), 0.0, 5.0);
+    }
+
+    #[test]
+    fn tilted_circular_arc_measures_angles_from_tilt_axis() {
+        // A quarter from the tilt axis at 90°: from (0, 10) round to (-10, 0).
+        let tilted = arc(0.0, FRAC_PI_2, FRAC_PI_2, 1.0);
+        assert_coord_near(tilted.start_point(), 0.0, 10.0);
+        assert_coord_near(tilted.end_point(), -10.0, 0.0);
+        assert_coord_near(
+            tilted.point_at_angle(FRAC_PI_4),
+            -10.0 / 2f64.sqrt(),
+            10.0 / 2f64.sqrt(),
+        );
+
+        let (min, max) = tilted.bounding_box();
+        assert_coord_near(min, -10.0, 0.0);
+        assert_coord_near(max, 0.0, 10.0);
+    }
+
     #[test]
     fn dimension_common_coordinates_include_aux() {
         let dim = Entity::Dimension(Dimension {
@@ -349,6 +1267,7 @@ mod tests {
                 point4_x: 0.0,
                 point4_y: 1.0,
                 color: None,
+                is_filled: true,
             }),
         ];
 
@@ -358,4 +1277,408 @@ mod tests {
         assert_eq!(min, Coord2D::new(-1.0, -2.0));
         assert_eq!(max, Coord2D::new(1.0, 2.0));
     }
+
+    #[test]
+    fn collect_entity_coordinates_expanded_places_nested_block_geometry() {
+        let insert = |ref_x: f64, rotation: f64, def_number: u32| {
+            Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let block_def = |number: u32, entities: Vec<Entity>| BlockDef {
+            base: EntityBase::default(),
+            number,
+            is_referenced: true,
+            created_time: 0,
+            name: format!("B{number}"),
+            entities,
+        };
+        let doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            entities: vec![insert(100.0, FRAC_PI_2, 1)],
+            block_defs: vec![
+                // Block 1 nests block 2 and also references itself.
+                block_def(1, vec![insert(10.0, 0.0, 2), insert(0.0, 0.0, 1)]),
+                block_def(
+                    2,
+                    vec![Entity::Line(Line {
+                        base: EntityBase::default(),
+                        start_x: 0.0,
+                        start_y: 0.0,
+                        end_x: 1.0,
+                        end_y: 0.0,
+                    })],
+                ),
+            ],
+        };
+
+        let points = collect_entity_coordinates_expanded(&doc);
+        assert_eq!(points.len(), 2);
+        let expected = [Coord2D::new(100.0, 10.0), Coord2D::new(100.0, 11.0)];
+        for (point, expected) in points.iter().zip(expected) {
+            assert!((point.x - expected.x).abs() < 1e-9, "{point:?}");
+            assert!((point.y - expected.y).abs() < 1e-9, "{point:?}");
+        }
+        assert!(collect_entity_coordinates(&doc.entities)
+            .iter()
+            .all(|point| point.y == 0.0));
+    }
+
+    #[test]
+    fn block_def_created_datetime_decodes_ctime() {
+        let mut block_def = BlockDef {
+            base: EntityBase::default(),
+            number: 1,
+            is_referenced: true,
+            created_time: 0,
+            name: "B1".to_string(),
+            entities: vec![],
+        };
+        assert_eq!(block_def_created_datetime(&block_def), None);
+
+        block_def.created_time = 951_830_445;
+        assert_eq!(
+            block_def_created_datetime(&block_def),
+            Some((2000, 2, 29, 13, 20, 45))
+        );
+        block_def.created_time = u32::MAX;
+        assert_eq!(
+            block_def_created_datetime(&block_def),
+            Some((2106, 2, 7, 6, 28, 15))
+        );
+    }
+
+    #[test]
+    fn renumber_block_defs_makes_numbers_contiguous() {
+        let insert = |def_number: u32| {
+            Entity::Block(Block {
+                base: EntityBase::default(),
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let block_def = |number: u32, entities: Vec<Entity>| BlockDef {
+            base: EntityBase::default(),
+            number,
+            is_referenced: true,
+            created_time: 0,
+            name: format!("B{number}"),
+            entities,
+        };
+        let mut doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            entities: vec![insert(99), insert(5), insert(1)],
+            block_defs: vec![block_def(5, vec![]), block_def(99, vec![insert(5)])],
+        };
+
+        renumber_block_defs(&mut doc);
+        let numbers = doc
+            .block_defs
+            .iter()
+            .map(|block_def| (block_def.number, block_def.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![(0, "B5"), (1, "B99")]);
+        // Block 1 has no definition and moves past the renumbered range.
+        assert_eq!(block_refs(&doc.entities), vec![1, 0, 2]);
+        assert_eq!(block_refs(&doc.block_defs[1].entities), vec![0]);
+    }
+
+    #[test]
+    fn repair_solids_rewinds_clockwise_outline() {
+        // Corners in record order: point 1, 4, 2, 3.
+        let solid = |corners: [(f64, f64); 4]| {
+            Entity::Solid(Solid {
+                base: EntityBase::default(),
+                point1_x: corners[0].0,
+                point1_y: corners[0].1,
+                point4_x: corners[1].0,
+                point4_y: corners[1].1,
+                point2_x: corners[2].0,
+                point2_y: corners[2].1,
+                point3_x: corners[3].0,
+                point3_y: corners[3].1,
+                color: None,
+                is_filled: true,
+            })
+        };
+        let mut doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            entities: vec![
+                // Clockwise square.
+                solid([(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]),
+                // Already counter-clockwise.
+                solid([(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]),
+                // Bow-tie: corners in DXF zigzag order.
+                solid([(0.0, 0.0), (10.0, 0.0), (0.0, 10.0), (10.0, 10.0)]),
+                // Clockwise triangle.
+                solid([(0.0, 0.0), (0.0, 10.0), (10.0, 0.0), (10.0, 0.0)]),
+            ],
+            block_defs: vec![],
+        };
+
+        assert_eq!(repair_solids(&mut doc), 3);
+
+        let outlines = doc
+            .entities
+            .iter()
+            .map(|entity| match entity {
+                Entity::Solid(solid) => {
+                    assert!(solid.signed_area() > 0.0, "{:?}", solid);
+                    solid.outline()
+                }
+                other => panic!("expected SOLID, got {:?}", other),
+            })
+            .collect::<Vec<_>>();
+        let square = [
+            Coord2D::new(0.0, 0.0),
+            Coord2D::new(10.0, 0.0),
+            Coord2D::new(10.0, 10.0),
+            Coord2D::new(0.0, 10.0),
+        ];
+        assert_eq!(outlines[0], square);
+        assert_eq!(outlines[1], square);
+        assert_eq!(outlines[2], square);
+        assert_eq!(outlines[3][2], outlines[3][3]);
+    }
+
+    #[test]
+    fn repair_solids_leaves_sample_solids_alone() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples/Ａマンション平面例.jww");
+        let mut doc = read_document_from_file(path).unwrap();
+        let original = doc.clone();
+        let outlines = |doc: &JwwDocument| {
+            doc.entities
+                .iter()
+                .chain(doc.block_defs.iter().flat_map(|def| def.entities.iter()))
+                .filter_map(|entity| match entity {
+                    Entity::Solid(solid) => Some(solid.outline()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(outlines(&doc).len(), 79);
+        for [a, b, c, d] in outlines(&doc) {
+            assert!(!segments_cross(a, b, c, d) && !segments_cross(b, c, d, a));
+        }
+
+        // They are already simple; only the winding may flip.
+        repair_solids(&mut doc);
+        for (repaired, original) in outlines(&doc).into_iter().zip(outlines(&original)) {
+            let mut reversed = original;
+            reversed[1..].reverse();
+            assert!(repaired == original || repaired == reversed);
+        }
+    }
+
+    #[test]
+    fn transformed_rotates_line_by_quarter_turn() {
+        let line = Entity::Line(Line {
+            base: EntityBase::default(),
+            start_x: 1.0,
+            start_y: 0.0,
+            end_x: 3.0,
+            end_y: 2.0,
+        });
+
+        let rotated = line.transformed(&Affine2D::rotation(FRAC_PI_2));
+        let Entity::Line(rotated) = rotated else {
+            panic!("expected line");
+        };
+        assert!(nearly_eq(rotated.start_x, 0.0) && nearly_eq(rotated.start_y, 1.0));
+        assert!(nearly_eq(rotated.end_x, -2.0) && nearly_eq(rotated.end_y, 3.0));
+    }
+
+    #[test]
+    fn transformed_rotates_arc_angles_and_tilt() {
+        let arc = |flatness: f64| {
+            Entity::Arc(Arc {
+                base: EntityBase::default(),
+                center_x: 10.0,
+                center_y: 0.0,
+                radius: 5.0,
+                start_angle: 0.25,
+                arc_angle: 1.0,
+                tilt_angle: 0.5,
+                flatness,
+                is_full_circle: false,
+            })
+        };
+        let transform = Affine2D::rotation(FRAC_PI_2).then(&Affine2D::translation(1.0, 1.0));
+
+        let Entity::Arc(circular) = arc(1.0).transformed(&transform) else {
+            panic!("expected arc");
+        };
+        assert!(nearly_eq(circular.center_x, 1.0) && nearly_eq(circular.center_y, 11.0));
+        assert!(nearly_eq(circular.radius, 5.0));
+        assert_eq!(circular.flatness, 1.0);
+        // Angles are measured from the tilt axis, which turns with the arc.
+        assert!(nearly_eq(circular.start_angle, 0.25));
+        assert!(nearly_eq(circular.tilt_angle, 0.5 + FRAC_PI_2));
+        assert!(nearly_eq(circular.arc_angle, 1.0));
+
+        let Entity::Arc(elliptical) = arc(0.5).transformed(&transform) else {
+            panic!("expected arc");
+        };
+        assert!(nearly_eq(elliptical.start_angle, 0.25));
+        assert!(nearly_eq(elliptical.tilt_angle, 0.5 + FRAC_PI_2));
+        assert!(nearly_eq(elliptical.flatness, 0.5));
+
+        // Mirroring across the y axis reverses the sweep.
+        let Entity::Arc(mirrored) = arc(1.0).transformed(&Affine2D::scale(-1.0, 1.0)) else {
+            panic!("expected arc");
+        };
+        assert!(nearly_eq(mirrored.tilt_angle, PI - 0.5));
+        assert!(nearly_eq(mirrored.start_angle, -1.25));
+        assert!(nearly_eq(mirrored.arc_angle, 1.0));
+    }
+
+    fn nearly_eq(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn extract_layer_keeps_layer_entities_and_referenced_blocks() {
+        let on_layer = |layer_group: u16, layer: u16| EntityBase {
+            layer_group,
+            layer,
+            ..EntityBase::default()
+        };
+        let line = |base: EntityBase| {
+            Entity::Line(Line {
+                base,
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 1.0,
+            })
+        };
+        let insert = |base: EntityBase, def_number: u32| {
+            Entity::Block(Block {
+                base,
+                ref_x: 0.0,
+                ref_y: 0.0,
+                scale_x: 1.0,
+                scale_y: 1.0,
+                rotation: 0.0,
+                def_number,
+                attributes: vec![],
+            })
+        };
+        let block_def = |number: u32, entities: Vec<Entity>| BlockDef {
+            base: EntityBase::default(),
+            number,
+            is_referenced: true,
+            created_time: 0,
+            name: format!("B{number}"),
+            entities,
+        };
+        let mut header = JwwHeader {
+            version: 600,
+            memo: String::new(),
+            paper_size: 0,
+            write_layer_group: 0,
+            layer_groups: std::array::from_fn(|_| Default::default()),
+            grid_origin: None,
+        };
+        header.layer_groups[1].layers[2].name = "Walls".to_string();
+        let doc = JwwDocument {
+            header,
+            entities: vec![
+                line(on_layer(1, 2)),
+                line(on_layer(1, 3)),
+                insert(on_layer(1, 2), 1),
+                insert(on_layer(0, 2), 3),
+            ],
+            block_defs: vec![
+                block_def(1, vec![insert(on_layer(5, 5), 2)]),
+                block_def(2, vec![line(on_layer(5, 5))]),
+                block_def(3, vec![line(on_layer(0, 2))]),
+            ],
+        };
+
+        let extracted = extract_layer(&doc, 1, 2);
+        assert_eq!(extracted.header, doc.header);
+        assert_eq!(
+            extracted.entities,
+            vec![doc.entities[0].clone(), doc.entities[2].clone()]
+        );
+        let numbers = extracted
+            .block_defs
+            .iter()
+            .map(|block_def| block_def.number)
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2]);
+    }
+
+    #[test]
+    fn entities_in_region_selects_by_first_coordinate() {
+        let line_from = |x: f64, y: f64| {
+            Entity::Line(Line {
+                base: EntityBase::default(),
+                start_x: x,
+                start_y: y,
+                end_x: 50.0,
+                end_y: 50.0,
+            })
+        };
+        let doc = JwwDocument {
+            header: JwwHeader {
+                version: 600,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            entities: vec![
+                line_from(0.5, 0.5),
+                line_from(2.0, 2.0),
+                line_from(0.1, 0.9),
+            ],
+            block_defs: Vec::new(),
+        };
+        let room = [
+            Coord2D::new(0.0, 0.0),
+            Coord2D::new(1.0, 0.0),
+            Coord2D::new(1.0, 1.0),
+            Coord2D::new(0.0, 1.0),
+        ];
+
+        let inside = doc.entities_in_region(&room);
+        assert_eq!(inside, vec![&doc.entities[0], &doc.entities[2]]);
+        assert!(doc.entities_in_region(&[]).is_empty());
+    }
 }
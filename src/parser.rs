@@ -1,23 +1,155 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::Path;
 
+use encoding_rs::{Encoding, SHIFT_JIS};
+
 use crate::error::JwwError;
-use crate::header::parse_header;
+use crate::header::parse_header_with_encoding;
 use crate::model::{
     Arc, Block, BlockDef, Dimension, Entity, EntityBase, JwwDocument, Line, Point, Solid, Text,
 };
 use crate::reader::Reader;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Decodes the memo, layer names and every text field. JWW files are
+    /// Shift-JIS; other encodings only help with re-saved or localized files.
+    pub encoding: &'static Encoding,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            encoding: SHIFT_JIS,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Looks the encoding up by WHATWG label, e.g. `"utf-8"` or `"windows-31j"`.
+    pub fn with_encoding_label(label: &str) -> Option<Self> {
+        Encoding::for_label(label.as_bytes()).map(|encoding| Self { encoding })
+    }
+}
+
 pub fn parse_document(data: &[u8]) -> Result<JwwDocument, JwwError> {
-    let header = parse_header(data)?;
+    parse_document_with_options(data, &ParseOptions::default())
+}
+
+pub fn parse_document_with_options(
+    data: &[u8],
+    options: &ParseOptions,
+) -> Result<JwwDocument, JwwError> {
+    parse_document_traced(data, options, None)
+}
+
+/// What one parse saw, for batch summaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    /// Parsed records per JWW class name, top level and inside block
+    /// definitions together. An unknown class fails the parse instead of
+    /// showing up here.
+    pub class_counts: BTreeMap<&'static str, usize>,
+    /// Block definition records read, duplicate numbers included.
+    pub block_def_count: usize,
+    /// Definition numbers referenced by an insert but never defined.
+    pub unresolved_block_refs: Vec<u32>,
+    /// Records in the top-level entity list that hold no entity: 1 if the
+    /// list ends at a 0x8000 sentinel, else 0.
+    pub skipped_records: usize,
+}
+
+/// [`parse_document`] together with a [`ParseReport`] gathered from the
+/// same parse.
+pub fn parse_document_reported(data: &[u8]) -> Result<(JwwDocument, ParseReport), JwwError> {
+    let mut trace = EntityListTrace::default();
+    let doc = parse_document_traced(data, &ParseOptions::default(), Some(&mut trace))?;
+
+    let mut class_counts = BTreeMap::<&'static str, usize>::new();
+    let entities = doc
+        .entities
+        .iter()
+        .chain(doc.block_defs.iter().flat_map(|def| def.entities.iter()));
+    for entity in entities {
+        if let Some(class) = JwwClass::of(entity) {
+            *class_counts.entry(class.as_name()).or_insert(0) += 1;
+        }
+    }
+    let report = ParseReport {
+        class_counts,
+        block_def_count: doc.block_defs.len(),
+        unresolved_block_refs: validate_block_references(&doc).unresolved_def_numbers,
+        skipped_records: trace.skipped,
+    };
+    Ok((doc, report))
+}
+
+/// Walks the same records as [`parse_document`] and fails the same way,
+/// but tallies each entity and block definition as it is read instead of
+/// building the document, so memory stays flat for large files.
+pub fn validate_document(data: &[u8]) -> Result<ParseReport, JwwError> {
+    let options = ParseOptions::default();
+    let header = parse_header_with_encoding(data, options.encoding)?;
     let entity_list_offset =
         find_entity_list_offset(data, header.version).ok_or(JwwError::EntityListNotFound)?;
-    let mut reader = Reader::new(&data[entity_list_offset..]);
-    let entities = parse_entity_list(&mut reader, header.version)?;
+    let mut reader = Reader::with_encoding(&data[entity_list_offset..], options.encoding);
+
+    let mut class_counts = BTreeMap::<&'static str, usize>::new();
+    let mut ref_numbers = BTreeSet::<u32>::new();
+    let mut tally = |entity: &Entity| {
+        if let Some(class) = JwwClass::of(entity) {
+            *class_counts.entry(class.as_name()).or_insert(0) += 1;
+        }
+        if let Entity::Block(block) = entity {
+            ref_numbers.insert(block.def_number);
+        }
+    };
+
+    let mut top_level = 0usize;
+    let records = walk_entity_list(&mut reader, header.version, None, &mut |entity| {
+        top_level += 1;
+        tally(&entity);
+    })
+    .map_err(|err| in_file_at(err, entity_list_offset))?;
+    let block_data_start = entity_list_offset + reader.bytes_read();
+    let mut def_numbers = BTreeSet::<u32>::new();
+    let mut block_def_count = 0usize;
+    if block_data_start < data.len() {
+        walk_block_def_list(
+            &data[block_data_start..],
+            header.version,
+            options.encoding,
+            &mut |block_def| {
+                block_def_count += 1;
+                def_numbers.insert(block_def.number);
+                block_def.entities.iter().for_each(&mut tally);
+            },
+        );
+    }
+
+    Ok(ParseReport {
+        class_counts,
+        block_def_count,
+        unresolved_block_refs: ref_numbers.difference(&def_numbers).copied().collect(),
+        skipped_records: records - top_level,
+    })
+}
+
+fn parse_document_traced(
+    data: &[u8],
+    options: &ParseOptions,
+    trace: Option<&mut EntityListTrace>,
+) -> Result<JwwDocument, JwwError> {
+    let header = parse_header_with_encoding(data, options.encoding)?;
+    let entity_list_offset =
+        find_entity_list_offset(data, header.version).ok_or(JwwError::EntityListNotFound)?;
+    let mut reader = Reader::with_encoding(&data[entity_list_offset..], options.encoding);
+    let entities = parse_entity_list(&mut reader, header.version, trace)
+        .map_err(|err| in_file_at(err, entity_list_offset))?;
     let block_data_start = entity_list_offset + reader.bytes_read();
     let block_defs = if block_data_start < data.len() {
-        parse_block_def_list(&data[block_data_start..], header.version)
+        parse_block_def_list(&data[block_data_start..], header.version, options.encoding)
     } else {
         Vec::new()
     };
@@ -28,12 +160,209 @@ pub fn parse_document(data: &[u8]) -> Result<JwwDocument, JwwError> {
     })
 }
 
+/// Where [`parse_entities_from`] stopped in the top-level entity list, with
+/// the class PIDs declared so far. Plain data, so it can be saved as a
+/// checkpoint and picked up by another process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResumeState {
+    pub version: u32,
+    /// File offset of the next record.
+    pub offset: usize,
+    /// Records the list's count says are left; 0 once the end sentinel has
+    /// been read.
+    pub remaining: usize,
+    pub pid_to_class_name: HashMap<u32, String>,
+    pub next_pid: u32,
+    /// [`EntityBase::seq`] the next entity gets.
+    pub next_seq: usize,
+}
+
+impl ResumeState {
+    /// The state before the first record of the top-level entity list.
+    pub fn start(data: &[u8]) -> Result<Self, JwwError> {
+        Self::start_with_options(data, &ParseOptions::default())
+    }
+
+    /// Like [`Self::start`], decoding the header with `options.encoding`.
+    pub fn start_with_options(data: &[u8], options: &ParseOptions) -> Result<Self, JwwError> {
+        let header = parse_header_with_encoding(data, options.encoding)?;
+        let offset =
+            find_entity_list_offset(data, header.version).ok_or(JwwError::EntityListNotFound)?;
+        let mut reader = Reader::with_encoding(&data[offset..], options.encoding);
+        let remaining = reader.read_count()?;
+        Ok(Self {
+            version: header.version,
+            offset: offset + reader.bytes_read(),
+            remaining,
+            pid_to_class_name: HashMap::new(),
+            next_pid: 1,
+            next_seq: 0,
+        })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Parses up to `count` top-level entities from where `state` left off and
+/// returns them with the state to continue from. Chunks parsed this way add
+/// up to [`JwwDocument::entities`]; block definitions are not read.
+pub fn parse_entities_from(
+    data: &[u8],
+    state: &ResumeState,
+    count: usize,
+) -> Result<(Vec<Entity>, ResumeState), JwwError> {
+    parse_entities_from_with_options(data, state, count, &ParseOptions::default())
+}
+
+/// Like [`parse_entities_from`], decoding text with `options.encoding`. Use
+/// the same options for every chunk and for [`ResumeState::start_with_options`].
+pub fn parse_entities_from_with_options(
+    data: &[u8],
+    state: &ResumeState,
+    count: usize,
+    options: &ParseOptions,
+) -> Result<(Vec<Entity>, ResumeState), JwwError> {
+    let mut state = state.clone();
+    let rest = data
+        .get(state.offset..)
+        .ok_or(JwwError::UnexpectedEof("resume offset"))?;
+    let mut reader = Reader::with_encoding(rest, options.encoding);
+    let mut entities = Vec::new();
+    while entities.len() < count && !state.is_done() {
+        let (step, next_pid) = parse_entity_with_pid_tracking(
+            &mut reader,
+            state.version,
+            &mut state.pid_to_class_name,
+            state.next_pid,
+        )?;
+        state.next_pid = next_pid;
+        state.remaining -= 1;
+        match step {
+            ParseStep::Entity(mut entity) => {
+                entity.base_mut().seq = state.next_seq;
+                state.next_seq += 1;
+                entities.push(entity);
+            }
+            ParseStep::End => state.remaining = 0,
+        }
+    }
+    state.offset += reader.bytes_read();
+    Ok((entities, state))
+}
+
+// `walk_entity_list` reports offsets within the list it was given; shift
+// them to file offsets once the list's own position is known.
+fn in_file_at(err: JwwError, list_offset: usize) -> JwwError {
+    match err {
+        JwwError::ParseAt {
+            index,
+            offset,
+            source,
+        } => JwwError::ParseAt {
+            index,
+            offset: list_offset + offset,
+            source,
+        },
+        err => err,
+    }
+}
+
+/// File offset of the record behind each top-level entity, in the same order
+/// as [`JwwDocument::entities`]. Meant for locating damage in corrupt files.
+pub fn parse_entity_offsets(data: &[u8]) -> Result<Vec<usize>, JwwError> {
+    let options = ParseOptions::default();
+    let header = parse_header_with_encoding(data, options.encoding)?;
+    let entity_list_offset =
+        find_entity_list_offset(data, header.version).ok_or(JwwError::EntityListNotFound)?;
+    let mut reader = Reader::with_encoding(&data[entity_list_offset..], options.encoding);
+    let mut trace = EntityListTrace::default();
+    parse_entity_list(&mut reader, header.version, Some(&mut trace))
+        .map_err(|err| in_file_at(err, entity_list_offset))?;
+    Ok(trace
+        .offsets
+        .into_iter()
+        .map(|offset| entity_list_offset + offset)
+        .collect())
+}
+
 pub fn read_document_from_file(path: impl AsRef<Path>) -> Result<JwwDocument, JwwError> {
     let data = fs::read(path)?;
     parse_document(&data)
 }
 
-fn find_entity_list_offset(data: &[u8], version: u32) -> Option<usize> {
+pub fn read_document_from_file_with_options(
+    path: impl AsRef<Path>,
+    options: &ParseOptions,
+) -> Result<JwwDocument, JwwError> {
+    let data = fs::read(path)?;
+    parse_document_with_options(&data, options)
+}
+
+/// MFC class names of the JWW entity records the parser understands.
+///
+/// JWW has no hatch record: Jw_cad writes hatching as plain `CDataSen`
+/// lines, so there are no boundary references to resolve into a DXF HATCH.
+/// Double lines (複線/2線 walls) are likewise just two independent
+/// `CDataSen` records with no shared wall record, so there is nothing to
+/// emit as a DXF MLINE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JwwClass {
+    Line,
+    Arc,
+    Point,
+    Text,
+    Solid,
+    Block,
+    Dimension,
+}
+
+impl JwwClass {
+    pub const ALL: [JwwClass; 7] = [
+        Self::Line,
+        Self::Arc,
+        Self::Point,
+        Self::Text,
+        Self::Solid,
+        Self::Block,
+        Self::Dimension,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|class| class.as_name() == name)
+    }
+
+    /// The record class an entity is read from; polylines are built in
+    /// memory and have none.
+    pub fn of(entity: &Entity) -> Option<Self> {
+        match entity {
+            Entity::Line(_) => Some(Self::Line),
+            Entity::Arc(_) => Some(Self::Arc),
+            Entity::Point(_) => Some(Self::Point),
+            Entity::Text(_) => Some(Self::Text),
+            Entity::Solid(_) => Some(Self::Solid),
+            Entity::Block(_) => Some(Self::Block),
+            Entity::Dimension(_) => Some(Self::Dimension),
+            Entity::Polyline(_) => None,
+        }
+    }
+
+    pub fn as_name(self) -> &'static str {
+        match self {
+            Self::Line => "CDataSen",
+            Self::Arc => "CDataEnko",
+            Self::Point => "CDataTen",
+            Self::Text => "CDataMoji",
+            Self::Solid => "CDataSolid",
+            Self::Block => "CDataBlock",
+            Self::Dimension => "CDataSunpou",
+        }
+    }
+}
+
+pub(crate) fn find_entity_list_offset(data: &[u8], version: u32) -> Option<usize> {
     let [schema_low, schema_high, _, _] = version.to_le_bytes();
     if data.len() < 128 {
         return None;
@@ -50,7 +379,7 @@ fn find_entity_list_offset(data: &[u8], version: u32) -> Option<usize> {
             if (8..=32).contains(&name_len) && i + 6 + name_len <= data.len() {
                 let class_name = &data[i + 6..i + 6 + name_len];
                 if class_name.starts_with(b"CData") && i >= 2 {
-                    return Some(i - 2);
+                    return Some(extended_count_start(data, i).unwrap_or(i - 2));
                 }
             }
         }
@@ -59,31 +388,95 @@ fn find_entity_list_offset(data: &[u8], version: u32) -> Option<usize> {
     None
 }
 
-fn parse_entity_list(reader: &mut Reader<'_>, version: u32) -> Result<Vec<Entity>, JwwError> {
-    let count = reader.read_u16()? as usize;
-    let mut entities = Vec::with_capacity(count);
+// Start of the count before the record at `record`, if it is MFC's extended
+// form: 0xFFFF and then a u32 that needed it.
+fn extended_count_start(data: &[u8], record: usize) -> Option<usize> {
+    let start = record.checked_sub(6)?;
+    let count = u32::from_le_bytes(data[start + 2..record].try_into().ok()?);
+    (data[start..start + 2] == [0xFF, 0xFF] && count >= 0xFFFF).then_some(start)
+}
+
+// Optional bookkeeping while reading an entity list.
+#[derive(Debug, Default)]
+struct EntityListTrace {
+    /// Where each returned entity's record starts, relative to the reader.
+    offsets: Vec<usize>,
+    /// Null records, which hold no entity.
+    skipped: usize,
+}
+
+fn parse_entity_list(
+    reader: &mut Reader<'_>,
+    version: u32,
+    trace: Option<&mut EntityListTrace>,
+) -> Result<Vec<Entity>, JwwError> {
+    let mut entities = Vec::new();
+    walk_entity_list(reader, version, trace, &mut |mut entity| {
+        entity.base_mut().seq = entities.len();
+        entities.push(entity);
+    })?;
+    Ok(entities)
+}
+
+// Hands each parsed entity to `visit` instead of keeping it, and returns
+// the number of records read, the end sentinel included. A list ends at the
+// sentinel even if its count promised more records.
+fn walk_entity_list(
+    reader: &mut Reader<'_>,
+    version: u32,
+    mut trace: Option<&mut EntityListTrace>,
+    visit: &mut dyn FnMut(Entity),
+) -> Result<usize, JwwError> {
+    let count = reader.read_count()?;
 
     let mut pid_to_class_name = HashMap::<u32, String>::new();
     let mut next_pid: u32 = 1;
 
-    for _ in 0..count {
-        let (entity, new_pid) =
-            parse_entity_with_pid_tracking(reader, version, &mut pid_to_class_name, next_pid)?;
+    for index in 0..count {
+        let start = reader.bytes_read();
+        let (step, new_pid) =
+            parse_entity_with_pid_tracking(reader, version, &mut pid_to_class_name, next_pid)
+                .map_err(|source| JwwError::ParseAt {
+                    index,
+                    offset: start,
+                    source: Box::new(source),
+                })?;
         next_pid = new_pid;
-        if let Some(entity) = entity {
-            entities.push(entity);
+        match step {
+            ParseStep::Entity(entity) => {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.offsets.push(start);
+                }
+                visit(entity);
+            }
+            ParseStep::End => {
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.skipped += 1;
+                }
+                return Ok(index + 1);
+            }
         }
     }
 
-    Ok(entities)
+    Ok(count)
 }
 
-fn parse_entity_with_pid_tracking(
+/// What one record of an entity list held. Not boxed, as every step is
+/// unpacked as soon as it is returned.
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum ParseStep {
+    Entity(Entity),
+    /// A bare 0x8000 tag, which ends the list whatever its count said.
+    End,
+}
+
+pub(crate) fn parse_entity_with_pid_tracking(
     reader: &mut Reader<'_>,
     version: u32,
     pid_to_class_name: &mut HashMap<u32, String>,
     mut next_pid: u32,
-) -> Result<(Option<Entity>, u32), JwwError> {
+) -> Result<(ParseStep, u32), JwwError> {
     let class_id = reader.read_u16()?;
 
     let class_name = if class_id == 0xFFFF {
@@ -94,7 +487,7 @@ fn parse_entity_with_pid_tracking(
         next_pid += 1;
         name
     } else if class_id == 0x8000 {
-        return Ok((None, next_pid));
+        return Ok((ParseStep::End, next_pid));
     } else {
         let class_pid = (class_id & 0x7FFF) as u32;
         pid_to_class_name
@@ -103,19 +496,21 @@ fn parse_entity_with_pid_tracking(
             .ok_or(JwwError::UnknownClassPid(class_pid))?
     };
 
-    let entity = match class_name.as_str() {
-        "CDataSen" => Some(Entity::Line(parse_line(reader, version)?)),
-        "CDataEnko" => Some(Entity::Arc(parse_arc(reader, version)?)),
-        "CDataTen" => Some(Entity::Point(parse_point(reader, version)?)),
-        "CDataMoji" => Some(Entity::Text(parse_text(reader, version)?)),
-        "CDataSolid" => Some(Entity::Solid(parse_solid(reader, version)?)),
-        "CDataBlock" => Some(Entity::Block(parse_block(reader, version)?)),
-        "CDataSunpou" => Some(Entity::Dimension(parse_dimension(reader, version)?)),
-        _ => return Err(JwwError::UnknownEntityClass(class_name)),
+    let Some(class) = JwwClass::from_name(&class_name) else {
+        return Err(JwwError::UnknownEntityClass(class_name));
+    };
+    let entity = match class {
+        JwwClass::Line => Entity::Line(parse_line(reader, version)?),
+        JwwClass::Arc => Entity::Arc(parse_arc(reader, version)?),
+        JwwClass::Point => Entity::Point(parse_point(reader, version)?),
+        JwwClass::Text => Entity::Text(parse_text(reader, version)?),
+        JwwClass::Solid => Entity::Solid(parse_solid(reader, version)?),
+        JwwClass::Block => Entity::Block(parse_block(reader, version)?),
+        JwwClass::Dimension => Entity::Dimension(parse_dimension(reader, version)?),
     };
 
     next_pid += 1;
-    Ok((entity, next_pid))
+    Ok((ParseStep::Entity(entity), next_pid))
 }
 
 fn parse_entity_base(reader: &mut Reader<'_>, version: u32) -> Result<EntityBase, JwwError> {
@@ -139,9 +534,14 @@ fn parse_entity_base(reader: &mut Reader<'_>, version: u32) -> Result<EntityBase
         layer,
         layer_group,
         flag,
+        seq: 0,
     })
 }
 
+// Line and arc records hold only their fixed fields in every supported
+// version. No sample from 7.00 or later has a trailing line-end style or any
+// other per-entity word after them, so none is read: guessing at one would
+// swallow the first word of the next record.
 fn parse_line(reader: &mut Reader<'_>, version: u32) -> Result<Line, JwwError> {
     let base = parse_entity_base(reader, version)?;
     Ok(Line {
@@ -209,6 +609,9 @@ fn parse_text(reader: &mut Reader<'_>, version: u32) -> Result<Text, JwwError> {
     })
 }
 
+// Entity flag bit marking a solid as an unfilled outline.
+const SOLID_OUTLINE_FLAG: u16 = 0x0001;
+
 fn parse_solid(reader: &mut Reader<'_>, version: u32) -> Result<Solid, JwwError> {
     let base = parse_entity_base(reader, version)?;
     let point1_x = reader.read_f64()?;
@@ -236,6 +639,7 @@ fn parse_solid(reader: &mut Reader<'_>, version: u32) -> Result<Solid, JwwError>
         point4_x,
         point4_y,
         color,
+        is_filled: base.flag & SOLID_OUTLINE_FLAG == 0,
     })
 }
 
@@ -249,6 +653,7 @@ fn parse_block(reader: &mut Reader<'_>, version: u32) -> Result<Block, JwwError>
         scale_y: reader.read_f64()?,
         rotation: reader.read_f64()?,
         def_number: reader.read_u32()?,
+        attributes: Vec::new(),
     })
 }
 
@@ -257,6 +662,8 @@ fn parse_dimension(reader: &mut Reader<'_>, version: u32) -> Result<Dimension, J
     let line = parse_line(reader, version)?;
     let text = parse_text(reader, version)?;
 
+    // From 4.20 on the record ends with the SXF mode, two extension lines
+    // and four arrow/end points, the one layout the sample files use.
     let mut sxf_mode = None;
     let mut aux_lines = Vec::new();
     let mut aux_points = Vec::new();
@@ -280,18 +687,30 @@ fn parse_dimension(reader: &mut Reader<'_>, version: u32) -> Result<Dimension, J
     })
 }
 
-fn parse_block_def_list(data: &[u8], version: u32) -> Vec<BlockDef> {
-    let mut reader = Reader::new(data);
+fn parse_block_def_list(data: &[u8], version: u32, encoding: &'static Encoding) -> Vec<BlockDef> {
+    let mut block_defs = Vec::<BlockDef>::new();
+    walk_block_def_list(data, version, encoding, &mut |block_def| {
+        block_defs.push(block_def)
+    });
+    block_defs
+}
+
+fn walk_block_def_list(
+    data: &[u8],
+    version: u32,
+    encoding: &'static Encoding,
+    visit: &mut dyn FnMut(BlockDef),
+) {
+    let mut reader = Reader::with_encoding(data, encoding);
     let count = match reader.read_u32() {
         Ok(v) => v,
-        Err(_) => return Vec::new(),
+        Err(_) => return,
     };
 
     if count > 10_000 {
-        return Vec::new();
+        return;
     }
 
-    let mut block_defs = Vec::<BlockDef>::with_capacity(count as usize);
     let mut class_map = HashMap::<u16, String>::new();
     let mut next_id = 1u16;
 
@@ -303,11 +722,9 @@ fn parse_block_def_list(data: &[u8], version: u32) -> Vec<BlockDef> {
         };
         next_id = new_next_id;
         if let Some(block_def) = block_def {
-            block_defs.push(block_def);
+            visit(block_def);
         }
     }
-
-    block_defs
 }
 
 fn parse_block_def_with_tracking(
@@ -330,16 +747,17 @@ fn parse_block_def_with_tracking(
     let base = parse_entity_base(reader, version)?;
     let number = reader.read_u32()?;
     let is_referenced = reader.read_u32()? != 0;
-    reader.skip(4)?; // CTime
+    let created_time = reader.read_u32()?;
     let name = reader.read_cstring()?;
 
-    let entities = parse_entity_list(reader, version).unwrap_or_default();
+    let entities = parse_entity_list(reader, version, None).unwrap_or_default();
 
     Ok((
         Some(BlockDef {
             base,
             number,
             is_referenced,
+            created_time,
             name,
             entities,
         }),
@@ -355,6 +773,52 @@ pub fn entity_counts(entities: &[Entity]) -> HashMap<&'static str, usize> {
     counts
 }
 
+/// Entity-type counts per `(layer_group, layer)`. Each entity counts on its
+/// own base layer, so block definition contents are attributed to the layers
+/// they were drawn on rather than to the inserting layer.
+pub fn entity_counts_by_layer(
+    entities: &[Entity],
+) -> HashMap<(u16, u16), HashMap<&'static str, usize>> {
+    let mut counts = HashMap::<(u16, u16), HashMap<&'static str, usize>>::new();
+    for entity in entities {
+        let base = entity.base();
+        *counts
+            .entry((base.layer_group, base.layer))
+            .or_default()
+            .entry(entity.entity_type())
+            .or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Entities drawn with each `(pen_color, pen_style, pen_width)`, top-level
+/// and block definition contents alike. A definition counts once however
+/// often it is inserted.
+pub fn collect_pen_usage(doc: &JwwDocument) -> BTreeMap<(u16, u8, u16), usize> {
+    let block_entities = doc.block_defs.iter().flat_map(|def| def.entities.iter());
+    let mut usage = BTreeMap::<(u16, u8, u16), usize>::new();
+    for entity in doc.entities.iter().chain(block_entities) {
+        let base = entity.base();
+        *usage
+            .entry((base.pen_color, base.pen_style, base.pen_width))
+            .or_insert(0) += 1;
+    }
+    usage
+}
+
+/// Indices into `entities` of each edit group's members, keyed by
+/// [`EntityBase::group`]. Group 0 means ungrouped and is left out.
+pub fn group_entities(entities: &[Entity]) -> HashMap<u32, Vec<usize>> {
+    let mut groups = HashMap::<u32, Vec<usize>>::new();
+    for (index, entity) in entities.iter().enumerate() {
+        let group = entity.base().group;
+        if group != 0 {
+            groups.entry(group).or_default().push(index);
+        }
+    }
+    groups
+}
+
 pub fn block_def_name_map(block_defs: &[BlockDef]) -> HashMap<u32, String> {
     let mut map = HashMap::<u32, String>::with_capacity(block_defs.len());
     for block_def in block_defs {
@@ -363,7 +827,7 @@ pub fn block_def_name_map(block_defs: &[BlockDef]) -> HashMap<u32, String> {
     map
 }
 
-pub fn resolve_block_name<'a>(def_number: u32, block_defs: &'a [BlockDef]) -> Option<&'a str> {
+pub fn resolve_block_name(def_number: u32, block_defs: &[BlockDef]) -> Option<&str> {
     block_defs
         .iter()
         .find(|def| def.number == def_number)
@@ -375,6 +839,8 @@ pub struct BlockReferenceValidation {
     pub total_references: usize,
     pub resolved_references: usize,
     pub unresolved_def_numbers: Vec<u32>,
+    /// Block definitions that at least one insert resolves to, ascending.
+    pub referenced_def_numbers: Vec<u32>,
 }
 
 impl BlockReferenceValidation {
@@ -395,9 +861,11 @@ pub fn validate_block_references(document: &JwwDocument) -> BlockReferenceValida
 
     let mut resolved_references = 0usize;
     let mut unresolved = BTreeSet::<u32>::new();
+    let mut referenced = BTreeSet::<u32>::new();
     for def_number in ref_numbers {
         if name_map.contains_key(&def_number) {
             resolved_references += 1;
+            referenced.insert(def_number);
         } else {
             unresolved.insert(def_number);
         }
@@ -407,6 +875,7 @@ pub fn validate_block_references(document: &JwwDocument) -> BlockReferenceValida
         total_references,
         resolved_references,
         unresolved_def_numbers: unresolved.into_iter().collect(),
+        referenced_def_numbers: referenced.into_iter().collect(),
     }
 }
 
@@ -424,11 +893,16 @@ mod tests {
     use std::io::Write;
     use std::path::{Path, PathBuf};
 
-    use crate::model::{BlockDef, Entity, EntityBase};
+    use crate::dxf::{convert_document, validate_conversion, ConvertOptions, DxfEntity};
+    use crate::model::{block_def_created_datetime, BlockDef, Entity, EntityBase, Line};
+    use crate::reader::Reader;
+    use crate::writer::document_to_jww_bytes;
 
     use super::{
-        block_def_name_map, entity_counts, read_document_from_file, resolve_block_name,
-        validate_block_references, JwwError,
+        block_def_name_map, entity_counts, entity_counts_by_layer, parse_document,
+        parse_document_reported, parse_entities_from, parse_entities_from_with_options,
+        read_document_from_file, resolve_block_name, validate_block_references, validate_document,
+        JwwClass, JwwError, ParseOptions, ResumeState,
     };
 
     fn jww_samples_dir() -> PathBuf {
@@ -458,6 +932,120 @@ mod tests {
         }
     }
 
+    #[test]
+    fn jww_class_names_round_trip() {
+        for class in JwwClass::ALL {
+            assert_eq!(JwwClass::from_name(class.as_name()), Some(class));
+        }
+        assert_eq!(JwwClass::from_name("CDataSen"), Some(JwwClass::Line));
+        assert_eq!(JwwClass::from_name("CDataList"), None);
+        assert_eq!(JwwClass::from_name("cdatasen"), None);
+    }
+
+    #[test]
+    fn parse_report_counts_match_parsed_sample() {
+        let data = fs::read(jww_samples_dir().join("Test1.jww")).unwrap();
+        let (doc, report) = parse_document_reported(&data).unwrap();
+        assert_eq!(
+            doc,
+            read_document_from_file(jww_samples_dir().join("Test1.jww")).unwrap()
+        );
+
+        let counts = entity_counts(&doc.entities);
+        let count = |entity_type: &str| counts.get(entity_type).copied().unwrap_or(0);
+        assert_eq!(report.class_counts.get("CDataSen"), Some(&count("LINE")));
+        assert_eq!(report.class_counts.get("CDataMoji"), Some(&count("TEXT")));
+        assert_eq!(report.class_counts.get("CDataTen"), Some(&count("POINT")));
+        assert_eq!(
+            report.class_counts.get("CDataEnko"),
+            Some(&(count("ARC") + count("CIRCLE")))
+        );
+        assert!(report.class_counts.len() >= 4);
+        assert_eq!(
+            report.class_counts.values().sum::<usize>(),
+            doc.entities.len()
+        );
+        assert_eq!(report.block_def_count, 0);
+        assert!(report.unresolved_block_refs.is_empty());
+        assert_eq!(report.skipped_records, 0);
+    }
+
+    #[test]
+    fn parse_entities_from_resumes_where_it_stopped() {
+        let data = fs::read(jww_samples_dir().join("Test1.jww")).unwrap();
+        let doc = parse_document(&data).unwrap();
+        let half = doc.entities.len() / 2;
+
+        let start = ResumeState::start(&data).unwrap();
+        let (mut entities, state) = parse_entities_from(&data, &start, half).unwrap();
+        assert_eq!(entities.len(), half);
+        assert!(!state.is_done());
+        assert!(state.offset > start.offset);
+
+        let (rest, state) = parse_entities_from(&data, &state, usize::MAX).unwrap();
+        entities.extend(rest);
+        assert!(state.is_done());
+        assert_eq!(entities, doc.entities);
+
+        let (none, done) = parse_entities_from(&data, &state, 10).unwrap();
+        assert!(none.is_empty());
+        assert_eq!(done, state);
+    }
+
+    #[test]
+    fn parse_entities_from_with_options_decodes_like_the_whole_parse() {
+        let data = fs::read(jww_samples_dir().join("Test1.jww")).unwrap();
+        let options = ParseOptions::with_encoding_label("utf-8").unwrap();
+        let doc = super::parse_document_with_options(&data, &options).unwrap();
+        assert_ne!(doc.entities, parse_document(&data).unwrap().entities);
+
+        let start = ResumeState::start_with_options(&data, &options).unwrap();
+        let (entities, state) =
+            parse_entities_from_with_options(&data, &start, usize::MAX, &options).unwrap();
+        assert!(state.is_done());
+        assert_eq!(entities, doc.entities);
+    }
+
+    #[test]
+    fn validate_document_matches_parse_report() {
+        let mut inputs = ["Test1.jww", "木造平面例.jww"]
+            .map(|name| fs::read(jww_samples_dir().join(name)).unwrap())
+            .to_vec();
+        inputs.push(build_minimal_jww_with_block_def());
+        // Two definitions sharing a number count as two.
+        let mut doc = parse_document(&inputs[0]).unwrap();
+        let block_def = |name: &str| BlockDef {
+            base: EntityBase::default(),
+            number: 7,
+            is_referenced: false,
+            created_time: 0,
+            name: name.to_string(),
+            entities: doc.entities[..1].to_vec(),
+        };
+        doc.block_defs = vec![block_def("A"), block_def("B")];
+        inputs.push(document_to_jww_bytes(&doc).unwrap());
+
+        for data in inputs {
+            let (doc, report) = parse_document_reported(&data).unwrap();
+            assert_eq!(validate_document(&data).unwrap(), report);
+            assert_eq!(report.block_def_count, doc.block_defs.len());
+            assert_eq!(
+                report.class_counts.values().sum::<usize>(),
+                doc.entities.len()
+                    + doc
+                        .block_defs
+                        .iter()
+                        .map(|def| def.entities.len())
+                        .sum::<usize>()
+            );
+        }
+
+        assert!(matches!(
+            validate_document(b"JwwData."),
+            Err(JwwError::UnexpectedEof(_))
+        ));
+    }
+
     #[test]
     fn real_data_scan_nested_dimensions_in_block_defs() {
         let dir = jww_samples_dir();
@@ -519,6 +1107,59 @@ mod tests {
         assert_eq!(counts.get("TEXT").copied().unwrap_or(0), 0);
     }
 
+    #[test]
+    fn parse_assigns_stable_entity_seq() {
+        let path = jww_samples_dir().join("敷地図.jww");
+        let doc = read_document_from_file(&path).unwrap();
+        assert_eq!(doc.entities[2].base().seq, 2);
+        for (index, entity) in doc.entities.iter().enumerate() {
+            assert_eq!(entity.base().seq, index);
+        }
+        assert_eq!(read_document_from_file(&path).unwrap(), doc);
+    }
+
+    #[test]
+    fn parse_entity_offsets_point_at_entity_records() {
+        let data = fs::read(jww_samples_dir().join("Test1.jww")).unwrap();
+        let doc = super::parse_document(&data).unwrap();
+        let offsets = super::parse_entity_offsets(&data).unwrap();
+
+        assert_eq!(offsets.len(), doc.entities.len());
+        assert!(offsets[1] > offsets[0]);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+        // The first record introduces its class with a 0xFFFF tag.
+        assert_eq!(&data[offsets[0]..offsets[0] + 2], &[0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn bad_class_pid_reports_entity_index_and_offset() {
+        let mut data = fs::read(jww_samples_dir().join("Test1.jww")).unwrap();
+        let offsets = super::parse_entity_offsets(&data).unwrap();
+        assert_eq!(validate_document(&data).unwrap().skipped_records, 0);
+        // A back-reference to a PID no class record ever declared.
+        let bad = offsets[3];
+        data[bad..bad + 2].copy_from_slice(&0xF000u16.to_le_bytes());
+
+        let err = super::parse_document(&data).unwrap_err();
+        let JwwError::ParseAt {
+            index,
+            offset,
+            source,
+        } = &err
+        else {
+            panic!("expected ParseAt, got {err:?}");
+        };
+        assert_eq!((*index, *offset), (3, bad));
+        assert!(matches!(**source, JwwError::UnknownClassPid(0x7000)));
+        assert!(err
+            .to_string()
+            .starts_with(&format!("entity 3 at byte {bad}: ")));
+        assert!(matches!(
+            validate_document(&data),
+            Err(JwwError::ParseAt { index: 3, .. })
+        ));
+    }
+
     #[test]
     fn invalid_signature_returns_error() {
         let err = super::parse_document(b"NotJwwData").unwrap_err();
@@ -540,6 +1181,11 @@ mod tests {
         let def = &doc.block_defs[0];
         assert_eq!(def.number, 1);
         assert!(!def.is_referenced);
+        assert_eq!(def.created_time, 1_700_000_000);
+        assert_eq!(
+            block_def_created_datetime(def),
+            Some((2023, 11, 14, 22, 13, 20))
+        );
         assert_eq!(def.name, "BLK");
         assert_eq!(resolve_block_name(1, &doc.block_defs), Some("BLK"));
 
@@ -550,6 +1196,39 @@ mod tests {
         assert!(!validation.has_unresolved());
     }
 
+    #[test]
+    fn validate_conversion_reports_unsupported_entities() {
+        let report = validate_conversion(
+            jww_samples_dir().join("Test1.jww"),
+            ConvertOptions::default(),
+        )
+        .unwrap();
+        assert!(report.is_clean(), "{report:?}");
+        assert!(report.entity_count > 0);
+
+        // Point the only insert at a block definition that does not exist.
+        let mut data = build_minimal_jww_with_block_def();
+        let at = data.windows(10).position(|w| w == b"CDataBlock").unwrap() + 10 + 15 + 40;
+        data[at..at + 4].copy_from_slice(&9u32.to_le_bytes());
+        let path = std::env::temp_dir().join(format!(
+            "ezjww_validate_conversion_{}.jww",
+            std::process::id()
+        ));
+        fs::write(&path, &data).unwrap();
+        let report = validate_conversion(
+            &path,
+            ConvertOptions {
+                explode_inserts: true,
+                ..ConvertOptions::default()
+            },
+        );
+        fs::remove_file(&path).unwrap();
+        let report = report.unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.unsupported_entities, vec!["UNRESOLVED_BLOCK(9)"]);
+        assert_eq!(report.warnings, vec!["unresolved block reference: 9"]);
+    }
+
     #[test]
     fn block_def_map_works() {
         let defs = vec![
@@ -557,6 +1236,7 @@ mod tests {
                 base: EntityBase::default(),
                 number: 3,
                 is_referenced: false,
+                created_time: 0,
                 name: "A".to_string(),
                 entities: vec![],
             },
@@ -564,6 +1244,7 @@ mod tests {
                 base: EntityBase::default(),
                 number: 7,
                 is_referenced: true,
+                created_time: 0,
                 name: "B".to_string(),
                 entities: vec![],
             },
@@ -591,6 +1272,212 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_document_locates_entity_list_for_version_700() {
+        let mut data = build_minimal_jww_with_dimension();
+        data[8..12].copy_from_slice(&700u32.to_le_bytes());
+        let class_header = [0xFF, 0xFF, 0x58, 0x02];
+        let at = data
+            .windows(class_header.len())
+            .position(|window| window == class_header)
+            .unwrap();
+        data[at + 2..at + 4].copy_from_slice(&700u16.to_le_bytes());
+
+        let doc = super::parse_document(&data).unwrap();
+        assert_eq!(doc.header.version, 700);
+        assert!(matches!(doc.entities[0], Entity::Dimension(_)));
+
+        data[8..12].copy_from_slice(&999u32.to_le_bytes());
+        let err = super::parse_document(&data).unwrap_err();
+        assert!(matches!(err, JwwError::UnsupportedVersion(999)));
+    }
+
+    #[test]
+    fn entity_counts_by_layer_groups_minimal_dimension() {
+        let data = build_minimal_jww_with_dimension();
+        let doc = super::parse_document(&data).unwrap();
+
+        let counts = entity_counts_by_layer(&doc.entities);
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[&(0, 0)].get("DIMENSION"), Some(&1));
+        assert_eq!(counts[&(0, 0)].len(), 1);
+    }
+
+    #[test]
+    fn collect_pen_usage_counts_block_contents() {
+        let mut doc = super::parse_document(&build_minimal_jww_with_block_def()).unwrap();
+        let line = |pen_color: u16, pen_style: u8, pen_width: u16| {
+            Entity::Line(Line {
+                base: EntityBase {
+                    pen_color,
+                    pen_style,
+                    pen_width,
+                    ..EntityBase::default()
+                },
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 1.0,
+                end_y: 0.0,
+            })
+        };
+        doc.entities = vec![line(2, 1, 0), line(5, 3, 10)];
+        doc.block_defs[0].entities = vec![line(2, 1, 0)];
+
+        let usage = super::collect_pen_usage(&doc);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[&(2, 1, 0)], 2);
+        assert_eq!(usage[&(5, 3, 10)], 1);
+    }
+
+    #[test]
+    fn end_sentinel_stops_lists_short_of_their_count() {
+        let mut data = build_minimal_jww_with_block_def();
+        let list_at = super::find_entity_list_offset(&data, 600).unwrap();
+        let defs_at = data
+            .windows(9)
+            .position(|window| window == b"CDataList")
+            .unwrap()
+            - 10;
+
+        // The top-level list claims three records but ends after the insert.
+        data[list_at..list_at + 2].copy_from_slice(&3u16.to_le_bytes());
+        data.splice(defs_at..defs_at, 0x8000u16.to_le_bytes());
+        let defs_at = defs_at + 2;
+        data[defs_at..defs_at + 4].copy_from_slice(&2u32.to_le_bytes());
+
+        // The first definition's nested list does the same with no entities,
+        // and a second definition follows it.
+        let nested_at = data.len() - 2;
+        data[nested_at..].copy_from_slice(&4u16.to_le_bytes());
+        data.extend_from_slice(&0x8000u16.to_le_bytes());
+        data.extend_from_slice(&0x8001u16.to_le_bytes());
+        data.extend_from_slice(&[0; 15]); // EntityBase
+        data.extend_from_slice(&2u32.to_le_bytes()); // number
+        data.extend_from_slice(&0u32.to_le_bytes()); // is_referenced
+        data.extend_from_slice(&0u32.to_le_bytes()); // ctime
+        data.extend_from_slice(&[2, b'B', b'2']);
+        data.extend_from_slice(&0u16.to_le_bytes());
+
+        let doc = super::parse_document(&data).unwrap();
+        assert_eq!(doc.entities.len(), 1);
+        assert!(matches!(doc.entities[0], Entity::Block(_)));
+        let names = doc
+            .block_defs
+            .iter()
+            .map(|def| def.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["BLK", "B2"]);
+        assert!(doc.block_defs.iter().all(|def| def.entities.is_empty()));
+
+        let report = validate_document(&data).unwrap();
+        assert_eq!(report.skipped_records, 1);
+        assert_eq!(report.block_def_count, 2);
+        let (entities, state) =
+            parse_entities_from(&data, &ResumeState::start(&data).unwrap(), 10).unwrap();
+        assert_eq!(entities.len(), 1);
+        assert!(state.is_done());
+    }
+
+    #[test]
+    fn group_entities_buckets_indices_by_group() {
+        let doc = super::parse_document(&build_minimal_jww_with_block_def()).unwrap();
+        let mut entities = vec![doc.entities[0].clone(); 4];
+        for (entity, group) in entities.iter_mut().zip([7, 0, 7, 3]) {
+            entity.base_mut().group = group;
+        }
+
+        let groups = super::group_entities(&entities);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&7], vec![0, 2]);
+        assert_eq!(groups[&3], vec![3]);
+        assert!(!groups.contains_key(&0));
+    }
+
+    #[test]
+    fn parse_document_decodes_utf8_memo_when_selected() {
+        let memo = "図面メモ";
+        let mut data = build_minimal_jww_with_dimension();
+        // The memo cstring sits right after the signature and version.
+        assert_eq!(data[12], 0);
+        data.splice(12..13, [memo.len() as u8].into_iter().chain(memo.bytes()));
+
+        let options = ParseOptions::with_encoding_label("utf-8").unwrap();
+        assert_eq!(options.encoding, encoding_rs::UTF_8);
+        let doc = super::parse_document_with_options(&data, &options).unwrap();
+        assert_eq!(doc.header.memo, memo);
+        match &doc.entities[0] {
+            Entity::Dimension(dim) => assert_eq!(dim.text.content, "1000"),
+            other => panic!("expected DIMENSION entity, got {:?}", other),
+        }
+
+        let doc = super::parse_document(&data).unwrap();
+        assert_ne!(doc.header.memo, memo);
+        assert!(ParseOptions::with_encoding_label("no-such-encoding").is_none());
+    }
+
+    #[test]
+    fn parse_dimension_with_short_tail_is_an_error() {
+        // The first dimension stops after two of the four aux points, so
+        // reading the 4.20 layout runs into the following line record.
+        let data = build_minimal_jww_with_mixed_dimension_tails();
+        let err = super::parse_document(&data).unwrap_err();
+        assert!(matches!(err, JwwError::ParseAt { index: 1, .. }), "{err:?}");
+    }
+
+    #[test]
+    fn parse_solid_outline_flag_converts_to_edges() {
+        let solid_record = |flag: u16| {
+            let mut data = Vec::<u8>::new();
+            data.extend_from_slice(&0u32.to_le_bytes()); // group
+            data.push(1); // pen_style
+            data.extend_from_slice(&1u16.to_le_bytes()); // pen_color
+            data.extend_from_slice(&1u16.to_le_bytes()); // pen_width
+            data.extend_from_slice(&0u16.to_le_bytes()); // layer
+            data.extend_from_slice(&0u16.to_le_bytes()); // layer_group
+            data.extend_from_slice(&flag.to_le_bytes());
+            // point1, point4, point2, point3: around the outline.
+            for value in [0.0f64, 0.0, 10.0, 0.0, 10.0, 10.0, 0.0, 10.0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data
+        };
+        let filled = super::parse_solid(&mut Reader::new(&solid_record(0)), 600).unwrap();
+        let outline = super::parse_solid(&mut Reader::new(&solid_record(0x0001)), 600).unwrap();
+        assert!(filled.is_filled);
+        assert!(!outline.is_filled);
+
+        let mut doc = read_document_from_file(jww_samples_dir().join("Test1.jww")).unwrap();
+        doc.block_defs.clear();
+        for (solid, expected) in [(filled, vec!["SOLID"]), (outline, vec!["LINE"; 4])] {
+            doc.entities = vec![Entity::Solid(solid)];
+            let types = convert_document(&doc)
+                .entities
+                .iter()
+                .map(DxfEntity::entity_type)
+                .collect::<Vec<_>>();
+            assert_eq!(types, expected);
+        }
+
+        // The edges are the square's sides, never a diagonal.
+        let edges = convert_document(&doc)
+            .entities
+            .iter()
+            .map(|entity| match entity {
+                DxfEntity::Line(v) => ((v.x1, v.y1), (v.x2, v.y2)),
+                other => panic!("expected LINE, got {other:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ((0.0, 0.0), (10.0, 0.0)),
+                ((10.0, 0.0), (10.0, 10.0)),
+                ((10.0, 10.0), (0.0, 10.0)),
+                ((0.0, 10.0), (0.0, 0.0)),
+            ]
+        );
+    }
+
     #[test]
     fn validate_unresolved_block_reference() {
         let data = build_minimal_jww_with_unresolved_block_ref();
@@ -673,7 +1560,7 @@ mod tests {
 
         data.extend_from_slice(&1u32.to_le_bytes()); // number
         data.extend_from_slice(&0u32.to_le_bytes()); // is_referenced
-        data.extend_from_slice(&0u32.to_le_bytes()); // ctime
+        data.extend_from_slice(&1_700_000_000u32.to_le_bytes()); // ctime
 
         // CString "BLK"
         data.push(3);
@@ -755,6 +1642,84 @@ mod tests {
         data
     }
 
+    fn build_minimal_jww_with_mixed_dimension_tails() -> Vec<u8> {
+        let mut data = Vec::<u8>::new();
+        data.extend_from_slice(b"JwwData.");
+        data.extend_from_slice(&600u32.to_le_bytes());
+        data.push(0); // memo
+        data.extend_from_slice(&0u32.to_le_bytes()); // paper size
+        data.extend_from_slice(&0u32.to_le_bytes()); // write layer group
+
+        for _ in 0..16 {
+            data.extend_from_slice(&0u32.to_le_bytes()); // group state
+            data.extend_from_slice(&0u32.to_le_bytes()); // write layer
+            data.extend_from_slice(&1.0f64.to_le_bytes()); // scale
+            data.extend_from_slice(&0u32.to_le_bytes()); // protect
+            for _ in 0..16 {
+                data.extend_from_slice(&0u32.to_le_bytes()); // layer state
+                data.extend_from_slice(&0u32.to_le_bytes()); // layer protect
+            }
+        }
+
+        data.extend_from_slice(&3u16.to_le_bytes()); // entity count
+        data.extend_from_slice(&0xFFFFu16.to_le_bytes()); // new class pid 1
+        data.extend_from_slice(&600u16.to_le_bytes()); // schema
+        let class_name = b"CDataSunpou";
+        data.extend_from_slice(&(class_name.len() as u16).to_le_bytes());
+        data.extend_from_slice(class_name);
+        append_dimension(&mut data, b"10", 2);
+
+        data.extend_from_slice(&0xFFFFu16.to_le_bytes()); // new class pid 3
+        data.extend_from_slice(&600u16.to_le_bytes()); // schema
+        let class_name = b"CDataSen";
+        data.extend_from_slice(&(class_name.len() as u16).to_le_bytes());
+        data.extend_from_slice(class_name);
+        append_entity_base(&mut data);
+        data.extend_from_slice(&5.0f64.to_le_bytes()); // start_x
+        data.extend_from_slice(&0.0f64.to_le_bytes()); // start_y
+        data.extend_from_slice(&6.0f64.to_le_bytes()); // end_x
+        data.extend_from_slice(&0.0f64.to_le_bytes()); // end_y
+
+        data.extend_from_slice(&0x8001u16.to_le_bytes()); // existing class pid 1
+        append_dimension(&mut data, b"20", 4);
+
+        data.extend_from_slice(&0u32.to_le_bytes()); // block def count
+        data
+    }
+
+    fn append_dimension(data: &mut Vec<u8>, content: &[u8], aux_points: usize) {
+        append_entity_base(data);
+        append_entity_base(data);
+        for value in [0.0f64, 0.0, 10.0, 0.0] {
+            data.extend_from_slice(&value.to_le_bytes()); // dimension line
+        }
+        append_entity_base(data);
+        for value in [0.0f64, 0.0, 0.0, 0.0] {
+            data.extend_from_slice(&value.to_le_bytes()); // text start/end
+        }
+        data.extend_from_slice(&0u32.to_le_bytes()); // text_type
+        for value in [1.0f64, 1.0, 0.0, 0.0] {
+            data.extend_from_slice(&value.to_le_bytes()); // size, spacing, angle
+        }
+        data.push(0); // font_name cstring
+        data.push(content.len() as u8);
+        data.extend_from_slice(content);
+
+        data.extend_from_slice(&1u16.to_le_bytes()); // sxf mode
+        for _ in 0..2 {
+            append_entity_base(data);
+            for value in [0.0f64, 1.0, 10.0, 1.0] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for _ in 0..aux_points {
+            append_entity_base(data);
+            data.extend_from_slice(&3.0f64.to_le_bytes()); // x
+            data.extend_from_slice(&4.0f64.to_le_bytes()); // y
+            data.extend_from_slice(&0u32.to_le_bytes()); // is_temporary
+        }
+    }
+
     fn append_entity_base(data: &mut Vec<u8>) {
         data.extend_from_slice(&0u32.to_le_bytes()); // group
         data.push(1); // pen_style
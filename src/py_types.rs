@@ -0,0 +1,1203 @@
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::dxf::{
+    entity_floats, sanitize_layers, DxfArc, DxfCircle, DxfDocument, DxfEntity, DxfLayer, DxfLine,
+    DxfPoint, DxfText, DxfVersion, TargetUnits, TextAlignment, DEFAULT_XDATA_APP_ID,
+};
+use crate::header::{JwwHeader, LayerGroupHeader, LayerHeader};
+use crate::model::{BlockDef, Entity, EntityBase, EntityFlags, JwwDocument, Line, Point, Text};
+use crate::parser::{block_def_name_map, group_entities};
+
+#[pyclass(module = "ezjww._core", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyLayer {
+    #[pyo3(get)]
+    state: u32,
+    #[pyo3(get)]
+    protect: u32,
+    #[pyo3(get)]
+    name: String,
+}
+
+#[pymethods]
+impl PyLayer {
+    fn __repr__(&self) -> String {
+        format!("PyLayer(name={:?}, state={})", self.name, self.state)
+    }
+}
+
+#[pyclass(module = "ezjww._core", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyLayerGroup {
+    #[pyo3(get)]
+    state: u32,
+    #[pyo3(get)]
+    write_layer: u32,
+    #[pyo3(get)]
+    scale: f64,
+    #[pyo3(get)]
+    protect: u32,
+    #[pyo3(get)]
+    name: String,
+    #[pyo3(get)]
+    layers: Vec<PyLayer>,
+}
+
+#[pymethods]
+impl PyLayerGroup {
+    fn __repr__(&self) -> String {
+        format!("PyLayerGroup(name={:?}, scale={})", self.name, self.scale)
+    }
+}
+
+#[pyclass(module = "ezjww._core", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyHeader {
+    #[pyo3(get)]
+    version: u32,
+    #[pyo3(get)]
+    memo: String,
+    #[pyo3(get)]
+    paper_size: u32,
+    #[pyo3(get)]
+    paper_size_mm: Option<(f64, f64)>,
+    #[pyo3(get)]
+    write_layer_group: u32,
+    #[pyo3(get)]
+    layer_groups: Vec<PyLayerGroup>,
+    #[pyo3(get)]
+    grid_origin: Option<(f64, f64)>,
+}
+
+#[pymethods]
+impl PyHeader {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyHeader(version={}, paper_size={}, write_layer_group={})",
+            self.version, self.paper_size, self.write_layer_group
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyEntityBase {
+    #[pyo3(get)]
+    group: u32,
+    #[pyo3(get)]
+    pen_style: u8,
+    #[pyo3(get)]
+    pen_color: u16,
+    #[pyo3(get)]
+    pen_width: u16,
+    #[pyo3(get)]
+    layer: u16,
+    #[pyo3(get)]
+    layer_group: u16,
+    #[pyo3(get)]
+    flag: u16,
+    #[pyo3(get)]
+    seq: usize,
+}
+
+#[pymethods]
+impl PyEntityBase {
+    #[getter]
+    fn flags<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        entity_flags_to_pydict(py, &EntityFlags::from_bits(self.flag))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyEntityBase(layer_group={}, layer={}, pen_color={}, pen_style={})",
+            self.layer_group, self.layer, self.pen_color, self.pen_style
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", subclass, frozen)]
+#[derive(Debug, Clone)]
+pub struct PyEntity {
+    #[pyo3(get, name = "type")]
+    entity_type: String,
+    #[pyo3(get)]
+    base: PyEntityBase,
+}
+
+#[pymethods]
+impl PyEntity {
+    fn __repr__(&self) -> String {
+        format!("PyEntity(type={:?})", self.entity_type)
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyLine {
+    #[pyo3(get)]
+    start_x: f64,
+    #[pyo3(get)]
+    start_y: f64,
+    #[pyo3(get)]
+    end_x: f64,
+    #[pyo3(get)]
+    end_y: f64,
+}
+
+#[pymethods]
+impl PyLine {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyLine(start=({}, {}), end=({}, {}))",
+            self.start_x, self.start_y, self.end_x, self.end_y
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyArc {
+    #[pyo3(get)]
+    center_x: f64,
+    #[pyo3(get)]
+    center_y: f64,
+    #[pyo3(get)]
+    radius: f64,
+    #[pyo3(get)]
+    start_angle: f64,
+    #[pyo3(get)]
+    arc_angle: f64,
+    #[pyo3(get)]
+    tilt_angle: f64,
+    #[pyo3(get)]
+    flatness: f64,
+    #[pyo3(get)]
+    is_full_circle: bool,
+}
+
+#[pymethods]
+impl PyArc {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyArc(center=({}, {}), radius={}, start_angle={}, arc_angle={})",
+            self.center_x, self.center_y, self.radius, self.start_angle, self.arc_angle
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyPoint {
+    #[pyo3(get)]
+    x: f64,
+    #[pyo3(get)]
+    y: f64,
+    #[pyo3(get)]
+    is_temporary: bool,
+    #[pyo3(get)]
+    code: u32,
+    #[pyo3(get)]
+    angle: f64,
+    #[pyo3(get)]
+    scale: f64,
+}
+
+#[pymethods]
+impl PyPoint {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyPoint(x={}, y={}, is_temporary={})",
+            self.x,
+            self.y,
+            if self.is_temporary { "True" } else { "False" }
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyText {
+    #[pyo3(get)]
+    start_x: f64,
+    #[pyo3(get)]
+    start_y: f64,
+    #[pyo3(get)]
+    end_x: f64,
+    #[pyo3(get)]
+    end_y: f64,
+    #[pyo3(get)]
+    text_type: u32,
+    #[pyo3(get)]
+    size_x: f64,
+    #[pyo3(get)]
+    size_y: f64,
+    #[pyo3(get)]
+    spacing: f64,
+    #[pyo3(get)]
+    angle: f64,
+    #[pyo3(get)]
+    font_name: String,
+    #[pyo3(get)]
+    content: String,
+}
+
+#[pymethods]
+impl PyText {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyText(content={:?}, start=({}, {}))",
+            self.content, self.start_x, self.start_y
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PySolid {
+    #[pyo3(get)]
+    point1_x: f64,
+    #[pyo3(get)]
+    point1_y: f64,
+    #[pyo3(get)]
+    point2_x: f64,
+    #[pyo3(get)]
+    point2_y: f64,
+    #[pyo3(get)]
+    point3_x: f64,
+    #[pyo3(get)]
+    point3_y: f64,
+    #[pyo3(get)]
+    point4_x: f64,
+    #[pyo3(get)]
+    point4_y: f64,
+    #[pyo3(get)]
+    color: Option<u32>,
+    #[pyo3(get)]
+    is_filled: bool,
+}
+
+#[pymethods]
+impl PySolid {
+    fn __repr__(&self) -> String {
+        format!(
+            "PySolid(p1=({}, {}), p2=({}, {}), p3=({}, {}), p4=({}, {}))",
+            self.point1_x,
+            self.point1_y,
+            self.point2_x,
+            self.point2_y,
+            self.point3_x,
+            self.point3_y,
+            self.point4_x,
+            self.point4_y
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyBlock {
+    #[pyo3(get)]
+    ref_x: f64,
+    #[pyo3(get)]
+    ref_y: f64,
+    #[pyo3(get)]
+    scale_x: f64,
+    #[pyo3(get)]
+    scale_y: f64,
+    #[pyo3(get)]
+    rotation: f64,
+    #[pyo3(get)]
+    def_number: u32,
+    #[pyo3(get)]
+    block_name: Option<String>,
+    #[pyo3(get)]
+    attributes: Vec<(String, String)>,
+}
+
+#[pymethods]
+impl PyBlock {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyBlock(def_number={}, block_name={:?}, ref=({}, {}))",
+            self.def_number, self.block_name, self.ref_x, self.ref_y
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyDimension {
+    line: Py<PyLine>,
+    text: Py<PyText>,
+    #[pyo3(get)]
+    sxf_mode: Option<u16>,
+    aux_lines: Py<PyList>,
+    aux_points: Py<PyList>,
+}
+
+#[pymethods]
+impl PyDimension {
+    #[getter]
+    fn line(&self, py: Python<'_>) -> Py<PyLine> {
+        self.line.clone_ref(py)
+    }
+
+    #[getter]
+    fn text(&self, py: Python<'_>) -> Py<PyText> {
+        self.text.clone_ref(py)
+    }
+
+    #[getter]
+    fn aux_lines(&self, py: Python<'_>) -> Py<PyList> {
+        self.aux_lines.clone_ref(py)
+    }
+
+    #[getter]
+    fn aux_points(&self, py: Python<'_>) -> Py<PyList> {
+        self.aux_points.clone_ref(py)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PyDimension(text={:?}, aux_lines={}, aux_points={})",
+            self.text.get().content,
+            self.aux_lines.bind(py).len(),
+            self.aux_points.bind(py).len()
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", extends = PyEntity, frozen)]
+pub struct PyPolyline {
+    /// `(x, y, bulge)` per vertex.
+    #[pyo3(get)]
+    vertices: Vec<(f64, f64, f64)>,
+    #[pyo3(get)]
+    closed: bool,
+}
+
+#[pymethods]
+impl PyPolyline {
+    fn __repr__(&self) -> String {
+        format!(
+            "PyPolyline(vertices={}, closed={})",
+            self.vertices.len(),
+            if self.closed { "True" } else { "False" }
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", frozen)]
+pub struct PyBlockDef {
+    #[pyo3(get)]
+    number: u32,
+    #[pyo3(get)]
+    is_referenced: bool,
+    #[pyo3(get)]
+    created_time: u32,
+    #[pyo3(get)]
+    name: String,
+    #[pyo3(get)]
+    base: PyEntityBase,
+    entities: Py<PyList>,
+}
+
+#[pymethods]
+impl PyBlockDef {
+    #[getter]
+    fn entities(&self, py: Python<'_>) -> Py<PyList> {
+        self.entities.clone_ref(py)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PyBlockDef(number={}, name={:?}, entities={})",
+            self.number,
+            self.name,
+            self.entities.bind(py).len()
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core", frozen)]
+pub struct PyDocument {
+    #[pyo3(get)]
+    header: PyHeader,
+    entities: Py<PyList>,
+    block_defs: Py<PyList>,
+    /// Entity indices of each non-zero edit group, by group number.
+    #[pyo3(get)]
+    groups: HashMap<u32, Vec<usize>>,
+}
+
+#[pymethods]
+impl PyDocument {
+    #[getter]
+    fn entities(&self, py: Python<'_>) -> Py<PyList> {
+        self.entities.clone_ref(py)
+    }
+
+    #[getter]
+    fn block_defs(&self, py: Python<'_>) -> Py<PyList> {
+        self.block_defs.clone_ref(py)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PyDocument(version={}, entities={}, block_defs={})",
+            self.header.version,
+            self.entities.bind(py).len(),
+            self.block_defs.bind(py).len()
+        )
+    }
+}
+
+// Builders for assembling DXF output from Python. Entities default to layer
+// "0" with BYLAYER color and line type.
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfLayer {
+    #[pyo3(get, set)]
+    name: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+    #[pyo3(get, set)]
+    frozen: bool,
+    #[pyo3(get, set)]
+    locked: bool,
+}
+
+#[pymethods]
+impl PyDxfLayer {
+    #[new]
+    #[pyo3(signature = (name, color=7, line_type="CONTINUOUS".to_string(), frozen=false, locked=false))]
+    fn new(name: String, color: i32, line_type: String, frozen: bool, locked: bool) -> Self {
+        Self {
+            name,
+            color,
+            line_type,
+            frozen,
+            locked,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PyDxfLayer(name={:?}, color={})", self.name, self.color)
+    }
+}
+
+impl PyDxfLayer {
+    fn to_dxf(&self) -> DxfLayer {
+        DxfLayer {
+            name: self.name.clone(),
+            color: self.color,
+            line_type: self.line_type.clone(),
+            frozen: self.frozen,
+            locked: self.locked,
+        }
+    }
+}
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfLine {
+    #[pyo3(get, set)]
+    x1: f64,
+    #[pyo3(get, set)]
+    y1: f64,
+    #[pyo3(get, set)]
+    x2: f64,
+    #[pyo3(get, set)]
+    y2: f64,
+    #[pyo3(get, set)]
+    layer: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+}
+
+#[pymethods]
+impl PyDxfLine {
+    #[new]
+    #[pyo3(signature = (x1, y1, x2, y2, layer="0".to_string(), color=256, line_type="BYLAYER".to_string()))]
+    fn new(
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+        layer: String,
+        color: i32,
+        line_type: String,
+    ) -> Self {
+        Self {
+            x1,
+            y1,
+            x2,
+            y2,
+            layer,
+            color,
+            line_type,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyDxfLine(({}, {}) -> ({}, {}), layer={:?})",
+            self.x1, self.y1, self.x2, self.y2, self.layer
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfCircle {
+    #[pyo3(get, set)]
+    center_x: f64,
+    #[pyo3(get, set)]
+    center_y: f64,
+    #[pyo3(get, set)]
+    radius: f64,
+    #[pyo3(get, set)]
+    layer: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+}
+
+#[pymethods]
+impl PyDxfCircle {
+    #[new]
+    #[pyo3(signature = (center_x, center_y, radius, layer="0".to_string(), color=256, line_type="BYLAYER".to_string()))]
+    fn new(
+        center_x: f64,
+        center_y: f64,
+        radius: f64,
+        layer: String,
+        color: i32,
+        line_type: String,
+    ) -> Self {
+        Self {
+            center_x,
+            center_y,
+            radius,
+            layer,
+            color,
+            line_type,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyDxfCircle(center=({}, {}), radius={}, layer={:?})",
+            self.center_x, self.center_y, self.radius, self.layer
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfArc {
+    #[pyo3(get, set)]
+    center_x: f64,
+    #[pyo3(get, set)]
+    center_y: f64,
+    #[pyo3(get, set)]
+    radius: f64,
+    #[pyo3(get, set)]
+    start_angle: f64,
+    #[pyo3(get, set)]
+    end_angle: f64,
+    #[pyo3(get, set)]
+    layer: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+}
+
+#[pymethods]
+impl PyDxfArc {
+    /// Angles are in degrees, counter-clockwise from start to end.
+    #[new]
+    #[pyo3(signature = (center_x, center_y, radius, start_angle, end_angle, layer="0".to_string(), color=256, line_type="BYLAYER".to_string()))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        center_x: f64,
+        center_y: f64,
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+        layer: String,
+        color: i32,
+        line_type: String,
+    ) -> Self {
+        Self {
+            center_x,
+            center_y,
+            radius,
+            start_angle,
+            end_angle,
+            layer,
+            color,
+            line_type,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyDxfArc(center=({}, {}), radius={}, angles={}..{}, layer={:?})",
+            self.center_x, self.center_y, self.radius, self.start_angle, self.end_angle, self.layer
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfPoint {
+    #[pyo3(get, set)]
+    x: f64,
+    #[pyo3(get, set)]
+    y: f64,
+    #[pyo3(get, set)]
+    layer: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+}
+
+#[pymethods]
+impl PyDxfPoint {
+    #[new]
+    #[pyo3(signature = (x, y, layer="0".to_string(), color=256, line_type="BYLAYER".to_string()))]
+    fn new(x: f64, y: f64, layer: String, color: i32, line_type: String) -> Self {
+        Self {
+            x,
+            y,
+            layer,
+            color,
+            line_type,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyDxfPoint(({}, {}), layer={:?})",
+            self.x, self.y, self.layer
+        )
+    }
+}
+
+#[pyclass(module = "ezjww._core")]
+#[derive(Debug, Clone)]
+pub struct PyDxfText {
+    #[pyo3(get, set)]
+    x: f64,
+    #[pyo3(get, set)]
+    y: f64,
+    #[pyo3(get, set)]
+    height: f64,
+    #[pyo3(get, set)]
+    content: String,
+    #[pyo3(get, set)]
+    rotation: f64,
+    #[pyo3(get, set)]
+    style: String,
+    #[pyo3(get, set)]
+    layer: String,
+    #[pyo3(get, set)]
+    color: i32,
+    #[pyo3(get, set)]
+    line_type: String,
+}
+
+#[pymethods]
+impl PyDxfText {
+    #[new]
+    #[pyo3(signature = (x, y, height, content, rotation=0.0, style="STANDARD".to_string(), layer="0".to_string(), color=256, line_type="BYLAYER".to_string()))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        x: f64,
+        y: f64,
+        height: f64,
+        content: String,
+        rotation: f64,
+        style: String,
+        layer: String,
+        color: i32,
+        line_type: String,
+    ) -> Self {
+        Self {
+            x,
+            y,
+            height,
+            content,
+            rotation,
+            style,
+            layer,
+            color,
+            line_type,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PyDxfText(({}, {}), content={:?}, layer={:?})",
+            self.x, self.y, self.content, self.layer
+        )
+    }
+}
+
+/// DXF document assembled from Python. `layers` and `entities` are live lists
+/// that can be appended to after construction.
+#[pyclass(module = "ezjww._core")]
+pub struct PyDxfDocument {
+    layers: Py<PyList>,
+    entities: Py<PyList>,
+}
+
+#[pymethods]
+impl PyDxfDocument {
+    #[new]
+    #[pyo3(signature = (layers=Vec::new(), entities=Vec::new()))]
+    fn new(py: Python<'_>, layers: Vec<PyObject>, entities: Vec<PyObject>) -> Self {
+        Self {
+            layers: PyList::new_bound(py, layers).unbind(),
+            entities: PyList::new_bound(py, entities).unbind(),
+        }
+    }
+
+    #[getter]
+    fn layers(&self, py: Python<'_>) -> Py<PyList> {
+        self.layers.clone_ref(py)
+    }
+
+    #[getter]
+    fn entities(&self, py: Python<'_>) -> Py<PyList> {
+        self.entities.clone_ref(py)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "PyDxfDocument(layers={}, entities={})",
+            self.layers.bind(py).len(),
+            self.entities.bind(py).len()
+        )
+    }
+}
+
+impl PyDxfDocument {
+    /// Layer names are cleaned up as in a JWW conversion, with entities
+    /// following their layer's new name. Every layer is kept, used or not.
+    pub fn to_dxf(&self, py: Python<'_>) -> PyResult<DxfDocument> {
+        let layers = self
+            .layers
+            .bind(py)
+            .iter()
+            .map(|item| Ok(item.extract::<PyRef<'_, PyDxfLayer>>()?.to_dxf()))
+            .collect::<PyResult<Vec<_>>>()?;
+        let mut entities = self
+            .entities
+            .bind(py)
+            .iter()
+            .map(|item| dxf_entity_from_py(&item))
+            .collect::<PyResult<Vec<_>>>()?;
+        let layers = sanitize_layers(layers, &mut entities, &mut [], &mut [], true);
+        Ok(DxfDocument {
+            layers,
+            entities,
+            blocks: Vec::new(),
+            unsupported_entities: Vec::new(),
+            units: TargetUnits::default(),
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::default(),
+        })
+    }
+}
+
+// NaN and infinity have no DXF spelling, so they are refused up front.
+fn dxf_entity_from_py(item: &Bound<'_, PyAny>) -> PyResult<DxfEntity> {
+    let entity = dxf_entity_from_py_unchecked(item)?;
+    if entity_floats(&entity)
+        .iter()
+        .any(|value| !value.is_finite())
+    {
+        return Err(PyValueError::new_err(format!(
+            "{} has a NaN or infinite value",
+            item.repr()?
+        )));
+    }
+    Ok(entity)
+}
+
+fn dxf_entity_from_py_unchecked(item: &Bound<'_, PyAny>) -> PyResult<DxfEntity> {
+    if let Ok(v) = item.extract::<PyRef<'_, PyDxfLine>>() {
+        return Ok(DxfEntity::Line(DxfLine {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x1: v.x1,
+            y1: v.y1,
+            x2: v.x2,
+            y2: v.y2,
+        }));
+    }
+    if let Ok(v) = item.extract::<PyRef<'_, PyDxfCircle>>() {
+        return Ok(DxfEntity::Circle(DxfCircle {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            center_x: v.center_x,
+            center_y: v.center_y,
+            radius: v.radius,
+        }));
+    }
+    if let Ok(v) = item.extract::<PyRef<'_, PyDxfArc>>() {
+        return Ok(DxfEntity::Arc(DxfArc {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            center_x: v.center_x,
+            center_y: v.center_y,
+            radius: v.radius,
+            start_angle: v.start_angle,
+            end_angle: v.end_angle,
+        }));
+    }
+    if let Ok(v) = item.extract::<PyRef<'_, PyDxfPoint>>() {
+        return Ok(DxfEntity::Point(DxfPoint {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x: v.x,
+            y: v.y,
+        }));
+    }
+    if let Ok(v) = item.extract::<PyRef<'_, PyDxfText>>() {
+        return Ok(DxfEntity::Text(DxfText {
+            layer: v.layer.clone(),
+            color: v.color,
+            line_type: v.line_type.clone(),
+            true_color: None,
+            xdata: Vec::new(),
+            extrusion: None,
+            x: v.x,
+            y: v.y,
+            height: v.height,
+            rotation: v.rotation,
+            content: v.content.clone(),
+            style: v.style.clone(),
+            width_factor: 1.0,
+            alignment: TextAlignment::default(),
+            alignment_point: None,
+            mirrored: false,
+        }));
+    }
+    Err(PyTypeError::new_err(format!(
+        "unsupported DXF entity: {}",
+        item.get_type().name()?
+    )))
+}
+
+pub fn register_classes(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyLayer>()?;
+    m.add_class::<PyLayerGroup>()?;
+    m.add_class::<PyHeader>()?;
+    m.add_class::<PyEntityBase>()?;
+    m.add_class::<PyEntity>()?;
+    m.add_class::<PyLine>()?;
+    m.add_class::<PyArc>()?;
+    m.add_class::<PyPoint>()?;
+    m.add_class::<PyText>()?;
+    m.add_class::<PySolid>()?;
+    m.add_class::<PyBlock>()?;
+    m.add_class::<PyDimension>()?;
+    m.add_class::<PyPolyline>()?;
+    m.add_class::<PyBlockDef>()?;
+    m.add_class::<PyDocument>()?;
+    m.add_class::<PyDxfLayer>()?;
+    m.add_class::<PyDxfLine>()?;
+    m.add_class::<PyDxfCircle>()?;
+    m.add_class::<PyDxfArc>()?;
+    m.add_class::<PyDxfPoint>()?;
+    m.add_class::<PyDxfText>()?;
+    m.add_class::<PyDxfDocument>()?;
+    Ok(())
+}
+
+pub fn document_to_pyclass(py: Python<'_>, document: &JwwDocument) -> PyResult<Py<PyDocument>> {
+    let block_name_map = block_def_name_map(&document.block_defs);
+
+    let entities = PyList::empty_bound(py);
+    for entity in &document.entities {
+        entities.append(entity_to_pyclass(py, entity, &block_name_map)?)?;
+    }
+
+    let block_defs = PyList::empty_bound(py);
+    for block_def in &document.block_defs {
+        block_defs.append(block_def_to_pyclass(py, block_def, &block_name_map)?)?;
+    }
+
+    Py::new(
+        py,
+        PyDocument {
+            header: header_to_pyclass(&document.header),
+            entities: entities.unbind(),
+            block_defs: block_defs.unbind(),
+            groups: group_entities(&document.entities),
+        },
+    )
+}
+
+fn header_to_pyclass(header: &JwwHeader) -> PyHeader {
+    PyHeader {
+        version: header.version,
+        memo: header.memo.clone(),
+        paper_size: header.paper_size,
+        paper_size_mm: header.paper_size_mm(),
+        write_layer_group: header.write_layer_group,
+        layer_groups: header
+            .layer_groups
+            .iter()
+            .map(layer_group_to_pyclass)
+            .collect(),
+        grid_origin: header.grid_origin.map(|origin| (origin.x, origin.y)),
+    }
+}
+
+fn layer_group_to_pyclass(group: &LayerGroupHeader) -> PyLayerGroup {
+    PyLayerGroup {
+        state: group.state,
+        write_layer: group.write_layer,
+        scale: group.scale,
+        protect: group.protect,
+        name: group.name.clone(),
+        layers: group.layers.iter().map(layer_to_pyclass).collect(),
+    }
+}
+
+fn layer_to_pyclass(layer: &LayerHeader) -> PyLayer {
+    PyLayer {
+        state: layer.state,
+        protect: layer.protect,
+        name: layer.name.clone(),
+    }
+}
+
+pub fn entity_flags_to_pydict<'py>(
+    py: Python<'py>,
+    flags: &EntityFlags,
+) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new_bound(py);
+    out.set_item("figure", flags.figure)?;
+    out.set_item("hatch", flags.hatch)?;
+    out.set_item("dimension", flags.dimension)?;
+    out.set_item("fitting", flags.fitting)?;
+    Ok(out)
+}
+
+fn entity_base_to_pyclass(base: &EntityBase) -> PyEntityBase {
+    PyEntityBase {
+        group: base.group,
+        pen_style: base.pen_style,
+        pen_color: base.pen_color,
+        pen_width: base.pen_width,
+        layer: base.layer,
+        layer_group: base.layer_group,
+        flag: base.flag,
+        seq: base.seq,
+    }
+}
+
+fn entity_initializer(entity_type: &str, base: &EntityBase) -> PyClassInitializer<PyEntity> {
+    PyClassInitializer::from(PyEntity {
+        entity_type: entity_type.to_string(),
+        base: entity_base_to_pyclass(base),
+    })
+}
+
+fn entity_to_pyclass(
+    py: Python<'_>,
+    entity: &Entity,
+    block_name_map: &HashMap<u32, String>,
+) -> PyResult<PyObject> {
+    let init = entity_initializer(entity.entity_type(), entity.base());
+    let object = match entity {
+        Entity::Line(v) => line_to_pyclass(py, v)?.into_any(),
+        Entity::Arc(v) => Py::new(
+            py,
+            init.add_subclass(PyArc {
+                center_x: v.center_x,
+                center_y: v.center_y,
+                radius: v.radius,
+                start_angle: v.start_angle,
+                arc_angle: v.arc_angle,
+                tilt_angle: v.tilt_angle,
+                flatness: v.flatness,
+                is_full_circle: v.is_full_circle,
+            }),
+        )?
+        .into_any(),
+        Entity::Point(v) => point_to_pyclass(py, v)?.into_any(),
+        Entity::Text(v) => text_to_pyclass(py, v)?.into_any(),
+        Entity::Solid(v) => Py::new(
+            py,
+            init.add_subclass(PySolid {
+                point1_x: v.point1_x,
+                point1_y: v.point1_y,
+                point2_x: v.point2_x,
+                point2_y: v.point2_y,
+                point3_x: v.point3_x,
+                point3_y: v.point3_y,
+                point4_x: v.point4_x,
+                point4_y: v.point4_y,
+                color: v.color,
+                is_filled: v.is_filled,
+            }),
+        )?
+        .into_any(),
+        Entity::Block(v) => Py::new(
+            py,
+            init.add_subclass(PyBlock {
+                ref_x: v.ref_x,
+                ref_y: v.ref_y,
+                scale_x: v.scale_x,
+                scale_y: v.scale_y,
+                rotation: v.rotation,
+                def_number: v.def_number,
+                block_name: block_name_map.get(&v.def_number).cloned(),
+                attributes: v.attributes.clone(),
+            }),
+        )?
+        .into_any(),
+        Entity::Dimension(v) => {
+            let aux_lines = PyList::empty_bound(py);
+            for line in &v.aux_lines {
+                aux_lines.append(line_to_pyclass(py, line)?)?;
+            }
+            let aux_points = PyList::empty_bound(py);
+            for point in &v.aux_points {
+                aux_points.append(point_to_pyclass(py, point)?)?;
+            }
+            Py::new(
+                py,
+                init.add_subclass(PyDimension {
+                    line: line_to_pyclass(py, &v.line)?,
+                    text: text_to_pyclass(py, &v.text)?,
+                    sxf_mode: v.sxf_mode,
+                    aux_lines: aux_lines.unbind(),
+                    aux_points: aux_points.unbind(),
+                }),
+            )?
+            .into_any()
+        }
+        Entity::Polyline(v) => Py::new(
+            py,
+            init.add_subclass(PyPolyline {
+                vertices: v
+                    .vertices
+                    .iter()
+                    .map(|vertex| (vertex.point.x, vertex.point.y, vertex.bulge))
+                    .collect(),
+                closed: v.closed,
+            }),
+        )?
+        .into_any(),
+    };
+    Ok(object)
+}
+
+fn line_fields(line: &Line) -> PyLine {
+    PyLine {
+        start_x: line.start_x,
+        start_y: line.start_y,
+        end_x: line.end_x,
+        end_y: line.end_y,
+    }
+}
+
+fn point_fields(point: &Point) -> PyPoint {
+    PyPoint {
+        x: point.x,
+        y: point.y,
+        is_temporary: point.is_temporary,
+        code: point.code,
+        angle: point.angle,
+        scale: point.scale,
+    }
+}
+
+fn text_fields(text: &Text) -> PyText {
+    PyText {
+        start_x: text.start_x,
+        start_y: text.start_y,
+        end_x: text.end_x,
+        end_y: text.end_y,
+        text_type: text.text_type,
+        size_x: text.size_x,
+        size_y: text.size_y,
+        spacing: text.spacing,
+        angle: text.angle,
+        font_name: text.font_name.clone(),
+        content: text.content.clone(),
+    }
+}
+
+fn line_to_pyclass(py: Python<'_>, line: &Line) -> PyResult<Py<PyLine>> {
+    Py::new(
+        py,
+        entity_initializer("LINE", &line.base).add_subclass(line_fields(line)),
+    )
+}
+
+fn point_to_pyclass(py: Python<'_>, point: &Point) -> PyResult<Py<PyPoint>> {
+    Py::new(
+        py,
+        entity_initializer("POINT", &point.base).add_subclass(point_fields(point)),
+    )
+}
+
+fn text_to_pyclass(py: Python<'_>, text: &Text) -> PyResult<Py<PyText>> {
+    Py::new(
+        py,
+        entity_initializer("TEXT", &text.base).add_subclass(text_fields(text)),
+    )
+}
+
+fn block_def_to_pyclass(
+    py: Python<'_>,
+    block_def: &BlockDef,
+    block_name_map: &HashMap<u32, String>,
+) -> PyResult<Py<PyBlockDef>> {
+    let entities = PyList::empty_bound(py);
+    for entity in &block_def.entities {
+        entities.append(entity_to_pyclass(py, entity, block_name_map)?)?;
+    }
+    Py::new(
+        py,
+        PyBlockDef {
+            number: block_def.number,
+            is_referenced: block_def.is_referenced,
+            created_time: block_def.created_time,
+            name: block_def.name.clone(),
+            base: entity_base_to_pyclass(&block_def.base),
+            entities: entities.unbind(),
+        },
+    )
+}
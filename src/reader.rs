@@ -1,24 +1,54 @@
 use std::io::Cursor;
 
-use encoding_rs::SHIFT_JIS;
+use encoding_rs::{Encoding, SHIFT_JIS};
 
 use crate::error::JwwError;
 
 pub struct Reader<'a> {
     cursor: Cursor<&'a [u8]>,
+    encoding: &'static Encoding,
 }
 
 impl<'a> Reader<'a> {
     pub fn new(data: &'a [u8]) -> Self {
+        Self::with_encoding(data, SHIFT_JIS)
+    }
+
+    pub fn with_encoding(data: &'a [u8], encoding: &'static Encoding) -> Self {
         Self {
             cursor: Cursor::new(data),
+            encoding,
         }
     }
 
+    /// A fresh reader over the unread bytes that decodes strings the same way.
+    pub fn lookahead(&self) -> Reader<'a> {
+        Reader::with_encoding(self.remaining(), self.encoding)
+    }
+
     pub fn bytes_read(&self) -> usize {
         self.cursor.position() as usize
     }
 
+    /// Offset of the next byte to read; the same as [`Self::bytes_read`].
+    pub fn position(&self) -> usize {
+        self.bytes_read()
+    }
+
+    /// Moves to `position`, which may be the end of the data but not past it.
+    pub fn set_position(&mut self, position: usize) -> Result<(), JwwError> {
+        if position > self.cursor.get_ref().len() {
+            return Err(JwwError::UnexpectedEof("bytes"));
+        }
+        self.cursor.set_position(position as u64);
+        Ok(())
+    }
+
+    pub fn remaining(&self) -> &'a [u8] {
+        let data = *self.cursor.get_ref();
+        &data[self.bytes_read().min(data.len())..]
+    }
+
     pub fn skip(&mut self, len: usize) -> Result<(), JwwError> {
         let pos = self.bytes_read();
         let new_pos = pos
@@ -39,10 +69,25 @@ impl<'a> Reader<'a> {
         Ok(u16::from_le_bytes(self.read_exact::<2>()?))
     }
 
+    /// Reads the next u16 without advancing, e.g. a record's class tag.
+    pub fn peek_u16(&self) -> Result<u16, JwwError> {
+        self.lookahead().read_u16()
+    }
+
     pub fn read_u32(&mut self) -> Result<u32, JwwError> {
         Ok(u32::from_le_bytes(self.read_exact::<4>()?))
     }
 
+    /// MFC list count: a u16, or 0xFFFF followed by a u32 for larger lists.
+    pub fn read_count(&mut self) -> Result<usize, JwwError> {
+        let count = self.read_u16()?;
+        if count < 0xFFFF {
+            Ok(count as usize)
+        } else {
+            Ok(self.read_u32()? as usize)
+        }
+    }
+
     pub fn read_f64(&mut self) -> Result<f64, JwwError> {
         Ok(f64::from_le_bytes(self.read_exact::<8>()?))
     }
@@ -71,7 +116,7 @@ impl<'a> Reader<'a> {
         }
 
         let bytes = self.read_bytes(len)?;
-        let (decoded, _, _) = SHIFT_JIS.decode(&bytes);
+        let (decoded, _, _) = self.encoding.decode(&bytes);
         Ok(decoded.trim_end_matches('\0').to_string())
     }
 
@@ -98,6 +143,8 @@ impl<'a> Reader<'a> {
 
 #[cfg(test)]
 mod tests {
+    use crate::error::JwwError;
+
     use super::Reader;
 
     #[test]
@@ -122,6 +169,38 @@ mod tests {
         assert_eq!(reader.read_cstring().unwrap(), "test");
     }
 
+    #[test]
+    fn peek_u16_does_not_advance() {
+        let data = [0xFF, 0xFF, 0x01, 0x00];
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.peek_u16().unwrap(), 0xFFFF);
+        assert_eq!(reader.position(), 0);
+        assert_eq!(reader.read_u16().unwrap(), 0xFFFF);
+        assert_eq!(reader.peek_u16().unwrap(), 1);
+        assert_eq!(reader.position(), 2);
+
+        reader.skip(1).unwrap();
+        assert!(matches!(reader.peek_u16(), Err(JwwError::UnexpectedEof(_))));
+        assert_eq!(reader.position(), 3);
+    }
+
+    #[test]
+    fn set_position_checks_bounds() {
+        let data = [1, 2, 3];
+        let mut reader = Reader::new(&data);
+        reader.set_position(2).unwrap();
+        assert_eq!(reader.read_u8().unwrap(), 3);
+        reader.set_position(3).unwrap();
+        assert!(reader.remaining().is_empty());
+        assert!(matches!(
+            reader.set_position(4),
+            Err(JwwError::UnexpectedEof(_))
+        ));
+        assert_eq!(reader.position(), 3);
+        reader.set_position(0).unwrap();
+        assert_eq!(reader.read_u8().unwrap(), 1);
+    }
+
     #[test]
     fn read_cstring_empty() {
         let data = [0];
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::error::JwwError;
+use crate::header::{is_jww_signature, parse_header_with_encoding, JwwHeader, JWW_SIGNATURE};
+use crate::model::Entity;
+use crate::parser::{
+    find_entity_list_offset, parse_entity_with_pid_tracking, ParseOptions, ParseStep,
+};
+use crate::reader::Reader;
+
+const STREAM_CHUNK_LEN: usize = 64 * 1024;
+// Bytes buffered before each record is parsed. Records are read without
+// peeking past their end, so this only saves refills: a longer record is
+// retried with more of the file.
+const STREAM_LOOKAHEAD_LEN: usize = 4 * 1024;
+// Bytes that must follow a candidate entity list offset before it is trusted;
+// covers the longest class-name signature the offset scan accepts.
+const ENTITY_LIST_SIGNATURE_LEN: usize = 64;
+
+/// Parses top-level entities one at a time from any seekable source, keeping
+/// only a bounded window of the file in memory. Block definitions, which
+/// follow the entity list, are not read.
+pub struct EntityStream<R> {
+    source: R,
+    options: ParseOptions,
+    header: JwwHeader,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    chunk_len: usize,
+    lookahead_len: usize,
+    remaining: usize,
+    pid_to_class_name: HashMap<u32, String>,
+    next_pid: u32,
+    next_seq: usize,
+    failed: bool,
+}
+
+impl<R: Read + Seek> EntityStream<R> {
+    pub fn new(source: R) -> Result<Self, JwwError> {
+        Self::with_options(source, &ParseOptions::default())
+    }
+
+    pub fn with_options(source: R, options: &ParseOptions) -> Result<Self, JwwError> {
+        Self::with_buffer_sizes(source, options, STREAM_CHUNK_LEN, STREAM_LOOKAHEAD_LEN)
+    }
+
+    pub(crate) fn with_buffer_sizes(
+        mut source: R,
+        options: &ParseOptions,
+        chunk_len: usize,
+        lookahead_len: usize,
+    ) -> Result<Self, JwwError> {
+        source.seek(SeekFrom::Start(0))?;
+        let mut stream = Self {
+            source,
+            options: *options,
+            header: JwwHeader {
+                version: 0,
+                memo: String::new(),
+                paper_size: 0,
+                write_layer_group: 0,
+                layer_groups: std::array::from_fn(|_| Default::default()),
+                grid_origin: None,
+            },
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            chunk_len: chunk_len.max(1),
+            lookahead_len,
+            remaining: 0,
+            pid_to_class_name: HashMap::new(),
+            next_pid: 1,
+            next_seq: 0,
+            failed: false,
+        };
+        let offset = stream.locate_entity_list()?;
+        stream.header = parse_header_with_encoding(&stream.buf, stream.options.encoding)?;
+        stream.pos = offset;
+        stream.fill(6)?;
+        let mut reader = Reader::with_encoding(&stream.buf[stream.pos..], stream.options.encoding);
+        stream.remaining = reader.read_count()?;
+        stream.pos += reader.bytes_read();
+        Ok(stream)
+    }
+
+    pub fn header(&self) -> &JwwHeader {
+        &self.header
+    }
+
+    // The header has no length field, so the file is read in growing prefixes
+    // until the same offset scan the eager parser uses finds the entity list.
+    fn locate_entity_list(&mut self) -> Result<usize, JwwError> {
+        loop {
+            self.read_chunk()?;
+            if self.buf.len() >= JWW_SIGNATURE.len() && !is_jww_signature(&self.buf) {
+                return Err(JwwError::InvalidSignature);
+            }
+            if self.buf.len() >= JWW_SIGNATURE.len() + 4 {
+                let version = u32::from_le_bytes(
+                    self.buf[JWW_SIGNATURE.len()..JWW_SIGNATURE.len() + 4]
+                        .try_into()
+                        .expect("slice is four bytes"),
+                );
+                if let Some(offset) = find_entity_list_offset(&self.buf, version) {
+                    // A candidate near the end of the window may hide an
+                    // earlier one that was cut off, so only trust it once it
+                    // is fully buffered.
+                    if self.eof || offset + ENTITY_LIST_SIGNATURE_LEN <= self.buf.len() {
+                        return Ok(offset);
+                    }
+                }
+            }
+            if self.eof {
+                return Err(if is_jww_signature(&self.buf) {
+                    JwwError::EntityListNotFound
+                } else {
+                    JwwError::InvalidSignature
+                });
+            }
+        }
+    }
+
+    fn fill(&mut self, min_len: usize) -> io::Result<()> {
+        while self.buf.len() - self.pos < min_len && !self.eof {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+            self.read_chunk()?;
+        }
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> io::Result<()> {
+        let start = self.buf.len();
+        self.buf.resize(start + self.chunk_len, 0);
+        let read = loop {
+            match self.source.read(&mut self.buf[start..]) {
+                Ok(read) => break read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.buf.truncate(start);
+                    return Err(err);
+                }
+            }
+        };
+        self.buf.truncate(start + read);
+        self.eof = read == 0;
+        Ok(())
+    }
+
+    fn next_entity(&mut self) -> Result<Option<Entity>, JwwError> {
+        let version = self.header.version;
+        let mut min_len = self.lookahead_len;
+        while self.remaining > 0 {
+            self.fill(min_len)?;
+            let mut reader = Reader::with_encoding(&self.buf[self.pos..], self.options.encoding);
+            match parse_entity_with_pid_tracking(
+                &mut reader,
+                version,
+                &mut self.pid_to_class_name,
+                self.next_pid,
+            ) {
+                Ok((step, next_pid)) => {
+                    self.pos += reader.bytes_read();
+                    self.next_pid = next_pid;
+                    self.remaining -= 1;
+                    min_len = self.lookahead_len;
+                    match step {
+                        ParseStep::Entity(mut entity) => {
+                            entity.base_mut().seq = self.next_seq;
+                            self.next_seq += 1;
+                            return Ok(Some(entity));
+                        }
+                        ParseStep::End => self.remaining = 0,
+                    }
+                }
+                // A record longer than the window; retry with more buffered.
+                Err(JwwError::UnexpectedEof(_)) if !self.eof => {
+                    min_len = self.buf.len() - self.pos + self.chunk_len;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read + Seek> Iterator for EntityStream<R> {
+    type Item = Result<Entity, JwwError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.next_entity() {
+            Ok(entity) => entity.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Cursor;
+    use std::path::Path;
+
+    use crate::error::JwwError;
+    use crate::parser::{read_document_from_file, ParseOptions};
+
+    use super::EntityStream;
+
+    #[test]
+    fn entity_stream_matches_eager_parse_for_samples() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples");
+        let mut files = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "jww").unwrap_or(false))
+            .collect::<Vec<_>>();
+        files.sort();
+
+        for path in files {
+            let doc = read_document_from_file(&path).unwrap();
+
+            let stream = EntityStream::new(File::open(&path).unwrap()).unwrap();
+            assert_eq!(stream.header(), &doc.header, "{}", path.display());
+            let entities = stream.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(entities, doc.entities, "{}", path.display());
+
+            // A tiny window forces every refill and retry path.
+            let stream = EntityStream::with_buffer_sizes(
+                File::open(&path).unwrap(),
+                &ParseOptions::default(),
+                7,
+                64,
+            )
+            .unwrap();
+            let entities = stream.collect::<Result<Vec<_>, _>>().unwrap();
+            assert_eq!(entities, doc.entities, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn entity_stream_rejects_non_jww_data() {
+        let result = EntityStream::new(Cursor::new(b"NotJwwData at all".to_vec()));
+        assert!(matches!(result, Err(JwwError::InvalidSignature)));
+    }
+}
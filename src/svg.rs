@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::dxf::{
+    DxfBlock, DxfDocument, DxfEntity, DxfInsert, DxfLwPolyline, HorizontalAlignment, TextAlignment,
+    VerticalAlignment,
+};
+
+const MAX_SVG_BLOCK_NESTING: usize = 32;
+const ELLIPSE_SEGMENTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SvgOptions {
+    /// Output width in pixels; defaults to the drawing extents in drawing units.
+    pub width: Option<f64>,
+    /// Output height in pixels; defaults to the drawing extents in drawing units.
+    pub height: Option<f64>,
+}
+
+pub fn document_to_svg(doc: &DxfDocument) -> String {
+    document_to_svg_with_options(doc, SvgOptions::default())
+}
+
+// JWW/DXF coordinates are Y-up while SVG is Y-down, so every emitted point is
+// written as (x, -y) and the viewBox is built from the flipped extents.
+pub fn document_to_svg_with_options(doc: &DxfDocument, options: SvgOptions) -> String {
+    let blocks = doc
+        .blocks
+        .iter()
+        .map(|block| (block.name.as_str(), block))
+        .collect::<HashMap<_, _>>();
+    let layer_colors = doc
+        .layers
+        .iter()
+        .map(|layer| (layer.name.as_str(), layer.color))
+        .collect::<HashMap<_, _>>();
+
+    let mut extents = Extents::default();
+    for entity in &doc.entities {
+        extend_entity(
+            &mut extents,
+            entity,
+            &SvgTransform::identity(),
+            &blocks,
+            &mut Vec::new(),
+        );
+    }
+    let (min_x, min_y, max_x, max_y) = extents.bounds().unwrap_or((0.0, 0.0, 1.0, 1.0));
+    let margin = ((max_x - min_x).max(max_y - min_y) * 0.02).max(1e-6);
+    let view_x = min_x - margin;
+    let view_y = -max_y - margin;
+    let view_w = (max_x - min_x) + margin * 2.0;
+    let view_h = (max_y - min_y) + margin * 2.0;
+
+    let mut writer = SvgWriter {
+        out: String::new(),
+        layer_colors,
+        blocks: &blocks,
+        point_radius: view_w.max(view_h) / 500.0,
+    };
+    let _ = writeln!(
+        writer.out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+        fmt_num(options.width.unwrap_or(view_w)),
+        fmt_num(options.height.unwrap_or(view_h)),
+        fmt_num(view_x),
+        fmt_num(view_y),
+        fmt_num(view_w),
+        fmt_num(view_h)
+    );
+    if !doc.blocks.is_empty() {
+        writer.out.push_str("<defs>\n");
+        for block in &doc.blocks {
+            writer.write_block(block);
+        }
+        writer.out.push_str("</defs>\n");
+    }
+    writer.out.push_str(
+        "<g fill=\"none\" stroke-width=\"1\" stroke-linecap=\"round\" stroke-linejoin=\"round\">\n",
+    );
+    for entity in &doc.entities {
+        writer.write_entity(entity);
+    }
+    writer.out.push_str("</g>\n</svg>\n");
+    writer.out
+}
+
+pub fn write_svg_to_file(
+    doc: &DxfDocument,
+    options: SvgOptions,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    fs::write(path, document_to_svg_with_options(doc, options))
+}
+
+struct SvgWriter<'a> {
+    out: String,
+    layer_colors: HashMap<&'a str, i32>,
+    blocks: &'a HashMap<&'a str, &'a DxfBlock>,
+    point_radius: f64,
+}
+
+impl SvgWriter<'_> {
+    fn write_block(&mut self, block: &DxfBlock) {
+        let _ = writeln!(self.out, "<g id=\"{}\">", block_id(&block.name));
+        for entity in &block.entities {
+            self.write_entity(entity);
+        }
+        self.out.push_str("</g>\n");
+    }
+
+    fn write_entity(&mut self, entity: &DxfEntity) {
+        match entity {
+            DxfEntity::Line(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let _ = writeln!(
+                    self.out,
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    fmt_num(v.x1),
+                    fmt_num(-v.y1),
+                    fmt_num(v.x2),
+                    fmt_num(-v.y2)
+                );
+            }
+            DxfEntity::Circle(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let _ = writeln!(
+                    self.out,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    fmt_num(v.center_x),
+                    fmt_num(-v.center_y),
+                    fmt_num(v.radius)
+                );
+            }
+            DxfEntity::Arc(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let sweep = normalize_deg(v.end_angle - v.start_angle);
+                let start = deg_to_rad(v.start_angle);
+                let end = deg_to_rad(v.end_angle);
+                let large_arc = i32::from(sweep > 180.0);
+                // Counter-clockwise in Y-up space is sweep-flag 0 once flipped.
+                let _ = writeln!(
+                    self.out,
+                    "<path d=\"M {} {} A {} {} 0 {large_arc} 0 {} {}\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    fmt_num(v.center_x + v.radius * start.cos()),
+                    fmt_num(-(v.center_y + v.radius * start.sin())),
+                    fmt_num(v.radius),
+                    fmt_num(v.radius),
+                    fmt_num(v.center_x + v.radius * end.cos()),
+                    fmt_num(-(v.center_y + v.radius * end.sin()))
+                );
+            }
+            DxfEntity::Ellipse(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let points = ellipse_points(
+                    v.center_x,
+                    v.center_y,
+                    v.major_axis_x,
+                    v.major_axis_y,
+                    v.minor_ratio,
+                    v.start_param,
+                    v.end_param,
+                );
+                let _ = writeln!(
+                    self.out,
+                    "<polyline points=\"{}\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    points_attr(&points)
+                );
+            }
+            DxfEntity::Point(v) => {
+                let fill = self.color(&v.layer, v.color);
+                let _ = writeln!(
+                    self.out,
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{fill}\" stroke=\"none\"/>",
+                    fmt_num(v.x),
+                    fmt_num(-v.y),
+                    fmt_num(self.point_radius)
+                );
+            }
+            DxfEntity::Text(v) => {
+                let fill = self.color(&v.layer, v.color);
+                let _ = writeln!(
+                    self.out,
+                    "<text x=\"{x}\" y=\"{y}\" font-size=\"{}\" transform=\"rotate({} {x} {y})\"{} fill=\"{fill}\" stroke=\"none\">{}</text>",
+                    fmt_num(v.height),
+                    fmt_num(-v.rotation),
+                    text_anchor_attrs(&v.alignment),
+                    escape_xml(&v.content),
+                    x = fmt_num(v.x),
+                    y = fmt_num(-v.y),
+                );
+            }
+            DxfEntity::MText(v) => {
+                let fill = self.color(&v.layer, v.color);
+                let x = fmt_num(v.x);
+                let y = fmt_num(-v.y);
+                let _ = write!(
+                    self.out,
+                    "<text x=\"{x}\" y=\"{y}\" font-size=\"{}\" transform=\"rotate({} {x} {y})\" fill=\"{fill}\" stroke=\"none\">",
+                    fmt_num(v.height),
+                    fmt_num(-v.rotation)
+                );
+                // Lines hang below the insertion point, matching the top-left attachment.
+                for (index, line) in v.content.split('\n').enumerate() {
+                    let dy = if index == 0 { v.height } else { v.height * 1.5 };
+                    let _ = write!(
+                        self.out,
+                        "<tspan x=\"{x}\" dy=\"{}\">{}</tspan>",
+                        fmt_num(dy),
+                        escape_xml(line)
+                    );
+                }
+                self.out.push_str("</text>\n");
+            }
+            DxfEntity::Solid(v) => {
+                let fill = self.color(&v.layer, v.color);
+                // DXF SOLID vertices are ordered 1-2-4-3 around the outline.
+                let points = [(v.x1, v.y1), (v.x2, v.y2), (v.x4, v.y4), (v.x3, v.y3)];
+                let _ = writeln!(
+                    self.out,
+                    "<polygon points=\"{}\" fill=\"{fill}\" stroke=\"none\"/>",
+                    points_attr(&points)
+                );
+            }
+            DxfEntity::Face3D(v) => {
+                let fill = self.color(&v.layer, v.color);
+                let points = [(v.x1, v.y1), (v.x2, v.y2), (v.x3, v.y3), (v.x4, v.y4)];
+                let _ = writeln!(
+                    self.out,
+                    "<polygon points=\"{}\" fill=\"{fill}\" stroke=\"none\"/>",
+                    points_attr(&points)
+                );
+            }
+            // The raster itself is not embedded; its frame marks where it goes.
+            DxfEntity::Image(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let points = v.corners().map(|(x, y)| (x, -y));
+                let _ = writeln!(
+                    self.out,
+                    "<polygon points=\"{}\" fill=\"none\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    points_attr(&points)
+                );
+            }
+            DxfEntity::LwPolyline(v) => {
+                let stroke = self.color(&v.layer, v.color);
+                let _ = writeln!(
+                    self.out,
+                    "<path d=\"{}\" stroke=\"{stroke}\" vector-effect=\"non-scaling-stroke\"/>",
+                    polyline_path(v)
+                );
+            }
+            DxfEntity::Insert(v) => {
+                let (base_x, base_y) = self
+                    .blocks
+                    .get(v.block_name.as_str())
+                    .map(|block| (block.base_x, block.base_y))
+                    .unwrap_or((0.0, 0.0));
+                let transform = SvgTransform::from_insert(v, base_x, base_y);
+                let _ = writeln!(
+                    self.out,
+                    "<use xlink:href=\"#{}\" transform=\"{}\"/>",
+                    block_id(&v.block_name),
+                    transform.to_svg_matrix()
+                );
+            }
+        }
+    }
+
+    fn color(&self, layer: &str, color: i32) -> String {
+        let aci = match color {
+            256 => self.layer_colors.get(layer).copied().unwrap_or(7),
+            _ => color,
+        };
+        aci_to_rgb(aci)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SvgTransform {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    tx: f64,
+    ty: f64,
+}
+
+impl SvgTransform {
+    fn identity() -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            tx: 0.0,
+            ty: 0.0,
+        }
+    }
+
+    // Insert placement in Y-up drawing space, with the block base point moved to the origin.
+    fn from_insert(insert: &DxfInsert, base_x: f64, base_y: f64) -> Self {
+        let rotation = deg_to_rad(insert.rotation);
+        let (sin, cos) = rotation.sin_cos();
+        let a = cos * insert.scale_x;
+        let b = sin * insert.scale_x;
+        let c = -sin * insert.scale_y;
+        let d = cos * insert.scale_y;
+        Self {
+            a,
+            b,
+            c,
+            d,
+            tx: insert.x - (a * base_x + c * base_y),
+            ty: insert.y - (b * base_x + d * base_y),
+        }
+    }
+
+    fn compose(&self, rhs: &Self) -> Self {
+        Self {
+            a: self.a * rhs.a + self.c * rhs.b,
+            b: self.b * rhs.a + self.d * rhs.b,
+            c: self.a * rhs.c + self.c * rhs.d,
+            d: self.b * rhs.c + self.d * rhs.d,
+            tx: self.a * rhs.tx + self.c * rhs.ty + self.tx,
+            ty: self.b * rhs.tx + self.d * rhs.ty + self.ty,
+        }
+    }
+
+    fn apply_point(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    // Conjugates the transform with the Y flip so it applies to already flipped block content.
+    fn to_svg_matrix(self) -> String {
+        format!(
+            "matrix({} {} {} {} {} {})",
+            fmt_num(self.a),
+            fmt_num(-self.b),
+            fmt_num(-self.c),
+            fmt_num(self.d),
+            fmt_num(self.tx),
+            fmt_num(-self.ty)
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Extents {
+    bounds: Option<(f64, f64, f64, f64)>,
+}
+
+impl Extents {
+    fn add(&mut self, x: f64, y: f64) {
+        if !x.is_finite() || !y.is_finite() {
+            return;
+        }
+        self.bounds = Some(match self.bounds {
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+            None => (x, y, x, y),
+        });
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        self.bounds
+    }
+}
+
+fn extend_entity<'a>(
+    extents: &mut Extents,
+    entity: &'a DxfEntity,
+    transform: &SvgTransform,
+    blocks: &HashMap<&str, &'a DxfBlock>,
+    stack: &mut Vec<&'a str>,
+) {
+    let mut add = |x: f64, y: f64| {
+        let (x, y) = transform.apply_point(x, y);
+        extents.add(x, y);
+    };
+    match entity {
+        DxfEntity::Line(v) => {
+            add(v.x1, v.y1);
+            add(v.x2, v.y2);
+        }
+        DxfEntity::Circle(v) => {
+            add(v.center_x - v.radius, v.center_y - v.radius);
+            add(v.center_x + v.radius, v.center_y + v.radius);
+            add(v.center_x - v.radius, v.center_y + v.radius);
+            add(v.center_x + v.radius, v.center_y - v.radius);
+        }
+        DxfEntity::Arc(v) => {
+            let sweep = normalize_deg(v.end_angle - v.start_angle);
+            let mut angles = vec![v.start_angle, v.start_angle + sweep];
+            let mut quadrant = (v.start_angle / 90.0).ceil() * 90.0;
+            while quadrant < v.start_angle + sweep {
+                angles.push(quadrant);
+                quadrant += 90.0;
+            }
+            for angle in angles {
+                let rad = deg_to_rad(angle);
+                add(
+                    v.center_x + v.radius * rad.cos(),
+                    v.center_y + v.radius * rad.sin(),
+                );
+            }
+        }
+        DxfEntity::Ellipse(v) => {
+            for (x, y) in ellipse_points(
+                v.center_x,
+                v.center_y,
+                v.major_axis_x,
+                v.major_axis_y,
+                v.minor_ratio,
+                v.start_param,
+                v.end_param,
+            ) {
+                add(x, -y);
+            }
+        }
+        DxfEntity::Point(v) => add(v.x, v.y),
+        DxfEntity::Text(v) => add(v.x, v.y),
+        DxfEntity::MText(v) => add(v.x, v.y),
+        DxfEntity::Solid(v) => {
+            add(v.x1, v.y1);
+            add(v.x2, v.y2);
+            add(v.x3, v.y3);
+            add(v.x4, v.y4);
+        }
+        DxfEntity::Face3D(v) => {
+            add(v.x1, v.y1);
+            add(v.x2, v.y2);
+            add(v.x3, v.y3);
+            add(v.x4, v.y4);
+        }
+        DxfEntity::LwPolyline(v) => {
+            for vertex in &v.vertices {
+                add(vertex.x, vertex.y);
+            }
+        }
+        DxfEntity::Image(v) => {
+            for (x, y) in v.corners() {
+                add(x, y);
+            }
+        }
+        DxfEntity::Insert(v) => {
+            let Some(block) = blocks.get(v.block_name.as_str()) else {
+                return;
+            };
+            if stack.len() >= MAX_SVG_BLOCK_NESTING || stack.contains(&block.name.as_str()) {
+                return;
+            }
+            let nested =
+                transform.compose(&SvgTransform::from_insert(v, block.base_x, block.base_y));
+            stack.push(block.name.as_str());
+            for child in &block.entities {
+                extend_entity(extents, child, &nested, blocks, stack);
+            }
+            stack.pop();
+        }
+    }
+}
+
+// Returns flipped (screen space) points sampled along the ellipse.
+fn ellipse_points(
+    center_x: f64,
+    center_y: f64,
+    major_axis_x: f64,
+    major_axis_y: f64,
+    minor_ratio: f64,
+    start_param: f64,
+    end_param: f64,
+) -> Vec<(f64, f64)> {
+    let mut sweep = end_param - start_param;
+    if sweep <= 0.0 {
+        sweep += 2.0 * PI;
+    }
+    let minor_x = -major_axis_y * minor_ratio;
+    let minor_y = major_axis_x * minor_ratio;
+    let segments = ((sweep / (2.0 * PI)) * ELLIPSE_SEGMENTS as f64)
+        .ceil()
+        .max(1.0) as usize;
+    (0..=segments)
+        .map(|i| {
+            let t = start_param + sweep * (i as f64) / (segments as f64);
+            let x = center_x + major_axis_x * t.cos() + minor_x * t.sin();
+            let y = center_y + major_axis_y * t.cos() + minor_y * t.sin();
+            (x, -y)
+        })
+        .collect()
+}
+
+fn polyline_path(polyline: &DxfLwPolyline) -> String {
+    let mut d = String::new();
+    let Some(first) = polyline.vertices.first() else {
+        return d;
+    };
+    let _ = write!(d, "M {} {}", fmt_num(first.x), fmt_num(-first.y));
+    let count = polyline.vertices.len();
+    let segments = if polyline.closed { count } else { count - 1 };
+    for index in 0..segments {
+        let from = &polyline.vertices[index];
+        let to = &polyline.vertices[(index + 1) % count];
+        if from.bulge == 0.0 {
+            let _ = write!(d, " L {} {}", fmt_num(to.x), fmt_num(-to.y));
+            continue;
+        }
+        let chord = ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt();
+        let radius = chord * (1.0 + from.bulge * from.bulge) / (4.0 * from.bulge.abs());
+        let large_arc = i32::from(from.bulge.abs() > 1.0);
+        // Positive bulge is counter-clockwise in Y-up space, i.e. sweep-flag 0 once flipped.
+        let sweep = i32::from(from.bulge < 0.0);
+        let _ = write!(
+            d,
+            " A {r} {r} 0 {large_arc} {sweep} {} {}",
+            fmt_num(to.x),
+            fmt_num(-to.y),
+            r = fmt_num(radius)
+        );
+    }
+    if polyline.closed {
+        d.push_str(" Z");
+    }
+    d
+}
+
+fn text_anchor_attrs(alignment: &TextAlignment) -> String {
+    let mut out = String::new();
+    match alignment.horizontal {
+        HorizontalAlignment::Left | HorizontalAlignment::Aligned | HorizontalAlignment::Fit => {}
+        HorizontalAlignment::Center | HorizontalAlignment::Middle => {
+            out.push_str(" text-anchor=\"middle\"")
+        }
+        HorizontalAlignment::Right => out.push_str(" text-anchor=\"end\""),
+    }
+    // Horizontal `Middle` centers the text box vertically as well.
+    let vertical = match (alignment.horizontal, alignment.vertical) {
+        (HorizontalAlignment::Middle, VerticalAlignment::Baseline) => VerticalAlignment::Middle,
+        (_, vertical) => vertical,
+    };
+    match vertical {
+        VerticalAlignment::Baseline => {}
+        VerticalAlignment::Bottom => out.push_str(" dominant-baseline=\"text-after-edge\""),
+        VerticalAlignment::Middle => out.push_str(" dominant-baseline=\"central\""),
+        VerticalAlignment::Top => out.push_str(" dominant-baseline=\"text-before-edge\""),
+    }
+    out
+}
+
+fn points_attr(points: &[(f64, f64)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{},{}", fmt_num(*x), fmt_num(*y)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn block_id(name: &str) -> String {
+    let mut out = String::from("block-");
+    for ch in name.chars() {
+        if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' {
+            out.push(ch);
+        } else {
+            let _ = write!(out, "_{:X}_", ch as u32);
+        }
+    }
+    out
+}
+
+// Matches the palette used by the Python plotting helper.
+fn aci_to_rgb(aci: i32) -> String {
+    match aci {
+        1 => "#ff0000".to_string(),
+        2 => "#ffff00".to_string(),
+        3 => "#00ff00".to_string(),
+        4 => "#00ffff".to_string(),
+        5 => "#0000ff".to_string(),
+        6 => "#ff00ff".to_string(),
+        7 => "#000000".to_string(),
+        8 => "#808080".to_string(),
+        9 => "#c0c0c0".to_string(),
+        _ if aci <= 0 || aci >= 256 => "#000000".to_string(),
+        _ => {
+            let (r, g, b) = hsv_to_rgb(f64::from(aci % 255) / 255.0, 0.7, 0.9);
+            format!(
+                "#{:02x}{:02x}{:02x}",
+                (r * 255.0).round() as u8,
+                (g * 255.0).round() as u8,
+                (b * 255.0).round() as u8
+            )
+        }
+    }
+}
+
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let sector = (h * 6.0).floor();
+    let f = h * 6.0 - sector;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+    match (sector as i32).rem_euclid(6) {
+        0 => (v, t, p),
+        1 => (q, v, p),
+        2 => (p, v, t),
+        3 => (p, q, v),
+        4 => (t, p, v),
+        _ => (v, p, q),
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn fmt_num(value: f64) -> String {
+    let text = format!("{value:.6}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" | "" => "0".to_string(),
+        _ => text.to_string(),
+    }
+}
+
+fn normalize_deg(value: f64) -> f64 {
+    let out = value.rem_euclid(360.0);
+    if out == 0.0 {
+        360.0
+    } else {
+        out
+    }
+}
+
+fn deg_to_rad(value: f64) -> f64 {
+    value * PI / 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dxf::{
+        DxfDocument, DxfEntity, DxfLine, DxfVersion, TargetUnits, DEFAULT_XDATA_APP_ID,
+    };
+
+    use super::{document_to_svg, document_to_svg_with_options, SvgOptions};
+
+    fn line_document(x1: f64, y1: f64, x2: f64, y2: f64) -> DxfDocument {
+        DxfDocument {
+            layers: vec![],
+            entities: vec![DxfEntity::Line(DxfLine {
+                layer: "0".to_string(),
+                color: 1,
+                line_type: "CONTINUOUS".to_string(),
+                true_color: None,
+                xdata: Vec::new(),
+                extrusion: None,
+                x1,
+                y1,
+                x2,
+                y2,
+            })],
+            blocks: vec![],
+            unsupported_entities: vec![],
+            units: TargetUnits::Millimeters,
+            xdata_app_id: DEFAULT_XDATA_APP_ID.to_string(),
+            layouts: Vec::new(),
+            fingerprint_guid: None,
+            version: DxfVersion::R2000,
+        }
+    }
+
+    fn attr(svg: &str, name: &str) -> String {
+        let start = svg.find(&format!(" {name}=\"")).unwrap() + name.len() + 3;
+        let end = start + svg[start..].find('"').unwrap();
+        svg[start..end].to_string()
+    }
+
+    #[test]
+    fn document_to_svg_flips_y_axis() {
+        let svg = document_to_svg(&line_document(0.0, 0.0, 100.0, 50.0));
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("stroke=\"#ff0000\""));
+
+        let view_box = attr(&svg, "viewBox")
+            .split(' ')
+            .map(|v| v.parse::<f64>().unwrap())
+            .collect::<Vec<_>>();
+        let line = &svg[svg.find("<line").unwrap()..];
+        let x1 = attr(line, "x1").parse::<f64>().unwrap();
+        let y1 = attr(line, "y1").parse::<f64>().unwrap();
+        let x2 = attr(line, "x2").parse::<f64>().unwrap();
+        let y2 = attr(line, "y2").parse::<f64>().unwrap();
+        assert_eq!((x1, y1, x2, y2), (0.0, 0.0, 100.0, -50.0));
+
+        // The higher endpoint must land nearer the top edge of the viewport.
+        let (view_y, view_h) = (view_box[1], view_box[3]);
+        let screen_y1 = y1 - view_y;
+        let screen_y2 = y2 - view_y;
+        assert!(screen_y2 < screen_y1);
+        assert!(screen_y2 > 0.0 && screen_y1 < view_h);
+        assert!(view_box[0] < 0.0 && view_box[0] + view_box[2] > 100.0);
+    }
+
+    #[test]
+    fn document_to_svg_applies_requested_size() {
+        let svg = document_to_svg_with_options(
+            &line_document(0.0, 0.0, 10.0, 10.0),
+            SvgOptions {
+                width: Some(800.0),
+                height: Some(600.0),
+            },
+        );
+        assert_eq!(attr(&svg, "width"), "800");
+        assert_eq!(attr(&svg, "height"), "600");
+    }
+}
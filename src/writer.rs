@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use encoding_rs::SHIFT_JIS;
+
+use crate::error::JwwError;
+use crate::header::{JwwHeader, JWW_SIGNATURE};
+use crate::model::{
+    Arc, Block, BlockDef, Entity, EntityBase, JwwDocument, Line, Point, Solid, Text,
+};
+use crate::parser::JwwClass;
+
+/// The only version the writer produces, whatever the document was read from.
+pub const WRITE_VERSION: u32 = 600;
+
+// Mirrors the header fields the parser skips between the layer groups and the
+// grid origin: 21 DWORD settings, then the printer and grid settings.
+const SKIPPED_SETTINGS_LEN: usize = 21 * 4 + 16 + 8 + 4 + 4 + 8 + 16;
+const SOLID_OUTLINE_FLAG: u16 = 0x0001;
+const BLOCK_DEF_CLASS: &[u8] = b"CDataList";
+
+/// Serializes `doc` as a version 600 JWW in the layout the parser reads:
+/// the header fields it decodes, then the entity list and the block
+/// definitions with their MFC class records and PID references.
+///
+/// Lines, arcs, points, text, solids and block inserts are written.
+/// Dimensions and polylines have no writer yet and are left out, and so are
+/// block insert attributes, which have no known JWW layout. Header
+/// settings the parser skips are written as zeros, so Jw_cad itself may not
+/// accept the file. The parser locates the entity list by its first class
+/// record, so a document needs at least one top-level entity to read back.
+pub fn document_to_jww_bytes(doc: &JwwDocument) -> Result<Vec<u8>, JwwError> {
+    let mut writer = JwwWriter::default();
+    writer.write_header(&doc.header)?;
+    writer.write_entity_list(&doc.entities)?;
+    writer.u32(len_u32(doc.block_defs.len())?);
+    for (index, block_def) in doc.block_defs.iter().enumerate() {
+        writer.write_block_def(block_def, index == 0)?;
+    }
+    Ok(writer.out)
+}
+
+pub fn write_jww(doc: &JwwDocument, path: impl AsRef<Path>) -> Result<(), JwwError> {
+    fs::write(path, document_to_jww_bytes(doc)?)?;
+    Ok(())
+}
+
+fn len_u32(len: usize) -> Result<u32, JwwError> {
+    u32::try_from(len).map_err(|_| too_large("block definition count"))
+}
+
+fn too_large(what: &str) -> JwwError {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{what} too large for JWW"),
+    )
+    .into()
+}
+
+fn not_shift_jis(value: &str) -> JwwError {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{value:?} has characters Shift-JIS cannot encode"),
+    )
+    .into()
+}
+
+#[derive(Default)]
+struct JwwWriter {
+    out: Vec<u8>,
+}
+
+impl JwwWriter {
+    fn write_header(&mut self, header: &JwwHeader) -> Result<(), JwwError> {
+        self.out.extend_from_slice(JWW_SIGNATURE);
+        self.u32(WRITE_VERSION);
+        self.cstring(&header.memo)?;
+        self.u32(header.paper_size);
+        self.u32(header.write_layer_group);
+        for group in &header.layer_groups {
+            self.u32(group.state);
+            self.u32(group.write_layer);
+            self.f64(group.scale);
+            self.u32(group.protect);
+            for layer in &group.layers {
+                self.u32(layer.state);
+                self.u32(layer.protect);
+            }
+        }
+
+        self.out.resize(self.out.len() + SKIPPED_SETTINGS_LEN, 0);
+        // A missing origin is stored as NaN, which the parser reads back as
+        // no origin.
+        let origin = header.grid_origin;
+        self.f64(origin.map_or(f64::NAN, |origin| origin.x));
+        self.f64(origin.map_or(f64::NAN, |origin| origin.y));
+        for group in &header.layer_groups {
+            for layer in &group.layers {
+                self.cstring(&layer.name)?;
+            }
+        }
+        for group in &header.layer_groups {
+            self.cstring(&group.name)?;
+        }
+        Ok(())
+    }
+
+    // Each list has its own MFC PID space: a class declaration and every
+    // object written take the next PID, and a later record of a declared
+    // class refers back to it with the high bit set.
+    fn write_entity_list(&mut self, entities: &[Entity]) -> Result<(), JwwError> {
+        let entities = entities
+            .iter()
+            .filter_map(|entity| Some((JwwClass::of(entity)?, entity)))
+            .filter(|(class, _)| *class != JwwClass::Dimension)
+            .collect::<Vec<_>>();
+        self.count(u32::try_from(entities.len()).map_err(|_| too_large("entity list"))?);
+
+        let mut class_pids = HashMap::<JwwClass, u32>::new();
+        let mut next_pid = 1u32;
+        for (class, entity) in entities {
+            match class_pids.get(&class) {
+                Some(&pid) => self.u16(0x8000 | pid as u16),
+                None => {
+                    if next_pid >= 0x7FFF {
+                        return Err(too_large("entity list"));
+                    }
+                    self.class_record(class.as_name().as_bytes());
+                    class_pids.insert(class, next_pid);
+                    next_pid += 1;
+                }
+            }
+            self.write_entity(entity)?;
+            next_pid += 1;
+        }
+        Ok(())
+    }
+
+    fn write_entity(&mut self, entity: &Entity) -> Result<(), JwwError> {
+        match entity {
+            Entity::Line(v) => self.line(v),
+            Entity::Arc(v) => self.arc(v),
+            Entity::Point(v) => self.point(v),
+            Entity::Text(v) => return self.text(v),
+            Entity::Solid(v) => self.solid(v),
+            Entity::Block(v) => self.block(v),
+            Entity::Dimension(_) | Entity::Polyline(_) => {}
+        }
+        Ok(())
+    }
+
+    fn write_block_def(&mut self, block_def: &BlockDef, first: bool) -> Result<(), JwwError> {
+        if first {
+            self.class_record(BLOCK_DEF_CLASS);
+        } else {
+            self.u16(0x8001);
+        }
+        self.entity_base(&block_def.base);
+        self.u32(block_def.number);
+        self.u32(u32::from(block_def.is_referenced));
+        self.u32(block_def.created_time);
+        self.cstring(&block_def.name)?;
+        self.write_entity_list(&block_def.entities)
+    }
+
+    fn class_record(&mut self, name: &[u8]) {
+        self.u16(0xFFFF);
+        self.u16(WRITE_VERSION as u16);
+        self.u16(name.len() as u16);
+        self.out.extend_from_slice(name);
+    }
+
+    fn entity_base(&mut self, base: &EntityBase) {
+        self.u32(base.group);
+        self.u8(base.pen_style);
+        self.u16(base.pen_color);
+        self.u16(base.pen_width);
+        self.u16(base.layer);
+        self.u16(base.layer_group);
+        self.u16(base.flag);
+    }
+
+    fn line(&mut self, line: &Line) {
+        self.entity_base(&line.base);
+        self.f64(line.start_x);
+        self.f64(line.start_y);
+        self.f64(line.end_x);
+        self.f64(line.end_y);
+    }
+
+    fn arc(&mut self, arc: &Arc) {
+        self.entity_base(&arc.base);
+        self.f64(arc.center_x);
+        self.f64(arc.center_y);
+        self.f64(arc.radius);
+        self.f64(arc.start_angle);
+        self.f64(arc.arc_angle);
+        self.f64(arc.tilt_angle);
+        self.f64(arc.flatness);
+        self.u32(u32::from(arc.is_full_circle));
+    }
+
+    fn point(&mut self, point: &Point) {
+        self.entity_base(&point.base);
+        self.f64(point.x);
+        self.f64(point.y);
+        self.u32(u32::from(point.is_temporary));
+        if point.base.pen_style == 100 {
+            self.u32(point.code);
+            self.f64(point.angle);
+            self.f64(point.scale);
+        }
+    }
+
+    fn text(&mut self, text: &Text) -> Result<(), JwwError> {
+        self.entity_base(&text.base);
+        self.f64(text.start_x);
+        self.f64(text.start_y);
+        self.f64(text.end_x);
+        self.f64(text.end_y);
+        self.u32(text.text_type);
+        self.f64(text.size_x);
+        self.f64(text.size_y);
+        self.f64(text.spacing);
+        self.f64(text.angle);
+        self.cstring(&text.font_name)?;
+        self.cstring(&text.content)
+    }
+
+    // JWW stores the corners as 1, 4, 2, 3; the outline flag follows
+    // `is_filled` rather than whatever the base flag held.
+    fn solid(&mut self, solid: &Solid) {
+        let mut base = solid.base;
+        base.flag &= !SOLID_OUTLINE_FLAG;
+        if !solid.is_filled {
+            base.flag |= SOLID_OUTLINE_FLAG;
+        }
+        let base = EntityBase {
+            pen_color: if solid.color.is_some() {
+                10
+            } else {
+                base.pen_color
+            },
+            ..base
+        };
+        self.entity_base(&base);
+        self.f64(solid.point1_x);
+        self.f64(solid.point1_y);
+        self.f64(solid.point4_x);
+        self.f64(solid.point4_y);
+        self.f64(solid.point2_x);
+        self.f64(solid.point2_y);
+        self.f64(solid.point3_x);
+        self.f64(solid.point3_y);
+        if base.pen_color == 10 {
+            self.u32(solid.color.unwrap_or(0));
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.entity_base(&block.base);
+        self.f64(block.ref_x);
+        self.f64(block.ref_y);
+        self.f64(block.scale_x);
+        self.f64(block.scale_y);
+        self.f64(block.rotation);
+        self.u32(block.def_number);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.out.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // MFC list count: a word, or 0xFFFF and a dword from 0xFFFF up.
+    fn count(&mut self, count: u32) {
+        match u16::try_from(count) {
+            Ok(count) if count != 0xFFFF => self.u16(count),
+            _ => {
+                self.u16(0xFFFF);
+                self.u32(count);
+            }
+        }
+    }
+
+    // MFC CString: a byte length, escalating to a word and then a dword
+    // behind 0xFF markers, followed by the Shift-JIS bytes. Characters
+    // Shift-JIS lacks are an error rather than HTML-style escapes.
+    fn cstring(&mut self, value: &str) -> Result<(), JwwError> {
+        let (bytes, _, had_errors) = SHIFT_JIS.encode(value);
+        if had_errors {
+            return Err(not_shift_jis(value));
+        }
+        let len = bytes.len();
+        if len < 0xFF {
+            self.u8(len as u8);
+        } else if len < 0xFFFF {
+            self.u8(0xFF);
+            self.u16(len as u16);
+        } else {
+            self.u8(0xFF);
+            self.u16(0xFFFF);
+            self.u32(u32::try_from(len).map_err(|_| too_large("string"))?);
+        }
+        self.out.extend_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use std::io::Cursor;
+
+    use crate::error::JwwError;
+    use crate::model::{Entity, JwwDocument};
+    use crate::parser::{parse_document, read_document_from_file};
+    use crate::stream::EntityStream;
+
+    use super::document_to_jww_bytes;
+
+    fn written_entities(entities: &[Entity]) -> Vec<Entity> {
+        entities
+            .iter()
+            .filter(|entity| !matches!(entity, Entity::Dimension(_) | Entity::Polyline(_)))
+            .cloned()
+            .map(|mut entity| {
+                entity.base_mut().seq = 0;
+                entity
+            })
+            .collect()
+    }
+
+    fn assert_same_supported_entities(original: &JwwDocument, reread: &JwwDocument) {
+        assert_eq!(
+            written_entities(&reread.entities),
+            written_entities(&original.entities)
+        );
+        assert_eq!(reread.block_defs.len(), original.block_defs.len());
+        for (reread, original) in reread.block_defs.iter().zip(&original.block_defs) {
+            assert_eq!(reread.number, original.number);
+            assert_eq!(reread.name, original.name);
+            assert_eq!(
+                written_entities(&reread.entities),
+                written_entities(&original.entities)
+            );
+        }
+    }
+
+    #[test]
+    fn write_jww_round_trips_sample_entities() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples");
+        for name in ["Test1.jww", "木造平面例.jww"] {
+            let original = read_document_from_file(samples.join(name)).unwrap();
+            let bytes = document_to_jww_bytes(&original).unwrap();
+            let reread = parse_document(&bytes).unwrap();
+
+            assert_eq!(reread.header.version, 600);
+            assert_eq!(reread.header.memo, original.header.memo);
+            assert_eq!(reread.header.layer_groups, original.header.layer_groups);
+            assert_eq!(reread.header.grid_origin, original.header.grid_origin);
+            assert_same_supported_entities(&original, &reread);
+        }
+    }
+
+    #[test]
+    fn write_jww_uses_extended_count_for_long_lists() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples");
+        let mut doc = read_document_from_file(samples.join("Test1.jww")).unwrap();
+        let line = doc
+            .entities
+            .iter()
+            .find(|entity| matches!(entity, Entity::Line(_)))
+            .unwrap()
+            .clone();
+        doc.entities = vec![line; 0x1_0000];
+
+        let bytes = document_to_jww_bytes(&doc).unwrap();
+        assert_eq!(parse_document(&bytes).unwrap().entities.len(), 0x1_0000);
+        let stream = EntityStream::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(stream.count(), 0x1_0000);
+    }
+
+    #[test]
+    fn write_jww_rejects_text_outside_shift_jis() {
+        let samples = Path::new(env!("CARGO_MANIFEST_DIR")).join("jww_samples");
+        let mut doc = read_document_from_file(samples.join("Test1.jww")).unwrap();
+        doc.header.memo = "\u{1F600}".to_string();
+
+        let Err(JwwError::Io(err)) = document_to_jww_bytes(&doc) else {
+            panic!("expected an encoding error");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
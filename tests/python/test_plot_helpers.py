@@ -31,6 +31,7 @@ class PlotHelperTests(unittest.TestCase):
         self.assertEqual(PLOT._line_style("dashed"), "--")
         self.assertEqual(PLOT._line_style("DASHDOT"), "-.")
         self.assertEqual(PLOT._line_style("DOT"), ":")
+        self.assertEqual(PLOT._line_style("DIVIDE"), "-.")
         self.assertEqual(PLOT._line_style("unknown"), "-")
 
     def test_ellipse_points_endpoints_for_full_loop(self):
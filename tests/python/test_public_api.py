@@ -1,6 +1,7 @@
 from __future__ import annotations
 
 import json
+import struct
 import sys
 import tempfile
 import unittest
@@ -58,6 +59,34 @@ class PublicApiTests(unittest.TestCase):
         with self.assertRaises(ValueError):
             msp.query("LINE[layer~=5]")
 
+    def test_read_document_returns_typed_classes(self):
+        doc = ezjww.read_document(str(sample_path()))
+        self.assertEqual(doc.header.version, 600)
+        self.assertEqual(len(doc.header.layer_groups), 16)
+        self.assertGreater(len(doc.entities), 0)
+        first = doc.entities[0]
+        self.assertIsInstance(first, ezjww._core.PyEntity)
+        self.assertEqual(first.type, "LINE")
+        self.assertIsInstance(first, ezjww._core.PyLine)
+        self.assertIn("PyLine(", repr(first))
+        self.assertNotIn(0, doc.groups)
+        for group, members in doc.groups.items():
+            for index in members:
+                self.assertEqual(doc.entities[index].base.group, group)
+
+        legacy = ezjww.read_document_dict(str(sample_path()))
+        self.assertEqual(len(legacy["entities"]), len(doc.entities))
+        self.assertEqual(legacy["groups"], doc.groups)
+        pens = legacy["pen_usage"]
+        self.assertEqual(
+            sum(pens.values()),
+            len(legacy["entities"])
+            + sum(len(block["entities"]) for block in legacy["block_defs"]),
+        )
+        for pen in pens:
+            self.assertEqual(len(pen), 3)
+            self.assertTrue(all(isinstance(value, int) for value in pen))
+
     def test_new_drawing_defaults(self):
         drawing = ezjww.new()
         self.assertEqual(len(drawing.modelspace()), 0)
@@ -98,6 +127,247 @@ class PublicApiTests(unittest.TestCase):
         drawing = ezjww.new()
         self.assertIsNone(drawing.bbox())
 
+    def test_bounding_box_from_path(self):
+        result = ezjww.bounding_box(str(sample_path()))
+        self.assertIsNotNone(result)
+        assert result is not None
+        self.assertEqual(set(result.keys()), {"min_x", "min_y", "max_x", "max_y"})
+        self.assertLessEqual(result["min_x"], result["max_x"])
+        self.assertLessEqual(result["min_y"], result["max_y"])
+
+    def test_read_json_tags_entities(self):
+        data = json.loads(ezjww.read_json(str(sample_path())))
+        self.assertEqual(set(data.keys()), {"header", "entities", "block_defs"})
+        self.assertGreater(len(data["entities"]), 0)
+        types = {entity["type"] for entity in data["entities"]}
+        self.assertIn("LINE", types)
+
+    def test_export_svg_writes_view_box(self):
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            svg_out = Path(tmp_dir) / "out.svg"
+            ezjww.export_svg(str(sample_path()), str(svg_out), 640, 480)
+            text = svg_out.read_text(encoding="utf-8")
+        self.assertTrue(text.startswith("<svg "))
+        self.assertIn('width="640"', text)
+        self.assertIn('height="480"', text)
+        self.assertIn("viewBox=", text)
+
+    def test_export_geojson_writes_feature_collection(self):
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            geojson_out = Path(tmp_dir) / "out.geojson"
+            ezjww.export_geojson(str(sample_path()), str(geojson_out))
+            data = json.loads(geojson_out.read_text(encoding="utf-8"))
+        self.assertEqual(data["type"], "FeatureCollection")
+        self.assertGreater(len(data["features"]), 0)
+        types = {feature["geometry"]["type"] for feature in data["features"]}
+        self.assertIn("LineString", types)
+        self.assertIn("layer", data["features"][0]["properties"])
+
+    def test_write_dxf_document_from_python_objects(self):
+        doc = ezjww.PyDxfDocument(
+            layers=[ezjww.PyDxfLayer("WALLS", color=1)],
+            entities=[ezjww.PyDxfLine(0.0, 0.0, 100.0, 0.0, layer="WALLS")],
+        )
+        doc.entities.append(ezjww.PyDxfLine(100.0, 0.0, 100.0, 50.0, layer="WALLS"))
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            dxf_out = Path(tmp_dir) / "out.dxf"
+            ezjww.write_dxf_document(doc, str(dxf_out))
+            text = dxf_out.read_text(encoding="utf-8")
+        lines = [line.strip() for line in text.splitlines()]
+        self.assertEqual(lines.count("LINE"), 2)
+        self.assertIn("WALLS", lines)
+
+    def test_write_dxf_document_rejects_non_finite_values(self):
+        doc = ezjww.PyDxfDocument(
+            entities=[ezjww.PyDxfLine(0.0, 0.0, float("nan"), 0.0)]
+        )
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            with self.assertRaises(ValueError):
+                ezjww.write_dxf_document(doc, str(Path(tmp_dir) / "out.dxf"))
+
+    def test_write_dxf_document_sanitizes_layer_names(self):
+        doc = ezjww.PyDxfDocument(
+            layers=[ezjww.PyDxfLayer("A/B"), ezjww.PyDxfLayer("a_b")],
+            entities=[ezjww.PyDxfLine(0.0, 0.0, 1.0, 0.0, layer="A/B")],
+        )
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            dxf_out = Path(tmp_dir) / "out.dxf"
+            ezjww.write_dxf_document(doc, str(dxf_out))
+            text = dxf_out.read_text(encoding="utf-8")
+        lines = [line.strip() for line in text.splitlines()]
+        self.assertNotIn("A/B", lines)
+        self.assertIn("A_B", lines)
+        self.assertIn("a_b_2", lines)
+
+    def test_write_dxf_document_rejects_unknown_entity(self):
+        doc = ezjww.PyDxfDocument(entities=["not an entity"])
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            with self.assertRaises(TypeError):
+                ezjww.write_dxf_document(doc, str(Path(tmp_dir) / "out.dxf"))
+
+    def test_read_header_resolves_paper_size(self):
+        header = ezjww.read_header(str(sample_path()))
+        self.assertEqual(header["paper_size"], 2)
+        self.assertEqual(header["paper_size_mm"], (594.0, 420.0))
+
+    def test_read_dxf_string_filtered_keeps_requested_layers(self):
+        path = str(sample_path())
+        full = ezjww.read_dxf_string(path)
+        self.assertEqual(ezjww.read_dxf_string_filtered(path, []).count("\nLINE\n"), 0)
+        used = {
+            (e["base"]["layer_group"], e["base"]["layer"])
+            for e in ezjww.read_document_dict(path)["entities"]
+        }
+        filtered = ezjww.read_dxf_string_filtered(path, [list(slot) for slot in used])
+        self.assertEqual(filtered, full)
+
+    def test_read_dxf_bytes_matches_string_in_cp1252(self):
+        data = ezjww.read_dxf_bytes(str(sample_path()))
+        self.assertIsInstance(data, bytes)
+        self.assertEqual(
+            data.decode("cp1252"), ezjww.read_dxf_string(str(sample_path()))
+        )
+        self.assertIn(b"$DWGCODEPAGE\n  3\nANSI_1252\n", data)
+
+    def test_convert_directory_py_reports_each_file(self):
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            input_dir = Path(tmp_dir) / "in"
+            output_dir = Path(tmp_dir) / "out"
+            input_dir.mkdir()
+            (input_dir / "good.jww").write_bytes(sample_path().read_bytes())
+            (input_dir / "bad.jww").write_bytes(b"not a jww file")
+            results = ezjww.convert_directory_py(str(input_dir), str(output_dir))
+            self.assertEqual([name for name, _, _ in results], ["bad.jww", "good.jww"])
+            self.assertFalse(results[0][1])
+            self.assertIn("signature", results[0][2])
+            self.assertEqual(results[1], ("good.jww", True, None))
+            self.assertEqual(
+                (output_dir / "good.dxf").read_bytes(),
+                ezjww.read_dxf_bytes(str(sample_path())),
+            )
+            self.assertFalse((output_dir / "bad.dxf").exists())
+
+    def test_probe_jww_reports_version(self):
+        probe = ezjww.probe_jww(str(sample_path()))
+        self.assertEqual(
+            probe, {"is_jww": True, "version": 600, "readable_header": True}
+        )
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            truncated = Path(tmp_dir) / "truncated.jww"
+            truncated.write_bytes(b"JwwData." + (600).to_bytes(4, "little"))
+            probe = ezjww.probe_jww(str(truncated))
+            self.assertEqual(
+                probe, {"is_jww": True, "version": 600, "readable_header": False}
+            )
+            other = Path(tmp_dir) / "other.txt"
+            other.write_bytes(b"hello")
+            probe = ezjww.probe_jww(str(other))
+            self.assertEqual(
+                probe, {"is_jww": False, "version": None, "readable_header": False}
+            )
+
+    def test_jww_version_reads_signature_prefix(self):
+        self.assertEqual(ezjww.jww_version(str(sample_path())), 600)
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            future = Path(tmp_dir) / "future.jww"
+            future.write_bytes(b"JwwData." + (999).to_bytes(4, "little"))
+            self.assertEqual(ezjww.jww_version(str(future)), 999)
+            short = Path(tmp_dir) / "short.jww"
+            short.write_bytes(b"JwwData.")
+            self.assertIsNone(ezjww.jww_version(str(short)))
+            other = Path(tmp_dir) / "other.txt"
+            other.write_bytes(b"hello, not a drawing")
+            self.assertIsNone(ezjww.jww_version(str(other)))
+
+    def test_bytes_readers_parse_crafted_buffer(self):
+        data = bytearray(b"JwwData." + struct.pack("<I", 600))
+        data += b"\x00"  # empty memo
+        data += struct.pack("<II", 0, 0)  # paper size, write layer group
+        for _ in range(16):
+            data += struct.pack("<IIdI", 0, 0, 1.0, 0)
+            data += struct.pack("<II", 0, 0) * 16
+        data += struct.pack("<H", 1)  # entity count
+        data += struct.pack("<HHH", 0xFFFF, 600, len(b"CDataSen")) + b"CDataSen"
+        data += struct.pack("<IBHHHHH", 0, 1, 1, 1, 0, 0, 0)  # entity base
+        data += struct.pack("<dddd", 0.0, 0.0, 10.0, 5.0)
+        data += struct.pack("<I", 0)  # block def count
+        data = bytes(data)
+
+        document = ezjww.read_document_bytes(data)
+        self.assertEqual(len(document.entities), 1)
+        as_dict = ezjww.read_document_dict_bytes(data)
+        self.assertEqual(as_dict["entities"][0]["type"], "LINE")
+        dxf = ezjww.read_dxf_document_bytes(data)
+        line = dxf["entities"][0]
+        self.assertEqual(
+            (line["x1"], line["y1"], line["x2"], line["y2"]), (0.0, 0.0, 10.0, 5.0)
+        )
+        self.assertIn("LINE", ezjww.read_dxf_string_bytes(data))
+        parsed = json.loads(ezjww.read_json_bytes(data))
+        self.assertEqual(parsed["entities"][0]["type"], "LINE")
+        with self.assertRaises(ValueError):
+            ezjww.read_document_bytes(b"not a drawing")
+
+    def test_check_conversion_reports_clean_sample(self):
+        result = ezjww.check_conversion(str(sample_path()))
+        self.assertTrue(result["ok"])
+        self.assertGreater(result["entity_count"], 0)
+        self.assertEqual(result["unsupported_entities"], [])
+        self.assertEqual(result["warnings"], [])
+        self.assertGreater(result["generated_segments"], 0)
+
+    def test_check_conversion_rejects_too_many_segments(self):
+        with self.assertRaises(ValueError):
+            ezjww.check_conversion(str(sample_path()), max_total_segments=0)
+
+    def test_read_report_counts_entity_classes(self):
+        report = ezjww.read_report(str(sample_path()))
+        document = ezjww.read_document_dict(str(sample_path()))
+        self.assertEqual(
+            sum(report["class_counts"].values()), len(document["entities"])
+        )
+        self.assertGreater(report["class_counts"]["CDataSen"], 0)
+        self.assertEqual(report["unresolved_block_refs"], [])
+
+    def test_validate_file_matches_read_report(self):
+        self.assertEqual(
+            ezjww.validate_file(str(sample_path())),
+            ezjww.read_report(str(sample_path())),
+        )
+
+    def test_entity_base_flags_decode_raw_flag(self):
+        document = ezjww.read_document_dict(str(sample_path()))
+        typed = ezjww.read_document(str(sample_path()))
+        for entity, typed_entity in zip(document["entities"], typed.entities):
+            base = entity["base"]
+            self.assertEqual(
+                base["flags"],
+                {
+                    "figure": bool(base["flag"] & 0x10),
+                    "hatch": bool(base["flag"] & 0x20),
+                    "dimension": bool(base["flag"] & 0x40),
+                    "fitting": bool(base["flag"] & 0x80),
+                },
+            )
+            self.assertEqual(typed_entity.base.flags, base["flags"])
+
+    def test_entity_offsets_are_increasing(self):
+        offsets = ezjww.entity_offsets(str(sample_path()))
+        self.assertGreater(len(offsets), 1)
+        self.assertGreater(offsets[1], offsets[0])
+
+    def test_merge_dxf_prefixes_layers(self):
+        with tempfile.TemporaryDirectory(prefix="ezjww_test_") as tmp_dir:
+            dxf_out = Path(tmp_dir) / "merged.dxf"
+            ezjww.merge_dxf(
+                [str(sample_path()), str(sample_path())], ["A-", "B-"], str(dxf_out)
+            )
+            lines = [line.strip() for line in dxf_out.read_text(encoding="utf-8").splitlines()]
+        self.assertTrue(any(line.startswith("A-") for line in lines))
+        self.assertTrue(any(line.startswith("B-") for line in lines))
+        with self.assertRaises(ValueError):
+            ezjww.merge_dxf([str(sample_path())], [], "unused.dxf")
+
     def test_stats_from_path(self):
         result = ezjww.stats(sample_path())
         self.assertIn("entity_count", result)